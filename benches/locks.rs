@@ -0,0 +1,147 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Compares [Lock] with `std::sync::Mutex`, `parking_lot::Mutex` and `spin::Mutex`.
+
+* `uncontended`: one lock and unlock, on one thread.
+* `contended/N`: N threads incrementing a shared counter, timed per increment.
+* `fairness/N`: N threads hammering the lock until *every* thread has acquired it a fixed number of times.
+  An unfair lock lets some threads finish early while others starve, which shows up as a longer time.
+
+Run with `cargo bench --bench locks`.
+*/
+
+use atomiclock_spinlock::Lock;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Barrier;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const THREADS: [usize; 3] = [2, 4, 16];
+//acquisitions each thread needs, in the fairness benchmark
+const QUOTA: u64 = 1000;
+
+trait BenchLock: Sync + Default {
+    const NAME: &'static str;
+    fn with(&self, f: impl FnOnce(&mut u64));
+}
+
+impl BenchLock for Lock<u64> {
+    const NAME: &'static str = "atomiclock_spinlock";
+    fn with(&self, f: impl FnOnce(&mut u64)) {
+        f(&mut self.spin_lock())
+    }
+}
+
+impl BenchLock for std::sync::Mutex<u64> {
+    const NAME: &'static str = "std";
+    fn with(&self, f: impl FnOnce(&mut u64)) {
+        f(&mut self.lock().unwrap())
+    }
+}
+
+impl BenchLock for parking_lot::Mutex<u64> {
+    const NAME: &'static str = "parking_lot";
+    fn with(&self, f: impl FnOnce(&mut u64)) {
+        f(&mut self.lock())
+    }
+}
+
+impl BenchLock for spin::Mutex<u64> {
+    const NAME: &'static str = "spin";
+    fn with(&self, f: impl FnOnce(&mut u64)) {
+        f(&mut self.lock())
+    }
+}
+
+fn uncontended<L: BenchLock>(c: &mut Criterion) {
+    let lock = L::default();
+    c.benchmark_group("uncontended").bench_function(L::NAME, |b| b.iter(|| black_box(&lock).with(|n| *n += 1)));
+}
+
+/**
+Runs `work` on `threads` threads at once, returning the time from the start until the last one finishes.
+*/
+fn race(threads: usize, work: impl Fn(usize) + Sync) -> Duration {
+    let barrier = Barrier::new(threads + 1);
+    thread::scope(|s| {
+        for t in 0..threads {
+            let (barrier, work) = (&barrier, &work);
+            s.spawn(move || {
+                barrier.wait();
+                work(t);
+                barrier.wait();
+            });
+        }
+        barrier.wait();
+        let start = Instant::now();
+        barrier.wait();
+        start.elapsed()
+    })
+}
+
+fn contended<L: BenchLock>(c: &mut Criterion) {
+    let mut group = c.benchmark_group("contended");
+    for threads in THREADS {
+        group.throughput(Throughput::Elements(1));
+        group.bench_with_input(BenchmarkId::new(L::NAME, threads), &threads, |b, &threads| {
+            b.iter_custom(|iters| {
+                let lock = L::default();
+                let per_thread = iters.div_ceil(threads as u64);
+                let elapsed = race(threads, |_| {
+                    for _ in 0..per_thread {
+                        lock.with(|n| *n += 1);
+                    }
+                });
+                //time per increment, across all threads
+                elapsed.mul_f64(iters as f64 / (per_thread * threads as u64) as f64)
+            })
+        });
+    }
+    group.finish();
+}
+
+fn fairness<L: BenchLock>(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fairness");
+    group.sample_size(20);
+    for threads in THREADS {
+        group.bench_with_input(BenchmarkId::new(L::NAME, threads), &threads, |b, &threads| {
+            b.iter_custom(|iters| {
+                let mut total = Duration::ZERO;
+                for _ in 0..iters {
+                    let lock = L::default();
+                    let done = AtomicUsize::new(0);
+                    total += race(threads, |_| {
+                        let mut mine = 0;
+                        //keep contending after our quota, so that starved threads still have to compete
+                        while done.load(Ordering::Relaxed) < threads {
+                            lock.with(|n| *n += 1);
+                            mine += 1;
+                            if mine == QUOTA {
+                                done.fetch_add(1, Ordering::Relaxed);
+                            }
+                        }
+                    });
+                }
+                total
+            })
+        });
+    }
+    group.finish();
+}
+
+fn all<L: BenchLock>(c: &mut Criterion) {
+    uncontended::<L>(c);
+    contended::<L>(c);
+    fairness::<L>(c);
+}
+
+criterion_group!(
+    benches,
+    all::<Lock<u64>>,
+    all::<std::sync::Mutex<u64>>,
+    all::<parking_lot::Mutex<u64>>,
+    all::<spin::Mutex<u64>>
+);
+criterion_main!(benches);
@@ -0,0 +1,75 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Compiles the tuning file named by `ATOMICLOCK_SPINLOCK_TUNING`, if any, into a table of per-lock settings.
+
+See the `config` module for the format.
+*/
+
+use std::fmt::Write;
+
+const KEYS: [&str; 5] = ["spins_before_yield", "warn_threshold", "backoff_cap", "low_latency", "handoff_after"];
+
+//a lock name, and its value for each of KEYS, as a Rust literal
+type Section = (String, [Option<String>; 5]);
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=ATOMICLOCK_SPINLOCK_TUNING");
+    let mut table = String::from("&[\n");
+    if let Some(path) = std::env::var_os("ATOMICLOCK_SPINLOCK_TUNING") {
+        let path = std::path::PathBuf::from(path);
+        println!("cargo:rerun-if-changed={}", path.display());
+        let file = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("can't read tuning file {}: {e}", path.display()));
+        for (name, values) in parse(&file).unwrap_or_else(|e| panic!("{}: {e}", path.display())) {
+            write!(table, "    ({name:?}, Tuned {{").unwrap();
+            for (key, value) in KEYS.iter().zip(values) {
+                match value {
+                    Some(value) => write!(table, " {key}: Some({value}),").unwrap(),
+                    None => write!(table, " {key}: None,").unwrap(),
+                }
+            }
+            table.push_str(" }),\n");
+        }
+    }
+    table.push(']');
+    let out = std::path::PathBuf::from(std::env::var_os("OUT_DIR").unwrap()).join("tuning.rs");
+    std::fs::write(out, table).unwrap();
+}
+
+/**
+Parses `[name]` sections of `key = value` lines.  Blank lines and `#` comments are ignored.
+*/
+fn parse(file: &str) -> Result<Vec<Section>, String> {
+    let mut locks: Vec<Section> = Vec::new();
+    for (number, line) in file.lines().enumerate() {
+        let number = number + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            if locks.iter().any(|(n, _)| n == name) {
+                return Err(format!("line {number}: lock {name:?} is tuned twice"));
+            }
+            locks.push((name.to_string(), Default::default()));
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(format!("line {number}: expected `[name]` or `key = value`"));
+        };
+        let Some((_, values)) = locks.last_mut() else {
+            return Err(format!("line {number}: setting outside of a `[name]` section"));
+        };
+        let (key, value) = (key.trim(), value.trim());
+        let Some(index) = KEYS.iter().position(|k| *k == key) else {
+            return Err(format!("line {number}: unknown setting {key:?}, expected one of {KEYS:?}"));
+        };
+        let literal = match key {
+            "low_latency" => value.parse::<bool>().map(|v| v.to_string()).map_err(|e| e.to_string()),
+            _ => value.parse::<u32>().map(|v| v.to_string()).map_err(|e| e.to_string()),
+        };
+        values[index] = Some(literal.map_err(|e| format!("line {number}: {key}: {e}"))?);
+    }
+    Ok(locks)
+}
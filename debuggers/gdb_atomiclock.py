@@ -0,0 +1,95 @@
+# SPDX-License-Identifier: MIT OR Apache-2.0
+"""
+GDB pretty-printers for atomiclock_spinlock's Lock and DebugState.
+
+Binaries embed this script, so GDB loads it when the binary's directory is on its auto-load safe-path;
+otherwise, `source debuggers/gdb_atomiclock.py`.  Locks are read straight from memory, so this works on core
+dumps.
+"""
+
+import re
+
+import gdb
+import gdb.printing
+
+
+def _leaf(value):
+    """The scalar inside nested wrappers, such as an AtomicUsize's UnsafeCell."""
+    while value.type.strip_typedefs().code == gdb.TYPE_CODE_STRUCT:
+        fields = value.type.strip_typedefs().fields()
+        if not fields:
+            return None
+        value = value[fields[0]]
+    return value
+
+
+def _field(value, name):
+    try:
+        return value[name]
+    except gdb.error:
+        return None
+
+
+def _name(value):
+    """The lock's name, from an Option<&str>, or None."""
+    match = re.search(r'Some\("(.*)"\)', str(value))
+    return match.group(1) if match else None
+
+
+def _summary(kind, name, locked, waiters):
+    state = "locked" if locked else "unlocked"
+    if waiters:
+        state += ", %d waiter%s" % (waiters, "" if waiters == 1 else "s")
+    return '%s%s (%s)' % (kind, ' "%s"' % name if name else "", state)
+
+
+def _owner(value):
+    """The owner token as a pointer, so it prints in hex."""
+    return value.cast(gdb.lookup_type("u8").pointer())
+
+
+class LockPrinter:
+    """A Lock, from its fields: the raw lock's state, the waiter count, the debug-assertions holder, and the data."""
+
+    def __init__(self, value):
+        self.value = value
+
+    def to_string(self):
+        locked = _leaf(self.value["raw"])
+        waiters = _leaf(self.value["waiters"])
+        return _summary("Lock", _name(self.value["name"]), locked is not None and int(locked) != 0,
+                        int(waiters) if waiters is not None else 0)
+
+    def children(self):
+        holder = _field(self.value, "holder")
+        if holder is not None and int(_leaf(holder)) != 0:
+            yield "owner", _owner(_leaf(holder))
+        yield "waiters", _leaf(self.value["waiters"])
+        yield "data", self.value["data"]["value"]
+
+
+class DebugStatePrinter:
+    """A DebugState, from Lock::debug_state."""
+
+    def __init__(self, value):
+        self.value = value
+
+    def to_string(self):
+        return _summary("DebugState", _name(self.value["name"]), bool(self.value["locked"]),
+                        int(self.value["waiters"]))
+
+    def children(self):
+        if int(self.value["owner"]) != 0:
+            yield "owner", _owner(self.value["owner"])
+        for name in ("live_guards", "acquisitions", "contended", "spin_nanos", "timeouts"):
+            yield name, self.value[name]
+
+
+def _printers():
+    printers = gdb.printing.RegexpCollectionPrettyPrinter("atomiclock_spinlock")
+    printers.add_printer("Lock", r"^atomiclock_spinlock::Lock<.*>$", LockPrinter)
+    printers.add_printer("DebugState", r"^atomiclock_spinlock::(\w+::)*DebugState$", DebugStatePrinter)
+    return printers
+
+
+gdb.printing.register_pretty_printer(gdb.current_objfile(), _printers(), replace=True)
@@ -0,0 +1,56 @@
+# SPDX-License-Identifier: MIT OR Apache-2.0
+"""
+LLDB summaries for atomiclock_spinlock's Lock and DebugState.
+
+Load with `command script import debuggers/lldb_atomiclock.py`, or from `~/.lldbinit`.  Locks are read
+straight from memory, so this works on core dumps.
+"""
+
+import re
+
+
+def _leaf(value):
+    """The scalar inside nested wrappers, such as an AtomicUsize's UnsafeCell."""
+    while value.IsValid() and value.GetNumChildren() > 0 and not value.GetType().IsPointerType():
+        value = value.GetChildAtIndex(0)
+    return value.GetValueAsUnsigned(0) if value.IsValid() else 0
+
+
+def _name(value):
+    """The lock's name, from an Option<&str>, or None."""
+    match = re.search(r'Some\("(.*)"\)', value.GetSummary() or str(value))
+    return match.group(1) if match else None
+
+
+def _summary(kind, name, locked, waiters, owner):
+    state = "locked" if locked else "unlocked"
+    if waiters:
+        state += ", %d waiter%s" % (waiters, "" if waiters == 1 else "s")
+    if owner:
+        state += ", owner 0x%x" % owner
+    return '%s%s (%s)' % (kind, ' "%s"' % name if name else "", state)
+
+
+def lock_summary(value, _internal_dict):
+    """A Lock, from its fields: the raw lock's state, the waiter count and the debug-assertions holder."""
+    value = value.GetNonSyntheticValue()
+    holder = value.GetChildMemberWithName("holder")
+    return _summary("Lock", _name(value.GetChildMemberWithName("name")),
+                    _leaf(value.GetChildMemberWithName("raw")) != 0,
+                    _leaf(value.GetChildMemberWithName("waiters")),
+                    _leaf(holder) if holder.IsValid() else 0)
+
+
+def debug_state_summary(value, _internal_dict):
+    """A DebugState, from Lock::debug_state."""
+    value = value.GetNonSyntheticValue()
+    field = lambda name: value.GetChildMemberWithName(name).GetValueAsUnsigned(0)
+    return _summary("DebugState", _name(value.GetChildMemberWithName("name")), field("locked") != 0,
+                    field("waiters"), field("owner"))
+
+
+def __lldb_init_module(debugger, _internal_dict):
+    debugger.HandleCommand(
+        'type summary add -x "^atomiclock_spinlock::Lock<.+>$" -F %s.lock_summary' % __name__)
+    debugger.HandleCommand(
+        'type summary add -x "^atomiclock_spinlock::(.+::)?DebugState$" -F %s.debug_state_summary' % __name__)
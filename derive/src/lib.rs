@@ -0,0 +1,216 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Macros for atomiclock_spinlock: `#[derive(SpinLocked)]`, which generates a version of a struct with a lock on
+each field, and `#[locked]`, which runs a method with a lock held.
+
+Use them through `atomiclock_spinlock`, with the `derive` feature, which re-exports them and documents them.
+*/
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::ext::IdentExt;
+use syn::parse::{Parse, ParseStream};
+use syn::{parse_macro_input, Data, DeriveInput, Expr, Fields, GenericArgument, Ident, ImplItemFn, LitStr, PathArguments, ReturnType, Token, Type};
+
+/**
+Generates `{Name}Locked`, with a `Lock` on each field of the struct.  See `atomiclock_spinlock::SpinLocked`.
+*/
+#[proc_macro_derive(SpinLocked, attributes(spin_locked))]
+pub fn derive_spin_locked(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let vis = &input.vis;
+    let locked = locked_name(&input)?;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(name, "SpinLocked needs a struct with named fields")),
+        },
+        _ => return Err(syn::Error::new_spanned(name, "SpinLocked needs a struct with named fields")),
+    };
+    let generics = &input.generics;
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+    let idents: Vec<&Ident> = fields.iter().map(|f| f.ident.as_ref().expect("named fields have names")).collect();
+    let types = fields.iter().map(|f| &f.ty);
+    let lock_names = idents.iter().map(|ident| format!("{}.{}", name, ident.unraw()));
+    let accessors = fields.iter().zip(&idents).map(|(field, ident)| {
+        let field_vis = &field.vis;
+        let ty = &field.ty;
+        let docs = field.attrs.iter().filter(|attr| attr.path().is_ident("doc"));
+        let summary = format!("The lock on `{}`.", ident.unraw());
+        quote! {
+            #[doc = #summary]
+            #[doc = ""]
+            #(#docs)*
+            #field_vis fn #ident(&self) -> &::atomiclock_spinlock::Lock<#ty> {
+                &self.#ident
+            }
+        }
+    });
+    let struct_doc = format!("[`{name}`], with a [`Lock`](::atomiclock_spinlock::Lock) on each field.  Generated by `#[derive(SpinLocked)]`.");
+    let types = types.collect::<Vec<_>>();
+
+    Ok(quote! {
+        #[doc = #struct_doc]
+        #vis struct #locked #generics #where_clause {
+            #(#idents: ::atomiclock_spinlock::Lock<#types>,)*
+        }
+
+        impl #impl_generics #locked #type_generics #where_clause {
+            /// Wraps each field in its own lock, named after the struct and field.
+            #vis fn new(data: #name #type_generics) -> Self {
+                let #name { #(#idents,)* } = data;
+                #locked { #(#idents: ::atomiclock_spinlock::Lock::with_name(#idents, #lock_names),)* }
+            }
+
+            /// Consumes the locks and returns the struct.
+            #vis fn into_inner(self) -> #name #type_generics {
+                #name { #(#idents: self.#idents.into_inner(),)* }
+            }
+
+            #(#accessors)*
+        }
+
+        impl #impl_generics ::core::convert::From<#name #type_generics> for #locked #type_generics #where_clause {
+            fn from(data: #name #type_generics) -> Self {
+                #locked::new(data)
+            }
+        }
+
+        impl #impl_generics ::core::convert::From<#locked #type_generics> for #name #type_generics #where_clause {
+            fn from(locked: #locked #type_generics) -> Self {
+                locked.into_inner()
+            }
+        }
+    })
+}
+
+/**
+The name given by `#[spin_locked(name = "...")]`, or `{Name}Locked`.
+*/
+fn locked_name(input: &DeriveInput) -> syn::Result<Ident> {
+    let mut locked = format_ident!("{}Locked", input.ident);
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("spin_locked")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                let name: LitStr = meta.value()?.parse()?;
+                locked = name.parse()?;
+                Ok(())
+            } else {
+                Err(meta.error("expected `name = \"...\"`"))
+            }
+        })?;
+    }
+    Ok(locked)
+}
+
+/**
+Runs the method with a `Lock` field of `self` held.  See `atomiclock_spinlock::locked`.
+*/
+#[proc_macro_attribute]
+pub fn locked(args: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as LockedArgs);
+    let method = parse_macro_input!(item as ImplItemFn);
+    expand_locked(args, method).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+/**
+How `#[locked]` acquires the lock.
+*/
+enum Acquire {
+    Spin,
+    Warn,
+    Timeout(Expr),
+}
+
+/**
+The arguments to `#[locked]`: the field, then `warn` or `timeout = duration`.
+*/
+struct LockedArgs {
+    field: Ident,
+    acquire: Acquire,
+}
+
+impl Parse for LockedArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let field: Ident = input.parse()?;
+        let mut acquire = Acquire::Spin;
+        if input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            let option: Ident = input.parse()?;
+            acquire = match option.to_string().as_str() {
+                "warn" => Acquire::Warn,
+                "timeout" => {
+                    input.parse::<Token![=]>()?;
+                    Acquire::Timeout(input.parse()?)
+                }
+                _ => return Err(syn::Error::new_spanned(option, "expected `warn` or `timeout = ...`")),
+            };
+            input.parse::<Option<Token![,]>>()?;
+        }
+        if !input.is_empty() {
+            return Err(input.error("expected the field, then `warn` or `timeout = ...`"));
+        }
+        Ok(LockedArgs { field, acquire })
+    }
+}
+
+fn expand_locked(args: LockedArgs, mut method: ImplItemFn) -> syn::Result<TokenStream2> {
+    if method.sig.receiver().is_none() {
+        return Err(syn::Error::new_spanned(&method.sig, "#[locked] needs a method that takes `self`"));
+    }
+    if let Some(asyncness) = method.sig.asyncness {
+        return Err(syn::Error::new_spanned(asyncness, "#[locked] can't hold a spinlock across an `.await`"));
+    }
+    let field = &args.field;
+    let body = &method.block;
+    let block = match &args.acquire {
+        Acquire::Spin => quote! {{
+            #[allow(unused_mut)]
+            let mut #field = self.#field.spin_lock();
+            #body
+        }},
+        Acquire::Warn => quote! {{
+            #[allow(unused_mut)]
+            let mut #field = self.#field.spin_lock_warn();
+            #body
+        }},
+        Acquire::Timeout(timeout) => {
+            let inner = option_inner(&method.sig.output).ok_or_else(|| {
+                syn::Error::new_spanned(&method.sig, "#[locked(timeout = ...)] needs a method that returns `Option<_>`")
+            })?;
+            //in a closure, so `return` and `?` in the body are for the value, not the Option
+            quote! {{
+                match self.#field.spin_lock_for(#timeout) {
+                    #[allow(unused_mut)]
+                    ::core::option::Option::Some(mut #field) => ::core::option::Option::Some((|| -> #inner #body)()),
+                    ::core::option::Option::None => ::core::option::Option::None,
+                }
+            }}
+        }
+    };
+    method.block = syn::parse2(block)?;
+    Ok(quote!(#method))
+}
+
+/**
+`T`, for a return type of `Option<T>`.
+*/
+fn option_inner(output: &ReturnType) -> Option<&Type> {
+    let ReturnType::Type(_, ty) = output else { return None };
+    let Type::Path(path) = &**ty else { return None };
+    let last = path.path.segments.last()?;
+    if last.ident != "Option" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &last.arguments else { return None };
+    match args.args.first()? {
+        GenericArgument::Type(inner) if args.args.len() == 1 => Some(inner),
+        _ => None,
+    }
+}
@@ -0,0 +1,33 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+The uncontended lock paths, as standalone functions, for inspecting the code they compile to.
+
+```text
+RUSTFLAGS="--cfg atomiclock_bare" cargo rustc --release --example codegen -- --emit asm
+```
+
+The `codegen` test builds this with `--cfg atomiclock_bare` and checks the assembly.
+*/
+
+use atomiclock_spinlock::Lock;
+
+/**
+Acquires the lock if it's free, bumps the data, and releases it.
+*/
+#[no_mangle]
+#[inline(never)]
+pub fn codegen_try_lock(lock: &Lock<u32>) -> bool {
+    match lock.try_lock() {
+        Some(mut guard) => {
+            *guard += 1;
+            true
+        }
+        None => false,
+    }
+}
+
+fn main() {
+    let lock = Lock::new(0);
+    assert!(codegen_try_lock(&lock));
+    assert_eq!(lock.into_inner(), 1);
+}
@@ -0,0 +1,157 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+A contention stress test, for trying lock variants on your own hardware.
+
+```text
+cargo run --release --example stress -- [--threads N] [--hold NS] [--work NS] [--seconds S] [--variant lock|rwlock] [--reads PERCENT]
+```
+
+Each thread repeatedly acquires the lock, holds it for `--hold` nanoseconds of busy work, releases it, and then
+does `--work` nanoseconds of busy work outside the lock.  At the end, it prints the throughput and the
+distribution of time spent waiting to acquire.
+
+With `--variant rwlock`, `--reads` percent of the acquisitions are shared reads, and the rest are writes.
+*/
+
+use atomiclock_spinlock::{Lock, RwLock};
+use std::hint::black_box;
+use std::process::exit;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Variant {
+    Lock,
+    RwLock,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Config {
+    threads: usize,
+    hold: Duration,
+    work: Duration,
+    duration: Duration,
+    variant: Variant,
+    reads: u32,
+}
+
+fn usage() -> ! {
+    eprintln!("usage: stress [--threads N] [--hold NS] [--work NS] [--seconds S] [--variant lock|rwlock] [--reads PERCENT]");
+    exit(2)
+}
+
+fn parse() -> Config {
+    let mut config = Config {
+        threads: thread::available_parallelism().map_or(4, |n| n.get()),
+        hold: Duration::from_nanos(100),
+        work: Duration::from_nanos(1000),
+        duration: Duration::from_secs(2),
+        variant: Variant::Lock,
+        reads: 90,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(flag) = args.next() {
+        let value = args.next().unwrap_or_else(|| usage());
+        let number = || value.parse::<u64>().unwrap_or_else(|_| usage());
+        match flag.as_str() {
+            "--threads" => config.threads = number() as usize,
+            "--hold" => config.hold = Duration::from_nanos(number()),
+            "--work" => config.work = Duration::from_nanos(number()),
+            "--seconds" => config.duration = Duration::from_secs(number()),
+            "--reads" => config.reads = number().min(100) as u32,
+            "--variant" => {
+                config.variant = match value.as_str() {
+                    "lock" => Variant::Lock,
+                    "rwlock" => Variant::RwLock,
+                    _ => usage(),
+                }
+            }
+            _ => usage(),
+        }
+    }
+    if config.threads == 0 {
+        usage();
+    }
+    config
+}
+
+//busy work, standing in for a critical section or the code between them
+fn busy(duration: Duration) {
+    let start = Instant::now();
+    while start.elapsed() < duration {
+        black_box(());
+    }
+}
+
+//a cheap per-thread generator, to choose between reads and writes
+fn xorshift(state: &mut u32) -> u32 {
+    *state ^= *state << 13;
+    *state ^= *state >> 17;
+    *state ^= *state << 5;
+    *state
+}
+
+/**
+Runs one thread's share of the test, returning how long each acquisition waited, in nanoseconds.
+*/
+fn run(config: Config, t: usize, lock: &Lock<u64>, rwlock: &RwLock<u64>, deadline: Instant) -> Vec<u64> {
+    let mut waits = Vec::new();
+    let mut rng = (t as u32).wrapping_mul(2654435761) | 1;
+    while Instant::now() < deadline {
+        let start = Instant::now();
+        match config.variant {
+            Variant::Lock => {
+                let mut guard = lock.spin_lock();
+                waits.push(start.elapsed().as_nanos() as u64);
+                *guard += 1;
+                busy(config.hold);
+            }
+            Variant::RwLock if xorshift(&mut rng) % 100 < config.reads => {
+                let guard = rwlock.read();
+                waits.push(start.elapsed().as_nanos() as u64);
+                black_box(*guard);
+                busy(config.hold);
+            }
+            Variant::RwLock => {
+                let mut guard = rwlock.write();
+                waits.push(start.elapsed().as_nanos() as u64);
+                *guard += 1;
+                busy(config.hold);
+            }
+        }
+        busy(config.work);
+    }
+    waits
+}
+
+fn percentile(sorted: &[u64], p: f64) -> Duration {
+    let index = ((sorted.len() - 1) as f64 * p).round() as usize;
+    Duration::from_nanos(sorted[index])
+}
+
+fn main() {
+    let config = parse();
+    let lock = Lock::new(0);
+    let rwlock = RwLock::new(0);
+    println!("{config:?}");
+    let deadline = Instant::now() + config.duration;
+    let mut waits: Vec<u64> = thread::scope(|s| {
+        let threads: Vec<_> = (0..config.threads)
+            .map(|t| {
+                let (lock, rwlock) = (&lock, &rwlock);
+                s.spawn(move || run(config, t, lock, rwlock, deadline))
+            })
+            .collect();
+        threads.into_iter().flat_map(|t| t.join().unwrap()).collect()
+    });
+    if waits.is_empty() {
+        println!("no acquisitions");
+        return;
+    }
+    waits.sort_unstable();
+    println!("acquisitions: {} ({:.0}/s)", waits.len(), waits.len() as f64 / config.duration.as_secs_f64());
+    println!("wait p50:   {:?}", percentile(&waits, 0.5));
+    println!("wait p99:   {:?}", percentile(&waits, 0.99));
+    println!("wait p99.9: {:?}", percentile(&waits, 0.999));
+    println!("wait max:   {:?}", Duration::from_nanos(waits[waits.len() - 1]));
+}
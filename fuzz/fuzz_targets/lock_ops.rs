@@ -0,0 +1,114 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Drives a [Lock] through random sequences of operations, while another thread contends for it, and checks
+the lock's state against a model after each step.
+
+Run with `ASAN_OPTIONS=detect_leaks=0 cargo +nightly fuzz run lock_ops`.  (LeakSanitizer misreports the
+standard library's per-thread state as leaked.)
+*/
+#![no_main]
+
+use atomiclock_spinlock::{Clock, Guard, Lock};
+use core::cell::Cell;
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    //increments done by the contending thread
+    contention: u8,
+    ops: Vec<Op>,
+}
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    SpinLock,
+    TryLock,
+    //a deadline, in attempts
+    TimedLock(u8),
+    Write(u32),
+    Release,
+    //dissolves the guard, leaving the lock held
+    IntoRaw,
+    //reconstitutes the dissolved guard, and releases it
+    ForceUnlock,
+}
+
+enum Held<'a> {
+    Free,
+    Guard(Guard<'a, (u32, u64)>),
+    Raw,
+}
+
+//a clock that ticks once per reading, so deadlines are a number of attempts
+struct Attempts(Cell<u64>);
+
+impl Clock for Attempts {
+    type Instant = u64;
+    type Duration = u64;
+    fn now(&self) -> u64 {
+        let now = self.0.get();
+        self.0.set(now + 1);
+        now
+    }
+}
+
+fuzz_target!(|input: Input| {
+    //ours, and the contending thread's
+    let lock = Lock::new((0u32, 0u64));
+    let mut value = 0;
+    let clock = Attempts(Cell::new(0));
+    std::thread::scope(|s| {
+        let lock = &lock;
+        s.spawn(move || {
+            for _ in 0..input.contention {
+                lock.spin_lock().1 += 1;
+            }
+        });
+        let mut held = Held::Free;
+        for op in input.ops {
+            held = match (op, held) {
+                (Op::SpinLock, Held::Free) => Held::Guard(lock.spin_lock()),
+                (Op::TryLock, Held::Free) => lock.try_lock().map_or(Held::Free, Held::Guard),
+                (Op::TimedLock(after), Held::Free) => {
+                    lock.spin_lock_for_with(&clock, after as u64).map_or(Held::Free, Held::Guard)
+                }
+                //we hold the lock, so nothing else can acquire it
+                (Op::SpinLock | Op::TryLock | Op::TimedLock(_), held @ (Held::Guard(_) | Held::Raw)) => {
+                    assert!(lock.try_lock().is_none());
+                    assert!(lock.spin_lock_for_with(&clock, 2).is_none());
+                    assert!(lock.is_locked());
+                    held
+                }
+                (Op::Write(n), Held::Guard(mut guard)) => {
+                    guard.0 = n;
+                    value = n;
+                    Held::Guard(guard)
+                }
+                (Op::Release, Held::Guard(guard)) => {
+                    drop(guard);
+                    Held::Free
+                }
+                (Op::IntoRaw, Held::Guard(guard)) => {
+                    assert_eq!(Guard::into_raw(guard), lock as *const _);
+                    Held::Raw
+                }
+                (Op::ForceUnlock, Held::Raw) => {
+                    let guard = unsafe { lock.guard_from_raw() };
+                    assert_eq!(guard.0, value);
+                    drop(guard);
+                    Held::Free
+                }
+                //not meaningful in this state
+                (_, held) => held,
+            };
+            if let Held::Guard(guard) = &held {
+                assert_eq!(guard.0, value);
+            }
+        }
+        if let Held::Raw = held {
+            drop(unsafe { lock.guard_from_raw() });
+        }
+    });
+    assert_eq!(lock.into_inner(), (value, input.contention as u64));
+});
@@ -0,0 +1,165 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Drives an [RwLock] through random sequences of reads, writes, upgrades and downgrades, while another thread
+writes to it, and checks the lock's state against a model after each step.
+
+Run with `ASAN_OPTIONS=detect_leaks=0 cargo +nightly fuzz run rwlock_ops`; see `lock_ops` for why.
+*/
+#![no_main]
+
+use atomiclock_spinlock::rwlock::{ReadGuard, UpgradableGuard, WriteGuard};
+use atomiclock_spinlock::RwLock;
+use libfuzzer_sys::arbitrary::{self, Arbitrary};
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    //increments done by the writing thread
+    contention: u8,
+    ops: Vec<Op>,
+}
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    Read,
+    TryRead,
+    Write(u32),
+    TryWrite(u32),
+    Upgradable,
+    TryUpgradable,
+    //drops the reader at this index, modulo the number of readers
+    DropRead(u8),
+    DropWrite,
+    DropUpgradable,
+    Upgrade,
+    TryUpgrade,
+    Downgrade,
+    DowngradeToUpgradable,
+    DowngradeUpgradable,
+}
+
+type Data = (u32, u64);
+
+//the guards this thread holds
+#[derive(Default)]
+struct Held<'a> {
+    reads: Vec<ReadGuard<'a, Data>>,
+    write: Option<WriteGuard<'a, Data>>,
+    upgradable: Option<UpgradableGuard<'a, Data>>,
+}
+
+impl Held<'_> {
+    fn is_empty(&self) -> bool {
+        self.reads.is_empty() && self.write.is_none() && self.upgradable.is_none()
+    }
+}
+
+fuzz_target!(|input: Input| {
+    //ours, and the writing thread's
+    let lock = RwLock::new((0u32, 0u64));
+    let mut value = 0;
+    std::thread::scope(|s| {
+        let lock = &lock;
+        s.spawn(move || {
+            for _ in 0..input.contention {
+                lock.write().1 += 1;
+            }
+        });
+        let mut held = Held::default();
+        for op in input.ops {
+            match op {
+                //only block when we hold nothing, or we could wait on ourselves
+                Op::Read if held.is_empty() => held.reads.push(lock.read()),
+                Op::Write(n) if held.is_empty() => {
+                    let mut guard = lock.write();
+                    guard.0 = n;
+                    value = n;
+                    held.write = Some(guard);
+                }
+                Op::Upgradable if held.is_empty() => held.upgradable = Some(lock.upgradable_read()),
+                Op::TryRead => {
+                    let guard = lock.try_read();
+                    if held.write.is_some() {
+                        assert!(guard.is_none());
+                    }
+                    held.reads.extend(guard);
+                }
+                Op::TryWrite(n) => {
+                    let guard = lock.try_write();
+                    if !held.is_empty() {
+                        assert!(guard.is_none());
+                    }
+                    if let Some(mut guard) = guard {
+                        guard.0 = n;
+                        value = n;
+                        held.write = Some(guard);
+                    }
+                }
+                Op::TryUpgradable => {
+                    let guard = lock.try_upgradable_read();
+                    if held.write.is_some() || held.upgradable.is_some() {
+                        assert!(guard.is_none());
+                    }
+                    held.upgradable = held.upgradable.take().or(guard);
+                }
+                Op::DropRead(i) if !held.reads.is_empty() => {
+                    let i = i as usize % held.reads.len();
+                    drop(held.reads.swap_remove(i));
+                }
+                Op::DropWrite => held.write = None,
+                Op::DropUpgradable => held.upgradable = None,
+                //the writing thread never reads, so our own readers are the only ones upgrade could wait on
+                Op::Upgrade if held.reads.is_empty() => {
+                    if let Some(guard) = held.upgradable.take() {
+                        held.write = Some(UpgradableGuard::upgrade(guard));
+                    }
+                }
+                Op::TryUpgrade => {
+                    if let Some(guard) = held.upgradable.take() {
+                        match UpgradableGuard::try_upgrade(guard) {
+                            Ok(guard) => {
+                                assert!(held.reads.is_empty());
+                                held.write = Some(guard);
+                            }
+                            Err(guard) => {
+                                assert!(!held.reads.is_empty());
+                                held.upgradable = Some(guard);
+                            }
+                        }
+                    }
+                }
+                Op::Downgrade => {
+                    if let Some(guard) = held.write.take() {
+                        held.reads.push(WriteGuard::downgrade(guard));
+                    }
+                }
+                Op::DowngradeToUpgradable => {
+                    if let Some(guard) = held.write.take() {
+                        held.upgradable = Some(WriteGuard::downgrade_to_upgradable(guard));
+                    }
+                }
+                Op::DowngradeUpgradable => {
+                    if let Some(guard) = held.upgradable.take() {
+                        held.reads.push(UpgradableGuard::downgrade(guard));
+                    }
+                }
+                //not meaningful in this state
+                _ => {}
+            }
+            assert!(held.write.is_none() || (held.reads.is_empty() && held.upgradable.is_none()));
+            assert_eq!(lock.readers(), held.reads.len());
+            if held.write.is_some() {
+                assert!(lock.is_locked_exclusive());
+            } else if !held.is_empty() {
+                assert!(!lock.is_locked_exclusive());
+            }
+            for guard in &held.reads {
+                assert_eq!(guard.0, value);
+            }
+            if let Some(guard) = &held.upgradable {
+                assert_eq!(guard.0, value);
+            }
+        }
+    });
+    assert_eq!(lock.into_inner(), (value, input.contention as u64));
+});
@@ -0,0 +1,77 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Affinity hints, with the `affinity` feature.
+
+On machines with several last-level caches (multi-socket servers, chiplet CPUs), each time a lock moves
+to a thread in another cache domain, the data it protects follows it across the interconnect.  Threads that
+are pinned can [declare] which domain they're pinned to, and then a contended [Lock](crate::Lock) prefers
+waiters in the domain of its last holder, where the data's cache lines are:  waiters in other domains back off
+[REMOTE_BACKOFF] times as long between attempts, so local waiters usually win the race when the lock is released.
+
+```
+# use atomiclock_spinlock::affinity;
+//after pinning this thread to a core on the second socket
+affinity::declare(Some(1));
+assert_eq!(affinity::declared(), Some(1));
+```
+
+Domains are whatever grouping suits the machine: an LLC domain, a NUMA node, or a core, for threads that
+share one.  Threads that haven't declared one, and locks whose last holder hadn't, wait as usual.
+
+This is a bias, not a queue: a remote waiter still gets the lock if no local one takes it.
+*/
+
+use crate::config::Settings;
+use core::cell::Cell;
+
+/**
+How many times longer waiters in other domains back off, by the cap on a single wait.
+*/
+pub const REMOTE_BACKOFF: u32 = 4;
+
+std::thread_local! {
+    //the declared domain plus one, or 0
+    static DOMAIN: Cell<usize> = const { Cell::new(0) };
+}
+
+/**
+Declares the affinity domain the current thread is pinned to, or that it isn't pinned, with `None`.
+*/
+pub fn declare(domain: Option<usize>) {
+    let token = domain.map_or(0, |d| d.wrapping_add(1));
+    let _ = DOMAIN.try_with(|d| d.set(token));
+}
+
+/**
+The affinity domain the current thread declared, if any.
+*/
+pub fn declared() -> Option<usize> {
+    token().checked_sub(1)
+}
+
+/**
+The current thread's domain, as recorded by locks.  0 is none.
+*/
+#[inline]
+pub(crate) fn token() -> usize {
+    DOMAIN.try_with(Cell::get).unwrap_or(0)
+}
+
+/**
+Whether a lock whose last holder recorded `holder` is in another domain than the current thread.
+*/
+#[inline]
+pub(crate) fn is_remote(holder: usize) -> bool {
+    let current = token();
+    holder != 0 && current != 0 && holder != current
+}
+
+/**
+The settings for waiting on a lock held in another domain.
+*/
+#[inline]
+pub(crate) fn remote(settings: &Settings) -> Settings {
+    let mut settings = *settings;
+    settings.backoff_cap = settings.backoff_cap.saturating_mul(REMOTE_BACKOFF);
+    settings
+}
@@ -0,0 +1,143 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+The instruction used to wait between attempts to acquire a contended lock.
+
+The best choice depends on the hardware:
+
+* On Cortex-M, with the `cortex-m` feature, `wfe` sleeps the core until another core releases a lock
+  (releases signal with `sev`).  This is chosen at compile time.
+* On x86 CPUs with `WAITPKG`, `tpause` waits in a light power-saving state for a short, bounded time.
+  Support is detected the first time a lock is contended.
+* Everywhere else, [core::hint::spin_loop] emits the architecture's spin-loop hint
+  (`pause` on x86, `isb`/`yield` on ARM, etc.)
+
+[relax_instruction] reports which one is in use.
+*/
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+use core::sync::atomic::{AtomicU8, Ordering};
+
+/**
+An instruction for waiting on a contended lock.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum RelaxInstruction {
+    /// [core::hint::spin_loop].
+    SpinLoopHint,
+    /// x86 `tpause`, with a short deadline.
+    Tpause,
+    /// ARM `wfe`.
+    Wfe,
+}
+
+/**
+The instruction used to wait on a contended lock on this machine.
+*/
+pub fn relax_instruction() -> RelaxInstruction {
+    crate::config::read_env();
+    #[cfg(all(feature = "cortex-m", target_arch = "arm"))]
+    return RelaxInstruction::Wfe;
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    return if has_waitpkg() { RelaxInstruction::Tpause } else { RelaxInstruction::SpinLoopHint };
+    #[allow(unreachable_code)]
+    RelaxInstruction::SpinLoopHint
+}
+
+/**
+How long to `tpause`, in TSC ticks.  Short enough to behave like a (cheaper) spin.
+*/
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+const TPAUSE_TICKS: u64 = 1000;
+
+//0: not yet detected, 1: no, 2: yes
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+static WAITPKG: AtomicU8 = AtomicU8::new(0);
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[inline]
+fn has_waitpkg() -> bool {
+    match WAITPKG.load(Ordering::Relaxed) {
+        0 => {
+            let detected = detect_waitpkg();
+            WAITPKG.store(if detected { 2 } else { 1 }, Ordering::Relaxed);
+            detected
+        }
+        n => n == 2,
+    }
+}
+
+/**
+Uses [core::hint::spin_loop] from now on, even where a better instruction is available.
+*/
+#[cfg(feature = "env-tuning")]
+pub(crate) fn force_spin_loop_hint() {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    WAITPKG.store(1, Ordering::Relaxed);
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[cold]
+fn detect_waitpkg() -> bool {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::{__cpuid, __cpuid_count};
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::{__cpuid, __cpuid_count};
+    //SGX enclaves can't execute cpuid, and neither can Miri
+    if cfg!(any(target_env = "sgx", miri)) {
+        return false;
+    }
+    #[allow(unused_unsafe)]
+    unsafe {
+        if __cpuid(0).eax < 7 {
+            return false;
+        }
+        //CPUID.(EAX=07H, ECX=0):ECX.WAITPKG[bit 5]
+        __cpuid_count(7, 0).ecx & (1 << 5) != 0
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[inline]
+fn tpause() {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::_rdtsc;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::_rdtsc;
+    #[allow(unused_unsafe)]
+    let deadline = unsafe { _rdtsc() }.wrapping_add(TPAUSE_TICKS);
+    unsafe {
+        //control 0: the C0.2 state, which saves more power but wakes slightly slower than C0.1
+        core::arch::asm!("tpause {ctl:e}", ctl = in(reg) 0u32, in("eax") deadline as u32, in("edx") (deadline >> 32) as u32,
+                         options(nomem, nostack));
+    }
+}
+
+/**
+Waits a little between attempts to acquire a contended lock.
+*/
+#[inline]
+pub(crate) fn relax() {
+    #[cfg(all(feature = "cortex-m", target_arch = "arm"))]
+    unsafe {
+        core::arch::asm!("wfe", options(nomem, nostack, preserves_flags));
+    }
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    if has_waitpkg() {
+        tpause();
+        return;
+    }
+    #[cfg(not(all(feature = "cortex-m", target_arch = "arm")))]
+    core::hint::spin_loop();
+}
+
+/**
+Signals any cores waiting in [relax] that a lock was released.
+*/
+#[inline]
+pub(crate) fn released() {
+    #[cfg(all(feature = "cortex-m", target_arch = "arm"))]
+    unsafe {
+        core::arch::asm!("sev", options(nomem, nostack, preserves_flags));
+    }
+}
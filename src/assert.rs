@@ -0,0 +1,109 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Assertions about locking, for tests.
+
+[assert_unlocked](crate::assert_unlocked) and [assert_held_by_current](crate::assert_held_by_current) check
+a [Lock](crate::Lock)'s state at key points, such as after an error path that must release it.  Like
+[debug_assert], they're only checked with debug assertions enabled.
+
+[assert_no_live_guards](crate::assert_no_live_guards) checks that no guards for a lock are left alive, such
+as one stashed in a long-lived struct, at the end of a test.
+*/
+
+/**
+Identifies the current thread, for as long as it's alive.  Never 0.
+*/
+#[cfg(all(debug_assertions, feature = "std"))]
+pub(crate) fn current_thread() -> usize {
+    std::thread_local! {
+        static TOKEN: u8 = const { 0 };
+    }
+    TOKEN.with(|token| crate::addr(token))
+}
+
+/**
+Asserts that a [Lock](crate::Lock) is not held, with debug assertions enabled.
+
+```
+# use atomiclock_spinlock::{assert_unlocked, Lock};
+let lock = Lock::new(0);
+drop(lock.spin_lock());
+assert_unlocked!(lock);
+assert_unlocked!(lock, "the guard should have been dropped");
+```
+
+Unlike [Lock::is_locked](crate::Lock::is_locked), this doesn't fail spuriously.
+*/
+#[macro_export]
+macro_rules! assert_unlocked {
+    ($lock:expr $(,)?) => {
+        if cfg!(debug_assertions) {
+            assert!(!($lock).__is_locked_for_assert(), concat!("assertion failed: ", stringify!($lock), " is unlocked"));
+        }
+    };
+    ($lock:expr, $($arg:tt)+) => {
+        if cfg!(debug_assertions) {
+            assert!(!($lock).__is_locked_for_assert(), $($arg)+);
+        }
+    };
+}
+
+/**
+Asserts that a [Lock](crate::Lock) is held by a guard acquired on the current thread, with debug
+assertions enabled.
+
+```
+# use atomiclock_spinlock::{assert_held_by_current, Lock};
+let lock = Lock::new(0);
+let _guard = lock.spin_lock();
+assert_held_by_current!(lock);
+```
+
+The holder is the thread that acquired the lock, even if the guard has since moved, as an
+`OwnedGuard` can.  Holders are only tracked when this crate is built with debug assertions
+and the `std` feature; otherwise this only checks that the lock is held by someone.
+*/
+#[macro_export]
+macro_rules! assert_held_by_current {
+    ($lock:expr $(,)?) => {
+        if cfg!(debug_assertions) {
+            assert!(($lock).__held_by_current_for_assert(),
+                concat!("assertion failed: ", stringify!($lock), " is held by the current thread"));
+        }
+    };
+    ($lock:expr, $($arg:tt)+) => {
+        if cfg!(debug_assertions) {
+            assert!(($lock).__held_by_current_for_assert(), $($arg)+);
+        }
+    };
+}
+
+/**
+Asserts that no guards for a [Lock](crate::Lock) are alive, with debug assertions enabled.
+
+```
+# use atomiclock_spinlock::{assert_no_live_guards, Lock};
+struct Worker<'a> { guard: Option<atomiclock_spinlock::Guard<'a, u32>> }
+let lock = Lock::new(0);
+let mut worker = Worker { guard: Some(lock.spin_lock()) };
+assert_eq!(lock.live_guards(), 1);
+worker.guard = None;
+assert_no_live_guards!(lock);
+```
+
+See [Lock::live_guards](crate::Lock::live_guards) for what counts.
+*/
+#[macro_export]
+macro_rules! assert_no_live_guards {
+    ($lock:expr $(,)?) => {
+        if cfg!(debug_assertions) {
+            let live = ($lock).live_guards();
+            assert!(live == 0, concat!("assertion failed: ", stringify!($lock), " has no live guards, but it has {}"), live);
+        }
+    };
+    ($lock:expr, $($arg:tt)+) => {
+        if cfg!(debug_assertions) {
+            assert!(($lock).live_guards() == 0, $($arg)+);
+        }
+    };
+}
@@ -0,0 +1,353 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+A `Cell`-like type for small values, through the native atomics where they're wide enough.
+*/
+
+use core::cell::UnsafeCell;
+use core::fmt::Debug;
+use core::mem::{size_of, transmute_copy};
+use core::sync::atomic::Ordering;
+#[cfg(target_has_atomic = "8")]
+use core::sync::atomic::AtomicU8;
+#[cfg(target_has_atomic = "16")]
+use core::sync::atomic::AtomicU16;
+#[cfg(target_has_atomic = "32")]
+use core::sync::atomic::AtomicU32;
+#[cfg(target_has_atomic = "64")]
+use core::sync::atomic::AtomicU64;
+use crate::Lock;
+
+/**
+`Copy` types whose values are only initialized bytes, so they can be moved through an integer atomic.
+
+The primitive numbers, `bool`, `char`, `()` and arrays of these implement it.  Implement it for your own
+`#[repr(C)]` or `#[repr(transparent)]` structs of these, to keep them in an [AtomicCellSpin].
+
+# Safety
+Every byte of every value must be initialized: the type has no padding, and no fields, such as
+`MaybeUninit` or unions, that can leave bytes uninitialized.  It mustn't contain pointers or references
+either, whose provenance doesn't survive the trip through an integer.
+*/
+pub unsafe trait Plain: Copy {}
+
+macro_rules! plain {
+    ($($ty:ty),*) => {
+        $(unsafe impl Plain for $ty {})*
+    };
+}
+plain!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, bool, char, ());
+unsafe impl<T: Plain, const N: usize> Plain for [T; N] {}
+
+//the widest atomic, so the value is aligned for whichever one its size needs
+#[repr(align(16))]
+struct Aligned<T>(T);
+
+/**
+A [SpinCell](crate::SpinCell) for small `Copy` values, without the lock.
+
+Values of 1, 2, 4 or 8 bytes are read and written with the matching atomic integer, where the target has
+one, and 16-byte values with `cmpxchg16b` on x86_64 CPUs that have it, so hot values don't bounce a lock
+between cores, and a thread can't be held up by one preempted mid-update.  Other sizes, or targets without
+the atomic, take a lock around each operation, as `SpinCell` does; [AtomicCellSpin::is_lock_free] says
+which it is.
+
+```
+# use atomiclock_spinlock::AtomicCellSpin;
+static RANGE: AtomicCellSpin<[u32; 2]> = AtomicCellSpin::new([0, 0]);
+RANGE.set([10, 20]);
+RANGE.update(|[lo, hi]| [lo + 1, hi]);
+assert_eq!(RANGE.get(), [11, 20]);
+assert_eq!(AtomicCellSpin::<[u32; 2]>::is_lock_free(), cfg!(target_has_atomic = "64"));
+```
+
+Values are stored 16-byte aligned, alongside the lock, whether or not it's needed.
+*/
+pub struct AtomicCellSpin<T> {
+    value: Aligned<UnsafeCell<T>>,
+    lock: Lock<()>,
+}
+
+//the value is only moved in and out whole, atomically or under the lock
+unsafe impl<T: Send> Sync for AtomicCellSpin<T> {}
+
+fn to_bits<T: Plain, B>(value: T) -> B {
+    //same size, and Plain values have no uninitialized bytes
+    unsafe { transmute_copy(&value) }
+}
+
+fn from_bits<T: Plain, B>(bits: B) -> T {
+    //the bits were a T when they were stored
+    unsafe { transmute_copy(&bits) }
+}
+
+/**
+Runs `$op` with `$atomic` bound to the cell's value as an atomic integer of its size, or `$locked` with
+the lock held when there isn't one.
+*/
+macro_rules! atomic {
+    ($cell:expr, |$atomic:ident| $op:expr, locked => $locked:expr) => {{
+        let ptr = $cell.value.0.get();
+        match size_of::<T>() {
+            #[cfg(target_has_atomic = "8")]
+            1 => {
+                let $atomic = unsafe { AtomicU8::from_ptr(ptr.cast()) };
+                $op
+            }
+            #[cfg(target_has_atomic = "16")]
+            2 => {
+                let $atomic = unsafe { AtomicU16::from_ptr(ptr.cast()) };
+                $op
+            }
+            #[cfg(target_has_atomic = "32")]
+            4 => {
+                let $atomic = unsafe { AtomicU32::from_ptr(ptr.cast()) };
+                $op
+            }
+            #[cfg(target_has_atomic = "64")]
+            8 => {
+                let $atomic = unsafe { AtomicU64::from_ptr(ptr.cast()) };
+                $op
+            }
+            #[cfg(target_arch = "x86_64")]
+            16 if wide::supported() => {
+                let $atomic = unsafe { wide::AtomicU128::from_ptr(ptr.cast()) };
+                $op
+            }
+            _ => {
+                let _held = $cell.lock.spin_lock();
+                $locked
+            }
+        }
+    }};
+}
+
+impl<T> AtomicCellSpin<T> {
+    const_fn! {
+        /**
+        Creates a new cell.
+        */
+        pub const fn new(value: T) -> Self {
+            AtomicCellSpin { value: Aligned(UnsafeCell::new(value)), lock: Lock::new(()) }
+        }
+    }
+
+    /**
+    Returns a mutable reference to the value.  No atomics or locking are needed, since the borrow is exclusive.
+*/
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.0.get_mut()
+    }
+
+    /**
+    Consumes the cell, returning the value.
+*/
+    pub fn into_inner(self) -> T {
+        self.value.0.into_inner()
+    }
+}
+
+impl<T: Plain> AtomicCellSpin<T> {
+    /**
+    Whether values of `T` are read and written with atomics, rather than under the lock.
+
+    For 16-byte values, this checks the CPU, the first time it's needed.
+*/
+    pub fn is_lock_free() -> bool {
+        match size_of::<T>() {
+            1 => cfg!(target_has_atomic = "8"),
+            2 => cfg!(target_has_atomic = "16"),
+            4 => cfg!(target_has_atomic = "32"),
+            8 => cfg!(target_has_atomic = "64"),
+            #[cfg(target_arch = "x86_64")]
+            16 => wide::supported(),
+            _ => false,
+        }
+    }
+
+    /**
+    Returns a copy of the value.
+*/
+    pub fn get(&self) -> T {
+        atomic!(self, |atomic| from_bits(atomic.load(Ordering::SeqCst)), locked => unsafe { *self.value.0.get() })
+    }
+
+    /**
+    Stores `value`.
+*/
+    pub fn set(&self, value: T) {
+        self.replace(value);
+    }
+
+    /**
+    Stores `value`, returning the previous value.
+*/
+    pub fn replace(&self, value: T) -> T {
+        atomic!(self, |atomic| from_bits(atomic.swap(to_bits(value), Ordering::SeqCst)),
+                locked => unsafe { core::ptr::replace(self.value.0.get(), value) })
+    }
+
+    /**
+    Stores `new` if the value is `current`, returning the previous value, or the value in place of `current`
+    if it wasn't.
+
+    Values are compared by their bytes, not [PartialEq], so for floats, `0.0` and `-0.0` differ, and a NaN
+    matches the same NaN.
+*/
+    pub fn compare_exchange(&self, current: T, new: T) -> Result<T, T> {
+        atomic!(self, |atomic| {
+            atomic.compare_exchange(to_bits(current), to_bits(new), Ordering::SeqCst, Ordering::SeqCst)
+                .map(from_bits).map_err(from_bits)
+        }, locked => {
+            let value = unsafe { &mut *self.value.0.get() };
+            if bytes(value) == bytes(&current) {
+                Ok(core::mem::replace(value, new))
+            } else {
+                Err(*value)
+            }
+        })
+    }
+
+    /**
+    Replaces the value with `f` of it, atomically with respect to the other operations on the cell.
+
+    Without the lock, `f` runs again if another thread changed the value meanwhile, so it may run more than
+    once, and shouldn't have side effects.
+*/
+    pub fn update(&self, mut f: impl FnMut(T) -> T) {
+        let mut current = self.get();
+        while let Err(actual) = self.compare_exchange(current, f(current)) {
+            current = actual;
+        }
+    }
+}
+
+fn bytes<T: Plain>(value: &T) -> &[u8] {
+    //Plain values are only initialized bytes
+    unsafe { core::slice::from_raw_parts((value as *const T).cast(), size_of::<T>()) }
+}
+
+/**
+16-byte atomics, through `cmpxchg16b`, which x86_64 CPUs have had since shortly after the first ones.
+*/
+#[cfg(target_arch = "x86_64")]
+mod wide {
+    use core::sync::atomic::{AtomicU8, Ordering};
+
+    //0: not yet detected, 1: no, 2: yes
+    static CMPXCHG16B: AtomicU8 = AtomicU8::new(0);
+
+    #[inline]
+    pub(super) fn supported() -> bool {
+        if cfg!(miri) {
+            return false;
+        }
+        if cfg!(target_feature = "cmpxchg16b") {
+            return true;
+        }
+        match CMPXCHG16B.load(Ordering::Relaxed) {
+            0 => {
+                let detected = detect();
+                CMPXCHG16B.store(if detected { 2 } else { 1 }, Ordering::Relaxed);
+                detected
+            }
+            n => n == 2,
+        }
+    }
+
+    #[cold]
+    fn detect() -> bool {
+        //SGX enclaves can't execute cpuid
+        if cfg!(target_env = "sgx") {
+            return false;
+        }
+        //CPUID.01H:ECX.CMPXCHG16B[bit 13]
+        #[allow(unused_unsafe)]
+        unsafe { core::arch::x86_64::__cpuid(1).ecx & (1 << 13) != 0 }
+    }
+
+    /**
+    A 16-byte atomic, with the methods of the smaller ones that [AtomicCellSpin](super::AtomicCellSpin)
+    uses.  `lock cmpxchg16b` is sequentially consistent, so the orderings are all `SeqCst`.
+    */
+    pub(super) struct AtomicU128(*mut u128);
+
+    impl AtomicU128 {
+        /**
+        # Safety
+        `ptr` is 16-byte aligned, only accessed through these while they're in use, and the CPU has
+        `cmpxchg16b`.
+        */
+        pub(super) unsafe fn from_ptr(ptr: *mut u128) -> AtomicU128 {
+            AtomicU128(ptr)
+        }
+
+        pub(super) fn compare_exchange(&self, current: u128, new: u128, _: Ordering, _: Ordering) -> Result<u128, u128> {
+            let (previous_lo, previous_hi): (u64, u64);
+            let exchanged: u8;
+            unsafe {
+                //rbx can't be an operand, so the low half of `new` is swapped into it around the instruction;
+                //LLVM may keep its own values in rbx, even one of ours, so every operand has a fixed register
+                core::arch::asm!(
+                    "xchg rsi, rbx",
+                    "lock cmpxchg16b xmmword ptr [rdi]",
+                    "sete cl",
+                    "mov rbx, rsi",
+                    in("rdi") self.0,
+                    inout("rsi") new as u64 => _,
+                    in("rcx") (new >> 64) as u64,
+                    lateout("cl") exchanged,
+                    inout("rax") current as u64 => previous_lo,
+                    inout("rdx") (current >> 64) as u64 => previous_hi,
+                    options(nostack),
+                );
+            }
+            let previous = u128::from(previous_hi) << 64 | u128::from(previous_lo);
+            if exchanged != 0 { Ok(previous) } else { Err(previous) }
+        }
+
+        pub(super) fn load(&self, order: Ordering) -> u128 {
+            //writes 0 over 0, or fails and reads the value, either way without changing it
+            match self.compare_exchange(0, 0, order, order) {
+                Ok(value) | Err(value) => value,
+            }
+        }
+
+        pub(super) fn swap(&self, new: u128, order: Ordering) -> u128 {
+            let mut current = self.load(order);
+            loop {
+                match self.compare_exchange(current, new, order, order) {
+                    Ok(previous) => return previous,
+                    Err(actual) => current = actual,
+                }
+            }
+        }
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<T: Plain> Clone for AtomicCellSpin<T> {
+    fn clone(&self) -> Self {
+        AtomicCellSpin::new(self.get())
+    }
+}
+
+impl<T: Plain + Debug> Debug for AtomicCellSpin<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("AtomicCellSpin").field(&self.get()).finish()
+    }
+}
+
+impl<T: Default> Default for AtomicCellSpin<T> {
+    fn default() -> Self {
+        AtomicCellSpin::new(T::default())
+    }
+}
+
+impl<T> From<T> for AtomicCellSpin<T> {
+    fn from(value: T) -> Self {
+        AtomicCellSpin::new(value)
+    }
+}
@@ -0,0 +1,238 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Auditing the lock paths for realtime safety, with the `rt-audit` feature.
+
+A thread on a realtime critical path can't afford to allocate, make syscalls, or format, since any of
+these can block for an unbounded time.  Acquiring and releasing a [Lock](crate::Lock) does none of these,
+except with the features listed in [HAZARDS], which allocate, read the clock or the environment, or call out
+to other code.  This module checks both ways:
+
+* At compile time, [assert_realtime_safe!](crate::assert_realtime_safe) in your crate fails the build if any
+  of those features are enabled, by you or by anything else in the dependency graph.
+* At run time, with `std` and debug assertions, install [AuditAlloc] as the global allocator, and it aborts the
+  process with a message if anything allocates while a lock is being acquired or released.
+
+Syscalls and formatting can't be caught at run time, so for those, the compile-time check is everything.
+With `--cfg atomiclock_bare`, the instrumentation features record nothing, so they aren't hazards, but
+nothing is audited at run time either.
+Beyond the features it checks, note that [Lock::spin_lock_warn](crate::Lock::spin_lock_warn) formats and logs
+with `perfwarn`, though [Lock::spin_lock_warn_alloc_free](crate::Lock::spin_lock_warn_alloc_free) doesn't,
+the deadline-based acquisitions read their clock (which for `std`'s is the OS), and async acquisition yields
+to its executor.  None of these are used by [Lock::spin_lock](crate::Lock::spin_lock),
+[Lock::try_lock](crate::Lock::try_lock) or releasing a guard.
+
+# Signal handlers
+
+A signal handler, or the child of `vfork` before it calls `exec`, may only do what's async-signal-safe: no
+allocation, no formatting, and no syscalls beyond the few POSIX lists.  [Lock::try_lock](crate::Lock::try_lock),
+[Lock::spin_lock](crate::Lock::spin_lock) and releasing a guard are safe there, unless a feature in
+[SIGNAL_HAZARDS] is enabled, which is [HAZARDS] and the features that yield the thread while waiting.
+[assert_signal_safe!](crate::assert_signal_safe) checks that at compile time.
+
+That makes the paths themselves safe, but a handler runs on a thread that was interrupted, perhaps while it held
+the very lock the handler wants.  Spinning on a lock the interrupted thread holds never finishes, since the
+holder can't run until the handler returns.  So in a handler, either use [Lock::try_lock](crate::Lock::try_lock),
+and give up if the lock is held, or only spin on locks that are never held with the signal unblocked:
+
+```
+# use atomiclock_spinlock::Lock;
+static PENDING: Lock<u32> = Lock::new(0);
+extern "C" fn on_signal(_: i32) {
+    //the interrupted thread may hold it, so don't wait
+    if let Some(mut pending) = PENDING.try_lock() {
+        *pending += 1;
+    }
+}
+# on_signal(0);
+# assert_eq!(*PENDING.spin_lock(), 1);
+```
+
+The locks keep some bookkeeping in thread-locals with debug assertions.  These are initialized at compile time,
+so reading them needs no allocation or syscall, but in a library loaded with `dlopen`, the first access to a
+thread-local on each thread can allocate.  Touch a lock on each thread before a handler can run on it, or build
+with `--cfg atomiclock_bare` and without debug assertions.
+*/
+
+/**
+The enabled features that make the lock paths allocate, make syscalls, or format, with a reason for each.
+
+Empty if the lock paths are realtime safe.
+*/
+pub const HAZARDS: &[&str] = &[
+    #[cfg(all(feature = "events", not(atomiclock_bare)))]
+    "events: delivers each event to a sink, which may do anything",
+    #[cfg(all(feature = "diagnostics", feature = "std", not(atomiclock_bare)))]
+    "diagnostics with std: reads the clock, and allocates to attribute spinning to threads",
+    #[cfg(all(feature = "perf-counters", not(atomiclock_bare)))]
+    "perf-counters: reads hardware counters with syscalls",
+    #[cfg(feature = "acquired-at")]
+    "acquired-at: reads the clock on every acquisition",
+    #[cfg(feature = "env-tuning")]
+    "env-tuning: reads the environment the first time a lock is contended",
+    #[cfg(feature = "chaos")]
+    "chaos: sleeps and yields at random",
+    #[cfg(feature = "rayon")]
+    "rayon: runs other rayon jobs while waiting",
+    #[cfg(feature = "park")]
+    "park: parks and unparks threads, with syscalls",
+    #[cfg(feature = "std-mutex")]
+    "std-mutex: locks an OS mutex, and sleeps on it, with syscalls",
+    #[cfg(feature = "wasm-wait")]
+    "wasm-wait: waits in memory.atomic.wait32, which blocks in the host",
+];
+
+/**
+The enabled features that make the lock paths unsafe in a signal handler, with a reason for each.
+
+This is [HAZARDS], and the features that yield the thread while waiting, with a syscall.  Empty if
+[Lock::try_lock](crate::Lock::try_lock), [Lock::spin_lock](crate::Lock::spin_lock) and releasing a guard are
+async-signal-safe.
+*/
+pub const SIGNAL_HAZARDS: &[&str] = {
+    const YIELDING: &[&str] = &[
+        #[cfg(feature = "crossbeam")]
+        "crossbeam: yields the thread with sched_yield while waiting",
+    ];
+    const ALL: [&str; HAZARDS.len() + YIELDING.len()] = {
+        let mut all = [""; HAZARDS.len() + YIELDING.len()];
+        let mut i = 0;
+        while i < all.len() {
+            all[i] = if i < HAZARDS.len() { HAZARDS[i] } else { YIELDING[i - HAZARDS.len()] };
+            i += 1;
+        }
+        all
+    };
+    &ALL
+};
+
+/**
+Fails the build if [HAZARDS] isn't empty, that is, if any enabled feature makes the lock paths realtime unsafe.
+
+```ignore
+atomiclock_spinlock::assert_realtime_safe!();
+```
+
+Print [HAZARDS] to see which features are to blame.
+*/
+#[macro_export]
+macro_rules! assert_realtime_safe {
+    () => {
+        const _: () = assert!($crate::audit::HAZARDS.is_empty(),
+            "atomiclock_spinlock has features enabled that allocate, make syscalls or format on the lock paths; see atomiclock_spinlock::audit::HAZARDS");
+    };
+}
+
+/**
+Fails the build if [SIGNAL_HAZARDS] isn't empty, that is, if any enabled feature makes the lock paths unsafe in
+a signal handler.
+
+```ignore
+atomiclock_spinlock::assert_signal_safe!();
+```
+
+This checks the features; see the [module documentation](self) for how to use the locks from a handler.
+*/
+#[macro_export]
+macro_rules! assert_signal_safe {
+    () => {
+        const _: () = assert!($crate::audit::SIGNAL_HAZARDS.is_empty(),
+            "atomiclock_spinlock has features enabled that allocate, make syscalls or format on the lock paths; see atomiclock_spinlock::audit::SIGNAL_HAZARDS");
+    };
+}
+
+#[cfg(all(feature = "std", debug_assertions))]
+std::thread_local! {
+    //how many lock paths the current thread is inside of
+    static DEPTH: core::cell::Cell<usize> = const { core::cell::Cell::new(0) };
+}
+
+/**
+Whether the current thread is acquiring or releasing a lock.
+
+This is only tracked with debug assertions; otherwise it's always false.
+*/
+#[cfg(feature = "std")]
+pub fn in_lock_path() -> bool {
+    #[cfg(debug_assertions)]
+    return DEPTH.try_with(|depth| depth.get() > 0).unwrap_or(false);
+    #[cfg(not(debug_assertions))]
+    false
+}
+
+/**
+Marks the current thread as inside a lock path, until dropped.
+*/
+pub(crate) struct Section {
+    _private: (),
+}
+
+#[inline]
+pub(crate) fn enter() -> Section {
+    #[cfg(all(feature = "std", debug_assertions))]
+    if crate::INSTRUMENTED {
+        let _ = DEPTH.try_with(|depth| depth.set(depth.get() + 1));
+    }
+    Section { _private: () }
+}
+
+impl Drop for Section {
+    #[inline]
+    fn drop(&mut self) {
+        #[cfg(all(feature = "std", debug_assertions))]
+        if crate::INSTRUMENTED {
+            let _ = DEPTH.try_with(|depth| depth.set(depth.get() - 1));
+        }
+    }
+}
+
+/**
+A global allocator that aborts if anything allocates on a lock path, with debug assertions.
+
+Wrap the allocator you'd otherwise use:
+
+```
+use atomiclock_spinlock::audit::AuditAlloc;
+#[global_allocator]
+static ALLOCATOR: AuditAlloc<std::alloc::System> = AuditAlloc(std::alloc::System);
+```
+
+Allocators mustn't unwind, so rather than panicking, it writes a message to stderr and aborts.
+Without debug assertions, it just forwards to the wrapped allocator.
+*/
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+pub struct AuditAlloc<A>(pub A);
+
+#[cfg(feature = "std")]
+impl<A> AuditAlloc<A> {
+    fn check(&self) {
+        if in_lock_path() {
+            use std::io::Write;
+            let _ = std::io::stderr().write_all(b"atomiclock_spinlock: allocated while acquiring or releasing a lock\n");
+            std::process::abort();
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+unsafe impl<A: std::alloc::GlobalAlloc> std::alloc::GlobalAlloc for AuditAlloc<A> {
+    unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+        self.check();
+        self.0.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: std::alloc::Layout) -> *mut u8 {
+        self.check();
+        self.0.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+        self.check();
+        self.0.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: std::alloc::Layout, new_size: usize) -> *mut u8 {
+        self.check();
+        self.0.realloc(ptr, layout, new_size)
+    }
+}
@@ -0,0 +1,144 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+A lock with its spin budget in the type.
+*/
+
+use core::fmt::Debug;
+use crate::raw::RawLock;
+use crate::{Guard, Lock};
+
+/**
+A [Lock] that spins at most `MAX_SPINS` times before giving up.
+
+The budget is a compile-time constant, so each instance gets its own, with no setting to load or branch on.
+This suits latency-critical inner loops, where waiting past a known bound is worse than doing something else.
+
+```
+# use atomiclock_spinlock::BudgetLock;
+static QUEUE: BudgetLock<u32, 100> = BudgetLock::new(0);
+match QUEUE.spin_lock() {
+    Some(mut guard) => *guard += 1,
+    None => { /* contended for more than 100 spins; come back later */ }
+}
+```
+
+It hands out ordinary [Guard]s, and [BudgetLock::as_lock] gives the unbounded [Lock] API.
+Giving up counts as a timeout in the lock's stats.
+*/
+pub struct BudgetLock<T, const MAX_SPINS: usize> {
+    lock: Lock<T>,
+}
+
+impl<T, const MAX_SPINS: usize> BudgetLock<T, MAX_SPINS> {
+    /**
+    The most times [BudgetLock::spin_lock] spins before giving up.
+*/
+    pub const MAX_SPINS: usize = MAX_SPINS;
+
+    const_fn! {
+        /**
+        Creates a new lock.
+        */
+        pub const fn new(data: T) -> Self {
+            BudgetLock { lock: Lock::new(data) }
+        }
+    }
+
+    const_fn! {
+        /**
+        Creates a new lock with a name, which appears in debug output.
+        */
+        pub const fn with_name(data: T, name: &'static str) -> Self {
+            BudgetLock { lock: Lock::with_name(data, name) }
+        }
+    }
+
+    /**
+    Spins until the lock can be acquired, or until it has spun `MAX_SPINS` times.
+
+    On single-threaded targets, gives up immediately if the lock is held, since nobody could release it.
+*/
+    pub fn spin_lock(&self) -> Option<Guard<'_, T>> {
+        let lock = &self.lock;
+        if lock.raw.try_lock() {
+            return Some(lock.acquired());
+        }
+        if crate::SINGLE_THREADED || MAX_SPINS == 0 {
+            #[cfg(feature = "diagnostics")]
+            lock.stats.timed_out();
+            return None;
+        }
+        let _waiting = lock.contended();
+        let mut spins = 0;
+        let acquired = lock.spin(|| {
+            spins += 1;
+            spins >= MAX_SPINS
+        });
+        #[cfg(feature = "diagnostics")]
+        if !acquired {
+            lock.stats.timed_out();
+        }
+        acquired.then(|| lock.acquired())
+    }
+
+    /**
+    No spin; provides access to the lock if available.
+*/
+    pub fn try_lock(&self) -> Option<Guard<'_, T>> {
+        self.lock.try_lock()
+    }
+
+    /**
+    The lock, without the budget, for the rest of the [Lock] API.
+*/
+    pub const fn as_lock(&self) -> &Lock<T> {
+        &self.lock
+    }
+
+    /**
+    Whether the lock is currently held.
+*/
+    pub fn is_locked(&self) -> bool {
+        self.lock.is_locked()
+    }
+
+    /**
+    The data.  No locking is needed, since the borrow is exclusive.
+*/
+    pub fn get_mut(&mut self) -> &mut T {
+        //we have the only reference to the lock, so nobody else holds it
+        unsafe { self.lock.data() }
+    }
+
+    /**
+    Consumes the lock and returns the inner data.
+*/
+    pub fn into_inner(self) -> T {
+        self.lock.into_inner()
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<T: Debug, const MAX_SPINS: usize> Debug for BudgetLock<T, MAX_SPINS> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.lock.try_lock() {
+            Some(guard) => f.debug_tuple("BudgetLock").field(&*guard).finish(),
+            None => f.write_str("BudgetLock(<locked>)"),
+        }
+    }
+}
+
+impl<T: Default, const MAX_SPINS: usize> Default for BudgetLock<T, MAX_SPINS> {
+    fn default() -> Self {
+        BudgetLock::new(T::default())
+    }
+}
+
+impl<T, const MAX_SPINS: usize> From<T> for BudgetLock<T, MAX_SPINS> {
+    fn from(data: T) -> Self {
+        BudgetLock::new(data)
+    }
+}
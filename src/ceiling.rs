@@ -0,0 +1,259 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+The immediate priority ceiling protocol.
+
+When a spinlock protects state shared between tasks of different priorities, a low-priority task holding the
+lock can be preempted by a medium-priority task, while a high-priority task spins waiting for the lock.
+[CeilingLock] bounds this priority inversion by raising the current task's priority to a configured ceiling
+*before* acquiring the lock, and restoring it after the lock is released.  The ceiling should be the highest
+priority of any task that takes the lock.
+
+How priorities are changed depends on the scheduler, and is described by a [Priority] implementation
+you provide.  On Linux, with the `priority-boost` feature, `ThreadPriority` is one for OS threads, which
+shortens hold times under load by making the holder less likely to be preempted.
+*/
+
+use core::cell::UnsafeCell;
+use core::fmt::Debug;
+use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
+use core::ops::{Deref, DerefMut};
+use crate::{DynGuard, DynLock, Guard, Lock};
+
+/**
+Changes the priority of the current thread or task.
+*/
+pub trait Priority {
+    /// A priority level.
+    type Level: Copy;
+    /**
+    Raises the current priority to at least `ceiling`, returning the previous priority.
+
+    If the current priority is already at or above `ceiling`, it should be left alone.
+    */
+    fn raise(ceiling: Self::Level) -> Self::Level;
+    /// Restores the priority returned by the corresponding [Priority::raise].
+    fn restore(previous: Self::Level);
+}
+
+/**
+The priority of the current OS thread, by its nice value, on Linux with the `priority-boost` feature.
+
+Levels are nice values, so a lower level is a higher priority, and a ceiling of `-5` runs the holder at
+nice -5 or better.
+
+```
+# use atomiclock_spinlock::CeilingLock;
+# use atomiclock_spinlock::ceiling::ThreadPriority;
+let lock = CeilingLock::<_, ThreadPriority>::new(0, -5);
+*lock.lock() += 1;
+```
+
+Raising the priority needs `CAP_SYS_NICE`, or a high enough `RLIMIT_NICE`.  Without them, the holder runs at
+its usual priority: boosting is best effort, and failing to boost isn't an error.
+
+The priority is the calling thread's, so [Priority::restore] must run on the thread that called
+[Priority::raise]; otherwise that thread stays boosted, and the other takes its old priority.
+[CeilingGuard] and `DynGuard` can't be sent to another thread, so [CeilingLock] always does this.
+*/
+#[cfg(all(feature = "priority-boost", target_os = "linux"))]
+#[derive(Debug)]
+pub struct ThreadPriority;
+
+#[cfg(all(feature = "priority-boost", target_os = "linux"))]
+impl ThreadPriority {
+    fn current() -> (libc::id_t, i32) {
+        let tid = unsafe { libc::syscall(libc::SYS_gettid) } as libc::id_t;
+        //a thread can always read its own priority, so -1 really is nice -1 rather than an error
+        let nice = unsafe { libc::getpriority(libc::PRIO_PROCESS, tid) };
+        (tid, nice)
+    }
+}
+
+#[cfg(all(feature = "priority-boost", target_os = "linux"))]
+impl Priority for ThreadPriority {
+    type Level = i32;
+    fn raise(ceiling: i32) -> i32 {
+        let (tid, nice) = Self::current();
+        if nice > ceiling {
+            //best effort; without permission, we stay where we are
+            unsafe { libc::setpriority(libc::PRIO_PROCESS, tid, ceiling) };
+        }
+        nice
+    }
+    fn restore(previous: i32) {
+        //the thread that raised its priority, since the guards aren't Send
+        let (tid, nice) = Self::current();
+        if nice != previous {
+            //lowering our own priority is always permitted
+            unsafe { libc::setpriority(libc::PRIO_PROCESS, tid, previous) };
+        }
+    }
+}
+
+/**
+A spinlock that runs its holder at a ceiling priority.
+*/
+pub struct CeilingLock<T, P: Priority> {
+    lock: Lock<T>,
+    ceiling: P::Level,
+    //the prior priority, while held by a DynGuard, which has nowhere else to keep it
+    dyn_previous: UnsafeCell<Option<P::Level>>,
+    priority: PhantomData<P>,
+}
+
+//dyn_previous is only accessed by the lock holder
+unsafe impl<T: Send, P: Priority + Sync> Sync for CeilingLock<T, P> where P::Level: Send + Sync {}
+
+/**
+A guard for [CeilingLock].
+
+Releases the lock, then restores the prior priority, when dropped.
+*/
+#[must_use]
+pub struct CeilingGuard<'a, T, P: Priority> {
+    guard: ManuallyDrop<Guard<'a, T>>,
+    previous: P::Level,
+    //the priority must be restored on the thread or task that raised it
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<T, P: Priority> CeilingLock<T, P> {
+    const_fn! {
+        /**
+        Creates a new lock with the given ceiling.
+*/
+        pub const fn new(data: T, ceiling: P::Level) -> Self {
+            CeilingLock {
+                lock: Lock::new(data),
+                ceiling,
+                dyn_previous: UnsafeCell::new(None),
+                priority: PhantomData,
+            }
+        }
+    }
+
+    /**
+    The lock's ceiling priority.
+*/
+    pub fn ceiling(&self) -> P::Level {
+        self.ceiling
+    }
+
+    /**
+    Raises the priority to the ceiling, then spins until the lock can be acquired.
+*/
+    pub fn lock(&self) -> CeilingGuard<'_, T, P> {
+        let previous = P::raise(self.ceiling);
+        CeilingGuard {
+            guard: ManuallyDrop::new(self.lock.spin_lock()),
+            previous,
+            _not_send: PhantomData,
+        }
+    }
+
+    /**
+    No spin; raises the priority to the ceiling and provides access to the lock if available.
+
+    If the lock is not available, the priority is restored before returning.
+*/
+    pub fn try_lock(&self) -> Option<CeilingGuard<'_, T, P>> {
+        let previous = P::raise(self.ceiling);
+        match self.lock.try_lock() {
+            Some(guard) => Some(CeilingGuard { guard: ManuallyDrop::new(guard), previous, _not_send: PhantomData }),
+            None => {
+                P::restore(previous);
+                None
+            }
+        }
+    }
+
+    /**
+    Consumes the lock and returns the inner data.
+*/
+    pub fn into_inner(self) -> T {
+        self.lock.into_inner()
+    }
+}
+
+impl<T, P: Priority> Drop for CeilingGuard<'_, T, P> {
+    fn drop(&mut self) {
+        //release the lock before we can be preempted by anyone who wants it
+        unsafe { ManuallyDrop::drop(&mut self.guard) };
+        P::restore(self.previous);
+    }
+}
+
+impl<T: Send, P: Priority + Sync> DynLock for CeilingLock<T, P> where P::Level: Send + Sync {
+    fn lock_dyn(&self) -> DynGuard<'_> {
+        let previous = P::raise(self.ceiling);
+        Guard::into_raw(self.lock.spin_lock());
+        unsafe {
+            *self.dyn_previous.get() = Some(previous);
+            DynGuard::new(self)
+        }
+    }
+    fn try_lock_dyn(&self) -> Option<DynGuard<'_>> {
+        let previous = P::raise(self.ceiling);
+        match self.lock.try_lock() {
+            Some(guard) => {
+                Guard::into_raw(guard);
+                unsafe {
+                    *self.dyn_previous.get() = Some(previous);
+                    Some(DynGuard::new(self))
+                }
+            }
+            None => {
+                P::restore(previous);
+                None
+            }
+        }
+    }
+    unsafe fn unlock_dyn(&self) {
+        let previous = (*self.dyn_previous.get()).take();
+        drop(self.lock.guard_from_raw());
+        if let Some(previous) = previous {
+            P::restore(previous);
+        }
+    }
+}
+
+//boilerplate
+//no Default or From, since a ceiling is required
+
+impl<T: Debug, P: Priority> Debug for CeilingLock<T, P> where P::Level: Debug {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("CeilingLock").field("lock", &self.lock).field("ceiling", &self.ceiling).finish()
+    }
+}
+
+impl<T: Debug, P: Priority> Debug for CeilingGuard<'_, T, P> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("CeilingGuard").field("guard", &*self.guard).finish()
+    }
+}
+
+impl<T, P: Priority> Deref for CeilingGuard<'_, T, P> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T, P: Priority> DerefMut for CeilingGuard<'_, T, P> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T, P: Priority> AsRef<T> for CeilingGuard<'_, T, P> {
+    fn as_ref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T, P: Priority> AsMut<T> for CeilingGuard<'_, T, P> {
+    fn as_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
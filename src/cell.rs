@@ -0,0 +1,122 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+A `Cell`-like type that can be shared between threads.
+*/
+
+use core::fmt::Debug;
+use crate::Lock;
+
+/**
+A [Lock] with the ergonomics of [core::cell::Cell].
+
+Each operation takes the lock just long enough to copy the value in or out, so there are no guards to
+hold or drop.  Use it for `Copy` data too big for the native atomics, such as a pair of timestamps or a
+small struct of counters.
+
+```
+# use atomiclock_spinlock::SpinCell;
+static LAST: SpinCell<(u64, u64)> = SpinCell::new((0, 0));
+LAST.set((1, 2));
+LAST.update(|(a, b)| (a + 1, b));
+assert_eq!(LAST.get(), (2, 2));
+```
+*/
+pub struct SpinCell<T> {
+    lock: Lock<T>,
+}
+
+impl<T> SpinCell<T> {
+    const_fn! {
+        /**
+        Creates a new cell.
+        */
+        pub const fn new(value: T) -> Self {
+            SpinCell { lock: Lock::new(value) }
+        }
+    }
+
+    /**
+    Stores `value`.
+*/
+    pub fn set(&self, value: T) {
+        drop(self.replace(value));
+    }
+
+    /**
+    Stores `value`, returning the previous value.
+*/
+    pub fn replace(&self, value: T) -> T {
+        core::mem::replace(&mut *self.lock.spin_lock(), value)
+    }
+
+    /**
+    Returns a mutable reference to the value.  No locking is needed, since the borrow is exclusive.
+*/
+    pub fn get_mut(&mut self) -> &mut T {
+        //we have the only reference to the cell, so nobody else can hold the lock
+        unsafe { self.lock.data() }
+    }
+
+    /**
+    Consumes the cell, returning the value.
+*/
+    pub fn into_inner(self) -> T {
+        self.lock.into_inner()
+    }
+}
+
+impl<T: Copy> SpinCell<T> {
+    /**
+    Returns a copy of the value.
+*/
+    pub fn get(&self) -> T {
+        *self.lock.spin_lock()
+    }
+
+    /**
+    Replaces the value with `f` of it, atomically with respect to the other operations on the cell.
+
+    `f` runs while the lock is held, so it should be short, and must not use this cell.
+*/
+    pub fn update(&self, f: impl FnOnce(T) -> T) {
+        let mut guard = self.lock.spin_lock();
+        *guard = f(*guard);
+    }
+}
+
+impl<T: Default> SpinCell<T> {
+    /**
+    Takes the value, leaving `Default::default()` in its place.
+*/
+    pub fn take(&self) -> T {
+        self.replace(T::default())
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<T: Copy> Clone for SpinCell<T> {
+    fn clone(&self) -> Self {
+        SpinCell::new(self.get())
+    }
+}
+
+impl<T: Copy + Debug> Debug for SpinCell<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("SpinCell").field(&self.get()).finish()
+    }
+}
+
+impl<T: Default> Default for SpinCell<T> {
+    fn default() -> Self {
+        SpinCell::new(T::default())
+    }
+}
+
+impl<T> From<T> for SpinCell<T> {
+    fn from(value: T) -> Self {
+        SpinCell::new(value)
+    }
+}
@@ -0,0 +1,337 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+A bounded multi-producer, single-consumer channel, for targets without `std` or a channel crate.
+
+```
+# use atomiclock_spinlock::channel::Channel;
+# use std::thread;
+static SAMPLES: Channel<u32, 8> = Channel::new();
+let (sender, receiver) = SAMPLES.split().unwrap();
+thread::scope(|s| {
+    for t in 0..2 {
+        let sender = sender.clone();
+        s.spawn(move || {
+            for n in 0..100 {
+                sender.send(t * 100 + n).unwrap();
+            }
+        });
+    }
+    drop(sender);
+    //ends once every sender is gone and the buffer is drained
+    let mut received = 0;
+    while let Ok(_) = receiver.recv() {
+        received += 1;
+    }
+    assert_eq!(received, 200);
+});
+```
+
+The buffer is a ring of `N` elements inline in the [Channel], behind a [Lock], so the channel doesn't
+allocate and can be `static`.  A full channel makes senders wait, and an empty one makes the receiver
+wait.  The blocking methods spin, re-acquiring the lock for each attempt; on single-threaded targets,
+they panic instead of spinning forever.  The async methods wait with [Lock::wait_async_until], so they're
+woken when the other side changes the buffer.
+*/
+
+use core::fmt::Debug;
+use crate::collections::{DebugRing, Ring};
+use crate::{Lock, SpinWait};
+
+struct State<T, const N: usize> {
+    buffer: Ring<T, N>,
+    senders: usize,
+    receiver: bool,
+}
+
+/**
+The storage for a channel.  [Channel::split] it into a [Sender] and a [Receiver].
+*/
+pub struct Channel<T, const N: usize> {
+    lock: Lock<State<T, N>>,
+}
+
+impl<T, const N: usize> Channel<T, N> {
+    const_fn! {
+        /**
+        Creates an empty channel.
+        */
+        pub const fn new() -> Self {
+            Channel { lock: Lock::new(State { buffer: Ring::new(), senders: 0, receiver: false }) }
+        }
+    }
+
+    /**
+    Returns the sending and receiving ends, or `None` if either end from an earlier split is still alive.
+
+    More senders come from cloning the [Sender].  Elements still buffered from an earlier split are
+    received by the new receiver.
+*/
+    pub fn split(&self) -> Option<(Sender<'_, T, N>, Receiver<'_, T, N>)> {
+        let mut state = self.lock.spin_lock();
+        if state.senders > 0 || state.receiver {
+            return None;
+        }
+        state.senders = 1;
+        state.receiver = true;
+        Some((Sender { channel: self }, Receiver { channel: self }))
+    }
+
+    /**
+    The maximum number of buffered elements, `N`.
+*/
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /**
+    The number of buffered elements.  Either end may change it right after this returns.
+*/
+    pub fn len(&self) -> usize {
+        self.lock.spin_lock().buffer.len()
+    }
+
+    /**
+    Whether nothing is buffered.  Either end may change it right after this returns.
+*/
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/**
+The sending end of a [Channel].  Clone it for more producers.
+*/
+pub struct Sender<'a, T, const N: usize> {
+    channel: &'a Channel<T, N>,
+}
+
+impl<T, const N: usize> Sender<'_, T, N> {
+    /**
+    Sends a value if there's room.
+*/
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        let mut state = self.channel.lock.spin_lock();
+        if !state.receiver {
+            return Err(TrySendError::Disconnected(value));
+        }
+        state.buffer.push(value).map_err(TrySendError::Full)
+    }
+
+    /**
+    Sends a value, spinning until there's room, or until the receiver is dropped.
+
+    # Panics
+    On single-threaded targets, panics if the channel is full.  A channel with no capacity is always full.
+*/
+    pub fn send(&self, mut value: T) -> Result<(), SendError<T>> {
+        let mut wait = SpinWait::new();
+        loop {
+            match self.try_send(value) {
+                Ok(()) => return Ok(()),
+                Err(TrySendError::Disconnected(value)) => return Err(SendError(value)),
+                Err(TrySendError::Full(rejected)) => value = rejected,
+            }
+            if crate::SINGLE_THREADED {
+                panic!("Channel is full; on a single-threaded target, spinning on it would never finish");
+            }
+            wait.spin();
+        }
+    }
+
+    /**
+    Sends a value, waiting asynchronously until there's room, or until the receiver is dropped.
+*/
+    pub async fn send_async(&self, value: T) -> Result<(), SendError<T>> {
+        let mut state = self.channel.lock.wait_async_until(|state| !state.receiver || state.buffer.len() < N).await;
+        if !state.receiver {
+            return Err(SendError(value));
+        }
+        let pushed = state.buffer.push(value);
+        debug_assert!(pushed.is_ok());
+        Ok(())
+    }
+}
+
+impl<T, const N: usize> Clone for Sender<'_, T, N> {
+    fn clone(&self) -> Self {
+        self.channel.lock.spin_lock().senders += 1;
+        Sender { channel: self.channel }
+    }
+}
+
+impl<T, const N: usize> Drop for Sender<'_, T, N> {
+    fn drop(&mut self) {
+        self.channel.lock.spin_lock().senders -= 1;
+    }
+}
+
+/**
+The receiving end of a [Channel].
+*/
+pub struct Receiver<'a, T, const N: usize> {
+    channel: &'a Channel<T, N>,
+}
+
+impl<T, const N: usize> Receiver<'_, T, N> {
+    /**
+    Receives the oldest buffered value, if there is one.
+*/
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut state = self.channel.lock.spin_lock();
+        state.buffer.pop().ok_or(if state.senders == 0 { TryRecvError::Disconnected } else { TryRecvError::Empty })
+    }
+
+    /**
+    Receives the oldest value, spinning until there is one, or until every sender is dropped and the
+    buffer is empty.
+
+    # Panics
+    On single-threaded targets, panics if the channel is empty while there are senders.
+*/
+    pub fn recv(&self) -> Result<T, RecvError> {
+        let mut wait = SpinWait::new();
+        loop {
+            match self.try_recv() {
+                Ok(value) => return Ok(value),
+                Err(TryRecvError::Disconnected) => return Err(RecvError),
+                Err(TryRecvError::Empty) => {}
+            }
+            if crate::SINGLE_THREADED {
+                panic!("Channel is empty; on a single-threaded target, spinning on it would never finish");
+            }
+            wait.spin();
+        }
+    }
+
+    /**
+    Receives the oldest value, waiting asynchronously until there is one, or until every sender is dropped
+    and the buffer is empty.
+*/
+    pub async fn recv_async(&self) -> Result<T, RecvError> {
+        let mut state = self.channel.lock.wait_async_until(|state| state.senders == 0 || !state.buffer.is_empty()).await;
+        state.buffer.pop().ok_or(RecvError)
+    }
+}
+
+impl<T, const N: usize> Drop for Receiver<'_, T, N> {
+    fn drop(&mut self) {
+        self.channel.lock.spin_lock().receiver = false;
+    }
+}
+
+/**
+Why [Sender::try_send] failed.  Either way, the value is returned.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrySendError<T> {
+    /**
+    The buffer is full.
+    */
+    Full(T),
+    /**
+    The receiver was dropped.
+    */
+    Disconnected(T),
+}
+
+/**
+Returned by [Sender::send] when the receiver was dropped, with the value that wasn't sent.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+/**
+Why [Receiver::try_recv] failed.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TryRecvError {
+    /**
+    Nothing is buffered, but there are senders.
+    */
+    Empty,
+    /**
+    Nothing is buffered, and every sender was dropped.
+    */
+    Disconnected,
+}
+
+/**
+Returned by [Receiver::recv] when nothing is buffered and every sender was dropped.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RecvError;
+
+/*
+boilerplate
+ */
+
+impl<T> core::fmt::Display for TrySendError<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            TrySendError::Full(_) => f.write_str("sending on a full channel"),
+            TrySendError::Disconnected(_) => f.write_str("sending on a channel whose receiver was dropped"),
+        }
+    }
+}
+
+impl<T> core::fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("sending on a channel whose receiver was dropped")
+    }
+}
+
+impl core::fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            TryRecvError::Empty => f.write_str("receiving on an empty channel"),
+            TryRecvError::Disconnected => f.write_str("receiving on an empty channel whose senders were dropped"),
+        }
+    }
+}
+
+impl core::fmt::Display for RecvError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("receiving on an empty channel whose senders were dropped")
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Debug> std::error::Error for TrySendError<T> {}
+#[cfg(feature = "std")]
+impl<T: Debug> std::error::Error for SendError<T> {}
+#[cfg(feature = "std")]
+impl std::error::Error for TryRecvError {}
+#[cfg(feature = "std")]
+impl std::error::Error for RecvError {}
+
+impl<T: Debug, const N: usize> Debug for Channel<T, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.lock.try_lock() {
+            Some(state) => f
+                .debug_struct("Channel")
+                .field("buffer", &DebugRing(&state.buffer))
+                .field("senders", &state.senders)
+                .field("receiver", &state.receiver)
+                .finish(),
+            None => f.write_str("Channel(<locked>)"),
+        }
+    }
+}
+
+impl<T, const N: usize> Debug for Sender<'_, T, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Sender").finish_non_exhaustive()
+    }
+}
+
+impl<T, const N: usize> Debug for Receiver<'_, T, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Receiver").finish_non_exhaustive()
+    }
+}
+
+impl<T, const N: usize> Default for Channel<T, N> {
+    fn default() -> Self {
+        Channel::new()
+    }
+}
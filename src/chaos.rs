@@ -0,0 +1,112 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Random delays around locking, to shake out races in code that uses the locks.
+
+With the `chaos` feature, every attempt to acquire a [Lock](crate::Lock) or [RwLock](crate::RwLock), and
+every release, may first wait a short, random time: a few spins, or with `std`, a yield to the scheduler.
+This widens the windows in which code around the lock can interleave badly, so a race that shows up once in
+a million runs shows up much sooner.  Run your test suite with the feature enabled, many times over:
+
+```text
+cargo test --features atomiclock_spinlock/chaos
+```
+
+The delays don't change what the locks guarantee, only how often the unlikely schedules happen.  They are
+not meant for production builds.  [set_enabled] turns them off and on at runtime, e.g. around a benchmark.
+*/
+
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+static SEED: AtomicU32 = AtomicU32::new(0x9e37_79b9);
+
+//the longest spin, in spin-loop hints
+const MAX_SPINS: u32 = 256;
+
+/**
+Turns the delays on or off, for all threads.  They start on.
+*/
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/**
+Seeds the generator that chooses the delays.
+
+Threads share the generator, so the delays aren't reproducible across runs of a multithreaded program, but
+a different seed gives a different mix of delays.
+*/
+pub fn seed(seed: u32) {
+    //xorshift has a fixed point at 0
+    SEED.store(seed | 1, Ordering::Relaxed);
+}
+
+//a shared xorshift.  Relaxed, so the generator adds no happens-before edges that could hide a race.
+fn next() -> u32 {
+    let mut x = SEED.load(Ordering::Relaxed);
+    x ^= x << 13;
+    x ^= x >> 17;
+    x ^= x << 5;
+    SEED.store(x, Ordering::Relaxed);
+    x
+}
+
+/**
+Maybe waits a little, before an attempt to acquire or a release.
+*/
+#[inline]
+pub(crate) fn delay() {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let r = next();
+    match r % 8 {
+        //usually no delay, so that locks are still taken back to back sometimes
+        0..=3 => {}
+        4..=6 => {
+            for _ in 0..(r >> 8) % MAX_SPINS {
+                core::hint::spin_loop();
+            }
+        }
+        _ => {
+            #[cfg(feature = "std")]
+            std::thread::yield_now();
+            #[cfg(not(feature = "std"))]
+            for _ in 0..MAX_SPINS * 4 {
+                core::hint::spin_loop();
+            }
+        }
+    }
+}
+
+/**
+[atomiclock::AtomicLock], delaying before each attempt and release.
+*/
+#[cfg(not(feature = "std-mutex"))]
+pub(crate) struct AtomicLock<T>(atomiclock::AtomicLock<T>);
+
+#[cfg(not(feature = "std-mutex"))]
+impl<T> AtomicLock<T> {
+    pub(crate) const fn new(data: T) -> Self {
+        AtomicLock(atomiclock::AtomicLock::new(data))
+    }
+
+    #[inline]
+    pub(crate) fn lock(&self) -> Option<atomiclock::Guard<'_, T>> {
+        delay();
+        self.0.lock()
+    }
+
+    #[inline]
+    pub(crate) fn unlock(&self) {
+        delay();
+        self.0.unlock()
+    }
+
+    /**
+    The lock without the delays.
+    */
+    pub(crate) fn raw(&self) -> &atomiclock::AtomicLock<T> {
+        &self.0
+    }
+}
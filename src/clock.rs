@@ -0,0 +1,52 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Time sources for deadline-based locking.
+
+[Lock::spin_lock_until_with](crate::Lock::spin_lock_until_with) and
+[Lock::spin_lock_for_with](crate::Lock::spin_lock_for_with) accept any [Clock], so deadlines
+work without the standard library, e.g. on a hardware cycle counter or tick timer.  [CycleClock] reads the
+CPU's, on x86 and AArch64.
+
+With the `test-clock` feature, `StdClock` reads virtual time instead of the system clock; see `manual`.
+*/
+
+#[cfg(feature = "test-clock")]
+pub mod manual;
+#[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
+mod cycles;
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
+pub use cycles::CycleClock;
+
+use core::ops::Add;
+
+/**
+A monotonic time source.
+*/
+pub trait Clock {
+    /// A point in time.  Must not decrease between calls to [Clock::now].
+    type Instant: PartialOrd + Add<Self::Duration, Output = Self::Instant>;
+    /// An amount of time, which can be added to [Clock::Instant].
+    type Duration;
+    /// The current time.
+    fn now(&self) -> Self::Instant;
+}
+
+/**
+The standard library's monotonic clock, [std::time::Instant].
+*/
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct StdClock;
+
+#[cfg(feature = "std")]
+impl Clock for StdClock {
+    type Instant = std::time::Instant;
+    type Duration = std::time::Duration;
+    fn now(&self) -> std::time::Instant {
+        #[cfg(feature = "test-clock")]
+        return manual::now();
+        #[allow(unreachable_code)]
+        std::time::Instant::now()
+    }
+}
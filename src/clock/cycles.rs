@@ -0,0 +1,100 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Deadlines on the CPU's cycle counter.
+*/
+
+use super::Clock;
+
+/**
+The CPU's cycle counter: `rdtsc` on x86, and the virtual counter `cntvct_el0` on AArch64.
+
+Reading it takes a handful of cycles, with no syscall or vDSO call, for bounded waits in kernels and
+kernel-bypass networking, where even `clock_gettime` costs too much.  Instants and durations are counts of
+ticks:
+
+```
+# use atomiclock_spinlock::{clock::CycleClock, Lock};
+let lock = Lock::new(0);
+let held = lock.spin_lock();
+//about a microsecond on a 1 GHz counter
+assert!(lock.spin_lock_for_with(&CycleClock, 1_000).is_none());
+```
+
+Use [CycleClock::frequency] to convert ticks to time.  On x86 this assumes an invariant TSC, which ticks at a
+constant rate on every core, as on any x86 from the last fifteen years; check for the `invariant_tsc` CPU flag.
+On AArch64 the counter always ticks at a constant rate.  It doesn't tick at the rate of the core's clock on
+either, so it doesn't slow down with frequency scaling.
+
+Available on x86, x86_64 and AArch64.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct CycleClock;
+
+impl CycleClock {
+    /**
+    How many times a second the counter ticks, if the hardware says.
+
+    AArch64 reports it in `cntfrq_el0`.  x86 doesn't report it in a way that can be relied on, so with `std`,
+    measure it with [CycleClock::calibrate] instead.
+    */
+    pub fn frequency() -> Option<u64> {
+        #[cfg(target_arch = "aarch64")]
+        {
+            let frequency: u64;
+            unsafe {
+                core::arch::asm!("mrs {}, cntfrq_el0", out(reg) frequency, options(nomem, nostack, preserves_flags));
+            }
+            return (frequency != 0).then_some(frequency);
+        }
+        #[allow(unreachable_code)]
+        None
+    }
+
+    /**
+    Measures how many times a second the counter ticks, against the standard library's clock.
+
+    This spins for about a millisecond, so call it once, at startup, and keep the result.  It's only as
+    precise as the measurement, typically to within a fraction of a percent.  Requires `std`.
+
+    ```
+    # use atomiclock_spinlock::clock::CycleClock;
+    let ticks_per_second = CycleClock::calibrate();
+    let ticks_per_microsecond = ticks_per_second / 1_000_000;
+    assert!(ticks_per_second > 0);
+    ```
+    */
+    #[cfg(feature = "std")]
+    pub fn calibrate() -> u64 {
+        use std::time::{Duration, Instant};
+        let (start, ticks) = (Instant::now(), CycleClock.now());
+        while start.elapsed() < Duration::from_millis(1) {
+            core::hint::spin_loop();
+        }
+        let (elapsed, ticks) = (start.elapsed(), CycleClock.now().wrapping_sub(ticks));
+        (u128::from(ticks) * 1_000_000_000 / elapsed.as_nanos().max(1)) as u64
+    }
+}
+
+impl Clock for CycleClock {
+    type Instant = u64;
+    type Duration = u64;
+    #[inline]
+    fn now(&self) -> u64 {
+        #[cfg(target_arch = "x86")]
+        use core::arch::x86::_rdtsc;
+        #[cfg(target_arch = "x86_64")]
+        use core::arch::x86_64::_rdtsc;
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        #[allow(unused_unsafe)]
+        return unsafe { _rdtsc() };
+        #[cfg(target_arch = "aarch64")]
+        {
+            let ticks: u64;
+            //isb, so the read isn't taken early, ahead of the attempt it's timing
+            unsafe {
+                core::arch::asm!("isb", "mrs {}, cntvct_el0", out(reg) ticks, options(nomem, nostack, preserves_flags));
+            }
+            ticks
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Manual time for [StdClock](super::StdClock), so tests of deadline behavior are fast and deterministic.
+
+With the `test-clock` feature, [StdClock](super::StdClock) no longer reads the system clock.  Each thread
+has its own virtual time, which starts at the thread's first reading and only moves when the thread moves
+it: explicitly with [advance], or by a tick on every reading (see [set_tick]).  This covers everything that takes a
+[std::time::Instant] or [std::time::Duration] deadline, including [Lock::spin_lock_for](crate::Lock::spin_lock_for)
+and [Lock::lock_async_timeout](crate::Lock::lock_async_timeout).
+
+```
+# use atomiclock_spinlock::{clock::manual, Lock};
+# use std::time::Duration;
+let lock = Lock::new(0);
+let _held = lock.spin_lock();
+//each attempt to acquire takes a virtual millisecond, so this gives up after about a thousand
+manual::set_tick(Duration::from_millis(1));
+assert!(lock.spin_lock_for(Duration::from_secs(1)).is_none());
+assert!(manual::elapsed() >= Duration::from_secs(1));
+```
+
+This is meant for test builds, e.g. enabled for your dev-dependency on this crate.  In any other build,
+deadlines get very long: the default tick is a nanosecond.
+*/
+
+use core::cell::Cell;
+use std::time::{Duration, Instant};
+
+//so that time never stands still, and a deadline of now is passed by the next reading
+const DEFAULT_TICK: Duration = Duration::from_nanos(1);
+
+struct Time {
+    //the real time of the thread's first reading
+    start: Cell<Option<Instant>>,
+    elapsed: Cell<Duration>,
+    tick: Cell<Duration>,
+}
+
+std::thread_local! {
+    static TIME: Time = const {
+        Time { start: Cell::new(None), elapsed: Cell::new(Duration::ZERO), tick: Cell::new(DEFAULT_TICK) }
+    };
+}
+
+/**
+The current thread's virtual time, then advances it by the tick.
+*/
+pub(crate) fn now() -> Instant {
+    TIME.with(|time| {
+        let start = time.start.get().unwrap_or_else(Instant::now);
+        time.start.set(Some(start));
+        let now = start + time.elapsed.get();
+        time.elapsed.set(time.elapsed.get() + time.tick.get());
+        now
+    })
+}
+
+/**
+Moves the current thread's virtual time forward.
+*/
+pub fn advance(duration: Duration) {
+    TIME.with(|time| time.elapsed.set(time.elapsed.get() + duration));
+}
+
+/**
+Sets how far the current thread's virtual time moves forward on each reading.  The default is a nanosecond.
+
+A spinning deadline reads the clock once per attempt, so with a tick, it gives up after a number of attempts
+instead of a length of real time.
+*/
+pub fn set_tick(tick: Duration) {
+    TIME.with(|time| time.tick.set(tick));
+}
+
+/**
+How far the current thread's virtual time has moved since its first reading.
+*/
+pub fn elapsed() -> Duration {
+    TIME.with(|time| time.elapsed.get())
+}
+
+/**
+Puts the current thread's virtual time back to its first reading, with the default tick.
+*/
+pub fn reset() {
+    TIME.with(|time| {
+        time.elapsed.set(Duration::ZERO);
+        time.tick.set(DEFAULT_TICK);
+    });
+}
@@ -0,0 +1,57 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Collections that take the lock internally.
+
+Each operation acquires the lock, does its work, and releases it, so callers never see a guard.  Where a
+sequence of operations must happen together, collections have a method that does them under one
+acquisition, or a `lock` method that returns the guard.
+*/
+
+#[cfg(feature = "alloc")]
+mod accumulator;
+mod bits;
+mod counter;
+#[cfg(feature = "alloc")]
+mod heap;
+#[cfg(feature = "std")]
+mod interner;
+mod log;
+#[cfg(feature = "std")]
+mod lru;
+#[cfg(feature = "std")]
+mod map;
+#[cfg(feature = "std")]
+mod memo;
+mod pool;
+mod queue;
+mod ring;
+#[cfg(feature = "alloc")]
+mod slab;
+mod stack;
+#[cfg(feature = "alloc")]
+mod vec;
+
+#[cfg(feature = "alloc")]
+pub use accumulator::{Accumulator, Buffer};
+pub use bits::BitAllocator;
+pub use counter::ShardedCounter;
+#[cfg(feature = "alloc")]
+pub use heap::SpinHeap;
+#[cfg(feature = "std")]
+pub use interner::{Interner, Symbol};
+pub use log::AppendLog;
+#[cfg(feature = "std")]
+pub use lru::LruCache;
+#[cfg(feature = "std")]
+pub use map::SpinMap;
+#[cfg(feature = "std")]
+pub use memo::Memo;
+pub use pool::{Pool, PoolGuard};
+pub use queue::SpinQueue;
+pub(crate) use queue::{DebugRing, Ring};
+pub use ring::{Snapshot, SpinRing};
+#[cfg(feature = "alloc")]
+pub use slab::{Slab, SlabKey};
+pub use stack::{PopAll, SpinStack};
+#[cfg(feature = "alloc")]
+pub use vec::SpinVec;
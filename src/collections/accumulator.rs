@@ -0,0 +1,142 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+
+use alloc::vec::Vec;
+use core::fmt::Debug;
+use crate::{Guard, Lock};
+
+/**
+Shared state that threads update in batches, to take its [Lock] less often.
+
+Each thread adds to its own [Buffer], which merges everything it holds into the shared state when it
+fills up, when [Buffer::flush] is called, and when it's dropped.  Until then, the shared state doesn't
+include the buffered values, so it can lag behind by up to a batch per thread.
+
+```
+# use atomiclock_spinlock::collections::Accumulator;
+# use std::thread;
+let hits = Accumulator::new(0u64, |total: &mut u64, n: u64| *total += n);
+thread::scope(|s| {
+    for _ in 0..4 {
+        s.spawn(|| {
+            let mut buffer = hits.buffer(64);
+            for _ in 0..1000 {
+                buffer.add(1);
+            }
+            //the remainder is merged when the buffer drops
+        });
+    }
+});
+assert_eq!(hits.into_inner(), 4000);
+```
+
+Requires the `alloc` feature.
+*/
+pub struct Accumulator<T, Acc, F = fn(&mut Acc, T)> {
+    shared: Lock<Acc>,
+    merge: F,
+    _values: core::marker::PhantomData<fn(T)>,
+}
+
+impl<T, Acc, F: Fn(&mut Acc, T)> Accumulator<T, Acc, F> {
+    const_fn! {
+        /**
+        Creates an accumulator, which merges each value into `initial` with `merge`.
+        */
+        pub const fn new(initial: Acc, merge: F) -> Self {
+            Accumulator { shared: Lock::new(initial), merge, _values: core::marker::PhantomData }
+        }
+    }
+
+    /**
+    A buffer that merges its values into the shared state in batches of `batch`.
+
+    Buffers are meant to be per-thread, but nothing stops a thread from having several.
+*/
+    pub fn buffer(&self, batch: usize) -> Buffer<'_, T, Acc, F> {
+        Buffer { accumulator: self, values: Vec::with_capacity(batch), batch: batch.max(1) }
+    }
+
+    /**
+    Merges one value right away, without buffering.
+*/
+    pub fn add(&self, value: T) {
+        (self.merge)(&mut self.shared.spin_lock(), value);
+    }
+
+    /**
+    Acquires the shared state, which includes every flushed value.
+*/
+    pub fn lock(&self) -> Guard<'_, Acc> {
+        self.shared.spin_lock()
+    }
+
+    /**
+    Consumes the accumulator, returning the shared state.  Every buffer has been dropped, and so flushed.
+*/
+    pub fn into_inner(self) -> Acc {
+        self.shared.into_inner()
+    }
+}
+
+/**
+One thread's pending values for an [Accumulator].
+*/
+pub struct Buffer<'a, T, Acc, F: Fn(&mut Acc, T) = fn(&mut Acc, T)> {
+    accumulator: &'a Accumulator<T, Acc, F>,
+    values: Vec<T>,
+    batch: usize,
+}
+
+impl<T, Acc, F: Fn(&mut Acc, T)> Buffer<'_, T, Acc, F> {
+    /**
+    Buffers a value, flushing if that fills the batch.
+*/
+    pub fn add(&mut self, value: T) {
+        self.values.push(value);
+        if self.values.len() >= self.batch {
+            self.flush();
+        }
+    }
+
+    /**
+    Merges the buffered values into the shared state, under one acquisition.
+*/
+    pub fn flush(&mut self) {
+        if self.values.is_empty() {
+            return;
+        }
+        let mut shared = self.accumulator.shared.spin_lock();
+        for value in self.values.drain(..) {
+            (self.accumulator.merge)(&mut shared, value);
+        }
+    }
+
+    /**
+    The number of values waiting to be flushed.
+*/
+    pub fn pending(&self) -> usize {
+        self.values.len()
+    }
+}
+
+impl<T, Acc, F: Fn(&mut Acc, T)> Drop for Buffer<'_, T, Acc, F> {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<T, Acc: Debug, F> Debug for Accumulator<T, Acc, F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("Accumulator").field(&self.shared).finish()
+    }
+}
+
+impl<T: Debug, Acc, F: Fn(&mut Acc, T)> Debug for Buffer<'_, T, Acc, F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Buffer").field("pending", &self.values).field("batch", &self.batch).finish()
+    }
+}
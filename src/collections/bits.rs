@@ -0,0 +1,119 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+
+use core::fmt::Debug;
+use crate::Lock;
+
+struct Bits<const N: usize> {
+    words: [u64; N],
+    //a word that may have a free bit; every word before it was full when it was last scanned
+    hint: usize,
+}
+
+/**
+Hands out small indices, such as DMA descriptor slots or handle numbers, from a bitmap behind a [Lock].
+
+The bitmap is `N` 64-bit words, so there are `64 * N` indices.  Allocating scans a word at a time,
+starting from where the last allocation left off, so it's a few instructions when indices are free.
+
+```
+# use atomiclock_spinlock::collections::BitAllocator;
+//128 descriptor slots
+static SLOTS: BitAllocator<2> = BitAllocator::new();
+let a = SLOTS.allocate().unwrap();
+let b = SLOTS.allocate().unwrap();
+assert_ne!(a, b);
+SLOTS.free(a);
+//the lowest free index goes first
+assert_eq!(SLOTS.allocate(), Some(a));
+assert_eq!(SLOTS.allocated(), 2);
+```
+*/
+pub struct BitAllocator<const N: usize> {
+    lock: Lock<Bits<N>>,
+}
+
+impl<const N: usize> BitAllocator<N> {
+    /**
+    The number of indices, `64 * N`.
+    */
+    pub const CAPACITY: usize = 64 * N;
+
+    const_fn! {
+        /**
+        Creates an allocator with every index free.
+        */
+        pub const fn new() -> Self {
+            BitAllocator { lock: Lock::new(Bits { words: [0; N], hint: 0 }) }
+        }
+    }
+
+    /**
+    Allocates the lowest free index, or returns `None` if they're all allocated.
+*/
+    pub fn allocate(&self) -> Option<usize> {
+        let mut bits = self.lock.spin_lock();
+        let start = bits.hint;
+        for w in start..N {
+            let word = bits.words[w];
+            if word != u64::MAX {
+                let bit = word.trailing_ones() as usize;
+                bits.words[w] |= 1 << bit;
+                bits.hint = w;
+                return Some(w * 64 + bit);
+            }
+        }
+        bits.hint = N;
+        None
+    }
+
+    /**
+    Frees an index, so it can be allocated again.
+
+    # Panics
+    If the index isn't allocated, which would mean it was freed twice, or never allocated.
+*/
+    pub fn free(&self, index: usize) {
+        let (w, bit) = (index / 64, index % 64);
+        let mut bits = self.lock.spin_lock();
+        assert!(w < N && bits.words[w] & 1 << bit != 0, "BitAllocator index {index} isn't allocated");
+        bits.words[w] &= !(1 << bit);
+        bits.hint = bits.hint.min(w);
+    }
+
+    /**
+    Whether the index is allocated.
+*/
+    pub fn is_allocated(&self, index: usize) -> bool {
+        let (w, bit) = (index / 64, index % 64);
+        w < N && self.lock.spin_lock().words[w] & 1 << bit != 0
+    }
+
+    /**
+    The number of allocated indices.  Another thread may change it right after this returns.
+*/
+    pub fn allocated(&self) -> usize {
+        self.lock.spin_lock().words.iter().map(|word| word.count_ones() as usize).sum()
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<const N: usize> Debug for BitAllocator<N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.lock.try_lock() {
+            Some(bits) => {
+                let allocated: u32 = bits.words.iter().map(|word| word.count_ones()).sum();
+                f.debug_struct("BitAllocator").field("capacity", &Self::CAPACITY).field("allocated", &allocated).finish()
+            }
+            None => f.write_str("BitAllocator(<locked>)"),
+        }
+    }
+}
+
+impl<const N: usize> Default for BitAllocator<N> {
+    fn default() -> Self {
+        BitAllocator::new()
+    }
+}
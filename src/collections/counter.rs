@@ -0,0 +1,135 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+
+use core::fmt::Debug;
+use crate::Lock;
+
+//a cache line, or two on targets that prefetch in pairs, so neighboring shards don't share one
+#[repr(align(128))]
+struct Padded<T>(T);
+
+/**
+A counter split into `N` shards, each behind its own [Lock] on its own cache line.
+
+Every thread increments one shard, so threads on different cores don't fight over a cache line the way
+they do with a single `Lock<u64>`.  [ShardedCounter::sum] adds up the shards.
+
+```
+# use atomiclock_spinlock::collections::ShardedCounter;
+# use std::thread;
+static REQUESTS: ShardedCounter = ShardedCounter::new();
+thread::scope(|s| {
+    for _ in 0..4 {
+        s.spawn(|| {
+            for _ in 0..1000 {
+                REQUESTS.add(1);
+            }
+        });
+    }
+});
+assert_eq!(REQUESTS.sum(), 4000);
+```
+
+With `std`, each thread is assigned a shard on its first increment, round-robin.  Without it, pick
+shards yourself with [ShardedCounter::add_to], say by core number.
+
+Additions wrap on overflow.
+*/
+pub struct ShardedCounter<const N: usize = 16> {
+    shards: [Padded<Lock<u64>>; N],
+}
+
+impl<const N: usize> ShardedCounter<N> {
+    #[cfg(not(loom))]
+    #[allow(clippy::declare_interior_mutable_const)] //only used to initialize the array
+    const ZERO: Padded<Lock<u64>> = Padded(Lock::new(0));
+
+    /**
+    Creates a counter at zero.
+*/
+    #[cfg(not(loom))]
+    pub const fn new() -> Self {
+        ShardedCounter { shards: [Self::ZERO; N] }
+    }
+
+    /**
+    Creates a counter at zero.
+*/
+    //loom's locks can't be created in a const, so there's no constant to repeat
+    #[cfg(loom)]
+    pub fn new() -> Self {
+        ShardedCounter { shards: core::array::from_fn(|_| Padded(Lock::new(0))) }
+    }
+
+    /**
+    Adds `n` to the shard for the current thread.
+
+    # Panics
+    If the counter has no shards.
+*/
+    #[cfg(feature = "std")]
+    pub fn add(&self, n: u64) {
+        self.add_to(this_thread(), n);
+    }
+
+    /**
+    Adds `n` to shard `shard`, modulo `N`.
+
+    # Panics
+    If the counter has no shards.
+*/
+    pub fn add_to(&self, shard: usize, n: u64) {
+        let mut count = self.shards[shard % N].0.spin_lock();
+        *count = count.wrapping_add(n);
+    }
+
+    /**
+    The total of the shards.  They're read one at a time, so with concurrent additions, the total might
+    not match the counter at any single moment.
+*/
+    pub fn sum(&self) -> u64 {
+        self.shards.iter().fold(0, |sum, shard| sum.wrapping_add(*shard.0.spin_lock()))
+    }
+
+    /**
+    Sets every shard to zero, returning the total they held.  As with [ShardedCounter::sum], additions
+    during the reset are either in the total or left in the counter, never lost.
+*/
+    pub fn reset(&self) -> u64 {
+        self.shards.iter().fold(0, |sum, shard| sum.wrapping_add(core::mem::take(&mut *shard.0.spin_lock())))
+    }
+}
+
+//the current thread's shard, before reducing modulo the shard count
+#[cfg(feature = "std")]
+fn this_thread() -> usize {
+    use core::cell::Cell;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    std::thread_local! {
+        static SHARD: Cell<Option<usize>> = const { Cell::new(None) };
+    }
+    SHARD.with(|shard| match shard.get() {
+        Some(assigned) => assigned,
+        None => {
+            let assigned = NEXT.fetch_add(1, Ordering::Relaxed);
+            shard.set(Some(assigned));
+            assigned
+        }
+    })
+}
+
+/*
+boilerplate
+ */
+
+impl<const N: usize> Debug for ShardedCounter<N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("ShardedCounter").field(&self.sum()).finish()
+    }
+}
+
+impl<const N: usize> Default for ShardedCounter<N> {
+    fn default() -> Self {
+        ShardedCounter::new()
+    }
+}
@@ -0,0 +1,132 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+
+use alloc::collections::BinaryHeap;
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+use core::fmt::Debug;
+use crate::{Guard, Lock};
+
+/**
+A min-heap behind a [Lock], for priority queues and timers.
+
+[SpinHeap::pop_if] looks at the smallest element and pops it only if it qualifies, under one
+acquisition, so no other thread can take or replace it between the check and the pop.
+
+```
+# use atomiclock_spinlock::collections::SpinHeap;
+let timers = SpinHeap::new();
+timers.push((30, "flush"));
+timers.push((10, "retry"));
+timers.push((20, "poll"));
+assert_eq!(timers.peek_cloned(), Some((10, "retry")));
+let now = 25;
+//pops only the timers that are due
+assert_eq!(timers.pop_if(|&(at, _)| at <= now), Some((10, "retry")));
+assert_eq!(timers.pop_if(|&(at, _)| at <= now), Some((20, "poll")));
+assert_eq!(timers.pop_if(|&(at, _)| at <= now), None);
+assert_eq!(timers.len(), 1);
+```
+
+Requires the `alloc` feature.
+*/
+pub struct SpinHeap<T> {
+    lock: Lock<BinaryHeap<Reverse<T>>>,
+}
+
+impl<T: Ord> SpinHeap<T> {
+    /**
+    Creates an empty heap.  Doesn't allocate.
+*/
+    //not const, since BinaryHeap::new isn't until Rust 1.80
+    pub fn new() -> Self {
+        SpinHeap { lock: Lock::new(BinaryHeap::new()) }
+    }
+
+    /**
+    Adds an element.
+*/
+    pub fn push(&self, value: T) {
+        self.lock.spin_lock().push(Reverse(value));
+    }
+
+    /**
+    Removes the smallest element, if there is one.
+*/
+    pub fn pop_min(&self) -> Option<T> {
+        self.lock.spin_lock().pop().map(|Reverse(value)| value)
+    }
+
+    /**
+    Removes the smallest element if `condition` returns true for it.
+
+    `condition` runs while the lock is held, so it should be short, and must not use this heap.
+*/
+    pub fn pop_if(&self, condition: impl FnOnce(&T) -> bool) -> Option<T> {
+        let mut heap = self.lock.spin_lock();
+        if !condition(&heap.peek()?.0) {
+            return None;
+        }
+        heap.pop().map(|Reverse(value)| value)
+    }
+
+    /**
+    Removes every element, smallest first.
+*/
+    pub fn drain_sorted(&self) -> Vec<T> {
+        let heap = core::mem::take(&mut *self.lock.spin_lock());
+        heap.into_sorted_vec().into_iter().rev().map(|Reverse(value)| value).collect()
+    }
+
+    /**
+    The number of elements.  Another thread may change it right after this returns.
+*/
+    pub fn len(&self) -> usize {
+        self.lock.spin_lock().len()
+    }
+
+    /**
+    Whether there are no elements.  Another thread may change it right after this returns.
+*/
+    pub fn is_empty(&self) -> bool {
+        self.lock.spin_lock().is_empty()
+    }
+
+    /**
+    Acquires the lock, for several operations at once.  Elements are wrapped in [Reverse], so the
+    heap's maximum is the smallest element.
+*/
+    pub fn lock(&self) -> Guard<'_, BinaryHeap<Reverse<T>>> {
+        self.lock.spin_lock()
+    }
+}
+
+impl<T: Ord + Clone> SpinHeap<T> {
+    /**
+    Returns a clone of the smallest element, if there is one.
+*/
+    pub fn peek_cloned(&self) -> Option<T> {
+        self.lock.spin_lock().peek().map(|Reverse(value)| value.clone())
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<T: Debug> Debug for SpinHeap<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("SpinHeap").field(&self.lock).finish()
+    }
+}
+
+impl<T: Ord> Default for SpinHeap<T> {
+    fn default() -> Self {
+        SpinHeap::new()
+    }
+}
+
+impl<T: Ord> FromIterator<T> for SpinHeap<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        SpinHeap { lock: Lock::new(iter.into_iter().map(Reverse).collect()) }
+    }
+}
@@ -0,0 +1,176 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+
+use core::borrow::Borrow;
+use core::fmt::Debug;
+use core::hash::{BuildHasher, Hash};
+use std::boxed::Box;
+use std::collections::hash_map::{HashMap, RandomState};
+use std::sync::Arc;
+use std::vec::Vec;
+use crate::Lock;
+
+/**
+A small handle to a value in an [Interner].
+
+Equal values interned in the same interner get the same symbol, so symbols can be compared and hashed
+instead of the values.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    /**
+    The symbol as a number, for storing compactly.
+*/
+    pub fn as_u32(self) -> u32 {
+        self.0
+    }
+}
+
+struct Shard<T: ?Sized, S> {
+    symbols: HashMap<Arc<T>, Symbol, S>,
+    //by index within the shard
+    values: Vec<Arc<T>>,
+}
+
+/**
+Deduplicates values, handing out a [Symbol] for each distinct one.
+
+```
+# use atomiclock_spinlock::collections::Interner;
+let names: Interner<str> = Interner::new();
+let a = names.intern("tokio");
+let b = names.intern(String::from("rayon"));
+assert_eq!(names.intern("tokio"), a);
+assert_ne!(a, b);
+assert_eq!(&*names.resolve(b), "rayon");
+```
+
+The values are split into shards by hash, each behind its own [Lock], so threads interning different
+values rarely contend.  Values stay interned until the interner is dropped.
+
+Requires the `std` feature.
+*/
+pub struct Interner<T: ?Sized, S = RandomState> {
+    shards: Box<[Lock<Shard<T, S>>]>,
+    shard_bits: u32,
+    hasher: S,
+}
+
+impl<T: ?Sized> Interner<T> {
+    /**
+    Creates an empty interner, with a few shards per available CPU.
+*/
+    pub fn new() -> Self {
+        let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Interner::with_shards(cpus * 4)
+    }
+
+    /**
+    Creates an empty interner with `shards` shards, rounded up to a power of two.
+*/
+    pub fn with_shards(shards: usize) -> Self {
+        Interner::with_shards_and_hasher(shards, RandomState::new())
+    }
+}
+
+impl<T: ?Sized, S: BuildHasher + Clone> Interner<T, S> {
+    /**
+    Creates an empty interner with `shards` shards, rounded up to a power of two, that hashes values with
+    `hasher`.
+
+    # Panics
+    If `shards` is more than 2<sup>16</sup>.
+*/
+    pub fn with_shards_and_hasher(shards: usize, hasher: S) -> Self {
+        let shards = shards.max(1).next_power_of_two();
+        assert!(shards <= 1 << 16, "too many shards");
+        Interner {
+            shards: (0..shards)
+                .map(|_| Lock::new(Shard { symbols: HashMap::with_hasher(hasher.clone()), values: Vec::new() }))
+                .collect(),
+            shard_bits: shards.trailing_zeros(),
+            hasher,
+        }
+    }
+}
+
+impl<T: ?Sized, S> Interner<T, S> {
+    /**
+    Returns the value for a symbol.
+
+    # Panics
+    If the symbol came from a different interner, and this one has no value for it.
+*/
+    pub fn resolve(&self, symbol: Symbol) -> Arc<T> {
+        let shard = symbol.0 as usize & (self.shards.len() - 1);
+        let index = (symbol.0 >> self.shard_bits) as usize;
+        let values = self.shards[shard].spin_lock();
+        values.values.get(index).expect("symbol is from a different interner").clone()
+    }
+
+    /**
+    The number of distinct values.  Shards are counted one at a time, so with concurrent interning, this
+    might not match the interner at any single moment.
+*/
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.spin_lock().values.len()).sum()
+    }
+
+    /**
+    Whether nothing has been interned.  Shards are checked one at a time, like [Interner::len].
+*/
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| shard.spin_lock().values.is_empty())
+    }
+}
+
+impl<T: ?Sized + Hash + Eq, S: BuildHasher> Interner<T, S> {
+    /**
+    Returns the symbol for a value, interning it if it's new.
+
+    `value` can be anything that converts to an `Arc<T>`; for `Interner<str>`, a `&str` or a `String`.
+    It's only converted when it's new.
+
+    # Panics
+    If a shard would hold more values than fit in a symbol.
+*/
+    pub fn intern<V: Borrow<T> + Into<Arc<T>>>(&self, value: V) -> Symbol {
+        //HashMap buckets by the low bits of the same hash, so pick shards by the high ones
+        let shard = (self.hasher.hash_one(value.borrow()) >> 32) as usize & (self.shards.len() - 1);
+        let mut values = self.shards[shard].spin_lock();
+        if let Some(&symbol) = values.symbols.get(value.borrow()) {
+            return symbol;
+        }
+        let index = u32::try_from(values.values.len()).ok().filter(|&index| index < u32::MAX >> self.shard_bits);
+        let symbol = Symbol(index.expect("Interner shard is full") << self.shard_bits | shard as u32);
+        let value = value.into();
+        values.values.push(value.clone());
+        values.symbols.insert(value, symbol);
+        symbol
+    }
+
+    /**
+    Returns the symbol for a value, if it's been interned.
+*/
+    pub fn get(&self, value: &T) -> Option<Symbol> {
+        let shard = (self.hasher.hash_one(value) >> 32) as usize & (self.shards.len() - 1);
+        self.shards[shard].spin_lock().symbols.get(value).copied()
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<T: ?Sized, S> Debug for Interner<T, S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Interner").field("shards", &self.shards.len()).finish_non_exhaustive()
+    }
+}
+
+impl<T: ?Sized> Default for Interner<T> {
+    fn default() -> Self {
+        Interner::new()
+    }
+}
@@ -0,0 +1,147 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+
+use core::cell::UnsafeCell;
+use core::fmt::Debug;
+use core::mem::MaybeUninit;
+use core::sync::atomic::Ordering;
+use crate::sync::AtomicUsize;
+use crate::Lock;
+
+/**
+An append-only log with room for `N` entries, whose readers never take the lock.
+
+Appending takes a [Lock], writes the next entry, and then publishes the new length.  Readers load the
+length and read the entries before it, which are never written again, so reading doesn't wait for
+writers and writers don't wait for readers.  This suits capturing events from hot paths, with a reader
+that catches up now and then.
+
+```
+# use atomiclock_spinlock::collections::AppendLog;
+static EVENTS: AppendLog<&str, 16> = AppendLog::new();
+EVENTS.append("started").unwrap();
+EVENTS.append("connected").unwrap();
+let seen = EVENTS.len();
+assert!(EVENTS.iter().eq(["started", "connected"].iter()));
+//a reader can pick up where it left off
+EVENTS.append("closed").unwrap();
+assert!(EVENTS.iter().skip(seen).eq(["closed"].iter()));
+```
+
+The storage is inline, so the log doesn't allocate, and once it's full, appends fail.
+*/
+pub struct AppendLog<T, const N: usize> {
+    writer: Lock<()>,
+    //entries before this are written, and immutable
+    len: AtomicUsize,
+    entries: UnsafeCell<MaybeUninit<[T; N]>>,
+}
+
+//readers share the committed entries; appends move entries in from the appending thread
+unsafe impl<T: Send + Sync, const N: usize> Sync for AppendLog<T, N> {}
+unsafe impl<T: Send, const N: usize> Send for AppendLog<T, N> {}
+
+impl<T, const N: usize> AppendLog<T, N> {
+    const_fn! {
+        /**
+        Creates an empty log.
+        */
+        pub const fn new() -> Self {
+            AppendLog { writer: Lock::new(()), len: AtomicUsize::new(0), entries: UnsafeCell::new(MaybeUninit::uninit()) }
+        }
+    }
+
+    /**
+    Appends an entry, returning its index, or gives it back if the log is full.
+*/
+    pub fn append(&self, value: T) -> Result<usize, T> {
+        let writer = self.writer.spin_lock();
+        //only appends change the length, and we hold the lock
+        let index = self.len.load(Ordering::Relaxed);
+        if index == N {
+            return Err(value);
+        }
+        //readers don't look past the length, and other writers wait for the lock
+        unsafe { self.entry(index).write(value) };
+        self.len.store(index + 1, Ordering::Release);
+        drop(writer);
+        Ok(index)
+    }
+
+    /**
+    The number of committed entries.  It only grows.
+*/
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Acquire)
+    }
+
+    /**
+    Whether nothing has been appended yet.
+*/
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /**
+    Whether there's no room for another entry.
+*/
+    pub fn is_full(&self) -> bool {
+        self.len() == N
+    }
+
+    /**
+    The entry at `index`, if it's committed.
+*/
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.committed().get(index)
+    }
+
+    /**
+    Iterates the entries committed when this is called.  Later appends aren't included; call again, and
+    skip the entries already seen, to continue.
+*/
+    pub fn iter(&self) -> core::slice::Iter<'_, T> {
+        self.committed().iter()
+    }
+
+    fn committed(&self) -> &[T] {
+        let len = self.len();
+        //the first len entries are initialized, published by the Release store, and never written again
+        unsafe { core::slice::from_raw_parts(self.entry(0), len) }
+    }
+
+    //pointer arithmetic only, so entries being read aren't reborrowed by a writer
+    fn entry(&self, index: usize) -> *mut T {
+        unsafe { (self.entries.get() as *mut T).add(index) }
+    }
+}
+
+impl<T, const N: usize> Drop for AppendLog<T, N> {
+    fn drop(&mut self) {
+        let len = self.len.load(Ordering::Acquire);
+        unsafe { core::ptr::drop_in_place(core::ptr::slice_from_raw_parts_mut(self.entry(0), len)) }
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<'a, T, const N: usize> IntoIterator for &'a AppendLog<T, N> {
+    type Item = &'a T;
+    type IntoIter = core::slice::Iter<'a, T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: Debug, const N: usize> Debug for AppendLog<T, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("AppendLog").field(&self.committed()).finish()
+    }
+}
+
+impl<T, const N: usize> Default for AppendLog<T, N> {
+    fn default() -> Self {
+        AppendLog::new()
+    }
+}
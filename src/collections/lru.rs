@@ -0,0 +1,297 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+
+use core::borrow::Borrow;
+use core::fmt::Debug;
+use core::hash::{BuildHasher, Hash};
+use std::boxed::Box;
+use std::collections::hash_map::{HashMap, RandomState};
+use std::vec::Vec;
+use crate::Lock;
+
+const NIL: usize = usize::MAX;
+
+struct Node<K, V> {
+    key: K,
+    value: V,
+    //toward the most and least recently used
+    newer: usize,
+    older: usize,
+}
+
+//one shard: a map to the nodes, which form a list from most to least recently used
+struct Shard<K, V, S> {
+    index: HashMap<K, usize, S>,
+    nodes: Vec<Node<K, V>>,
+    newest: usize,
+    oldest: usize,
+    capacity: usize,
+}
+
+impl<K: Hash + Eq + Clone, V, S: BuildHasher> Shard<K, V, S> {
+    fn unlink(&mut self, i: usize) {
+        let (newer, older) = (self.nodes[i].newer, self.nodes[i].older);
+        match newer {
+            NIL => self.newest = older,
+            newer => self.nodes[newer].older = older,
+        }
+        match older {
+            NIL => self.oldest = newer,
+            older => self.nodes[older].newer = newer,
+        }
+    }
+
+    fn push_newest(&mut self, i: usize) {
+        self.nodes[i].newer = NIL;
+        self.nodes[i].older = self.newest;
+        match self.newest {
+            NIL => self.oldest = i,
+            newest => self.nodes[newest].newer = i,
+        }
+        self.newest = i;
+    }
+
+    //finds a key, and marks it most recently used
+    fn touch<Q: Hash + Eq + ?Sized>(&mut self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+    {
+        let i = *self.index.get(key)?;
+        self.unlink(i);
+        self.push_newest(i);
+        Some(i)
+    }
+
+    //adds a key that isn't present, evicting the least recently used if the shard is full
+    fn add(&mut self, key: K, value: V) -> usize {
+        if self.nodes.len() < self.capacity {
+            let i = self.nodes.len();
+            self.nodes.push(Node { key: key.clone(), value, newer: NIL, older: NIL });
+            self.index.insert(key, i);
+            self.push_newest(i);
+            return i;
+        }
+        //reuse the oldest node
+        let i = self.oldest;
+        self.unlink(i);
+        let node = &mut self.nodes[i];
+        let evicted = core::mem::replace(&mut node.key, key.clone());
+        node.value = value;
+        self.index.remove(&evicted);
+        self.index.insert(key, i);
+        self.push_newest(i);
+        i
+    }
+
+    fn remove<Q: Hash + Eq + ?Sized>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+    {
+        let i = self.index.remove(key)?;
+        self.unlink(i);
+        //keep the nodes dense by moving the last one into the hole, and pointing its neighbors at it
+        let last = self.nodes.len() - 1;
+        if i != last {
+            self.nodes.swap(i, last);
+            let (newer, older) = (self.nodes[i].newer, self.nodes[i].older);
+            match newer {
+                NIL => self.newest = i,
+                newer => self.nodes[newer].older = i,
+            }
+            match older {
+                NIL => self.oldest = i,
+                older => self.nodes[older].newer = i,
+            }
+            *self.index.get_mut::<K>(&self.nodes[i].key).expect("moved node is indexed") = i;
+        }
+        self.nodes.pop().map(|node| node.value)
+    }
+}
+
+/**
+A cache that keeps the most recently used entries, split into shards, each behind its own [Lock].
+
+```
+# use atomiclock_spinlock::collections::LruCache;
+let cache = LruCache::with_shards(2, 1);
+cache.insert("a", 1);
+cache.insert("b", 2);
+//touches "a", so "b" is now the least recently used
+assert_eq!(cache.get_cloned("a"), Some(1));
+assert_eq!(cache.get_or_insert_with("c", || 3), 3);
+assert_eq!(cache.get_cloned("b"), None);
+assert_eq!(cache.len(), 2);
+```
+
+Each shard holds an equal part of the capacity and evicts on its own, so with several shards, the
+entries evicted are the least recently used in their shard, not necessarily in the whole cache.
+
+Requires the `std` feature.
+*/
+pub struct LruCache<K, V, S = RandomState> {
+    shards: Box<[Lock<Shard<K, V, S>>]>,
+    hasher: S,
+}
+
+impl<K, V> LruCache<K, V> {
+    /**
+    Creates an empty cache holding up to about `capacity` entries, with a few shards per available CPU.
+*/
+    pub fn new(capacity: usize) -> Self {
+        let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+        LruCache::with_shards(capacity, cpus * 4)
+    }
+
+    /**
+    Creates an empty cache with `shards` shards, rounded up to a power of two, and at most as many as
+    `capacity`.  It holds up to `capacity` entries, rounded up to a multiple of the shards.
+*/
+    pub fn with_shards(capacity: usize, shards: usize) -> Self {
+        LruCache::with_shards_and_hasher(capacity, shards, RandomState::new())
+    }
+}
+
+impl<K, V, S: BuildHasher + Clone> LruCache<K, V, S> {
+    /**
+    Creates an empty cache like [LruCache::with_shards], that hashes keys with `hasher`.
+*/
+    pub fn with_shards_and_hasher(capacity: usize, shards: usize, hasher: S) -> Self {
+        let capacity = capacity.max(1);
+        let mut shards = shards.max(1).next_power_of_two();
+        while shards > capacity {
+            shards /= 2;
+        }
+        let per_shard = capacity.div_ceil(shards);
+        LruCache {
+            shards: (0..shards)
+                .map(|_| {
+                    Lock::new(Shard {
+                        index: HashMap::with_capacity_and_hasher(per_shard, hasher.clone()),
+                        nodes: Vec::with_capacity(per_shard),
+                        newest: NIL,
+                        oldest: NIL,
+                        capacity: per_shard,
+                    })
+                })
+                .collect(),
+            hasher,
+        }
+    }
+}
+
+impl<K, V, S> LruCache<K, V, S> {
+    /**
+    The number of entries.  Shards are counted one at a time, so with concurrent changes, this might
+    not match the cache at any single moment.
+*/
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.spin_lock().nodes.len()).sum()
+    }
+
+    /**
+    Whether there are no entries.  Shards are checked one at a time, like [LruCache::len].
+*/
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| shard.spin_lock().nodes.is_empty())
+    }
+
+    /**
+    The most entries the cache holds.
+*/
+    pub fn capacity(&self) -> usize {
+        self.shards.iter().map(|shard| shard.spin_lock().capacity).sum()
+    }
+
+    /**
+    Removes every entry, one shard at a time.
+*/
+    pub fn clear(&self) {
+        for shard in self.shards.iter() {
+            let mut shard = shard.spin_lock();
+            shard.index.clear();
+            shard.nodes.clear();
+            shard.newest = NIL;
+            shard.oldest = NIL;
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone, V, S: BuildHasher> LruCache<K, V, S> {
+    fn shard<Q: Hash + ?Sized>(&self, key: &Q) -> &Lock<Shard<K, V, S>> {
+        //HashMap buckets by the low bits of the same hash, so pick shards by the high ones
+        let hash = self.hasher.hash_one(key) >> 32;
+        &self.shards[hash as usize & (self.shards.len() - 1)]
+    }
+
+    /**
+    Inserts a value, as the most recently used, returning the previous value for the key, if any.  If the
+    key is new and its shard is full, the shard's least recently used entry is evicted.
+*/
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        let mut shard = self.shard(&key).spin_lock();
+        if let Some(i) = shard.touch(&key) {
+            return Some(core::mem::replace(&mut shard.nodes[i].value, value));
+        }
+        shard.add(key, value);
+        None
+    }
+
+    /**
+    Removes a key, returning its value, if any.
+*/
+    pub fn remove<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+    {
+        self.shard(key).spin_lock().remove(key)
+    }
+
+    /**
+    Whether the cache has the key.  Doesn't count as a use.
+*/
+    pub fn contains_key<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+    {
+        self.shard(key).spin_lock().index.contains_key(key)
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone, S: BuildHasher> LruCache<K, V, S> {
+    /**
+    Returns a clone of the key's value, if any, marking it most recently used.
+*/
+    pub fn get_cloned<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+    {
+        let mut shard = self.shard(key).spin_lock();
+        let i = shard.touch(key)?;
+        Some(shard.nodes[i].value.clone())
+    }
+
+    /**
+    Returns a clone of the key's value, inserting `f()` first if it's missing.  Either way, the key becomes
+    the most recently used.
+
+    `f` runs while holding the key's shard lock, so concurrent requests for the key compute it only once.
+    It must not use this cache.
+*/
+    pub fn get_or_insert_with(&self, key: K, f: impl FnOnce() -> V) -> V {
+        let mut shard = self.shard(&key).spin_lock();
+        let i = match shard.touch(&key) {
+            Some(i) => i,
+            None => shard.add(key, f()),
+        };
+        shard.nodes[i].value.clone()
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<K, V, S> Debug for LruCache<K, V, S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("LruCache").field("shards", &self.shards.len()).finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,200 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+
+use core::borrow::Borrow;
+use core::fmt::Debug;
+use core::hash::{BuildHasher, Hash};
+use std::boxed::Box;
+use std::collections::hash_map::{Entry, HashMap, RandomState};
+use crate::Lock;
+
+/**
+A hash map split into shards, each behind its own [Lock].
+
+A key's hash picks its shard, so threads working on different keys rarely contend, and each operation
+holds one shard's lock only briefly.
+
+```
+# use atomiclock_spinlock::collections::SpinMap;
+let map = SpinMap::new();
+map.insert("apples", 3);
+map.with_entry("apples", |entry| *entry.or_insert(0) += 1);
+assert_eq!(map.get_cloned("apples"), Some(4));
+assert_eq!(map.remove("apples"), Some(4));
+assert!(map.is_empty());
+```
+
+There's no way to borrow a value out of the map, since the borrow would outlive the shard's lock.
+Clone it out with [SpinMap::get_cloned], or work on it in place with [SpinMap::with] or
+[SpinMap::with_entry].
+
+Requires the `std` feature.
+*/
+pub struct SpinMap<K, V, S = RandomState> {
+    shards: Box<[Lock<HashMap<K, V, S>>]>,
+    hasher: S,
+}
+
+impl<K, V> SpinMap<K, V> {
+    /**
+    Creates an empty map, with a few shards per available CPU.
+*/
+    pub fn new() -> Self {
+        let cpus = std::thread::available_parallelism().map_or(1, |n| n.get());
+        SpinMap::with_shards(cpus * 4)
+    }
+
+    /**
+    Creates an empty map with `shards` shards, rounded up to a power of two.
+*/
+    pub fn with_shards(shards: usize) -> Self {
+        SpinMap::with_shards_and_hasher(shards, RandomState::new())
+    }
+}
+
+impl<K, V, S: BuildHasher + Clone> SpinMap<K, V, S> {
+    /**
+    Creates an empty map with `shards` shards, rounded up to a power of two, that hashes keys with `hasher`.
+*/
+    pub fn with_shards_and_hasher(shards: usize, hasher: S) -> Self {
+        let shards = shards.max(1).next_power_of_two();
+        SpinMap {
+            shards: (0..shards).map(|_| Lock::new(HashMap::with_hasher(hasher.clone()))).collect(),
+            hasher,
+        }
+    }
+}
+
+impl<K, V, S> SpinMap<K, V, S> {
+    /**
+    The number of shards.
+*/
+    pub fn shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    /**
+    The number of entries.  Shards are counted one at a time, so with concurrent changes, this might
+    not match the map at any single moment.
+*/
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.spin_lock().len()).sum()
+    }
+
+    /**
+    Whether there are no entries.  Shards are checked one at a time, like [SpinMap::len].
+*/
+    pub fn is_empty(&self) -> bool {
+        self.shards.iter().all(|shard| shard.spin_lock().is_empty())
+    }
+
+    /**
+    Removes every entry, one shard at a time.
+*/
+    pub fn clear(&self) {
+        for shard in self.shards.iter() {
+            shard.spin_lock().clear();
+        }
+    }
+
+    /**
+    Consumes the map, returning every entry.
+*/
+    pub fn into_entries(self) -> impl Iterator<Item = (K, V)> {
+        self.shards.into_vec().into_iter().flat_map(|shard| shard.into_inner())
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> SpinMap<K, V, S> {
+    fn shard<Q: Hash + ?Sized>(&self, key: &Q) -> &Lock<HashMap<K, V, S>> {
+        //HashMap buckets by the low bits of the same hash, so pick shards by the high ones, or every key in
+        //a shard would land in the same buckets
+        let hash = self.hasher.hash_one(key) >> 32;
+        &self.shards[hash as usize & (self.shards.len() - 1)]
+    }
+
+    /**
+    Inserts a value, returning the previous one for the key, if any.
+*/
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        self.shard(&key).spin_lock().insert(key, value)
+    }
+
+    /**
+    Removes a key, returning its value, if any.
+*/
+    pub fn remove<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+    {
+        self.shard(key).spin_lock().remove(key)
+    }
+
+    /**
+    Whether the map has a value for the key.
+*/
+    pub fn contains_key<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+    {
+        self.shard(key).spin_lock().contains_key(key)
+    }
+
+    /**
+    Returns a clone of the key's value, if any.
+*/
+    pub fn get_cloned<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        V: Clone,
+    {
+        self.shard(key).spin_lock().get(key).cloned()
+    }
+
+    /**
+    Calls `f` with the key's value, if any, while holding its shard's lock.
+
+    `f` must not use this map: another key may share the shard, and spinning on it would never finish.
+*/
+    pub fn with<Q: Hash + Eq + ?Sized, R>(&self, key: &Q, f: impl FnOnce(Option<&mut V>) -> R) -> R
+    where
+        K: Borrow<Q>,
+    {
+        let mut shard = self.shard(key).spin_lock();
+        f(HashMap::get_mut(&mut shard, key))
+    }
+
+    /**
+    Calls `f` with the key's [Entry], while holding its shard's lock, to insert or update in one step.
+
+    `f` must not use this map, as with [SpinMap::with].
+*/
+    pub fn with_entry<R>(&self, key: K, f: impl FnOnce(Entry<'_, K, V>) -> R) -> R {
+        f(self.shard(&key).spin_lock().entry(key))
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<K, V, S> Debug for SpinMap<K, V, S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SpinMap").field("shards", &self.shards.len()).finish_non_exhaustive()
+    }
+}
+
+impl<K, V> Default for SpinMap<K, V> {
+    fn default() -> Self {
+        SpinMap::new()
+    }
+}
+
+impl<K: Hash + Eq, V> FromIterator<(K, V)> for SpinMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let map = SpinMap::new();
+        for (key, value) in iter {
+            map.insert(key, value);
+        }
+        map
+    }
+}
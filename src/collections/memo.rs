@@ -0,0 +1,115 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+
+use core::borrow::Borrow;
+use core::fmt::Debug;
+use core::hash::Hash;
+use std::sync::Arc;
+use crate::collections::SpinMap;
+use crate::Lock;
+
+/**
+A cache of computed values, which computes each key once even when threads ask for it at the same time.
+
+```
+# use atomiclock_spinlock::collections::Memo;
+# use std::sync::atomic::{AtomicUsize, Ordering};
+# use std::thread;
+let memo = Memo::new();
+let computed = AtomicUsize::new(0);
+thread::scope(|s| {
+    for _ in 0..4 {
+        s.spawn(|| {
+            let square = memo.get_or_compute(12, |n| {
+                computed.fetch_add(1, Ordering::Relaxed);
+                n * n
+            });
+            assert_eq!(square, 144);
+        });
+    }
+});
+assert_eq!(computed.load(Ordering::Relaxed), 1);
+```
+
+The entries live in a [SpinMap], and each key has its own [Lock], held while its value is computed.
+Threads that want the same key meanwhile spin on that lock, and then reuse the value, so computations
+should be short.  Different keys compute in parallel.  If a computation panics, the key stays uncomputed,
+and the next thread to ask computes it.
+
+Requires the `std` feature.
+*/
+pub struct Memo<K, V> {
+    map: SpinMap<K, Arc<Lock<Option<V>>>>,
+}
+
+impl<K, V> Memo<K, V> {
+    /**
+    Creates an empty cache.
+*/
+    pub fn new() -> Self {
+        Memo { map: SpinMap::new() }
+    }
+
+    /**
+    Forgets every value, one shard at a time.  Computations already running finish, but their values
+    aren't kept.
+*/
+    pub fn clear(&self) {
+        self.map.clear();
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Memo<K, V> {
+    /**
+    Returns the key's value, computing it with `f` first if no thread has yet.
+
+    `f` must not ask for the same key, which would spin forever on the key it's computing.
+*/
+    pub fn get_or_compute(&self, key: K, f: impl FnOnce(&K) -> V) -> V {
+        let slot = self.map.with_entry(key.clone(), |entry| entry.or_default().clone());
+        let mut value = slot.spin_lock();
+        if let Some(value) = &*value {
+            return value.clone();
+        }
+        let computed = f(&key);
+        *value = Some(computed.clone());
+        computed
+    }
+
+    /**
+    Returns the key's value, if it has been computed.  Spins while it's being computed.
+*/
+    pub fn get<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+    {
+        let slot = self.map.get_cloned(key)?;
+        let value = slot.spin_lock();
+        value.clone()
+    }
+
+    /**
+    Forgets the key's value, so the next request computes it again.
+*/
+    pub fn invalidate<Q: Hash + Eq + ?Sized>(&self, key: &Q)
+    where
+        K: Borrow<Q>,
+    {
+        self.map.remove(key);
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<K, V> Debug for Memo<K, V> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Memo").finish_non_exhaustive()
+    }
+}
+
+impl<K, V> Default for Memo<K, V> {
+    fn default() -> Self {
+        Memo::new()
+    }
+}
@@ -0,0 +1,136 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+
+use core::fmt::Debug;
+use core::mem::ManuallyDrop;
+use core::ops::{Deref, DerefMut};
+use crate::collections::SpinStack;
+
+/**
+A pool of reusable objects, keeping up to `N` idle ones.
+
+[Pool::acquire] hands out an idle object, or creates one if there are none, and the [PoolGuard] returns
+it to the pool when dropped.  If the pool already has `N` idle objects, the returned one is dropped
+instead.  The idle objects are stored inline, so the pool doesn't allocate and can be `static`.
+
+```
+# use atomiclock_spinlock::collections::Pool;
+static BUFFERS: Pool<Vec<u8>, 4> = Pool::new(|| Vec::with_capacity(4096));
+let mut buffer = BUFFERS.acquire();
+buffer.extend_from_slice(b"hello");
+drop(buffer);
+//the same buffer, still holding its old contents
+let buffer = BUFFERS.acquire();
+assert_eq!(&buffer[..], b"hello");
+assert!(buffer.capacity() >= 4096);
+```
+
+Objects come back as they were left, so clear them on acquisition if that matters.
+*/
+pub struct Pool<T, const N: usize, F = fn() -> T> {
+    idle: SpinStack<T, N>,
+    create: F,
+}
+
+impl<T, const N: usize, F> Pool<T, N, F> {
+    const_fn! {
+        /**
+        Creates an empty pool, which makes new objects with `create`.
+        */
+        pub const fn new(create: F) -> Self {
+            Pool { idle: SpinStack::new(), create }
+        }
+    }
+
+    /**
+    Takes an idle object, if there is one.
+*/
+    pub fn try_acquire(&self) -> Option<PoolGuard<'_, T, N, F>> {
+        Some(PoolGuard { pool: self, value: ManuallyDrop::new(self.idle.pop()?) })
+    }
+
+    /**
+    Adds an object to the pool, or gives it back if the pool already has `N` idle objects.
+*/
+    pub fn put(&self, value: T) -> Result<(), T> {
+        self.idle.push(value)
+    }
+
+    /**
+    The number of idle objects.  Another thread may change it right after this returns.
+*/
+    pub fn idle(&self) -> usize {
+        self.idle.len()
+    }
+}
+
+impl<T, const N: usize, F: Fn() -> T> Pool<T, N, F> {
+    /**
+    Takes an idle object, or creates one if there are none.
+*/
+    pub fn acquire(&self) -> PoolGuard<'_, T, N, F> {
+        self.try_acquire().unwrap_or_else(|| PoolGuard { pool: self, value: ManuallyDrop::new((self.create)()) })
+    }
+}
+
+/**
+An object on loan from a [Pool], returned to it on drop.
+*/
+#[must_use]
+pub struct PoolGuard<'a, T, const N: usize, F = fn() -> T> {
+    pool: &'a Pool<T, N, F>,
+    value: ManuallyDrop<T>,
+}
+
+impl<T, const N: usize, F> PoolGuard<'_, T, N, F> {
+    /**
+    Keeps the object, instead of returning it to the pool.
+*/
+    pub fn detach(guard: Self) -> T {
+        let mut guard = ManuallyDrop::new(guard);
+        //the guard won't be dropped, so the value is taken only once
+        unsafe { ManuallyDrop::take(&mut guard.value) }
+    }
+}
+
+impl<T, const N: usize, F> Drop for PoolGuard<'_, T, N, F> {
+    fn drop(&mut self) {
+        //taken only here, by the last use of the guard
+        let value = unsafe { ManuallyDrop::take(&mut self.value) };
+        drop(self.pool.put(value));
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<T, const N: usize, F> Deref for PoolGuard<'_, T, N, F> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T, const N: usize, F> DerefMut for PoolGuard<'_, T, N, F> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T: Debug, const N: usize, F> Debug for PoolGuard<'_, T, N, F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("PoolGuard").field(&*self.value).finish()
+    }
+}
+
+impl<T: Debug, const N: usize, F> Debug for Pool<T, N, F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Pool").field("idle", &self.idle).finish_non_exhaustive()
+    }
+}
+
+impl<T: Default, const N: usize> Default for Pool<T, N> {
+    fn default() -> Self {
+        Pool::new(T::default)
+    }
+}
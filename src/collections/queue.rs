@@ -0,0 +1,201 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+
+use core::fmt::Debug;
+use crate::{Lock, SpinWait};
+
+/**
+A FIFO queue with room for `N` elements, behind a [Lock].
+
+The storage is inline, so the queue doesn't allocate and can be `static`.
+
+```
+# use atomiclock_spinlock::collections::SpinQueue;
+# use std::thread;
+static WORK: SpinQueue<u32, 4> = SpinQueue::new();
+thread::scope(|s| {
+    s.spawn(|| {
+        for n in 0..10 {
+            //spins while the queue is full
+            WORK.push(n);
+        }
+    });
+    for n in 0..10 {
+        //spins while the queue is empty
+        assert_eq!(WORK.pop(), n);
+    }
+});
+assert_eq!(WORK.try_pop(), None);
+```
+
+The spinning methods re-acquire the lock for each attempt, so the other side can get in between.
+On single-threaded targets, they panic instead of spinning forever.
+*/
+pub struct SpinQueue<T, const N: usize> {
+    lock: Lock<Ring<T, N>>,
+}
+
+//also the buffer of a `channel::Channel`
+pub(crate) struct Ring<T, const N: usize> {
+    slots: [Option<T>; N],
+    //index of the oldest element
+    head: usize,
+    len: usize,
+}
+
+impl<T, const N: usize> Ring<T, N> {
+    const EMPTY: Option<T> = None;
+
+    pub(crate) const fn new() -> Self {
+        Ring { slots: [Self::EMPTY; N], head: 0, len: 0 }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub(crate) fn push(&mut self, value: T) -> Result<(), T> {
+        if self.len == N {
+            return Err(value);
+        }
+        self.slots[(self.head + self.len) % N] = Some(value);
+        self.len += 1;
+        Ok(())
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        let value = self.slots[self.head].take();
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        value
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &T> {
+        (0..self.len).filter_map(move |i| self.slots[(self.head + i) % N].as_ref())
+    }
+}
+
+impl<T, const N: usize> SpinQueue<T, N> {
+    const_fn! {
+        /**
+        Creates an empty queue.
+        */
+        pub const fn new() -> Self {
+            SpinQueue { lock: Lock::new(Ring::new()) }
+        }
+    }
+
+    /**
+    Appends an element if there's room, or gives it back if the queue is full.
+*/
+    pub fn try_push(&self, value: T) -> Result<(), T> {
+        self.lock.spin_lock().push(value)
+    }
+
+    /**
+    Appends an element, spinning until there's room.
+
+    # Panics
+    On single-threaded targets, panics if the queue is full.  A queue with no capacity is always full.
+*/
+    pub fn push(&self, mut value: T) {
+        let mut wait = SpinWait::new();
+        loop {
+            match self.try_push(value) {
+                Ok(()) => return,
+                Err(rejected) => value = rejected,
+            }
+            if crate::SINGLE_THREADED {
+                panic!("SpinQueue is full; on a single-threaded target, spinning on it would never finish");
+            }
+            wait.spin();
+        }
+    }
+
+    /**
+    Removes the oldest element, if there is one.
+*/
+    pub fn try_pop(&self) -> Option<T> {
+        self.lock.spin_lock().pop()
+    }
+
+    /**
+    Removes the oldest element, spinning until there is one.
+
+    # Panics
+    On single-threaded targets, panics if the queue is empty.
+*/
+    pub fn pop(&self) -> T {
+        let mut wait = SpinWait::new();
+        loop {
+            if let Some(value) = self.try_pop() {
+                return value;
+            }
+            if crate::SINGLE_THREADED {
+                panic!("SpinQueue is empty; on a single-threaded target, spinning on it would never finish");
+            }
+            wait.spin();
+        }
+    }
+
+    /**
+    The number of elements.  Another thread may change it right after this returns.
+*/
+    pub fn len(&self) -> usize {
+        self.lock.spin_lock().len
+    }
+
+    /**
+    Whether there are no elements.  Another thread may change it right after this returns.
+*/
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /**
+    Whether there's no room for another element.  Another thread may change it right after this returns.
+*/
+    pub fn is_full(&self) -> bool {
+        self.len() == N
+    }
+
+    /**
+    The maximum number of elements, `N`.
+*/
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<T: Debug, const N: usize> Debug for SpinQueue<T, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.lock.try_lock() {
+            Some(ring) => f.debug_tuple("SpinQueue").field(&DebugRing(&ring)).finish(),
+            None => f.write_str("SpinQueue(<locked>)"),
+        }
+    }
+}
+
+pub(crate) struct DebugRing<'a, T, const N: usize>(pub(crate) &'a Ring<T, N>);
+
+impl<T: Debug, const N: usize> Debug for DebugRing<'_, T, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.0.iter()).finish()
+    }
+}
+
+impl<T, const N: usize> Default for SpinQueue<T, N> {
+    fn default() -> Self {
+        SpinQueue::new()
+    }
+}
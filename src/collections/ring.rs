@@ -0,0 +1,189 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+
+use core::fmt::Debug;
+use crate::Lock;
+
+struct Samples<T, const N: usize> {
+    slots: [Option<T>; N],
+    //where the next sample goes, how many are in the ring, and how many were ever pushed
+    next: usize,
+    len: usize,
+    pushed: u64,
+}
+
+impl<T, const N: usize> Samples<T, N> {
+    const EMPTY: Option<T> = None;
+
+    //oldest first
+    fn iter(&self) -> impl Iterator<Item = &T> {
+        (0..N).filter_map(move |i| self.slots[(self.next + i) % N].as_ref())
+    }
+}
+
+/**
+The last `N` samples, behind a [Lock].
+
+Pushing to a full ring overwrites the oldest sample, so writers never wait for readers to catch up.
+Readers take a [SpinRing::snapshot], copying the samples out under the lock.
+
+```
+# use atomiclock_spinlock::collections::SpinRing;
+static LATENCIES: SpinRing<u32, 3> = SpinRing::new();
+for sample in [10, 12, 9, 40] {
+    LATENCIES.push(sample);
+}
+assert!(LATENCIES.snapshot().eq([12, 9, 40]));
+assert_eq!(LATENCIES.pushed(), 4);
+```
+
+The storage is inline, so the ring doesn't allocate and can be `static`.
+*/
+pub struct SpinRing<T, const N: usize> {
+    lock: Lock<Samples<T, N>>,
+}
+
+impl<T, const N: usize> SpinRing<T, N> {
+    const_fn! {
+        /**
+        Creates an empty ring.
+        */
+        pub const fn new() -> Self {
+            SpinRing { lock: Lock::new(Samples { slots: [Samples::<T, N>::EMPTY; N], next: 0, len: 0, pushed: 0 }) }
+        }
+    }
+
+    /**
+    Adds a sample, returning the one it overwrote, if the ring was full.  A ring with no capacity
+    returns the sample itself.
+*/
+    pub fn push(&self, value: T) -> Option<T> {
+        if N == 0 {
+            return Some(value);
+        }
+        let mut ring = self.lock.spin_lock();
+        let next = ring.next;
+        let overwritten = ring.slots[next].replace(value);
+        ring.next = (next + 1) % N;
+        ring.len = (ring.len + 1).min(N);
+        ring.pushed += 1;
+        overwritten
+    }
+
+    /**
+    The number of samples ever pushed, including overwritten ones.  Comparing it between snapshots tells
+    a reader how many it missed.
+*/
+    pub fn pushed(&self) -> u64 {
+        self.lock.spin_lock().pushed
+    }
+
+    /**
+    The number of samples in the ring, at most `N`.
+*/
+    pub fn len(&self) -> usize {
+        self.lock.spin_lock().len
+    }
+
+    /**
+    Whether nothing has been pushed since the ring was created or cleared.
+*/
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /**
+    Removes every sample.  [SpinRing::pushed] keeps counting.
+*/
+    pub fn clear(&self) {
+        let mut ring = self.lock.spin_lock();
+        ring.slots = [Samples::<T, N>::EMPTY; N];
+        ring.next = 0;
+        ring.len = 0;
+    }
+}
+
+impl<T: Clone, const N: usize> SpinRing<T, N> {
+    /**
+    Copies the samples out, oldest first, under one acquisition.
+*/
+    pub fn snapshot(&self) -> Snapshot<T, N> {
+        let ring = self.lock.spin_lock();
+        let mut snapshot = Snapshot { slots: [Samples::<T, N>::EMPTY; N], len: 0, next: 0 };
+        for value in ring.iter() {
+            snapshot.slots[snapshot.len] = Some(value.clone());
+            snapshot.len += 1;
+        }
+        snapshot
+    }
+
+    /**
+    A copy of the newest sample, if there is one.
+*/
+    pub fn latest(&self) -> Option<T> {
+        let ring = self.lock.spin_lock();
+        ring.slots[(ring.next + N.checked_sub(1)?) % N].clone()
+    }
+}
+
+/**
+Samples copied out of a [SpinRing], oldest first.
+*/
+pub struct Snapshot<T, const N: usize> {
+    slots: [Option<T>; N],
+    len: usize,
+    next: usize,
+}
+
+impl<T, const N: usize> Iterator for Snapshot<T, N> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        if self.next == self.len {
+            return None;
+        }
+        self.next += 1;
+        self.slots[self.next - 1].take()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len - self.next, Some(self.len - self.next))
+    }
+}
+
+impl<T, const N: usize> ExactSizeIterator for Snapshot<T, N> {}
+
+/*
+boilerplate
+ */
+
+impl<T: Debug, const N: usize> Debug for SpinRing<T, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.lock.try_lock() {
+            Some(ring) => {
+                let mut s = f.debug_struct("SpinRing");
+                s.field("pushed", &ring.pushed);
+                s.field("samples", &DebugSamples(&ring));
+                s.finish()
+            }
+            None => f.write_str("SpinRing(<locked>)"),
+        }
+    }
+}
+
+struct DebugSamples<'a, T, const N: usize>(&'a Samples<T, N>);
+
+impl<T: Debug, const N: usize> Debug for DebugSamples<'_, T, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.0.iter()).finish()
+    }
+}
+
+impl<T: Debug, const N: usize> Debug for Snapshot<T, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.slots[self.next..self.len].iter().flatten()).finish()
+    }
+}
+
+impl<T, const N: usize> Default for SpinRing<T, N> {
+    fn default() -> Self {
+        SpinRing::new()
+    }
+}
@@ -0,0 +1,186 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+
+use alloc::vec::Vec;
+use core::fmt::Debug;
+use crate::Lock;
+
+/**
+A handle to a value in a [Slab].
+
+Keys stay valid until their value is removed, and aren't reused: a slot that's removed and filled again
+gets a new key, so a stale key finds nothing instead of someone else's value.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SlabKey {
+    index: u32,
+    generation: u32,
+}
+
+impl SlabKey {
+    /**
+    The slot's index, for use as a small ID.  Distinct live values have distinct indices, but a removed
+    value's index is reused.
+*/
+    pub fn index(self) -> u32 {
+        self.index
+    }
+}
+
+enum Slot<T> {
+    Occupied { generation: u32, value: T },
+    //the generation the next value in this slot gets
+    Vacant { generation: u32, next_free: Option<u32> },
+}
+
+struct Slots<T> {
+    slots: Vec<Slot<T>>,
+    free: Option<u32>,
+    len: usize,
+}
+
+impl<T> Slots<T> {
+    fn occupied(&mut self, key: SlabKey) -> Option<&mut T> {
+        match self.slots.get_mut(key.index as usize)? {
+            Slot::Occupied { generation, value } if *generation == key.generation => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/**
+Values stored under generated keys, behind a [Lock].
+
+```
+# use atomiclock_spinlock::collections::Slab;
+let devices = Slab::new();
+let uart = devices.insert("uart");
+let spi = devices.insert("spi");
+assert_eq!(devices.get_cloned(spi), Some("spi"));
+assert_eq!(devices.remove(uart), Some("uart"));
+assert_eq!(devices.get_cloned(uart), None);
+//the slot is reused, under a new key
+let i2c = devices.insert("i2c");
+assert_eq!(i2c.index(), uart.index());
+assert_ne!(i2c, uart);
+```
+
+Removed slots are reused before the slab grows.  Values can't be borrowed out past the lock; clone them
+with [Slab::get_cloned], or work on them in place with [Slab::with].
+
+Requires the `alloc` feature.
+*/
+pub struct Slab<T> {
+    lock: Lock<Slots<T>>,
+}
+
+impl<T> Slab<T> {
+    const_fn! {
+        /**
+        Creates an empty slab.  Doesn't allocate.
+        */
+        pub const fn new() -> Self {
+            Slab { lock: Lock::new(Slots { slots: Vec::new(), free: None, len: 0 }) }
+        }
+    }
+
+    /**
+    Stores a value, returning its key.
+
+    # Panics
+    If the slab would hold more than `u32::MAX` slots.
+*/
+    pub fn insert(&self, value: T) -> SlabKey {
+        let mut slab = self.lock.spin_lock();
+        slab.len += 1;
+        if let Some(index) = slab.free {
+            let slot = &mut slab.slots[index as usize];
+            let Slot::Vacant { generation, next_free } = *slot else {
+                unreachable!("free list points at an occupied slot")
+            };
+            *slot = Slot::Occupied { generation, value };
+            slab.free = next_free;
+            return SlabKey { index, generation };
+        }
+        let index = u32::try_from(slab.slots.len()).expect("Slab is full");
+        slab.slots.push(Slot::Occupied { generation: 0, value });
+        SlabKey { index, generation: 0 }
+    }
+
+    /**
+    Removes the key's value, if it's still there.
+*/
+    pub fn remove(&self, key: SlabKey) -> Option<T> {
+        let mut slab = self.lock.spin_lock();
+        slab.occupied(key)?;
+        //once the generation wraps, the slot is retired, rather than risk matching an old key
+        let retired = key.generation == u32::MAX;
+        let next_free = if retired { None } else { slab.free };
+        let vacant = Slot::Vacant { generation: key.generation.wrapping_add(1), next_free };
+        let Slot::Occupied { value, .. } = core::mem::replace(&mut slab.slots[key.index as usize], vacant) else {
+            unreachable!("occupied slot was vacant")
+        };
+        if !retired {
+            slab.free = Some(key.index);
+        }
+        slab.len -= 1;
+        Some(value)
+    }
+
+    /**
+    Whether the key's value is still there.
+*/
+    pub fn contains(&self, key: SlabKey) -> bool {
+        self.lock.spin_lock().occupied(key).is_some()
+    }
+
+    /**
+    Calls `f` with the key's value, if it's still there, while holding the lock.
+
+    `f` must not use this slab.
+*/
+    pub fn with<R>(&self, key: SlabKey, f: impl FnOnce(Option<&mut T>) -> R) -> R {
+        f(self.lock.spin_lock().occupied(key))
+    }
+
+    /**
+    The number of values.  Another thread may change it right after this returns.
+*/
+    pub fn len(&self) -> usize {
+        self.lock.spin_lock().len
+    }
+
+    /**
+    Whether there are no values.  Another thread may change it right after this returns.
+*/
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T: Clone> Slab<T> {
+    /**
+    Returns a clone of the key's value, if it's still there.
+*/
+    pub fn get_cloned(&self, key: SlabKey) -> Option<T> {
+        self.lock.spin_lock().occupied(key).cloned()
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<T> Debug for Slab<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.lock.try_lock() {
+            Some(slab) => f.debug_struct("Slab").field("len", &slab.len).finish_non_exhaustive(),
+            None => f.write_str("Slab(<locked>)"),
+        }
+    }
+}
+
+impl<T> Default for Slab<T> {
+    fn default() -> Self {
+        Slab::new()
+    }
+}
@@ -0,0 +1,161 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+
+use core::fmt::Debug;
+use crate::Lock;
+
+/**
+A LIFO stack with room for `N` elements, behind a [Lock].
+
+The storage is inline, so the stack doesn't allocate, which suits free-lists and deferred work in code
+that can't use the heap.
+
+```
+# use atomiclock_spinlock::collections::SpinStack;
+static DEFERRED: SpinStack<u32, 8> = SpinStack::new();
+DEFERRED.push(1).unwrap();
+DEFERRED.push(2).unwrap();
+DEFERRED.push(3).unwrap();
+assert_eq!(DEFERRED.pop(), Some(3));
+//takes the rest under one acquisition, newest first
+assert!(DEFERRED.pop_all().eq([2, 1]));
+assert!(DEFERRED.is_empty());
+```
+*/
+pub struct SpinStack<T, const N: usize> {
+    lock: Lock<Slots<T, N>>,
+}
+
+struct Slots<T, const N: usize> {
+    slots: [Option<T>; N],
+    len: usize,
+}
+
+impl<T, const N: usize> Slots<T, N> {
+    const EMPTY: Option<T> = None;
+}
+
+impl<T, const N: usize> SpinStack<T, N> {
+    const_fn! {
+        /**
+        Creates an empty stack.
+        */
+        pub const fn new() -> Self {
+            SpinStack { lock: Lock::new(Slots { slots: [Slots::<T, N>::EMPTY; N], len: 0 }) }
+        }
+    }
+
+    /**
+    Pushes an element if there's room, or gives it back if the stack is full.
+*/
+    pub fn push(&self, value: T) -> Result<(), T> {
+        let mut stack = self.lock.spin_lock();
+        if stack.len == N {
+            return Err(value);
+        }
+        let len = stack.len;
+        stack.slots[len] = Some(value);
+        stack.len += 1;
+        Ok(())
+    }
+
+    /**
+    Pops the newest element, if there is one.
+*/
+    pub fn pop(&self) -> Option<T> {
+        let mut stack = self.lock.spin_lock();
+        stack.len = stack.len.checked_sub(1)?;
+        let len = stack.len;
+        stack.slots[len].take()
+    }
+
+    /**
+    Takes every element under one acquisition, leaving the stack empty.
+
+    The iterator yields them newest first, and doesn't hold the lock.
+*/
+    pub fn pop_all(&self) -> PopAll<T, N> {
+        let mut stack = self.lock.spin_lock();
+        let slots = core::mem::replace(&mut stack.slots, [Slots::<T, N>::EMPTY; N]);
+        PopAll { slots, len: core::mem::take(&mut stack.len) }
+    }
+
+    /**
+    The number of elements.  Another thread may change it right after this returns.
+*/
+    pub fn len(&self) -> usize {
+        self.lock.spin_lock().len
+    }
+
+    /**
+    Whether there are no elements.  Another thread may change it right after this returns.
+*/
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /**
+    The maximum number of elements, `N`.
+*/
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+}
+
+/**
+The elements taken by [SpinStack::pop_all], newest first.
+*/
+pub struct PopAll<T, const N: usize> {
+    slots: [Option<T>; N],
+    len: usize,
+}
+
+impl<T, const N: usize> Iterator for PopAll<T, N> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.len = self.len.checked_sub(1)?;
+        self.slots[self.len].take()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<T, const N: usize> ExactSizeIterator for PopAll<T, N> {}
+
+/*
+boilerplate
+ */
+
+impl<T: Debug, const N: usize> Debug for SpinStack<T, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.lock.try_lock() {
+            Some(stack) => {
+                let mut f = f.debug_tuple("SpinStack");
+                f.field(&DebugSlots(&stack.slots[..stack.len]));
+                f.finish()
+            }
+            None => f.write_str("SpinStack(<locked>)"),
+        }
+    }
+}
+
+//oldest first, like a Vec
+struct DebugSlots<'a, T>(&'a [Option<T>]);
+
+impl<T: Debug> Debug for DebugSlots<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.0.iter().flatten()).finish()
+    }
+}
+
+impl<T: Debug, const N: usize> Debug for PopAll<T, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.slots[..self.len].iter().rev().flatten()).finish()
+    }
+}
+
+impl<T, const N: usize> Default for SpinStack<T, N> {
+    fn default() -> Self {
+        SpinStack::new()
+    }
+}
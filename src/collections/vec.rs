@@ -0,0 +1,131 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+
+use alloc::vec::Vec;
+use core::fmt::Debug;
+use crate::{Guard, Lock};
+
+/**
+A [Vec] behind a [Lock].
+
+```
+# use atomiclock_spinlock::collections::SpinVec;
+static PENDING: SpinVec<u32> = SpinVec::new();
+PENDING.push(1);
+PENDING.push(2);
+assert_eq!(PENDING.len(), 2);
+assert_eq!(PENDING.drain(), [1, 2]);
+assert!(PENDING.is_empty());
+```
+
+Requires the `alloc` feature.
+*/
+pub struct SpinVec<T> {
+    lock: Lock<Vec<T>>,
+}
+
+impl<T> SpinVec<T> {
+    const_fn! {
+        /**
+        Creates an empty vector.  Doesn't allocate.
+        */
+        pub const fn new() -> Self {
+            SpinVec { lock: Lock::new(Vec::new()) }
+        }
+    }
+
+    /**
+    Appends an element.
+*/
+    pub fn push(&self, value: T) {
+        self.lock.spin_lock().push(value);
+    }
+
+    /**
+    Removes the last element, if there is one.
+*/
+    pub fn pop(&self) -> Option<T> {
+        self.lock.spin_lock().pop()
+    }
+
+    /**
+    Removes all the elements, returning them in order.
+
+    The vector's allocation moves to the result, so pushing afterwards allocates anew.
+*/
+    pub fn drain(&self) -> Vec<T> {
+        core::mem::take(&mut *self.lock.spin_lock())
+    }
+
+    /**
+    Removes all the elements, keeping the allocation.
+*/
+    pub fn clear(&self) {
+        self.lock.spin_lock().clear();
+    }
+
+    /**
+    The number of elements.  Another thread may change it right after this returns.
+*/
+    pub fn len(&self) -> usize {
+        self.lock.spin_lock().len()
+    }
+
+    /**
+    Whether there are no elements.  Another thread may change it right after this returns.
+*/
+    pub fn is_empty(&self) -> bool {
+        self.lock.spin_lock().is_empty()
+    }
+
+    /**
+    Acquires the lock, for several operations at once.
+*/
+    pub fn lock(&self) -> Guard<'_, Vec<T>> {
+        self.lock.spin_lock()
+    }
+
+    /**
+    Consumes the wrapper, returning the vector.
+*/
+    pub fn into_inner(self) -> Vec<T> {
+        self.lock.into_inner()
+    }
+}
+
+impl<T> Extend<T> for &SpinVec<T> {
+    /**
+    Appends the elements under one acquisition.  The iterator runs while the lock is held, so it must not
+    use this vector.
+    */
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.lock.spin_lock().extend(iter);
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<T: Debug> Debug for SpinVec<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("SpinVec").field(&self.lock).finish()
+    }
+}
+
+impl<T> Default for SpinVec<T> {
+    fn default() -> Self {
+        SpinVec::new()
+    }
+}
+
+impl<T> From<Vec<T>> for SpinVec<T> {
+    fn from(vec: Vec<T>) -> Self {
+        SpinVec { lock: Lock::new(vec) }
+    }
+}
+
+impl<T> FromIterator<T> for SpinVec<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Vec::from_iter(iter).into()
+    }
+}
@@ -0,0 +1,6 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Drop-in replacements for other lock crates' APIs, for easy migration.
+*/
+
+pub mod spin;
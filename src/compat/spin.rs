@@ -0,0 +1,111 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Names matching the [spin](https://crates.io/crates/spin) crate.
+
+Switching from `spin` is usually a one-line change:
+
+```text
+use atomiclock_spinlock::compat::spin::Mutex; //was: use spin::Mutex;
+static DATA: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+DATA.lock().push(1);
+```
+
+[Mutex::lock] uses [Lock::spin_lock_warn], so code that migrates gains contention warnings with no other changes.
+The reader-writer lock is this crate's [RwLock](crate::RwLock) as is; note that its upgradable read is spelled
+`upgradable_read`, not `upgradeable_read`.
+*/
+
+use core::fmt::Debug;
+use core::ops::Deref;
+use crate::{Guard, Lock};
+
+/**
+A spinlock with `spin`'s method names.
+
+This dereferences to [Lock], for everything else.
+*/
+#[derive(Default)]
+pub struct Mutex<T> {
+    lock: Lock<T>,
+}
+
+/// A guard for [Mutex].
+pub type MutexGuard<'a, T> = Guard<'a, T>;
+/// A reader-writer spinlock.
+pub type RwLock<T> = crate::RwLock<T>;
+/// A read guard for [RwLock].
+pub type RwLockReadGuard<'a, T> = crate::rwlock::ReadGuard<'a, T>;
+/// A write guard for [RwLock].
+pub type RwLockWriteGuard<'a, T> = crate::rwlock::WriteGuard<'a, T>;
+/// An upgradable read guard for [RwLock].
+pub type RwLockUpgradableGuard<'a, T> = crate::rwlock::UpgradableGuard<'a, T>;
+
+impl<T> Mutex<T> {
+    const_fn! {
+        /**
+        Creates a new lock.
+*/
+        pub const fn new(data: T) -> Mutex<T> {
+            Mutex { lock: Lock::new(data) }
+        }
+    }
+
+    /**
+    Spins until the lock can be acquired, issuing a perfwarn if spinning were needed.
+
+    See [Lock::spin_lock_warn].
+*/
+    #[track_caller]
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        self.lock.spin_lock_warn()
+    }
+
+    /**
+    No spin; provides access to the lock if available.
+*/
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        self.lock.try_lock()
+    }
+
+    /**
+    Whether the lock is currently held.  See [Lock::is_locked].
+*/
+    pub fn is_locked(&self) -> bool {
+        self.lock.is_locked()
+    }
+
+    /**
+    Provides access to the data without locking, since the borrow guarantees exclusive access.
+*/
+    pub fn get_mut(&mut self) -> &mut T {
+        unsafe { self.lock.data() }
+    }
+
+    /**
+    Consumes the lock and returns the inner data.
+*/
+    pub fn into_inner(self) -> T {
+        self.lock.into_inner()
+    }
+}
+
+//boilerplate
+
+impl<T> Deref for Mutex<T> {
+    type Target = Lock<T>;
+    fn deref(&self) -> &Lock<T> {
+        &self.lock
+    }
+}
+
+impl<T: Debug> Debug for Mutex<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.lock.fmt(f)
+    }
+}
+
+impl<T> From<T> for Mutex<T> {
+    fn from(data: T) -> Mutex<T> {
+        Mutex::new(data)
+    }
+}
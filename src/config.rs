@@ -0,0 +1,332 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Process-wide defaults for how contended locks wait.
+
+Locks that weren't built with explicit settings consult these on each contended acquisition, so they can be
+tuned without touching the code that creates the locks:
+
+```
+use atomiclock_spinlock::config;
+let mut settings = config::defaults();
+settings.backoff_cap = 8;
+config::set_defaults(settings);
+# config::set_defaults(config::Settings::new());
+```
+
+The defaults are plain atomics, so this works without `std`, and changes take effect on the next wait.
+With `std`, [calibrate] derives settings for the machine it runs on.
+
+# Environment
+
+With the `env-tuning` feature, these environment variables override the defaults, so a program can be
+tuned without recompiling it:
+
+* `ATOMICLOCK_SPIN_BEFORE_YIELD` - [Settings::spins_before_yield].
+* `ATOMICLOCK_SPIN_WARN_THRESHOLD` - [Settings::warn_threshold].
+* `ATOMICLOCK_SPIN_BACKOFF_CAP` - [Settings::backoff_cap].
+* `ATOMICLOCK_SPIN_LOW_LATENCY` - [Settings::low_latency], `1` or `0`.
+* `ATOMICLOCK_SPIN_HANDOFF_AFTER` - [Settings::handoff_after].
+* `ATOMICLOCK_SPIN_RELAX` - which relax instruction to use: `hint` always uses the spin-loop hint, and
+  `auto` (the default) picks the best one for the machine.  See [arch](crate::arch).
+
+They're read once, the first time the defaults are needed, and [set_defaults] leaves the settings they
+override alone.  Invalid values are ignored, with a warning via logwise if the `perfwarn` feature is enabled.
+
+# Build-time tuning
+
+Named locks can instead be tuned when the crate is built, from a file that `ATOMICLOCK_SPINLOCK_TUNING` names by
+absolute path.  The file has a section for each lock name, with any of the [Settings] fields:
+
+```text
+# tuned from last week's load test
+[connection pool]
+backoff_cap = 8
+warn_threshold = 100
+
+[audio callback]
+low_latency = true
+```
+
+A lock built with a name from the file waits with those settings; fields the file leaves out follow the
+process-wide defaults.  `diagnostics::to_tuning` writes such a file from the stats of a
+previous run, which you can adjust and feed back into the next build.  A malformed file fails the build.
+*/
+
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+/**
+How contended locks wait.  See [set_defaults].
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct Settings {
+    /**
+    How many times a waiter spins before it starts yielding between attempts.
+
+    With the `rayon` feature, a waiter on a rayon worker thread yields by running other pending rayon jobs.
+    With `park`, waiters park their threads instead.  Otherwise, waiters never yield, so this has no effect.
+    */
+    pub spins_before_yield: u32,
+    /**
+    How many times [Lock::spin_lock_warn](crate::Lock::spin_lock_warn) spins before it warns.
+
+    0 warns on any contention.
+    */
+    pub warn_threshold: u32,
+    /**
+    The most relax instructions issued in a single wait.
+
+    The number doubles with each wait, up to this cap.  1 issues a single instruction per wait.
+    */
+    pub backoff_cap: u32,
+    /**
+    Low-latency mode: waiters only ever busy-spin.
+
+    They never yield or park, not to rayon, nor in `memory.atomic.wait32` with `wasm-wait`, whatever
+    [Settings::spins_before_yield] says.  This is for threads pinned to their own cores, such as real-time threads,
+    where any interaction with a scheduler costs more than burning the core.  The relax instruction still
+    applies, since it doesn't involve the scheduler; set `ATOMICLOCK_SPIN_RELAX=hint` with `env-tuning` to rule
+    out `tpause` too.
+
+    Async acquisition still yields to its executor.  The mode is reported by `diagnostics`, and in
+    [Lock::spin_lock_warn](crate::Lock::spin_lock_warn)'s warnings.
+    */
+    pub low_latency: bool,
+    /**
+    How many times a waiter spins before it asks for the lock to be handed to it, with the `handoff` feature.
+
+    The lock isn't fair, so without this, a waiter can lose the race for it indefinitely.  Once a waiter
+    has spun this many times, it asks, and the next release hands the lock straight to it, rather than
+    releasing it for anyone to take.  One waiter asks at a time, and only [Lock::spin_lock](crate::Lock::spin_lock)
+    and the other acquisitions that wait until they succeed ask, since one that gave up after asking would
+    leave the lock handed to nobody.  Without the feature, this has no effect.
+    */
+    pub handoff_after: u32,
+}
+
+impl Settings {
+    /**
+    The built-in settings, which apply until [set_defaults] is called.
+    */
+    pub const fn new() -> Settings {
+        Settings {
+            spins_before_yield: 16,
+            warn_threshold: 0,
+            backoff_cap: 1,
+            low_latency: false,
+            handoff_after: 1024,
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings::new()
+    }
+}
+
+static SPINS_BEFORE_YIELD: AtomicU32 = AtomicU32::new(Settings::new().spins_before_yield);
+static WARN_THRESHOLD: AtomicU32 = AtomicU32::new(Settings::new().warn_threshold);
+static BACKOFF_CAP: AtomicU32 = AtomicU32::new(Settings::new().backoff_cap);
+static LOW_LATENCY: AtomicBool = AtomicBool::new(Settings::new().low_latency);
+static HANDOFF_AFTER: AtomicU32 = AtomicU32::new(Settings::new().handoff_after);
+
+/**
+Sets the defaults for locks that weren't built with explicit settings.
+
+Each setting is updated separately, so a wait that races with this call may see a mix of old and new settings.
+With `env-tuning`, settings from the environment take precedence, so this leaves them alone.
+*/
+pub fn set_defaults(settings: Settings) {
+    read_env();
+    let fields = [
+        (0, &SPINS_BEFORE_YIELD, settings.spins_before_yield),
+        (1, &WARN_THRESHOLD, settings.warn_threshold),
+        (2, &BACKOFF_CAP, settings.backoff_cap),
+        (4, &HANDOFF_AFTER, settings.handoff_after),
+    ];
+    for (field, setting, value) in fields {
+        if !from_env(field) {
+            setting.store(value, Ordering::Relaxed);
+        }
+    }
+    if !from_env(3) {
+        LOW_LATENCY.store(settings.low_latency, Ordering::Relaxed);
+    }
+}
+
+/**
+The current defaults.
+*/
+#[inline]
+pub fn defaults() -> Settings {
+    read_env();
+    Settings {
+        spins_before_yield: SPINS_BEFORE_YIELD.load(Ordering::Relaxed),
+        warn_threshold: WARN_THRESHOLD.load(Ordering::Relaxed),
+        backoff_cap: BACKOFF_CAP.load(Ordering::Relaxed),
+        low_latency: LOW_LATENCY.load(Ordering::Relaxed),
+        handoff_after: HANDOFF_AFTER.load(Ordering::Relaxed),
+    }
+}
+
+//bit per Settings field, in declaration order, set when the environment overrode it
+#[cfg(feature = "env-tuning")]
+static FROM_ENV: core::sync::atomic::AtomicU8 = core::sync::atomic::AtomicU8::new(0);
+
+#[cfg(feature = "env-tuning")]
+static ENV: std::sync::Once = std::sync::Once::new();
+
+/**
+Whether the environment overrode the field, by its index in [Settings].
+*/
+fn from_env(field: usize) -> bool {
+    #[cfg(feature = "env-tuning")]
+    return FROM_ENV.load(Ordering::Relaxed) & (1 << field) != 0;
+    #[cfg(not(feature = "env-tuning"))]
+    {
+        let _ = field;
+        false
+    }
+}
+
+/**
+Applies the `ATOMICLOCK_SPIN_*` environment variables, the first time it's called.
+*/
+#[inline]
+pub(crate) fn read_env() {
+    #[cfg(feature = "env-tuning")]
+    ENV.call_once(load_env);
+}
+
+#[cfg(feature = "env-tuning")]
+#[cold]
+fn load_env() {
+    let fields = [
+        (0, "ATOMICLOCK_SPIN_BEFORE_YIELD", &SPINS_BEFORE_YIELD),
+        (1, "ATOMICLOCK_SPIN_WARN_THRESHOLD", &WARN_THRESHOLD),
+        (2, "ATOMICLOCK_SPIN_BACKOFF_CAP", &BACKOFF_CAP),
+        (4, "ATOMICLOCK_SPIN_HANDOFF_AFTER", &HANDOFF_AFTER),
+    ];
+    for (field, var, setting) in fields {
+        let Some(value) = std::env::var_os(var) else {
+            continue;
+        };
+        match value.to_str().and_then(|v| v.trim().parse().ok()) {
+            Some(value) => {
+                setting.store(value, Ordering::Relaxed);
+                FROM_ENV.fetch_or(1 << field, Ordering::Relaxed);
+            }
+            None => invalid_env(var, &value),
+        }
+    }
+    if let Some(value) = std::env::var_os("ATOMICLOCK_SPIN_LOW_LATENCY") {
+        match value.to_str().map(str::trim) {
+            Some(on @ ("1" | "0")) => {
+                LOW_LATENCY.store(on == "1", Ordering::Relaxed);
+                FROM_ENV.fetch_or(1 << 3, Ordering::Relaxed);
+            }
+            _ => invalid_env("ATOMICLOCK_SPIN_LOW_LATENCY", &value),
+        }
+    }
+    if let Some(value) = std::env::var_os("ATOMICLOCK_SPIN_RELAX") {
+        match value.to_str().map(str::trim) {
+            Some("hint") => crate::arch::force_spin_loop_hint(),
+            Some("auto") => {}
+            _ => invalid_env("ATOMICLOCK_SPIN_RELAX", &value),
+        }
+    }
+}
+
+/**
+Reports an environment variable that was ignored, with `perfwarn`.
+*/
+#[cfg(feature = "env-tuning")]
+fn invalid_env(var: &str, value: &std::ffi::OsStr) {
+    #[cfg(feature = "perfwarn")]
+    logwise::warn_sync!("ignoring invalid {var}={value}",
+        var=std::string::ToString::to_string(var), value=std::format!("{value:?}"));
+    #[cfg(not(feature = "perfwarn"))]
+    let _ = (var, value);
+}
+
+/**
+Measures this machine, and derives settings suited to it.
+
+This times the relax instruction (see [arch](crate::arch)) and counts the available cores.  On a single core,
+a waiter can't make progress until the holder runs, so waiters yield right away.  Otherwise, a single wait
+is capped at about a microsecond, and waiters yield after spinning for about 20 microseconds.
+The warn threshold is taken from the current defaults.
+
+It takes up to a few milliseconds, and the result depends on the load at the time, so it's meant to be run
+once, at startup:
+
+```
+use atomiclock_spinlock::config;
+let settings = config::calibrate();
+assert!(settings.backoff_cap >= 1);
+config::set_defaults(settings);
+# config::set_defaults(config::Settings::new());
+```
+*/
+#[cfg(feature = "std")]
+pub fn calibrate() -> Settings {
+    use std::time::Instant;
+    const RELAXES: u32 = 1000;
+    let mut settings = defaults();
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    if cores == 1 {
+        settings.spins_before_yield = 0;
+        settings.backoff_cap = 1;
+        return settings;
+    }
+    //the fastest of a few runs, to discount preemption
+    let mut fastest = u128::MAX;
+    for _ in 0..5 {
+        let start = Instant::now();
+        for _ in 0..RELAXES {
+            crate::arch::relax();
+        }
+        fastest = fastest.min(start.elapsed().as_nanos());
+    }
+    let relax_nanos = (fastest / u128::from(RELAXES)).max(1);
+    let cap = (1_000 / relax_nanos).clamp(1, 1024) as u32;
+    settings.backoff_cap = cap;
+    //waits double up to the cap, so spinning time is dominated by waits at the cap
+    let per_wait = relax_nanos * u128::from(cap);
+    settings.spins_before_yield = (20_000 / per_wait).clamp(1, 1024) as u32;
+    settings
+}
+
+/**
+Settings from the build-time tuning file.  `None` follows the defaults.
+*/
+struct Tuned {
+    spins_before_yield: Option<u32>,
+    warn_threshold: Option<u32>,
+    backoff_cap: Option<u32>,
+    low_latency: Option<bool>,
+    handoff_after: Option<u32>,
+}
+
+//generated by build.rs from ATOMICLOCK_SPINLOCK_TUNING; empty without it
+const TUNED: &[(&str, Tuned)] = include!(concat!(env!("OUT_DIR"), "/tuning.rs"));
+
+/**
+The settings for a lock with this name.
+*/
+#[inline]
+pub(crate) fn settings_for(name: Option<&str>) -> Settings {
+    let defaults = defaults();
+    let tuned = name.and_then(|name| TUNED.iter().find(|(n, _)| *n == name));
+    match tuned {
+        None => defaults,
+        Some((_, tuned)) => Settings {
+            spins_before_yield: tuned.spins_before_yield.unwrap_or(defaults.spins_before_yield),
+            warn_threshold: tuned.warn_threshold.unwrap_or(defaults.warn_threshold),
+            backoff_cap: tuned.backoff_cap.unwrap_or(defaults.backoff_cap),
+            low_latency: tuned.low_latency.unwrap_or(defaults.low_latency),
+            handoff_after: tuned.handoff_after.unwrap_or(defaults.handoff_after),
+        },
+    }
+}
@@ -0,0 +1,95 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+A [critical-section](https://crates.io/crates/critical-section) provider built on the spinlock.
+
+On multicore microcontrollers, disabling interrupts isn't enough to get mutual exclusion, because
+the other cores keep running.  [SpinCriticalSection] serializes critical sections across cores with a
+single global spinlock.  Install it in your binary with:
+
+```text
+struct MyCore;
+unsafe impl atomiclock_spinlock::critical_section::CoreId for MyCore {
+    fn core_id() -> usize { /* read the core number from your hardware, plus one */ }
+}
+critical_section::set_impl!(atomiclock_spinlock::critical_section::SpinCriticalSection<MyCore>);
+```
+
+With the `std` feature, [StdThread] identifies threads instead of cores.
+
+Critical sections may nest on the same core; only the outermost one takes and releases the lock.
+
+This uses the `restore-state-bool` restore state of critical-section.  Note that this does not disable
+interrupts, so an interrupt handler that enters a critical section while its core is already in one will
+treat it as nested.
+*/
+
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/**
+Identifies the current core (or thread) for the purpose of detecting nested critical sections.
+
+# Safety
+[CoreId::core_id] must return a nonzero value that is unique to the executing core among all cores
+that may enter a critical section at the same time, and stable for as long as a critical section is held.
+*/
+pub unsafe trait CoreId {
+    fn core_id() -> usize;
+}
+
+/**
+A critical-section [Impl](::critical_section::Impl) that spins on a global lock.
+
+`C` identifies the executing core.
+*/
+#[derive(Debug)]
+pub struct SpinCriticalSection<C>(PhantomData<C>);
+
+static LOCK: atomiclock::AtomicLock<()> = atomiclock::AtomicLock::new(());
+//core_id of the core inside the critical section, or 0
+static OWNER: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl<C: CoreId> ::critical_section::Impl for SpinCriticalSection<C> {
+    unsafe fn acquire() -> ::critical_section::RawRestoreState {
+        let me = C::core_id();
+        //only we can store our own id, so if we see it, we're nested
+        if OWNER.load(Ordering::Relaxed) == me {
+            return false;
+        }
+        loop {
+            if let Some(guard) = LOCK.lock() {
+                //released in `release`
+                core::mem::forget(guard);
+                break;
+            }
+            core::hint::spin_loop();
+        }
+        OWNER.store(me, Ordering::Relaxed);
+        true
+    }
+
+    unsafe fn release(restore_state: ::critical_section::RawRestoreState) {
+        if restore_state {
+            OWNER.store(0, Ordering::Relaxed);
+            LOCK.unlock();
+        }
+    }
+}
+
+/**
+Identifies std threads.
+*/
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct StdThread;
+
+#[cfg(feature = "std")]
+unsafe impl CoreId for StdThread {
+    fn core_id() -> usize {
+        std::thread_local! {
+            static ID: u8 = const { 0 };
+        }
+        //the address of a thread-local is unique among live threads, and never null
+        ID.with(|id| crate::addr(id))
+    }
+}
@@ -0,0 +1,102 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Snapshots of a lock's state, for debuggers.
+*/
+
+use crate::raw::RawLock;
+use crate::Lock;
+
+/**
+A snapshot of a [Lock]'s state, from [Lock::debug_state], for inspecting a stuck lock.
+
+The layout is `#[repr(C)]`, with the same fields whatever the features, so debugger scripts can rely on it.
+Fields that need a feature, or debug assertions, to be tracked are 0 without it.
+
+Pretty-printers for GDB and LLDB are in the crate's `debuggers` directory.  They format both this and a
+[Lock] itself, read straight from memory, so they work on a core dump, where nothing can be called.  GDB
+loads its script from the binary, if its directory is on GDB's `auto-load safe-path`, or load it by hand with
+`source debuggers/gdb_atomiclock.py`; for LLDB, `command script import debuggers/lldb_atomiclock.py`.
+Then:
+
+```text
+(gdb) p COUNTER
+$1 = Lock "counter" (locked, 2 waiters) = {owner = 0x7ffff7d8a5b8, waiters = 2, data = 41}
+(gdb) p COUNTER.debug_state()
+$2 = DebugState "counter" (locked, 2 waiters) = {owner = 0x7ffff7d8a5b8, acquisitions = 0, ...}
+```
+
+Like the rest of a lock's state, this is a snapshot, which may be out of date by the time you read it.
+*/
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub struct DebugState {
+    /// Whether the lock is held.
+    pub locked: bool,
+    /// Identifies the thread that acquired the lock, as an address in its thread-local storage, or 0 if it's not
+    /// held or not known.  Only tracked with `std` and debug assertions.
+    pub owner: usize,
+    /// Number of threads spinning on the lock.
+    pub waiters: usize,
+    /// Number of guards alive, as [Lock::live_guards].  Only tracked with debug assertions.
+    pub live_guards: usize,
+    /// The lock's name, if any.
+    pub name: Option<&'static str>,
+    /// Number of times the lock was acquired.  Only tracked with `diagnostics`.
+    pub acquisitions: u64,
+    /// Number of acquisitions that found the lock held.  Only tracked with `diagnostics`.
+    pub contended: u64,
+    /// Total time spent spinning on the lock, in nanoseconds.  Only tracked with `diagnostics` and `std`.
+    pub spin_nanos: u64,
+    /// Number of deadline-based acquisitions that gave up.  Only tracked with `diagnostics`.
+    pub timeouts: u64,
+}
+
+impl<T, R: RawLock> Lock<T, R> {
+    /**
+    A snapshot of the lock's state, for debuggers and for logging a lock that seems stuck.
+
+    ```
+    # use atomiclock_spinlock::Lock;
+    let lock = Lock::with_name(0, "counter");
+    let guard = lock.spin_lock();
+    let state = lock.debug_state();
+    assert!(state.locked);
+    assert_eq!(state.name, Some("counter"));
+    ```
+
+    Unlike [Lock::is_locked], `locked` is practically never spuriously `true`.
+*/
+    pub fn debug_state(&self) -> DebugState {
+        #[cfg(feature = "diagnostics")]
+        let stats = self.stats();
+        let locked = self.__is_locked_for_assert();
+        #[cfg(all(debug_assertions, feature = "std"))]
+        let owner = if locked { self.holder.load(core::sync::atomic::Ordering::Relaxed) } else { 0 };
+        #[cfg(not(all(debug_assertions, feature = "std")))]
+        let owner = 0;
+        DebugState {
+            locked,
+            owner,
+            waiters: self.waiters(),
+            live_guards: self.live_guards(),
+            name: self.name,
+            #[cfg(feature = "diagnostics")]
+            acquisitions: stats.acquisitions,
+            #[cfg(feature = "diagnostics")]
+            contended: stats.contended,
+            #[cfg(feature = "diagnostics")]
+            spin_nanos: stats.spin_time.as_nanos().try_into().unwrap_or(u64::MAX),
+            #[cfg(feature = "diagnostics")]
+            timeouts: stats.timeouts,
+            #[cfg(not(feature = "diagnostics"))]
+            acquisitions: 0,
+            #[cfg(not(feature = "diagnostics"))]
+            contended: 0,
+            #[cfg(not(feature = "diagnostics"))]
+            spin_nanos: 0,
+            #[cfg(not(feature = "diagnostics"))]
+            timeouts: 0,
+        }
+    }
+}
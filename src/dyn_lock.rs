@@ -0,0 +1,97 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Type-erased locks.
+
+[DynLock] is an object-safe view of a lock that doesn't mention the protected type, so heterogeneous
+collections of locks (`&[&dyn DynLock]`) can be locked and unlocked by code that doesn't know what's inside,
+such as debug tooling.
+*/
+
+use core::fmt::Debug;
+use core::marker::PhantomData;
+use crate::{Guard, Lock, StaticLock};
+
+/**
+A lock that can be used without knowing its type.
+
+Implemented by [Lock], [StaticLock], [CriticalLock](crate::CriticalLock),
+[CeilingLock](crate::CeilingLock), and with the `test-util` feature, `TestLock`.
+*/
+pub trait DynLock: Sync {
+    /// Acquires the lock, the same way the lock's own `lock` or `spin_lock` method would.
+    fn lock_dyn(&self) -> DynGuard<'_>;
+    /// No spin; acquires the lock if available.
+    fn try_lock_dyn(&self) -> Option<DynGuard<'_>>;
+    /**
+    Releases the lock.  This is called when a [DynGuard] is dropped.
+
+    # Safety
+    The lock must be held by a [DynGuard], which is being dropped.
+    */
+    unsafe fn unlock_dyn(&self);
+}
+
+/**
+A guard for a [DynLock].
+
+This holds the lock, but doesn't provide access to the data, since its type is unknown.
+Releases the lock when dropped.
+*/
+#[must_use]
+pub struct DynGuard<'a> {
+    lock: &'a dyn DynLock,
+    //some guards must be released on the core that acquired them
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<'a> DynGuard<'a> {
+    /**
+    Creates a guard for a held lock.  Dropping the guard calls [DynLock::unlock_dyn].
+
+    # Safety
+    The lock must be held, and the caller must not release it by any other means.
+*/
+    pub unsafe fn new(lock: &'a dyn DynLock) -> DynGuard<'a> {
+        DynGuard { lock, _not_send: PhantomData }
+    }
+}
+
+impl Drop for DynGuard<'_> {
+    fn drop(&mut self) {
+        unsafe { self.lock.unlock_dyn() }
+    }
+}
+
+impl Debug for DynGuard<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("DynGuard").field("lock", &(self.lock as *const dyn DynLock as *const ())).finish()
+    }
+}
+
+impl<T: Send> DynLock for Lock<T> {
+    fn lock_dyn(&self) -> DynGuard<'_> {
+        Guard::into_raw(self.spin_lock());
+        unsafe { DynGuard::new(self) }
+    }
+    fn try_lock_dyn(&self) -> Option<DynGuard<'_>> {
+        Guard::into_raw(self.try_lock()?);
+        Some(unsafe { DynGuard::new(self) })
+    }
+    unsafe fn unlock_dyn(&self) {
+        drop(self.guard_from_raw())
+    }
+}
+
+impl<T: Send + 'static> DynLock for StaticLock<T> {
+    fn lock_dyn(&self) -> DynGuard<'_> {
+        Guard::into_raw(self.spin_lock());
+        unsafe { DynGuard::new(self) }
+    }
+    fn try_lock_dyn(&self) -> Option<DynGuard<'_>> {
+        Guard::into_raw(self.try_lock()?);
+        Some(unsafe { DynGuard::new(self) })
+    }
+    unsafe fn unlock_dyn(&self) {
+        drop(self.guard_from_raw())
+    }
+}
@@ -0,0 +1,82 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Structured lock events for external profilers.
+
+When a sink is installed with [set_sink], every acquisition, release and contention
+of every [Lock](crate::Lock) is reported to it.  This is enough for an offline tool
+to reconstruct lock timelines.
+
+The sink is called on the hot path, while locks are (or are about to be) held.  It should be
+cheap, and it must not lock any [Lock](crate::Lock) itself.
+
+To see contention on a timeline, install a [ChromeTrace], which keeps the events and writes them as a
+Chrome trace.
+*/
+
+mod chrome;
+pub use chrome::ChromeTrace;
+
+use std::sync::OnceLock;
+use std::thread::ThreadId;
+use std::time::Instant;
+
+/**
+What happened to the lock.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    /// The lock was acquired.
+    Acquire,
+    /// The lock is being released.
+    Release,
+    /// The lock was found to be held, and the thread will spin.
+    Contention,
+}
+
+/**
+A single lock event.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Event {
+    pub kind: EventKind,
+    pub timestamp: Instant,
+    /// Identifies the lock.  This is the lock's address, so it is only unique among live locks.
+    pub lock: usize,
+    /// The name of the lock, if any.
+    pub name: Option<&'static str>,
+    pub thread: ThreadId,
+}
+
+/**
+Receives lock events.
+*/
+pub trait Sink: Sync {
+    fn event(&self, event: &Event);
+}
+
+static SINK: OnceLock<&'static dyn Sink> = OnceLock::new();
+
+/**
+Installs the sink that receives all lock events.
+
+The sink can be installed only once.  If a sink was already installed, the argument is returned.
+*/
+pub fn set_sink(sink: &'static dyn Sink) -> Result<(), &'static dyn Sink> {
+    SINK.set(sink)
+}
+
+#[inline]
+pub(crate) fn emit<T, R: crate::raw::RawLock>(kind: EventKind, lock: &crate::Lock<T, R>) {
+    if !crate::INSTRUMENTED {
+        return;
+    }
+    if let Some(sink) = SINK.get() {
+        sink.event(&Event {
+            kind,
+            timestamp: Instant::now(),
+            lock: crate::addr(lock),
+            name: lock.name(),
+            thread: std::thread::current().id(),
+        });
+    }
+}
@@ -0,0 +1,183 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Exporting lock timelines in Chrome's trace-event format.
+*/
+
+use super::{Event, EventKind, Sink};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::sync::{Mutex, PoisonError};
+use std::thread::ThreadId;
+use std::time::Instant;
+use std::vec::Vec;
+
+//events kept by ChromeTrace::new, about 50 MB
+const DEFAULT_LIMIT: usize = 1 << 20;
+
+/**
+A [Sink] that keeps the events, and writes them as a timeline in Chrome's trace-event JSON format, which
+[Perfetto](https://ui.perfetto.dev), `chrome://tracing` and speedscope open, alongside traces from other
+profilers.
+
+Each thread gets a row, with a slice for each time it waited for a lock, from the first contended attempt
+to acquiring it, and a slice for each time it held one.  Install it as the sink, run the workload, and write
+the trace:
+
+```
+# use atomiclock_spinlock::{events::{self, ChromeTrace}, Lock};
+static TRACE: ChromeTrace = ChromeTrace::new();
+events::set_sink(&TRACE).ok();
+let lock = Lock::with_name(0, "counter");
+*lock.spin_lock() += 1;
+let mut json = Vec::new();
+TRACE.write_json(&mut json).unwrap();
+assert!(String::from_utf8(json).unwrap().contains(r#""name":"hold counter""#));
+```
+
+Recording takes a `std::sync::Mutex` on every event, so it slows down the locks it records, and may change
+how they contend.  It keeps up to a limit of events, and then drops new ones, counted by
+[ChromeTrace::dropped].  Intervals that are still open when the trace is written, or that started before the
+sink was installed, are left out.
+*/
+#[derive(Debug)]
+pub struct ChromeTrace {
+    events: Mutex<Recorded>,
+    limit: usize,
+}
+
+#[derive(Debug)]
+struct Recorded {
+    events: Vec<Event>,
+    dropped: usize,
+}
+
+impl ChromeTrace {
+    /**
+    Creates an empty trace, which keeps up to about a million events.
+*/
+    pub const fn new() -> ChromeTrace {
+        ChromeTrace::with_limit(DEFAULT_LIMIT)
+    }
+
+    /**
+    Creates an empty trace, which keeps up to `limit` events.
+*/
+    pub const fn with_limit(limit: usize) -> ChromeTrace {
+        ChromeTrace { events: Mutex::new(Recorded { events: Vec::new(), dropped: 0 }), limit }
+    }
+
+    //the sink mustn't panic on the lock paths, so a poisoned trace is used as is
+    fn recorded(&self) -> std::sync::MutexGuard<'_, Recorded> {
+        self.events.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /**
+    The number of events dropped because the trace was full.
+*/
+    pub fn dropped(&self) -> usize {
+        self.recorded().dropped
+    }
+
+    /**
+    Discards the events recorded so far, to trace a new stretch of the workload.
+*/
+    pub fn clear(&self) {
+        let mut recorded = self.recorded();
+        recorded.events.clear();
+        recorded.dropped = 0;
+    }
+
+    /**
+    Writes the events recorded so far as a JSON trace.  Recording continues meanwhile.
+*/
+    pub fn write_json(&self, out: impl Write) -> io::Result<()> {
+        let events = self.recorded().events.clone();
+        write_events(&events, out)
+    }
+}
+
+impl Sink for ChromeTrace {
+    fn event(&self, event: &Event) {
+        let mut recorded = self.recorded();
+        if recorded.events.len() < self.limit {
+            recorded.events.push(*event);
+        } else {
+            recorded.dropped += 1;
+        }
+    }
+}
+
+/**
+Pairs up the events into intervals, and writes them as complete ("X") trace events.
+*/
+fn write_events(events: &[Event], out: impl Write) -> io::Result<()> {
+    let mut out = io::BufWriter::new(out);
+    let start = events.iter().map(|e| e.timestamp).min();
+    //threads are numbered in order of appearance, since ThreadId has no stable number
+    let mut threads: HashMap<ThreadId, usize> = HashMap::new();
+    //the start of the open wait and hold for each thread and lock
+    let mut waiting: HashMap<(ThreadId, usize), Instant> = HashMap::new();
+    let mut holding: HashMap<(ThreadId, usize), Instant> = HashMap::new();
+    let pid = std::process::id();
+    write!(out, r#"{{"displayTimeUnit":"ns","traceEvents":["#)?;
+    let mut first = true;
+    for event in events {
+        let next = threads.len() + 1;
+        let tid = *threads.entry(event.thread).or_insert(next);
+        let key = (event.thread, event.lock);
+        let (phase, began) = match event.kind {
+            EventKind::Contention => {
+                waiting.insert(key, event.timestamp);
+                continue;
+            }
+            EventKind::Acquire => {
+                holding.insert(key, event.timestamp);
+                ("wait", waiting.remove(&key))
+            }
+            EventKind::Release => ("hold", holding.remove(&key)),
+        };
+        let (Some(began), Some(start)) = (began, start) else { continue };
+        if !first {
+            out.write_all(b",")?;
+        }
+        first = false;
+        write!(out, r#"{{"name":"{phase} "#)?;
+        match event.name {
+            Some(name) => write_escaped(&mut out, name)?,
+            None => write!(out, "{:#x}", event.lock)?,
+        }
+        write!(out, r#"","cat":"lock","ph":"X","ts":{:.3},"dur":{:.3},"pid":{pid},"tid":{tid},"args":{{"lock":"{:#x}"}}}}"#,
+            micros(began - start), micros(event.timestamp - began), event.lock)?;
+    }
+    out.write_all(b"]}")?;
+    out.flush()
+}
+
+fn micros(duration: std::time::Duration) -> f64 {
+    duration.as_secs_f64() * 1e6
+}
+
+/**
+Writes the contents of a JSON string.
+*/
+fn write_escaped(out: &mut impl Write, text: &str) -> io::Result<()> {
+    for c in text.chars() {
+        match c {
+            '"' => out.write_all(br#"\""#)?,
+            '\\' => out.write_all(br"\\")?,
+            c if u32::from(c) < 0x20 => write!(out, "\\u{:04x}", u32::from(c))?,
+            c => write!(out, "{c}")?,
+        }
+    }
+    Ok(())
+}
+
+/*
+boilerplate
+ */
+
+impl Default for ChromeTrace {
+    fn default() -> Self {
+        ChromeTrace::new()
+    }
+}
@@ -0,0 +1,44 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+The fences behind [Lock::acquire_fence](crate::Lock::acquire_fence) and
+[Lock::release_fence](crate::Lock::release_fence), which order memory for devices as well as for the CPUs.
+
+Each is an atomic fence, for the Rust memory model, and then, where the CPU's fences only order memory for the
+other CPUs, the barrier that orders it for devices too, as Linux's `dma_rmb` and `dma_wmb` do.
+*/
+
+use core::sync::atomic::{fence, Ordering};
+
+/**
+Orders the loads before it before the loads and stores after it.
+*/
+#[inline]
+pub(crate) fn acquire() {
+    fence(Ordering::Acquire);
+    //outer shareable, which takes in the devices
+    #[cfg(all(target_arch = "aarch64", not(miri)))]
+    unsafe {
+        core::arch::asm!("dmb oshld", options(nostack, preserves_flags));
+    }
+    //device input, then memory and device reads and writes
+    #[cfg(all(any(target_arch = "riscv32", target_arch = "riscv64"), not(miri)))]
+    unsafe {
+        core::arch::asm!("fence ir, iorw", options(nostack, preserves_flags));
+    }
+}
+
+/**
+Orders the loads and stores before it before the stores after it.
+*/
+#[inline]
+pub(crate) fn release() {
+    fence(Ordering::Release);
+    #[cfg(all(target_arch = "aarch64", not(miri)))]
+    unsafe {
+        core::arch::asm!("dmb osh", options(nostack, preserves_flags));
+    }
+    #[cfg(all(any(target_arch = "riscv32", target_arch = "riscv64"), not(miri)))]
+    unsafe {
+        core::arch::asm!("fence iorw, ow", options(nostack, preserves_flags));
+    }
+}
@@ -0,0 +1,115 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+A C interface to the spinlock.
+
+This lets C and Rust code in the same program share one lock implementation, including its instrumentation.
+The functions are `#[no_mangle] extern "C"`, so a header can be generated with
+[cbindgen](https://crates.io/crates/cbindgen):
+
+```c
+SpinLock *lock = spinlock_new_named("uart");
+spinlock_lock(lock);
+//...
+spinlock_unlock(lock);
+spinlock_free(lock);
+```
+
+From C, [SpinLock] is an opaque type that is only handled by pointer.
+*/
+
+use alloc::boxed::Box;
+use core::ffi::{c_char, CStr};
+use crate::Lock;
+
+/**
+An opaque lock handle for C.
+*/
+#[derive(Debug, Default)]
+pub struct SpinLock {
+    lock: Lock<()>,
+}
+
+/**
+Creates a new unlocked lock.  Free it with [spinlock_free].
+*/
+#[no_mangle]
+pub extern "C" fn spinlock_new() -> *mut SpinLock {
+    Box::into_raw(Box::new(SpinLock { lock: Lock::new(()) }))
+}
+
+/**
+Creates a new unlocked lock with a name, which appears in instrumentation.  Free it with [spinlock_free].
+
+If `name` is null or not valid UTF-8, the lock is unnamed.
+
+# Safety
+`name` must be null, or a NUL-terminated string that remains valid and unchanged for the rest of the program,
+such as a string literal.
+*/
+#[no_mangle]
+pub unsafe extern "C" fn spinlock_new_named(name: *const c_char) -> *mut SpinLock {
+    let name = if name.is_null() {
+        None
+    } else {
+        CStr::from_ptr(name).to_str().ok()
+    };
+    let lock = match name {
+        Some(name) => Lock::with_name((), name),
+        None => Lock::new(()),
+    };
+    Box::into_raw(Box::new(SpinLock { lock }))
+}
+
+/**
+Frees a lock.
+
+# Safety
+`lock` must be null, or come from [spinlock_new] or [spinlock_new_named] and not have been freed.
+The lock must not be held, and must not be used afterwards.
+*/
+#[no_mangle]
+pub unsafe extern "C" fn spinlock_free(lock: *mut SpinLock) {
+    if !lock.is_null() {
+        drop(Box::from_raw(lock));
+    }
+}
+
+/**
+Spins until the lock can be acquired.  Release it with [spinlock_unlock].
+
+# Safety
+`lock` must be a valid lock.
+*/
+#[no_mangle]
+pub unsafe extern "C" fn spinlock_lock(lock: *const SpinLock) {
+    //C releases the lock with spinlock_unlock
+    core::mem::forget((*lock).lock.spin_lock());
+}
+
+/**
+No spin; acquires the lock if available, returning whether it was acquired.
+
+# Safety
+`lock` must be a valid lock.
+*/
+#[no_mangle]
+pub unsafe extern "C" fn spinlock_try_lock(lock: *const SpinLock) -> bool {
+    match (*lock).lock.try_lock() {
+        Some(guard) => {
+            core::mem::forget(guard);
+            true
+        }
+        None => false,
+    }
+}
+
+/**
+Releases a lock acquired with [spinlock_lock] or [spinlock_try_lock].
+
+# Safety
+`lock` must be a valid lock, held by the caller.
+*/
+#[no_mangle]
+pub unsafe extern "C" fn spinlock_unlock(lock: *const SpinLock) {
+    (*lock).lock.unlock_raw();
+}
@@ -0,0 +1,401 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Acquiring locks from async code.
+
+[Lock::lock_async] returns a [LockFuture], which tries the lock, spins briefly if it is contended, and then
+waits to be woken when the lock is released, so async code can share a lock with sync code without tying up a
+worker thread for the whole wait.  Waiting futures are kept on a list in the lock, which doesn't allocate.
+
+This works on single-threaded targets too, where the lock may be held by another task that needs to run
+before it's released.
+
+With the `tokio` feature, the future cooperates with the tokio scheduler: each poll consumes task budget, and
+yielding goes through `tokio::task::yield_now`, so a contended lock can't starve other tasks on the same worker.
+Outside a tokio runtime, it behaves as without the feature.
+
+When a lock may be held for a pathologically long time, `Lock::lock_blocking` (with `std`) moves the wait off the
+executor, to a blocking pool of your choice.
+
+Other executors can plug in their own way of yielding, and how long to spin first, with a [YieldStrategy].
+
+```
+# use atomiclock_spinlock::Lock;
+# use atomiclock_spinlock::future::YieldStrategy;
+//spin less, e.g. because the executor has other work for this thread
+struct Impatient;
+impl YieldStrategy for Impatient {
+    fn spins(&self) -> u32 { 4 }
+}
+
+async fn increment(lock: &Lock<u32>) {
+    *lock.lock_async_using(Impatient).await += 1;
+}
+```
+*/
+
+use core::fmt::Debug;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use crate::raw::RawLock;
+use crate::wakers::{Kind, Node};
+use crate::{Clock, Guard, Lock, Waiting};
+
+#[cfg(feature = "std")]
+mod blocking;
+#[cfg(feature = "std")]
+pub use blocking::{BlockingLockFuture, Spawner};
+
+//attempts per poll before yielding
+const SPINS: u32 = 64;
+
+/**
+How async acquisition waits when a lock is contended.
+
+Each poll tries the lock [YieldStrategy::spins] times.  If that isn't enough, futures that can register
+with the lock wait for it to be released.  Otherwise (with [Lock::poll_lock_using], or while waiting
+for a deadline) they call [YieldStrategy::yield_now], which must arrange for the task to be polled again.
+*/
+pub trait YieldStrategy {
+    /**
+    Attempts per poll, before waiting.
+
+    On single-threaded targets, only one attempt is made regardless, since spinning can't help.
+*/
+    fn spins(&self) -> u32 {
+        SPINS
+    }
+
+    /**
+    Arranges for the task to be polled again soon.  The poll returns [Poll::Pending] afterwards.
+
+    The default wakes the task immediately.
+*/
+    fn yield_now(&self, cx: &mut Context<'_>) {
+        cx.waker().wake_by_ref();
+    }
+}
+
+/**
+The [YieldStrategy] used unless another is given.
+
+With the `tokio` feature, this yields through `tokio::task::yield_now`, so the runtime polls its other
+tasks first.  Otherwise, it wakes the task immediately.
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct DefaultYield;
+
+impl YieldStrategy for DefaultYield {
+    fn yield_now(&self, cx: &mut Context<'_>) {
+        #[cfg(feature = "tokio")]
+        {
+            //the first poll of yield_now defers our wakeup until the runtime has polled its other tasks
+            let _ = core::pin::pin!(tokio::task::yield_now()).poll(cx);
+        }
+        #[cfg(not(feature = "tokio"))]
+        cx.waker().wake_by_ref();
+    }
+}
+
+/**
+A future that resolves to a [Guard] once the lock is acquired.
+
+Created by [Lock::lock_async].
+*/
+#[must_use = "futures do nothing unless polled"]
+pub struct LockFuture<'a, T, Y: YieldStrategy = DefaultYield> {
+    lock: &'a Lock<T>,
+    waiter: Waiter<'a>,
+    strategy: Y,
+}
+
+/**
+A future's place in the lock's accounting and waker list.
+*/
+struct Waiter<'a> {
+    //present once we've found the lock contended
+    waiting: Option<Waiting<'a>>,
+    node: Node,
+    registered: bool,
+}
+
+impl Waiter<'_> {
+    const fn new() -> Self {
+        Waiter { waiting: None, node: Node::new(), registered: false }
+    }
+
+    /**
+    Leaves the waker list, and stops counting as a waiter.
+*/
+    fn finish<T>(&mut self, lock: &Lock<T>, acquired: bool) {
+        self.waiting = None;
+        if self.registered {
+            lock.wakers.unregister(&self.node, acquired);
+            self.registered = false;
+        }
+    }
+}
+
+impl<T> Lock<T> {
+    /**
+    Acquires the lock asynchronously.
+
+    The first poll tries the lock, then spins briefly.  If that isn't enough, the future waits until the lock
+    is released.
+*/
+    pub fn lock_async(&self) -> LockFuture<'_, T> {
+        self.lock_async_using(DefaultYield)
+    }
+
+    /**
+    Acquires the lock asynchronously, waiting according to `strategy`.
+*/
+    pub fn lock_async_using<Y: YieldStrategy>(&self, strategy: Y) -> LockFuture<'_, T, Y> {
+        LockFuture { lock: self, waiter: Waiter::new(), strategy }
+    }
+
+    /**
+    Tries to acquire the lock as part of polling a hand-written future.
+
+    This behaves like one poll of [Lock::lock_async], except that there is nowhere to keep our place in
+    the lock's waker list.  So if the lock can't be acquired after spinning briefly, the task is woken to
+    poll again right away, as if it had yielded.  Also, each pending poll counts as a separate contended
+    acquisition in the lock's statistics.
+*/
+    pub fn poll_lock(&self, cx: &mut Context<'_>) -> Poll<Guard<'_, T>> {
+        self.poll_lock_using(cx, &DefaultYield)
+    }
+
+    /**
+    Like [Lock::poll_lock], but spins and yields according to `strategy`.
+*/
+    pub fn poll_lock_using<Y: YieldStrategy>(&self, cx: &mut Context<'_>, strategy: &Y) -> Poll<Guard<'_, T>> {
+        poll_acquire(self, &mut None, None, strategy, cx)
+    }
+
+    /**
+    Acquires the lock asynchronously, or gives up at the deadline.
+
+    The deadline is checked each time the future is polled, so no timer is needed.
+*/
+    #[cfg(feature = "std")]
+    pub fn lock_async_until(&self, deadline: std::time::Instant) -> LockTimeoutFuture<'_, T, crate::clock::StdClock> {
+        self.lock_async_until_with(&crate::clock::StdClock, deadline)
+    }
+
+    /**
+    Acquires the lock asynchronously, or gives up after the duration.
+
+    # Panics
+    Panics if the deadline overflows [std::time::Instant], such as for [Duration::MAX](std::time::Duration::MAX).
+    Use [Lock::lock_async_until] to avoid this.
+*/
+    #[cfg(feature = "std")]
+    pub fn lock_async_timeout(&self, duration: std::time::Duration) -> LockTimeoutFuture<'_, T, crate::clock::StdClock> {
+        self.lock_async_until(crate::clock::StdClock.now() + duration)
+    }
+
+    /**
+    Acquires the lock asynchronously, once `condition` holds for the data.
+
+    Each attempt acquires the lock and checks `condition`.  If it doesn't hold, the lock is released and the
+    future waits before trying again.  Since the data can only change under the lock, this covers the
+    producer/consumer pattern: producers update the data with any kind of lock acquisition, and consumers
+    wait for the update with this.
+
+    ```
+    # use atomiclock_spinlock::Lock;
+    # use std::collections::VecDeque;
+    async fn consume(queue: &Lock<VecDeque<u32>>) -> u32 {
+        queue.wait_async_until(|q| !q.is_empty()).await.pop_front().unwrap()
+    }
+    ```
+*/
+    pub fn wait_async_until<F: FnMut(&mut T) -> bool>(&self, condition: F) -> WaitUntilFuture<'_, T, F> {
+        WaitUntilFuture { lock: self, waiter: Waiter::new(), condition }
+    }
+
+    /**
+    Acquires the lock asynchronously, or gives up when the clock passes the deadline.
+*/
+    pub fn lock_async_until_with<'a, C: Clock>(&'a self, clock: &'a C, deadline: C::Instant) -> LockTimeoutFuture<'a, T, C> {
+        LockTimeoutFuture { lock: self, waiter: Waiter::new(), clock, deadline }
+    }
+}
+
+impl<'a, T, Y: YieldStrategy> Future for LockFuture<'a, T, Y> {
+    type Output = Guard<'a, T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Guard<'a, T>> {
+        //the waiter's node is never moved out
+        let this = unsafe { self.get_unchecked_mut() };
+        poll_waiter(this.lock, &mut this.waiter, &this.strategy, cx)
+    }
+}
+
+impl<T, Y: YieldStrategy> Drop for LockFuture<'_, T, Y> {
+    fn drop(&mut self) {
+        self.waiter.finish(self.lock, false);
+    }
+}
+
+/**
+A future that resolves to a [Guard] once the lock is acquired, or to `None` at a deadline.
+
+Created by [Lock::lock_async_until_with] and friends.
+*/
+#[must_use = "futures do nothing unless polled"]
+pub struct LockTimeoutFuture<'a, T, C: Clock> {
+    lock: &'a Lock<T>,
+    waiter: Waiter<'a>,
+    clock: &'a C,
+    deadline: C::Instant,
+}
+
+impl<'a, T, C: Clock> Future for LockTimeoutFuture<'a, T, C> {
+    type Output = Option<Guard<'a, T>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Guard<'a, T>>> {
+        //the waiter's node is never moved out
+        let this = unsafe { self.get_unchecked_mut() };
+        if let Poll::Ready(guard) = poll_waiter(this.lock, &mut this.waiter, &DefaultYield, cx) {
+            return Poll::Ready(Some(guard));
+        }
+        if this.clock.now() > this.deadline {
+            this.waiter.finish(this.lock, false);
+            #[cfg(feature = "diagnostics")]
+            this.lock.stats.timed_out();
+            return Poll::Ready(None);
+        }
+        //nobody wakes us at the deadline, so keep polling
+        DefaultYield.yield_now(cx);
+        Poll::Pending
+    }
+}
+
+impl<T, C: Clock> Drop for LockTimeoutFuture<'_, T, C> {
+    fn drop(&mut self) {
+        self.waiter.finish(self.lock, false);
+    }
+}
+
+/**
+A future that resolves to a [Guard] once a condition holds for the data.
+
+Created by [Lock::wait_async_until].
+*/
+#[must_use = "futures do nothing unless polled"]
+pub struct WaitUntilFuture<'a, T, F> {
+    lock: &'a Lock<T>,
+    waiter: Waiter<'a>,
+    condition: F,
+}
+
+impl<'a, T, F: FnMut(&mut T) -> bool> Future for WaitUntilFuture<'a, T, F> {
+    type Output = Guard<'a, T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Guard<'a, T>> {
+        //the waiter's node is never moved out
+        let this = unsafe { self.get_unchecked_mut() };
+        let mut guard = core::task::ready!(poll_waiter(this.lock, &mut this.waiter, &DefaultYield, cx));
+        if (this.condition)(&mut guard) {
+            return Poll::Ready(guard);
+        }
+        //register before releasing, so we're woken by the next change
+        unsafe { this.lock.wakers.register(&this.waiter.node, Kind::Condition, cx.waker()) };
+        this.waiter.registered = true;
+        //we didn't change anything, so other condition waiters needn't check again
+        Guard::into_raw(guard);
+        this.lock.release(false);
+        Poll::Pending
+    }
+}
+
+impl<T, F> Drop for WaitUntilFuture<'_, T, F> {
+    fn drop(&mut self) {
+        self.waiter.finish(self.lock, false);
+    }
+}
+
+fn poll_waiter<'a, T>(lock: &'a Lock<T>, waiter: &mut Waiter<'a>, strategy: &impl YieldStrategy, cx: &mut Context<'_>) -> Poll<Guard<'a, T>> {
+    let Waiter { waiting, node, registered } = waiter;
+    poll_acquire(lock, waiting, Some((node, registered)), strategy, cx)
+}
+
+/**
+One attempt at the lock.  Without a node to register, this yields instead of waiting for a release.
+*/
+fn poll_acquire<'a, T>(lock: &'a Lock<T>, waiting: &mut Option<Waiting<'a>>, mut node: Option<(&Node, &mut bool)>, strategy: &impl YieldStrategy, cx: &mut Context<'_>) -> Poll<Guard<'a, T>> {
+    #[cfg(feature = "tokio")]
+    let coop = core::task::ready!(tokio::task::coop::poll_proceed(cx));
+    //on a single-threaded target, only yielding can help
+    let spins = if crate::SINGLE_THREADED { 1 } else { strategy.spins().max(1) };
+    for _ in 0..spins {
+        if lock.raw.try_lock() {
+            #[cfg(feature = "tokio")]
+            coop.made_progress();
+            return Poll::Ready(acquired(lock, waiting, node));
+        }
+        if waiting.is_none() {
+            *waiting = Some(lock.waiting());
+        }
+        crate::arch::relax();
+    }
+    match &mut node {
+        None => {
+            strategy.yield_now(cx);
+            Poll::Pending
+        }
+        Some((node, registered)) => {
+            unsafe { lock.wakers.register(node, Kind::Lock, cx.waker()) };
+            **registered = true;
+            //the lock may have been released before we registered
+            if lock.raw.try_lock() {
+                #[cfg(feature = "tokio")]
+                coop.made_progress();
+                Poll::Ready(acquired(lock, waiting, Some((node, registered))))
+            } else {
+                Poll::Pending
+            }
+        }
+    }
+}
+
+fn acquired<'a, T>(lock: &'a Lock<T>, waiting: &mut Option<Waiting<'a>>, node: Option<(&Node, &mut bool)>) -> Guard<'a, T> {
+    *waiting = None;
+    if let Some((node, registered)) = node {
+        if *registered {
+            lock.wakers.unregister(node, true);
+            *registered = false;
+        }
+    }
+    lock.acquired()
+}
+
+impl<T, Y: YieldStrategy> Debug for LockFuture<'_, T, Y> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("LockFuture")
+            .field("name", &self.lock.name())
+            .field("contended", &self.waiter.waiting.is_some())
+            .finish()
+    }
+}
+
+impl<T, F> Debug for WaitUntilFuture<'_, T, F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("WaitUntilFuture")
+            .field("name", &self.lock.name())
+            .field("contended", &self.waiter.waiting.is_some())
+            .finish()
+    }
+}
+
+impl<T, C: Clock> Debug for LockTimeoutFuture<'_, T, C> where C::Instant: Debug {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("LockTimeoutFuture")
+            .field("name", &self.lock.name())
+            .field("contended", &self.waiter.waiting.is_some())
+            .field("deadline", &self.deadline)
+            .finish()
+    }
+}
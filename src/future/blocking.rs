@@ -0,0 +1,180 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Offloading pathological waits to a blocking pool.
+*/
+
+use core::fmt::Debug;
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicU8, Ordering};
+use core::task::{Context, Poll, Waker};
+use std::boxed::Box;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use super::{DefaultYield, Waiter, YieldStrategy};
+use crate::clock::StdClock;
+use crate::{Clock, Guard, Lock};
+
+/**
+Runs jobs off the async executor, for [Lock::lock_blocking].
+
+This is implemented for closures, so a spawner is typically something like
+`|job| { tokio::task::spawn_blocking(job); }`.
+*/
+pub trait Spawner {
+    /**
+    Runs `job` on a thread where it's fine to block.
+*/
+    fn spawn_blocking(&self, job: Box<dyn FnOnce() + Send>);
+}
+
+impl<F: Fn(Box<dyn FnOnce() + Send>)> Spawner for F {
+    fn spawn_blocking(&self, job: Box<dyn FnOnce() + Send>) {
+        self(job)
+    }
+}
+
+//states of a Handoff
+const WAITING: u8 = 0;
+const ACQUIRED: u8 = 1;
+const CLAIMED: u8 = 2;
+const CANCELLED: u8 = 3;
+
+/**
+Passes the lock from the blocking job to the future.
+*/
+#[derive(Debug)]
+struct Handoff {
+    state: AtomicU8,
+    waker: atomiclock::AtomicLock<Option<Waker>>,
+}
+
+impl<T: Send + 'static> Lock<T> {
+    /**
+    Acquires the lock asynchronously, moving the wait to a blocking pool if it takes longer than `threshold`.
+
+    Until the threshold, this behaves like [Lock::lock_async], except that it keeps polling so that it
+    notices the threshold.  After that, the acquisition is handed to `spawner`, and the future resolves when
+    the job acquires the lock, so a lock that's held for a pathologically long time doesn't tie up an
+    executor thread.  If the future is dropped first, the job gives up.
+
+    On single-threaded targets, there is nowhere to block, so the wait is never offloaded.
+
+    ```
+    # use atomiclock_spinlock::Lock;
+    # use std::time::Duration;
+    static LOCK: Lock<u32> = Lock::new(0);
+    async fn increment() {
+        let spawner = |job| { std::thread::spawn(job); };
+        *LOCK.lock_blocking(Duration::from_millis(1), spawner).await += 1;
+    }
+    ```
+*/
+    pub fn lock_blocking<S: Spawner>(&'static self, threshold: Duration, spawner: S) -> BlockingLockFuture<T, S> {
+        BlockingLockFuture {
+            lock: self,
+            waiter: Waiter::new(),
+            threshold,
+            started: None,
+            spawner: Some(spawner),
+            handoff: None,
+        }
+    }
+}
+
+/**
+A future that resolves to a [Guard] once the lock is acquired, either by itself or by a blocking job.
+
+Created by [Lock::lock_blocking].
+*/
+#[must_use = "futures do nothing unless polled"]
+pub struct BlockingLockFuture<T: 'static, S> {
+    lock: &'static Lock<T>,
+    waiter: Waiter<'static>,
+    threshold: Duration,
+    //when we first found the lock contended
+    started: Option<Instant>,
+    //taken when the job is spawned
+    spawner: Option<S>,
+    handoff: Option<Arc<Handoff>>,
+}
+
+impl<T: Send + 'static, S: Spawner> Future for BlockingLockFuture<T, S> {
+    type Output = Guard<'static, T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Guard<'static, T>> {
+        //the waiter's node is never moved out
+        let this = unsafe { self.get_unchecked_mut() };
+        if let Some(handoff) = &this.handoff {
+            *crate::spin_raw(&handoff.waker) = Some(cx.waker().clone());
+            //checked after storing the waker, so either the job sees the waker or we see the lock
+            if handoff.state.compare_exchange(ACQUIRED, CLAIMED, Ordering::Acquire, Ordering::Relaxed).is_ok() {
+                //the job acquired the lock for us
+                return Poll::Ready(unsafe { this.lock.guard_from_raw() });
+            }
+            return Poll::Pending;
+        }
+        if let Poll::Ready(guard) = super::poll_waiter(this.lock, &mut this.waiter, &DefaultYield, cx) {
+            return Poll::Ready(guard);
+        }
+        let now = StdClock.now();
+        let started = *this.started.get_or_insert(now);
+        if crate::SINGLE_THREADED || now.duration_since(started) < this.threshold {
+            //nobody wakes us at the threshold, so keep polling
+            DefaultYield.yield_now(cx);
+            return Poll::Pending;
+        }
+        this.waiter.finish(this.lock, false);
+        let handoff = Arc::new(Handoff {
+            state: AtomicU8::new(WAITING),
+            waker: atomiclock::AtomicLock::new(Some(cx.waker().clone())),
+        });
+        this.handoff = Some(handoff.clone());
+        let lock = this.lock;
+        let job = move || {
+            let waiting = lock.contended();
+            let acquired = lock.spin(|| handoff.state.load(Ordering::Relaxed) == CANCELLED);
+            drop(waiting);
+            if !acquired {
+                return;
+            }
+            Guard::into_raw(lock.acquired());
+            if handoff.state.compare_exchange(WAITING, ACQUIRED, Ordering::Release, Ordering::Relaxed).is_err() {
+                //the future was dropped meanwhile
+                drop(unsafe { lock.guard_from_raw() });
+                return;
+            }
+            let waker = crate::spin_raw(&handoff.waker).take();
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        };
+        //only spawned once, since the handoff is now set
+        if let Some(spawner) = this.spawner.take() {
+            spawner.spawn_blocking(Box::new(job));
+        }
+        Poll::Pending
+    }
+}
+
+impl<T: 'static, S> Drop for BlockingLockFuture<T, S> {
+    fn drop(&mut self) {
+        self.waiter.finish(self.lock, false);
+        if let Some(handoff) = &self.handoff {
+            if handoff.state.swap(CANCELLED, Ordering::Acquire) == ACQUIRED {
+                //the job acquired the lock, but nobody claimed it
+                drop(unsafe { self.lock.guard_from_raw() });
+            }
+        }
+    }
+}
+
+impl<T: 'static, S> Debug for BlockingLockFuture<T, S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("BlockingLockFuture")
+            .field("name", &self.lock.name())
+            .field("contended", &self.started.is_some())
+            .field("offloaded", &self.handoff.is_some())
+            .finish()
+    }
+}
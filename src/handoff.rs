@@ -0,0 +1,90 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Handing a contended lock straight to a starving waiter, with the `handoff` feature.
+
+The locks aren't fair: on release, whichever waiter tries next wins, so an unlucky one can lose every race.
+With this feature, a waiter that has spun [Settings::handoff_after](crate::config::Settings::handoff_after)
+times asks for the lock, and the next release leaves it held, and hands it to that waiter, instead of
+releasing it.  This bounds starvation to one critical section after asking, without a queue of waiters.
+
+Only one waiter asks at a time; the others keep spinning, and one of them asks once the lock has been handed.
+A release that bypasses the [Lock](crate::Lock), by unlocking the raw lock directly, doesn't hand off, so the
+waiter that asked can also still acquire the lock as usual.  With `std-mutex`, waiters block in the mutex instead,
+and nothing is handed off.
+*/
+
+use core::sync::atomic::Ordering;
+use crate::sync::AtomicUsize;
+
+//nobody has asked
+const NONE: usize = 0;
+//a waiter has asked, and the next release hands the lock to it
+const REQUESTED: usize = 1;
+//the lock was left held for the waiter that asked
+const HANDED_OFF: usize = 2;
+
+/**
+Per-lock state for handing off.
+*/
+#[derive(Debug)]
+pub(crate) struct Handoff(AtomicUsize);
+
+impl Handoff {
+    const_fn! {
+        pub(crate) const fn new() -> Handoff {
+            Handoff(AtomicUsize::new(NONE))
+        }
+    }
+
+    /**
+    Asks for the lock to be handed to the current thread.  Returns whether it asked, which fails if
+    another waiter already has.
+
+    A waiter that asked must keep waiting until it has the lock, either from [Handoff::take] or by acquiring it
+    itself, and then call [Handoff::acquired].
+    */
+    #[inline]
+    pub(crate) fn request(&self) -> bool {
+        self.0.compare_exchange(NONE, REQUESTED, Ordering::Relaxed, Ordering::Relaxed).is_ok()
+    }
+
+    /**
+    For the waiter that asked: whether the lock was handed to it.  If so, it holds the lock.
+    */
+    #[inline]
+    pub(crate) fn take(&self) -> bool {
+        //pairs with the release in hand_off, so the last holder's writes are visible
+        if self.0.load(Ordering::Acquire) == HANDED_OFF {
+            self.0.store(NONE, Ordering::Relaxed);
+            true
+        } else {
+            false
+        }
+    }
+
+    /**
+    For the waiter that asked: it acquired the lock itself, so withdraws the request.
+    */
+    #[inline]
+    pub(crate) fn acquired(&self) {
+        //while we hold the lock, nobody releases it, so nobody can hand it off
+        self.0.store(NONE, Ordering::Relaxed);
+    }
+
+    /**
+    On release: if a waiter asked, hands the lock to it, in which case the lock must be left held.
+    */
+    #[inline]
+    pub(crate) fn hand_off(&self) -> bool {
+        self.0.load(Ordering::Relaxed) == REQUESTED
+            && self.0.compare_exchange(REQUESTED, HANDED_OFF, Ordering::Release, Ordering::Relaxed).is_ok()
+    }
+
+    /**
+    Forgets any request, for a lock reset after a fork, where the waiter that asked didn't survive.
+    */
+    #[inline]
+    pub(crate) fn reset(&self) {
+        self.0.store(NONE, Ordering::Relaxed);
+    }
+}
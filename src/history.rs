@@ -0,0 +1,179 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Locks that remember their previous values, for debugging.
+*/
+
+use core::fmt::Debug;
+use core::ops::{Deref, DerefMut};
+use crate::{Guard, Lock};
+
+struct State<T, const N: usize> {
+    value: T,
+    //a ring of the previous values; the most recent is just before `next`
+    previous: [Option<T>; N],
+    next: usize,
+}
+
+impl<T, const N: usize> State<T, N> {
+    const EMPTY: Option<T> = None;
+
+    fn previous(&self, k: usize) -> Option<&T> {
+        if k >= N {
+            return None;
+        }
+        self.previous[(self.next + N - 1 - k) % N].as_ref()
+    }
+}
+
+impl<T: Clone, const N: usize> State<T, N> {
+    fn record(&mut self) {
+        if N == 0 {
+            return;
+        }
+        self.previous[self.next] = Some(self.value.clone());
+        self.next = (self.next + 1) % N;
+    }
+}
+
+/**
+A [Lock] that keeps clones of the last `N` values it held.
+
+Each guard that writes through [DerefMut] first saves a clone of the value as it was, so when the value is
+wrong, the history shows what it was before, and before that.
+
+```
+# use atomiclock_spinlock::HistoryLock;
+let lock: HistoryLock<u32, 2> = HistoryLock::new(1);
+*lock.lock() = 2;
+*lock.lock() += 1;
+//reading doesn't add to the history
+assert_eq!(*lock.lock(), 3);
+assert_eq!(lock.previous(0), Some(2));
+assert_eq!(lock.previous(1), Some(1));
+*lock.lock() = 4;
+//only the last two are kept
+assert_eq!(lock.previous(1), Some(2));
+assert_eq!(lock.previous(2), None);
+```
+
+A guard saves at most one value, however many times it writes.
+*/
+pub struct HistoryLock<T, const N: usize> {
+    lock: Lock<State<T, N>>,
+}
+
+impl<T, const N: usize> HistoryLock<T, N> {
+    const_fn! {
+        /**
+        Creates a new lock, with no history.
+        */
+        pub const fn new(value: T) -> Self {
+            HistoryLock { lock: Lock::new(State { value, previous: [State::<T, N>::EMPTY; N], next: 0 }) }
+        }
+    }
+
+    /**
+    Spins until the lock can be acquired.
+*/
+    pub fn lock(&self) -> HistoryGuard<'_, T, N> {
+        HistoryGuard { guard: self.lock.spin_lock(), recorded: false }
+    }
+
+    /**
+    No spin; acquires the lock if it's available.
+*/
+    pub fn try_lock(&self) -> Option<HistoryGuard<'_, T, N>> {
+        Some(HistoryGuard { guard: self.lock.try_lock()?, recorded: false })
+    }
+
+    /**
+    Consumes the lock, returning the value.
+*/
+    pub fn into_inner(self) -> T {
+        self.lock.into_inner().value
+    }
+}
+
+impl<T: Clone, const N: usize> HistoryLock<T, N> {
+    /**
+    A clone of the value before the `k`th most recent write, where 0 is the last write, if it's still kept.
+*/
+    pub fn previous(&self, k: usize) -> Option<T> {
+        self.lock.spin_lock().previous(k).cloned()
+    }
+}
+
+/**
+A guard for a [HistoryLock].
+*/
+#[must_use]
+pub struct HistoryGuard<'a, T, const N: usize> {
+    guard: Guard<'a, State<T, N>>,
+    //whether this guard saved the value yet
+    recorded: bool,
+}
+
+impl<T, const N: usize> HistoryGuard<'_, T, N> {
+    /**
+    The value before the `k`th most recent write, as in [HistoryLock::previous], without cloning it.
+*/
+    pub fn previous(guard: &Self, k: usize) -> Option<&T> {
+        guard.guard.previous(k)
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<T, const N: usize> Deref for HistoryGuard<'_, T, N> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.guard.value
+    }
+}
+
+impl<T: Clone, const N: usize> DerefMut for HistoryGuard<'_, T, N> {
+    fn deref_mut(&mut self) -> &mut T {
+        if !self.recorded {
+            self.guard.record();
+            self.recorded = true;
+        }
+        &mut self.guard.value
+    }
+}
+
+impl<T: Debug, const N: usize> Debug for HistoryGuard<'_, T, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("HistoryGuard").field(&self.guard.value).finish()
+    }
+}
+
+impl<T: Debug, const N: usize> Debug for HistoryLock<T, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.lock.try_lock() {
+            Some(state) => {
+                let mut s = f.debug_struct("HistoryLock");
+                s.field("value", &state.value);
+                s.field("previous", &Previous(&state));
+                s.finish()
+            }
+            None => f.write_str("HistoryLock(<locked>)"),
+        }
+    }
+}
+
+//most recent first
+struct Previous<'a, T, const N: usize>(&'a State<T, N>);
+
+impl<T: Debug, const N: usize> Debug for Previous<'_, T, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries((0..N).map_while(|k| self.0.previous(k))).finish()
+    }
+}
+
+impl<T: Default, const N: usize> Default for HistoryLock<T, N> {
+    fn default() -> Self {
+        HistoryLock::new(T::default())
+    }
+}
@@ -0,0 +1,89 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Guards that run a closure just before they release the lock.
+*/
+
+use core::fmt::Debug;
+use core::ops::{Deref, DerefMut};
+use crate::Guard;
+
+/**
+A [Guard] with a closure to run on the data right before the lock is released.
+
+Create one with [Guard::on_unlock].
+*/
+#[must_use]
+pub struct HookedGuard<'a, T, F: FnOnce(&mut T)> {
+    guard: Guard<'a, T>,
+    //taken when it runs
+    hook: Option<F>,
+}
+
+impl<'a, T> Guard<'a, T> {
+    /**
+    Attaches `hook`, to run on the data right before the lock is released, such as to bump a version
+    number, or log the final value, at every place the guard might be dropped.
+
+    ```
+    # use atomiclock_spinlock::{Guard, Lock};
+    struct Versioned { value: u32, version: u64 }
+    let lock = Lock::new(Versioned { value: 0, version: 0 });
+    let mut guard = Guard::on_unlock(lock.spin_lock(), |v| v.version += 1);
+    guard.value = 5;
+    drop(guard);
+    assert_eq!(lock.spin_lock().version, 1);
+    ```
+
+    The hook also runs if the guard is dropped while unwinding.  If the hook panics, the lock is released
+    anyway.
+
+    This is an associated function, since the guard dereferences to the data; call it as
+    `Guard::on_unlock(guard, hook)`.
+*/
+    pub fn on_unlock<F: FnOnce(&mut T)>(guard: Self, hook: F) -> HookedGuard<'a, T, F> {
+        HookedGuard { guard, hook: Some(hook) }
+    }
+}
+
+impl<'a, T, F: FnOnce(&mut T)> HookedGuard<'a, T, F> {
+    /**
+    Removes the hook without running it, returning the plain guard.
+*/
+    pub fn into_guard(mut this: Self) -> Guard<'a, T> {
+        this.hook = None;
+        //move the guard out without running Drop; the hook is gone, so nothing else needs dropping
+        let this = core::mem::ManuallyDrop::new(this);
+        unsafe { core::ptr::read(&this.guard) }
+    }
+}
+
+impl<T, F: FnOnce(&mut T)> Drop for HookedGuard<'_, T, F> {
+    fn drop(&mut self) {
+        if let Some(hook) = self.hook.take() {
+            hook(&mut self.guard);
+        }
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<T, F: FnOnce(&mut T)> Deref for HookedGuard<'_, T, F> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T, F: FnOnce(&mut T)> DerefMut for HookedGuard<'_, T, F> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T: Debug, F: FnOnce(&mut T)> Debug for HookedGuard<'_, T, F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("HookedGuard").field(&*self.guard).finish()
+    }
+}
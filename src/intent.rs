@@ -0,0 +1,331 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Hierarchical locking with intention modes, for trees of locks.
+
+Each node of the tree, such as a scene graph node or a directory, is an [IntentLock], typically holding its
+children.  Locking a node in [Shared] or [Exclusive] mode covers the node and the whole subtree under it.  To
+lock a node further down, each node on the way is locked in an *intention* mode first, which says that
+something below will be locked:
+
+* [IntentShared] (IS) - something below will be locked in [Shared] mode.
+* [IntentExclusive] (IX) - something below will be locked in any mode.
+* [Shared] (S) - reading the node and everything below.
+* [Exclusive] (X) - writing the node and everything below.
+
+Modes are compatible when their holders can't step on each other:
+
+| held \ wanted | IS | IX | S | X |
+|---------------|----|----|---|---|
+| IS            | ✓  | ✓  | ✓ |   |
+| IX            | ✓  | ✓  |   |   |
+| S             | ✓  |    | ✓ |   |
+| X             |    |    |   |   |
+
+So writers on different branches each hold IX on their common ancestors, and proceed in parallel, while a
+reader that takes S high up waits until they're done, and then sees a consistent subtree.
+
+```
+use atomiclock_spinlock::intent::{Exclusive, IntentExclusive, IntentGuard, IntentLock, Shared};
+struct Node { value: u32, children: Vec<IntentLock<Node>> }
+let leaf = || IntentLock::new(Node { value: 0, children: Vec::new() });
+let scene = IntentLock::new(Node { value: 0, children: vec![leaf(), leaf()] });
+std::thread::scope(|s| {
+    for i in 0..2 {
+        let scene = &scene;
+        //writers on different children don't wait for each other
+        s.spawn(move || {
+            let root: IntentGuard<_, IntentExclusive> = scene.lock();
+            let mut child: IntentGuard<_, Exclusive> = root.children[i].lock_under(&root);
+            child.value += 1;
+        });
+    }
+});
+let root: IntentGuard<_, Shared> = scene.lock();
+let total: u32 = root.children.iter().map(|child| child.lock_under::<Shared, _, _>(&root).value).sum();
+assert_eq!(total, 2);
+```
+
+A guard for a node's children borrows the guard for the node, so the intention outlives the locks below it.
+Every mode but [Exclusive] reads the node, which is how the children are reached, so the data must be `Sync`.
+Nothing checks that the guard passed to [IntentLock::lock_under] is for the node's actual parent; if it
+isn't, each node still excludes incompatible holders, but a coarse lock no longer covers the subtree.
+
+Like the other locks, this is not fair.
+*/
+
+use core::cell::UnsafeCell;
+use core::fmt::Debug;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::Ordering;
+use crate::sync::AtomicUsize;
+
+//state layout: bit 0 is the exclusive holder, and then a count for each of the other modes
+const EXCLUSIVE: usize = 1;
+const COUNT_BITS: u32 = (usize::BITS - 1) / 3;
+const COUNT_MASK: usize = (1 << COUNT_BITS) - 1;
+const INTENT_SHARED_SHIFT: u32 = 1;
+const INTENT_EXCLUSIVE_SHIFT: u32 = 1 + COUNT_BITS;
+const SHARED_SHIFT: u32 = 1 + 2 * COUNT_BITS;
+
+mod private {
+    pub trait Sealed {}
+}
+
+/**
+A mode to lock an [IntentLock] in: [IntentShared], [IntentExclusive], [Shared] or [Exclusive].
+*/
+pub trait Mode: private::Sealed {
+    /**
+    The bits of the state this mode's holders are counted in.
+    */
+    #[doc(hidden)]
+    const SHIFT: u32;
+    /**
+    The modes this one can't be held alongside, as a mask of the state.
+    */
+    #[doc(hidden)]
+    const CONFLICTS: usize;
+}
+
+/**
+A parent mode that allows locking children in mode `M`.
+
+Intention modes allow what they intend: [IntentShared] allows [IntentShared] and [Shared] below, and
+[IntentExclusive] allows any mode below.  [Shared] and [Exclusive] already cover the subtree, but allow the
+same as their intention modes, so code that needs a guard for a child can get one.
+
+```compile_fail
+# use atomiclock_spinlock::intent::{Exclusive, IntentGuard, IntentLock, IntentShared};
+let parent = IntentLock::new(IntentLock::new(0));
+let intent: IntentGuard<_, IntentShared> = parent.lock();
+//an intention to read doesn't allow writing below
+let child: IntentGuard<_, Exclusive> = intent.lock_under(&intent);
+```
+*/
+pub trait Permits<M: Mode>: Mode {}
+
+/**
+Intention shared (IS): something below will be locked in [Shared] mode.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct IntentShared;
+
+/**
+Intention exclusive (IX): something below will be locked in any mode.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct IntentExclusive;
+
+/**
+Shared (S): reading the node and everything below.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Shared;
+
+/**
+Exclusive (X): writing the node and everything below.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Exclusive;
+
+impl private::Sealed for IntentShared {}
+impl private::Sealed for IntentExclusive {}
+impl private::Sealed for Shared {}
+impl private::Sealed for Exclusive {}
+
+impl Mode for IntentShared {
+    const SHIFT: u32 = INTENT_SHARED_SHIFT;
+    const CONFLICTS: usize = EXCLUSIVE;
+}
+
+impl Mode for IntentExclusive {
+    const SHIFT: u32 = INTENT_EXCLUSIVE_SHIFT;
+    const CONFLICTS: usize = EXCLUSIVE | COUNT_MASK << SHARED_SHIFT;
+}
+
+impl Mode for Shared {
+    const SHIFT: u32 = SHARED_SHIFT;
+    const CONFLICTS: usize = EXCLUSIVE | COUNT_MASK << INTENT_EXCLUSIVE_SHIFT;
+}
+
+impl Mode for Exclusive {
+    //the exclusive bit, which counts to 1
+    const SHIFT: u32 = 0;
+    const CONFLICTS: usize = usize::MAX;
+}
+
+impl Permits<IntentShared> for IntentShared {}
+impl Permits<Shared> for IntentShared {}
+impl Permits<IntentShared> for Shared {}
+impl Permits<Shared> for Shared {}
+impl<M: Mode> Permits<M> for IntentExclusive {}
+impl<M: Mode> Permits<M> for Exclusive {}
+
+/**
+One node of a tree of locks.  See the [module documentation](self).
+*/
+pub struct IntentLock<T> {
+    state: AtomicUsize,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Send for IntentLock<T> {}
+unsafe impl<T: Send + Sync> Sync for IntentLock<T> {}
+
+/**
+A guard for an [IntentLock], in mode `M`.  Dereferences to the node's data, mutably only in [Exclusive] mode.
+*/
+#[must_use]
+pub struct IntentGuard<'a, T, M: Mode> {
+    lock: &'a IntentLock<T>,
+    mode: PhantomData<M>,
+}
+
+impl<T> IntentLock<T> {
+    const_fn! {
+        /**
+        Creates a new, unlocked node.
+        */
+        pub const fn new(data: T) -> IntentLock<T> {
+            IntentLock { state: AtomicUsize::new(0), data: UnsafeCell::new(data) }
+        }
+    }
+
+    /**
+    Spins until the node can be locked in mode `M`, without a parent, as for the root of the tree.
+*/
+    pub fn lock<M: Mode>(&self) -> IntentGuard<'_, T, M> {
+        let mut spins = crate::wait::Spins::new();
+        while !self.raw_try_lock::<M>() {
+            if crate::SINGLE_THREADED {
+                panic!("IntentLock is already held; on a single-threaded target, spinning on it would never finish");
+            }
+            crate::wait::relax(&mut spins);
+        }
+        IntentGuard { lock: self, mode: PhantomData }
+    }
+
+    /**
+    No spin; locks the node in mode `M` if that's compatible with the modes it's held in.
+*/
+    pub fn try_lock<M: Mode>(&self) -> Option<IntentGuard<'_, T, M>> {
+        self.raw_try_lock::<M>().then(|| IntentGuard { lock: self, mode: PhantomData })
+    }
+
+    /**
+    Spins until the node can be locked in mode `M`, where `parent` is held in a mode that allows it.
+
+    The guard borrows `parent`, so the parent stays locked for as long as the child is.
+*/
+    pub fn lock_under<'p, M: Mode, U, P: Permits<M>>(&'p self, parent: &'p IntentGuard<'_, U, P>) -> IntentGuard<'p, T, M> {
+        let _ = parent;
+        self.lock()
+    }
+
+    /**
+    No spin; locks the node in mode `M` if that's compatible with the modes it's held in, where `parent`
+    is held in a mode that allows it.
+*/
+    pub fn try_lock_under<'p, M: Mode, U, P: Permits<M>>(&'p self, parent: &'p IntentGuard<'_, U, P>) -> Option<IntentGuard<'p, T, M>> {
+        let _ = parent;
+        self.try_lock()
+    }
+
+    /**
+    Whether the node is held in any mode.  This is a snapshot; it may be out of date by the time you read it.
+*/
+    pub fn is_locked(&self) -> bool {
+        self.state.load(Ordering::Relaxed) != 0
+    }
+
+    /**
+    The data, without locking, since the borrow is exclusive.
+*/
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    /**
+    Consumes the lock and returns the inner data.
+*/
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+
+    fn raw_try_lock<M: Mode>(&self) -> bool {
+        #[cfg(feature = "chaos")]
+        crate::chaos::delay();
+        let one = 1 << M::SHIFT;
+        let mut state = self.state.load(Ordering::Relaxed);
+        loop {
+            //a full count is as good as held, rather than overflowing into the next mode
+            if state & M::CONFLICTS != 0 || (state >> M::SHIFT) & COUNT_MASK == COUNT_MASK {
+                return false;
+            }
+            match self.state.compare_exchange_weak(state, state + one, Ordering::Acquire, Ordering::Relaxed) {
+                Ok(_) => {
+                    crate::tsan::acquire(self);
+                    return true;
+                }
+                Err(actual) => state = actual,
+            }
+        }
+    }
+}
+
+impl<T, M: Mode> Deref for IntentGuard<'_, T, M> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        //every mode excludes the exclusive one
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T> DerefMut for IntentGuard<'_, T, Exclusive> {
+    fn deref_mut(&mut self) -> &mut T {
+        //the exclusive mode excludes every other
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T, M: Mode> Drop for IntentGuard<'_, T, M> {
+    fn drop(&mut self) {
+        #[cfg(feature = "chaos")]
+        crate::chaos::delay();
+        crate::tsan::release(self.lock);
+        self.lock.state.fetch_sub(1 << M::SHIFT, Ordering::Release);
+        crate::arch::released();
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<T: Debug> Debug for IntentLock<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.try_lock::<Shared>() {
+            Some(guard) => f.debug_tuple("IntentLock").field(&&*guard).finish(),
+            None => f.write_str("IntentLock(<locked>)"),
+        }
+    }
+}
+
+impl<T: Default> Default for IntentLock<T> {
+    fn default() -> Self {
+        IntentLock::new(T::default())
+    }
+}
+
+impl<T> From<T> for IntentLock<T> {
+    fn from(data: T) -> Self {
+        IntentLock::new(data)
+    }
+}
+
+impl<T: Debug, M: Mode> Debug for IntentGuard<'_, T, M> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("IntentGuard").field(&**self).finish()
+    }
+}
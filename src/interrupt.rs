@@ -0,0 +1,270 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Interrupt-safe locking for bare metal.
+
+A plain spinlock that is also taken from an interrupt handler can deadlock: if the interrupt fires on the
+core that holds the lock, the handler spins forever waiting for code it has preempted.  [CriticalLock]
+avoids this by disabling local interrupts *before* acquiring the lock, and restoring the prior interrupt
+state after the lock is released.
+
+How interrupts are masked is architecture-specific, and is described by an [Interrupts] implementation.
+This module provides:
+
+* `CortexM`, with the `cortex-m` feature, on ARM M-profile targets.
+* `RiscvMachine`, with the `riscv` feature, on RISC-V targets running in machine mode.
+
+Other architectures can implement [Interrupts] themselves.
+*/
+
+use core::cell::UnsafeCell;
+use core::fmt::Debug;
+use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
+use core::ops::{Deref, DerefMut};
+use crate::{DynGuard, DynLock, Guard, Lock};
+
+/**
+Masks and restores interrupts on the current core.
+
+# Safety
+After [Interrupts::disable] returns, no interrupt may run on the current core until the state is
+passed to [Interrupts::restore].
+*/
+pub unsafe trait Interrupts {
+    /// The interrupt state prior to [Interrupts::disable].
+    type State: Copy;
+    /// Disables interrupts on the current core, returning the previous state.
+    fn disable() -> Self::State;
+    /**
+    Restores interrupts to the state before the corresponding [Interrupts::disable].
+
+    # Safety
+    `state` must come from the most recent unrestored call to [Interrupts::disable] on this core.
+    */
+    unsafe fn restore(state: Self::State);
+}
+
+/**
+Masks interrupts via `PRIMASK` on ARM M-profile cores.
+*/
+#[cfg(all(feature = "cortex-m", target_arch = "arm"))]
+#[derive(Debug)]
+pub struct CortexM;
+
+#[cfg(all(feature = "cortex-m", target_arch = "arm"))]
+unsafe impl Interrupts for CortexM {
+    type State = u32;
+    fn disable() -> u32 {
+        let primask: u32;
+        unsafe {
+            core::arch::asm!("mrs {}, PRIMASK", out(reg) primask, options(nomem, nostack, preserves_flags));
+            //not nomem: this orders memory accesses after it
+            core::arch::asm!("cpsid i", options(nostack, preserves_flags));
+        }
+        primask
+    }
+    unsafe fn restore(primask: u32) {
+        //bit 0 set means interrupts were already masked
+        if primask & 1 == 0 {
+            core::arch::asm!("cpsie i", options(nostack, preserves_flags));
+        }
+    }
+}
+
+/**
+Masks interrupts via `mstatus.MIE` on RISC-V cores in machine mode.
+*/
+#[cfg(all(feature = "riscv", any(target_arch = "riscv32", target_arch = "riscv64")))]
+#[derive(Debug)]
+pub struct RiscvMachine;
+
+#[cfg(all(feature = "riscv", any(target_arch = "riscv32", target_arch = "riscv64")))]
+unsafe impl Interrupts for RiscvMachine {
+    type State = usize;
+    fn disable() -> usize {
+        let mstatus: usize;
+        unsafe {
+            core::arch::asm!("csrrci {}, mstatus, 8", out(reg) mstatus, options(nostack));
+        }
+        mstatus
+    }
+    unsafe fn restore(mstatus: usize) {
+        //MIE is bit 3
+        if mstatus & 8 != 0 {
+            core::arch::asm!("csrsi mstatus, 8", options(nostack));
+        }
+    }
+}
+
+/**
+A spinlock that disables local interrupts while held.
+
+This makes it safe to take the lock both from normal code and from interrupt handlers.
+*/
+pub struct CriticalLock<T, I: Interrupts> {
+    lock: Lock<T>,
+    //the prior interrupt state, while held by a DynGuard, which has nowhere else to keep it
+    dyn_state: UnsafeCell<Option<I::State>>,
+    interrupts: PhantomData<I>,
+}
+
+//dyn_state is only accessed by the lock holder
+unsafe impl<T: Send, I: Interrupts + Sync> Sync for CriticalLock<T, I> where I::State: Send {}
+
+/**
+A guard for [CriticalLock].
+
+Releases the lock, then restores the prior interrupt state, when dropped.
+*/
+#[must_use]
+pub struct CriticalGuard<'a, T, I: Interrupts> {
+    guard: ManuallyDrop<Guard<'a, T>>,
+    state: I::State,
+    //the interrupt state must be restored on the core that disabled it
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<T, I: Interrupts> CriticalLock<T, I> {
+    const_fn! {
+        /**
+        Creates a new lock.
+*/
+        pub const fn new(data: T) -> Self {
+            CriticalLock {
+                lock: Lock::new(data),
+                dyn_state: UnsafeCell::new(None),
+                interrupts: PhantomData,
+            }
+        }
+    }
+
+    /**
+    Disables interrupts, then spins until the lock can be acquired.
+*/
+    pub fn lock(&self) -> CriticalGuard<'_, T, I> {
+        let state = I::disable();
+        CriticalGuard {
+            guard: ManuallyDrop::new(self.lock.spin_lock()),
+            state,
+            _not_send: PhantomData,
+        }
+    }
+
+    /**
+    No spin; disables interrupts and provides access to the lock if available.
+
+    If the lock is not available, interrupts are restored before returning.
+*/
+    pub fn try_lock(&self) -> Option<CriticalGuard<'_, T, I>> {
+        let state = I::disable();
+        match self.lock.try_lock() {
+            Some(guard) => Some(CriticalGuard { guard: ManuallyDrop::new(guard), state, _not_send: PhantomData }),
+            None => {
+                unsafe { I::restore(state) };
+                None
+            }
+        }
+    }
+
+    /**
+    Consumes the lock and returns the inner data.
+*/
+    pub fn into_inner(self) -> T {
+        self.lock.into_inner()
+    }
+}
+
+impl<T, I: Interrupts> Drop for CriticalGuard<'_, T, I> {
+    fn drop(&mut self) {
+        //release the lock before interrupts can run again
+        unsafe {
+            ManuallyDrop::drop(&mut self.guard);
+            I::restore(self.state);
+        }
+    }
+}
+
+impl<T: Send, I: Interrupts + Sync> DynLock for CriticalLock<T, I> where I::State: Send {
+    fn lock_dyn(&self) -> DynGuard<'_> {
+        let state = I::disable();
+        Guard::into_raw(self.lock.spin_lock());
+        unsafe {
+            *self.dyn_state.get() = Some(state);
+            DynGuard::new(self)
+        }
+    }
+    fn try_lock_dyn(&self) -> Option<DynGuard<'_>> {
+        let state = I::disable();
+        match self.lock.try_lock() {
+            Some(guard) => {
+                Guard::into_raw(guard);
+                unsafe {
+                    *self.dyn_state.get() = Some(state);
+                    Some(DynGuard::new(self))
+                }
+            }
+            None => {
+                unsafe { I::restore(state) };
+                None
+            }
+        }
+    }
+    unsafe fn unlock_dyn(&self) {
+        let state = (*self.dyn_state.get()).take();
+        drop(self.lock.guard_from_raw());
+        if let Some(state) = state {
+            I::restore(state);
+        }
+    }
+}
+
+//boilerplate
+
+impl<T: Debug, I: Interrupts> Debug for CriticalLock<T, I> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("CriticalLock").field("lock", &self.lock).finish()
+    }
+}
+
+impl<T: Default, I: Interrupts> Default for CriticalLock<T, I> {
+    fn default() -> Self {
+        CriticalLock::new(T::default())
+    }
+}
+
+impl<T, I: Interrupts> From<T> for CriticalLock<T, I> {
+    fn from(data: T) -> Self {
+        CriticalLock::new(data)
+    }
+}
+
+impl<T: Debug, I: Interrupts> Debug for CriticalGuard<'_, T, I> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("CriticalGuard").field("guard", &*self.guard).finish()
+    }
+}
+
+impl<T, I: Interrupts> Deref for CriticalGuard<'_, T, I> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T, I: Interrupts> DerefMut for CriticalGuard<'_, T, I> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T, I: Interrupts> AsRef<T> for CriticalGuard<'_, T, I> {
+    fn as_ref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T, I: Interrupts> AsMut<T> for CriticalGuard<'_, T, I> {
+    fn as_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
@@ -0,0 +1,183 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Locks embedded in the structs they protect.
+
+Wrapping a struct in a [Lock](crate::Lock) puts the lock around it, which intrusive data structures can't
+always afford: a node in an intrusive list is reached through pointers to the node itself, so the lock
+has to live inside it.  An [Intrusive] struct has a [RawSpinLock] as a field, and wraps the fields it protects
+in [Guarded].  Locking the struct gives an [IntrusiveGuard], which reads the struct's other fields as usual,
+and the guarded ones through [IntrusiveGuard::get]:
+
+```
+use atomiclock_spinlock::intrusive::{Guarded, Intrusive};
+use atomiclock_spinlock::RawSpinLock;
+
+struct Node {
+    lock: RawSpinLock,
+    id: u32,
+    visits: Guarded<u64>,
+}
+//Node's lock word is always its own field, and Node holds no other Intrusive struct
+unsafe impl Intrusive for Node {
+    fn lock_word(&self) -> &RawSpinLock {
+        &self.lock
+    }
+}
+
+let node = Node { lock: RawSpinLock::new(), id: 7, visits: Guarded::new(0) };
+let mut guard = node.lock();
+assert_eq!(guard.id, 7);
+*guard.get(|node| &node.visits) += 1;
+drop(guard);
+assert_eq!(*node.lock().get(|node| &node.visits), 1);
+```
+
+Waiting, warnings and instrumentation are those of the [RawSpinLock].
+*/
+
+use core::cell::UnsafeCell;
+use core::fmt::Debug;
+use core::ops::Deref;
+use crate::RawSpinLock;
+
+/**
+A struct with its own lock word, protecting its [Guarded] fields.
+
+# Safety
+[Intrusive::lock_word] must return the same lock every time it's called on the same struct, so that its
+[Guarded] fields are only ever protected by that one lock.
+
+Every [Guarded] stored inline in the struct, however deeply, must be protected by that lock, since
+[IntrusiveGuard::get] hands out any of them.  So the struct mustn't hold another [Intrusive] struct by value,
+whose [Guarded] fields its own lock word protects; hold it through a pointer, such as a reference or `Box`,
+instead.
+*/
+pub unsafe trait Intrusive {
+    /**
+    The lock word that protects this struct's [Guarded] fields.
+    */
+    fn lock_word(&self) -> &RawSpinLock;
+
+    /**
+    Spins until the struct's lock can be acquired.  See [RawSpinLock::lock].
+    */
+    fn lock(&self) -> IntrusiveGuard<'_, Self>
+    where
+        Self: Sized,
+    {
+        self.lock_word().lock();
+        IntrusiveGuard { owner: self }
+    }
+
+    /**
+    No spin; acquires the struct's lock if available.
+    */
+    fn try_lock(&self) -> Option<IntrusiveGuard<'_, Self>>
+    where
+        Self: Sized,
+    {
+        self.lock_word().try_lock().then(|| IntrusiveGuard { owner: self })
+    }
+}
+
+/**
+A field protected by its struct's lock word.  See [Intrusive].
+*/
+pub struct Guarded<T> {
+    data: UnsafeCell<T>,
+}
+
+//the data is only reached through an IntrusiveGuard, so one thread at a time
+unsafe impl<T: Send> Sync for Guarded<T> {}
+
+impl<T> Guarded<T> {
+    /**
+    Creates a guarded field.
+    */
+    pub const fn new(data: T) -> Self {
+        Guarded { data: UnsafeCell::new(data) }
+    }
+
+    /**
+    The data.  No locking is needed, since the borrow is exclusive.
+    */
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    /**
+    Consumes the field and returns the data.
+    */
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+/**
+A guard for an [Intrusive] struct, which dereferences to the struct, and reaches its [Guarded] fields with
+[IntrusiveGuard::get].
+*/
+#[must_use]
+pub struct IntrusiveGuard<'a, S: Intrusive> {
+    owner: &'a S,
+}
+
+impl<S: Intrusive> IntrusiveGuard<'_, S> {
+    /**
+    One of the struct's guarded fields, picked out by `field`.
+
+    # Panics
+    If `field` returns a field of some other struct.
+    */
+    pub fn get<T>(&mut self, field: impl FnOnce(&S) -> &Guarded<T>) -> &mut T {
+        let guarded = field(self.owner);
+        let start = self.owner as *const S as *const u8;
+        let at = guarded as *const Guarded<T> as *const u8;
+        //only the struct's own fields are protected by its lock, and Intrusive promises all of them are
+        assert!(at >= start && at < start.wrapping_add(core::mem::size_of::<S>()), "field is not part of the locked struct");
+        unsafe { &mut *guarded.data.get() }
+    }
+}
+
+impl<S: Intrusive> Drop for IntrusiveGuard<'_, S> {
+    fn drop(&mut self) {
+        //we hold it, from lock or try_lock
+        unsafe { self.owner.lock_word().unlock() };
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<T> Debug for Guarded<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        //reading the data needs the struct's lock, which we can't reach from here
+        f.write_str("Guarded(..)")
+    }
+}
+
+impl<T: Default> Default for Guarded<T> {
+    fn default() -> Self {
+        Guarded::new(T::default())
+    }
+}
+
+impl<T> From<T> for Guarded<T> {
+    fn from(data: T) -> Self {
+        Guarded::new(data)
+    }
+}
+
+impl<S: Intrusive + Debug> Debug for IntrusiveGuard<'_, S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("IntrusiveGuard").field(self.owner).finish()
+    }
+}
+
+impl<S: Intrusive> Deref for IntrusiveGuard<'_, S> {
+    type Target = S;
+    fn deref(&self) -> &S {
+        self.owner
+    }
+}
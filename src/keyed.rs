@@ -0,0 +1,169 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Locks created on demand, one per key.
+*/
+
+use alloc::sync::Arc;
+use core::fmt::Debug;
+use core::hash::Hash;
+use std::collections::HashMap;
+use crate::{Guard, Lock};
+
+/**
+A lock per key, such as per file or per session, created the first time a key is locked, and dropped
+once nobody holds or waits on it.
+
+```
+# use atomiclock_spinlock::KeyedLocks;
+let sessions = KeyedLocks::new();
+let alice = sessions.lock(&"alice");
+//other keys aren't held up
+let bob = sessions.lock(&"bob");
+assert!(sessions.try_lock(&"alice").is_none());
+drop(alice);
+drop(bob);
+//and unused locks don't pile up
+assert!(sessions.is_empty());
+```
+
+The locks protect no data of their own; they serialize whatever the key stands for.  Finding a key's
+lock takes a short-lived lock over all of them, so this suits keys that are locked for a while, rather
+than many brief locks on a hot path, where a [SpinMap](crate::collections::SpinMap) is better.
+
+Requires the `std` feature.
+*/
+pub struct KeyedLocks<K> {
+    //each lock is shared by the map and by everyone holding or waiting on it
+    locks: Lock<HashMap<K, Arc<Lock<()>>>>,
+}
+
+/**
+A guard for one key of a [KeyedLocks].
+*/
+#[must_use]
+pub struct KeyedGuard<'a, K: Hash + Eq> {
+    locks: &'a KeyedLocks<K>,
+    key: K,
+    //None once dropped
+    lock: Option<Arc<Lock<()>>>,
+}
+
+impl<K> KeyedLocks<K> {
+    /**
+    Creates an empty set of locks.
+*/
+    pub fn new() -> Self {
+        KeyedLocks { locks: Lock::new(HashMap::new()) }
+    }
+}
+
+impl<K: Hash + Eq> KeyedLocks<K> {
+    /**
+    Gives up a reference to the key's lock, and drops the lock if that was the last one outside the map.
+*/
+    fn put(&self, key: &K, lock: Arc<Lock<()>>) {
+        //new references are only taken with the map locked, so nobody can take one meanwhile
+        let mut locks = self.locks.spin_lock();
+        drop(lock);
+        if locks.get(key).is_some_and(|lock| Arc::strong_count(lock) == 1) {
+            locks.remove(key);
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone> KeyedLocks<K> {
+    /**
+    The key's lock, creating it if need be.
+*/
+    fn get(&self, key: &K) -> Arc<Lock<()>> {
+        self.locks.spin_lock().entry(key.clone()).or_default().clone()
+    }
+
+    /**
+    Spins until the key's lock can be acquired.
+*/
+    pub fn lock(&self, key: &K) -> KeyedGuard<'_, K> {
+        let lock = self.get(key);
+        //the KeyedGuard releases it
+        Guard::into_raw(lock.spin_lock());
+        KeyedGuard { locks: self, key: key.clone(), lock: Some(lock) }
+    }
+
+    /**
+    No spin; acquires the key's lock if available.
+*/
+    pub fn try_lock(&self, key: &K) -> Option<KeyedGuard<'_, K>> {
+        let lock = self.get(key);
+        //the KeyedGuard releases it
+        let acquired = lock.try_lock().map(Guard::into_raw).is_some();
+        if !acquired {
+            self.put(key, lock);
+            return None;
+        }
+        Some(KeyedGuard { locks: self, key: key.clone(), lock: Some(lock) })
+    }
+
+    /**
+    Whether the key's lock is currently held.
+*/
+    pub fn is_locked(&self, key: &K) -> bool {
+        self.locks.spin_lock().get(key).is_some_and(|lock| lock.is_locked())
+    }
+
+    /**
+    The number of keys whose locks are held or waited on.
+*/
+    pub fn len(&self) -> usize {
+        self.locks.spin_lock().len()
+    }
+
+    /**
+    Whether no key's lock is held or waited on.
+*/
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<K: Hash + Eq> KeyedGuard<'_, K> {
+    /**
+    The key this guard holds the lock for.
+*/
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+}
+
+impl<K: Hash + Eq> Drop for KeyedGuard<'_, K> {
+    fn drop(&mut self) {
+        let Some(lock) = self.lock.take() else { return };
+        //we hold it, from lock or try_lock
+        drop(unsafe { lock.guard_from_raw() });
+        self.locks.put(&self.key, lock);
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<K> Debug for KeyedLocks<K> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.locks.try_lock() {
+            Some(locks) => f.debug_struct("KeyedLocks").field("keys", &locks.len()).finish(),
+            None => f.write_str("KeyedLocks(<locked>)"),
+        }
+    }
+}
+
+impl<K> Default for KeyedLocks<K> {
+    fn default() -> Self {
+        KeyedLocks::new()
+    }
+}
+
+impl<K: Hash + Eq + Debug> Debug for KeyedGuard<'_, K> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("KeyedGuard").field("key", &self.key).finish()
+    }
+}
@@ -0,0 +1,135 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Lazily-initialized values, for `no_std` code that would otherwise use `lazy_static` or `once_cell`.
+*/
+
+use core::cell::UnsafeCell;
+use core::fmt::Debug;
+use core::mem::MaybeUninit;
+use core::ops::Deref;
+use core::sync::atomic::{AtomicBool, Ordering};
+use crate::Lock;
+
+/**
+A value initialized on first access.
+
+The first thread to access the value runs the initializer while holding a spinlock; other threads
+spin until it finishes.  If the initializer panics, later accesses panic too.
+
+Usually declared with [spin_lazy_static!](crate::spin_lazy_static).
+*/
+pub struct Lazy<T, F = fn() -> T> {
+    init: Lock<Option<F>>,
+    ready: AtomicBool,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+//the value is only written once, under the lock, before `ready` is published
+unsafe impl<T: Send + Sync, F: Send> Sync for Lazy<T, F> {}
+
+impl<T, F> Lazy<T, F> {
+    const_fn! {
+        /**
+        Creates a value that will be initialized by `init` on first access.
+        */
+        pub const fn new(init: F) -> Self {
+            Lazy {
+                init: Lock::new(Some(init)),
+                ready: AtomicBool::new(false),
+                value: UnsafeCell::new(MaybeUninit::uninit()),
+            }
+        }
+    }
+
+    /**
+    Returns the value if it has already been initialized.
+    */
+    pub fn get(this: &Self) -> Option<&T> {
+        if this.ready.load(Ordering::Acquire) {
+            //ready is only set after the value is written
+            Some(unsafe { (*this.value.get()).assume_init_ref() })
+        } else {
+            None
+        }
+    }
+}
+
+impl<T, F: FnOnce() -> T> Lazy<T, F> {
+    /**
+    Initializes the value if needed, and returns it.
+    */
+    pub fn force(this: &Self) -> &T {
+        if let Some(value) = Lazy::get(this) {
+            return value;
+        }
+        let mut init = this.init.spin_lock();
+        if !this.ready.load(Ordering::Acquire) {
+            let f = init.take().expect("Lazy initializer previously panicked");
+            let value = f();
+            //we hold the lock and ready is unset, so nobody else is reading or writing the value
+            unsafe { (*this.value.get()).write(value) };
+            this.ready.store(true, Ordering::Release);
+        }
+        drop(init);
+        Lazy::get(this).unwrap()
+    }
+}
+
+impl<T, F> Drop for Lazy<T, F> {
+    fn drop(&mut self) {
+        if *self.ready.get_mut() {
+            unsafe { self.value.get_mut().assume_init_drop() }
+        }
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<T, F: FnOnce() -> T> Deref for Lazy<T, F> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        Lazy::force(self)
+    }
+}
+
+impl<T: Debug, F> Debug for Lazy<T, F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match Lazy::get(self) {
+            Some(value) => f.debug_tuple("Lazy").field(value).finish(),
+            None => f.write_str("Lazy(<uninit>)"),
+        }
+    }
+}
+
+impl<T: Default> Default for Lazy<T> {
+    fn default() -> Self {
+        Lazy::new(T::default)
+    }
+}
+
+/**
+Declares lazily-initialized statics, in the style of `lazy_static!`.
+
+```
+use atomiclock_spinlock::spin_lazy_static;
+spin_lazy_static! {
+    static ref TABLE: [u32; 4] = [1, 2, 3, 4];
+    pub static ref GREETING: &'static str = "hello";
+}
+assert_eq!(TABLE[2], 3);
+assert_eq!(*GREETING, "hello");
+```
+
+Each static is a [Lazy], which dereferences to the value.  The initializer runs on first access.
+*/
+#[macro_export]
+macro_rules! spin_lazy_static {
+    ($($(#[$attr:meta])* $vis:vis static ref $name:ident: $t:ty = $init:expr;)*) => {
+        $(
+            $(#[$attr])*
+            $vis static $name: $crate::Lazy<$t> = $crate::Lazy::new(|| $init);
+        )*
+    };
+}
@@ -0,0 +1,200 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Two copies of the data, so readers never wait for writers.
+*/
+
+use core::cell::UnsafeCell;
+use core::fmt::Debug;
+use core::ops::Deref;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use crate::Lock;
+
+//a cache line, or two on targets that prefetch in pairs, so readers arriving don't slow the indexes down
+#[repr(align(128))]
+struct Padded<T>(T);
+
+/**
+Read-mostly data kept in two copies, so readers are wait-free: a read never waits, for writers or anyone
+else, and always finishes in a bounded number of steps.
+
+Readers read whichever copy is current, with [LeftRight::read], without taking any lock.  A writer, holding
+a [Lock] that excludes other writers, applies its update with [LeftRight::write] to the other copy, points
+new readers at it, waits for readers to leave the old one, and then applies the same update to that one:
+
+```
+# use atomiclock_spinlock::LeftRight;
+let routes = LeftRight::new(vec!["10.0.0.0/8"]);
+std::thread::scope(|s| {
+    s.spawn(|| routes.write(|routes| routes.push("192.168.0.0/16")));
+    //never waits for the writer, and sees it all or not at all
+    let len = routes.read().len();
+    assert!(len == 1 || len == 2);
+});
+assert_eq!(*routes.read(), ["10.0.0.0/8", "192.168.0.0/16"]);
+```
+
+Compared with an [RwLock](crate::RwLock), readers don't wait for a writer to finish, and writers can't
+starve readers, or be starved by them entering.  In exchange, the data takes twice the memory, every update
+runs twice, and a writer waits for the readers that were reading the old copy, so a reader that holds its
+[LeftRightGuard] for a long time holds up writers, though never other readers.
+
+This is the algorithm of Ramalhete and Correia's *Left-Right*, with the read indicators as two counters.
+*/
+pub struct LeftRight<T> {
+    copies: [UnsafeCell<T>; 2],
+    //the copy readers read
+    current: Padded<AtomicUsize>,
+    //the read indicator readers arrive on
+    version: Padded<AtomicUsize>,
+    //readers on each read indicator
+    readers: [Padded<AtomicUsize>; 2],
+    writer: Lock<()>,
+}
+
+/**
+A read of a [LeftRight]'s current copy.  Dereferences to the data.
+
+Writers finish updating the other copy while this is alive, but wait for it to be dropped before updating
+this one.
+*/
+#[must_use]
+pub struct LeftRightGuard<'a, T> {
+    lock: &'a LeftRight<T>,
+    copy: &'a T,
+    version: usize,
+}
+
+//the writer writes both copies from its thread, and readers share them
+unsafe impl<T: Send> Send for LeftRight<T> {}
+unsafe impl<T: Send + Sync> Sync for LeftRight<T> {}
+
+impl<T: Clone> LeftRight<T> {
+    /**
+    Creates the two copies, from a clone of the data.
+*/
+    pub fn new(data: T) -> Self {
+        LeftRight::from_copies(data.clone(), data)
+    }
+}
+
+impl<T> LeftRight<T> {
+    const_fn! {
+        /**
+        Creates a `LeftRight` from two copies of the data, for data that can't be cloned.  They must be equal,
+        as far as readers can tell.
+        */
+        pub const fn from_copies(left: T, right: T) -> Self {
+            LeftRight {
+                copies: [UnsafeCell::new(left), UnsafeCell::new(right)],
+                current: Padded(AtomicUsize::new(0)),
+                version: Padded(AtomicUsize::new(0)),
+                readers: [Padded(AtomicUsize::new(0)), Padded(AtomicUsize::new(0))],
+                writer: Lock::new(()),
+            }
+        }
+    }
+
+    /**
+    Reads the current copy, without waiting.
+*/
+    pub fn read(&self) -> LeftRightGuard<'_, T> {
+        let version = self.version.0.load(Ordering::SeqCst);
+        self.readers[version].0.fetch_add(1, Ordering::SeqCst);
+        let current = self.current.0.load(Ordering::SeqCst);
+        //the writer doesn't write this copy until we depart from the indicator we arrived on
+        let copy = unsafe { &*self.copies[current].get() };
+        LeftRightGuard { lock: self, copy, version }
+    }
+
+    /**
+    Applies `update` to both copies, in turn, spinning for other writers and then for readers of the old copy.
+
+    `update` runs once on each copy, so it must do the same to both: it mustn't depend on anything that may
+    change between the two calls, such as the time or a counter of its own, and shouldn't panic.  If it
+    panics, the lock is released, and the copies may then differ.
+*/
+    pub fn write(&self, mut update: impl FnMut(&mut T)) {
+        let _writer = self.writer.spin_lock();
+        let current = self.current.0.load(Ordering::Relaxed);
+        //nobody reads the other copy: readers that did have left, before the last write finished
+        update(unsafe { &mut *self.copies[1 - current].get() });
+        self.current.0.store(1 - current, Ordering::SeqCst);
+        //readers now come to the new copy, but some may not have read `current` yet, so wait for both
+        //indicators to empty, toggling between them so new readers can't keep one from emptying
+        let version = self.version.0.load(Ordering::Relaxed);
+        self.wait_for_readers(1 - version);
+        self.version.0.store(1 - version, Ordering::SeqCst);
+        self.wait_for_readers(version);
+        update(unsafe { &mut *self.copies[current].get() });
+    }
+
+    fn wait_for_readers(&self, version: usize) {
+        let mut spins = crate::wait::Spins::new();
+        while self.readers[version].0.load(Ordering::SeqCst) != 0 {
+            if crate::SINGLE_THREADED {
+                panic!("LeftRight is being read; on a single-threaded target, waiting for the reader would never finish");
+            }
+            crate::wait::relax(&mut spins);
+        }
+    }
+
+    /**
+    The data, without reading through the indicators, since the borrow is exclusive.  Changes must be made
+    to both copies, so this is the pair, left then right.
+*/
+    pub fn get_mut(&mut self) -> (&mut T, &mut T) {
+        let [left, right] = &mut self.copies;
+        (left.get_mut(), right.get_mut())
+    }
+
+    /**
+    Consumes the `LeftRight` and returns the current copy.
+*/
+    pub fn into_inner(self) -> T {
+        let current = self.current.0.load(Ordering::Relaxed);
+        let [left, right] = self.copies;
+        if current == 0 { left.into_inner() } else { right.into_inner() }
+    }
+}
+
+impl<T> Deref for LeftRightGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        self.copy
+    }
+}
+
+impl<T> Drop for LeftRightGuard<'_, T> {
+    fn drop(&mut self) {
+        //the writer's wait sees our reads finish before it writes
+        self.lock.readers[self.version].0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<T: Debug> Debug for LeftRight<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("LeftRight").field(&&*self.read()).finish()
+    }
+}
+
+impl<T: Clone + Default> Default for LeftRight<T> {
+    fn default() -> Self {
+        LeftRight::new(T::default())
+    }
+}
+
+impl<T: Clone> From<T> for LeftRight<T> {
+    fn from(data: T) -> Self {
+        LeftRight::new(data)
+    }
+}
+
+impl<T: Debug> Debug for LeftRightGuard<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("LeftRightGuard").field(&self.copy).finish()
+    }
+}
@@ -6,55 +6,1187 @@ A simple spinlock.
 
 This is a simple spinlock. It is not a fair lock, and it does not provide any way to sleep the current thread if the lock is not available.
 
+The crate is `no_std`.  APIs that need the standard library are behind the `std` feature.
+
+On single-threaded targets (`wasm32` without the `atomics` target feature), a held lock can never be released
+by someone else, so the spinning APIs panic on contention instead of hanging forever, and the deadline-based
+APIs give up immediately.  [Lock::spin_lock_checked] returns an error instead.
+
+Otherwise, acquiring a lock does not panic, except where documented, so the crate is suitable for
+`panic = "abort"` firmware and for use across FFI boundaries.  This is checked by the `no_panic` test suite.
+It does not hold with `events`, `perf-counters`, `acquired-at`, `owner-tracking`, `env-tuning`, `park`,
+`std-mutex`, or `diagnostics` together with `std`, which allocate, park, or read the environment or the system clock, while locking.
+Nor with `link-hooks`, whose hooks are chosen by the linker, so the compiler can't see that they don't panic.
+Releasing a lock asserts (in atomiclock) that it was held, which can't fail unless `unsafe` code has
+broken the lock.
+
+When tasks of different priorities share a lock, [CeilingLock] bounds priority inversion by running
+the holder at a ceiling priority.
+
+[DynLock] locks and unlocks any of these without naming the protected type, for heterogeneous collections of locks.
+To stripe data across several locks, [Lock::new_array] builds a [LockArray], with each lock on its own cache line.
+[Lock::view] hands out the lock on one part of the data, such as a field, as a [LockView], whose guards
+are for just that part.
+
+[RwLock] is a reader-writer variant, and [rwlock::BiasedRwLock] one biased towards readers on many cores,
+[OptimisticLock] lets readers copy `Copy` data without taking the lock, [LeftRight] keeps two copies of read-mostly data so readers never wait, and [SpinCell] gives `Copy`
+data `Cell`-style `get` and `set` without guards, or, for values small enough, [AtomicCellSpin] does
+without the lock.  For hierarchical state,
+such as a scene graph, [intent] locks a tree with intention modes, so coarse readers and writers on different
+branches coexist.
+
+Async code can acquire locks with [Lock::lock_async], on any executor; see [future::YieldStrategy].  To make holding a guard across `.await` a compile
+error, use [LocalGuard].
+
+[collections] has common containers that take the lock internally, such as a bounded `SpinQueue` that needs no heap.
+[channel] is a bounded multi-producer, single-consumer channel on the same footing, and with `std`,
+`RateLimiter` is a token bucket, and `KeyedLocks` locks per key, such as per file or per session.
+With `alloc`, `RangeLock` locks disjoint ranges of one buffer separately, for working on its chunks in parallel,
+as does `Lock::lock_range` for a `Lock<Vec<T>>`.
+
+Lock-free retry loops of your own can back off the same way the locks do, with [SpinWait].
+
+Migrating from the `spin` crate?  See [compat::spin].
+
+For data that mustn't move, such as futures, [PinLock] hands it out pinned.
+
+To bound how long an acquisition may spin, with the bound in the type, use [BudgetLock].
+
+To find out what a shared value was before it went wrong, keep its history in a [HistoryLock].  To see why a lock is stuck,
+[Lock::debug_state] snapshots it, and the crate's `debuggers` directory has GDB and LLDB scripts that
+print locks, even in a core dump.
+
+For processes that share memory, a [ProcessLock] has a fixed layout that each of them can lock in place.
+
+How contended locks wait can be tuned process-wide with [config::set_defaults].
+
+To protect something the type system can't own, such as registers or a C struct, use [RawSpinLock].  To embed
+the lock in the struct it protects, as intrusive data structures need, see [intrusive].
+
+A [Lock] can spin on a raw lock other than atomiclock, such as a hardware spinlock register or a word in shared
+memory; see [raw].
+
+Tests can check locking invariants with [assert_unlocked] and [assert_held_by_current].
+
+To see how the locks behave under contention on your hardware, run the `stress` example
+(`cargo run --release --example stress -- --help`), or the `locks` benchmark against other mutexes.
+
+Code using [Lock] and [RwLock] can be model-checked with [loom](https://crates.io/crates/loom): built with
+`RUSTFLAGS="--cfg loom"`, the locks' state uses loom's atomics, and contended locks yield to loom's scheduler.
+Under loom, constructors are not `const`, so locks can't be `static`, and the `lock_api` feature is unavailable.
+`RUSTFLAGS="--cfg shuttle"` does the same for [shuttle](https://crates.io/crates/shuttle), without the restrictions.
+Under ThreadSanitizer, add `--cfg tsan` so the locks annotate their happens-before edges.
+
+Builds that can't afford any instrumentation on the lock paths, whatever features the dependency graph enables,
+can add `--cfg atomiclock_bare`.  Then `events`, `diagnostics`, `perfwarn`, `perf-counters` and `rt-audit` record
+nothing, though their APIs remain, so crates that use them still build.  Acquiring and releasing an uncontended
+lock is then just the underlying atomics, which the `codegen` test checks in the generated assembly.  Features
+that change what the locks do, such as `acquired-at`, `poison` or `park`, still apply, as do the checks made
+with debug assertions.
+
+# Features
+
+* `std` (default) - enables [Lock::spin_lock_until] and [Lock::spin_lock_for], and is required by most other features.
+  Without it, use a [Clock] of your own with [Lock::spin_lock_until_with] and [Lock::spin_lock_for_with].
+* `alloc` - enables APIs that need a heap, but not the rest of the standard library, such as [OwnedGuard].
+  Implied by `std`.
+* `perfwarn` (default) - [Lock::spin_lock_warn] reports contention via [logwise](https://crates.io/crates/logwise).
+  Without this feature the logwise dependency is dropped and [Lock::spin_lock_warn] behaves like [Lock::spin_lock].
+  [Lock::spin_lock_warn_alloc_free] counts contention without allocating instead, for [log_deferred_warnings] to log.
+* `strict` - [Lock::spin_lock_warn] panics instead of issuing a perfwarn when it encounters contention.
+  Useful in test suites to enforce that a path never contends.
+* `events` - streams acquisition, release and contention events to a user-supplied sink.  See the `events` module.
+* `diagnostics` - keeps per-lock statistics, and a registry of locks that can be exported.  See the `diagnostics` module.
+  Requires `alloc`; timing and per-thread attribution additionally require `std`.
+* `prometheus` - renders the diagnostics registry in the Prometheus exposition format.  See the `prometheus` module.
+* `critical-section` - a [critical-section](https://crates.io/crates/critical-section) provider built on the spinlock,
+  for multicore microcontrollers.  See the `critical_section` module.
+* `cortex-m` - on ARM M-profile targets, contended locks wait with `wfe` and releases signal with `sev`,
+  instead of burning the core.  Also enables interrupt masking for [CriticalLock], see [interrupt].
+* `handoff` - a waiter that has spun for a while asks for the lock, and the next release hands it over
+  directly, bounding starvation.  See the `handoff` module.
+* `affinity` - threads declare the cache domain they're pinned to, and contended locks prefer waiters in the
+  domain of their last holder.  See the `affinity` module.  Requires `std`.
+* `priority-boost` - on Linux, `ceiling::ThreadPriority`, for a [CeilingLock] that raises the holding thread's
+  scheduling priority while it holds the lock.  Requires `std`.
+* `riscv` - interrupt masking for [CriticalLock] on RISC-V targets in machine mode.  See [interrupt].
+* `rtic` - use [Lock] as a shared resource in [RTIC](https://rtic.rs) apps.  See the `rtic` module.
+* `wasm-wait` - on wasm with threads, contended locks wait with `memory.atomic.wait32` after spinning briefly,
+  instead of spinning forever.  Locks must not be contended on the browser's main thread.
+* `tokio` - async acquisition cooperates with the tokio scheduler's budget and yielding.  See [future].
+* `crossbeam` - contended locks, and [SpinWait], back off with [crossbeam-utils](https://crates.io/crates/crossbeam-utils)'s
+  `Backoff`, spinning and then yielding the thread, instead of the [config] settings and helping rayon.
+  Requires `std`.
+* `park` - contended locks park their threads through [parking_lot_core](https://crates.io/crates/parking_lot_core)
+  after spinning for a while, and releases unpark them, making a hybrid lock.  Requires `std`.
+* `std-mutex` - [Lock] is built on `std::sync::Mutex` instead of spinning, with the same API and instrumentation,
+  to compare the spinlock against an OS mutex without changing any code that uses it.  [RwLock] and the
+  crate's internal locks still spin.  Requires `std`.
+* `rayon` - contended locks on rayon worker threads run other rayon jobs while waiting, instead of spinning.
+* `test-clock` - deadlines on the standard library's clock use per-thread virtual time, which tests move by hand,
+  so timeout tests are deterministic and don't sleep.  See `clock::manual`.
+* `chaos` - random short delays before each attempt to acquire and each release, to shake out races in
+  code that uses the locks.  For testing only.  See the `chaos` module.
+* `test-util` - `TestLock`, a lock that can act busy or follow a scripted order of threads, for testing
+  contention and timeout handling, and reproducing interleavings.  With `std`, also the `scenario!` macro,
+  for tests that run several threads in lockstep.
+* `lock_api` - use the spinlock with [lock_api](https://crates.io/crates/lock_api)'s `Mutex`.  See the `lock_api` module.
+  Timed locking requires `std`.
+* `acquired-at` - guards record when they were acquired, for `Guard::acquired_at` and `Guard::held_for`,
+  so long critical sections can check how long they've held the lock.  Reads the clock on every acquisition,
+  so acquiring can panic if the OS clock fails.  Requires `std`.
+* `owner-tracking` - locks record the thread that holds them, since when, and where it acquired them, so timed
+  acquisitions such as `Lock::spin_lock_for_or_holder` can say what they waited on.  Reads the clock on every
+  acquisition.  Requires `std`.
+* `acquisition-history` - locks also keep their last 16 holders, with the thread, call site, and when each acquired
+  and released the lock, for post-mortems, in `Lock::recent_acquisitions` and the `diagnostics` reports.  Reads the
+  clock on every release too.  Implies `owner-tracking` and `diagnostics`.
+* `env-tuning` - `ATOMICLOCK_SPIN_*` environment variables override the [config] defaults.  Reads the environment
+  the first time a lock is contended, so acquiring can panic if that fails.  Requires `std`.
+* `poison` - tracks whether a guard was dropped while its thread panicked, like `std::sync::Mutex`, with
+  `Lock::is_poisoned`, and acquisitions that fail on a poisoned lock, such as `Lock::spin_lock_unpoisoned`.
+  Requires `std`.
+* `zeroize` - `SecretLock`, for key material, which wipes the data with [zeroize](https://crates.io/crates/zeroize)
+  when dropped, and doesn't format it.
+* `rt-audit` - checks that the lock paths don't allocate, make syscalls or format, for realtime code and
+  signal handlers.  See the `audit` module.
+* `ffi` - `extern "C"` functions for sharing locks with C code.  See the `ffi` module.  Requires `alloc`.
+* `derive` - `#[derive(SpinLocked)]`, which generates a version of a struct with a lock on each field, and
+  `#[locked]`, which runs a method with a lock held.  See `SpinLocked` and `locked`.
+* `link-hooks` - locks call weak `extern "C"` hooks on contention and release, which profilers or the
+  application override at link time.  See the `link_hooks` module.
+* `perf-counters` - on Linux, samples hardware counters (cycles, cache misses) while spinning.  See the `perf` module.
+  With `perfwarn`, [Lock::spin_lock_warn] logs the counts.
+
  */
+#![no_std]
+#![debugger_visualizer(gdb_script_file = "../debuggers/gdb_atomiclock.py")]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
+use core::fmt::Debug;
 use core::ops::{Deref, DerefMut};
+use core::sync::atomic::Ordering;
+use sync::AtomicUsize;
+use raw::RawLock;
+#[cfg(all(feature = "perfwarn", not(atomiclock_bare)))]
 use logwise::interval::PerfwarnInterval;
 
+/**
+Defines a `const fn`, except under `cfg(loom)`, where loom's atomics can't be created in const contexts.
+*/
+macro_rules! const_fn {
+    ($(#[$attr:meta])* $vis:vis const fn $($rest:tt)*) => {
+        #[cfg(not(loom))]
+        $(#[$attr])* $vis const fn $($rest)*
+        #[cfg(loom)]
+        $(#[$attr])* $vis fn $($rest)*
+    };
+}
+
+pub mod arch;
+pub mod assert;
+#[cfg(feature = "rt-audit")]
+pub mod audit;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+pub mod ceiling;
+pub mod channel;
+pub mod clock;
+pub mod collections;
+pub mod compat;
+pub mod config;
+pub mod dyn_lock;
+pub mod future;
+pub mod interrupt;
+pub mod intrusive;
+pub mod intent;
+pub mod raw;
+pub mod rwlock;
+mod atomic_cell;
+mod budget;
+mod cell;
+mod debug_state;
+mod fence;
+mod history;
+mod hook;
+mod lazy;
+mod left_right;
+mod local;
+mod lock_array;
+mod multi;
+mod optimistic;
+mod pin;
+mod process;
+mod project;
+mod raw_spin;
+mod shared;
+mod split;
+mod static_lock;
+mod sync;
+mod token;
+mod transaction;
+mod tsan;
+mod view;
+mod wait;
+mod wakers;
+#[cfg(feature = "alloc")]
+mod owned;
+#[cfg(feature = "alloc")]
+mod range;
+#[cfg(feature = "poison")]
+mod poison;
+#[cfg(feature = "owner-tracking")]
+mod owner;
+#[cfg(feature = "zeroize")]
+mod secret;
+#[cfg(feature = "test-util")]
+mod test_lock;
+#[cfg(all(feature = "test-util", feature = "std"))]
+pub mod scenario;
+#[cfg(feature = "events")]
+pub mod events;
+#[cfg(feature = "affinity")]
+pub mod affinity;
+#[cfg(feature = "handoff")]
+pub mod handoff;
+#[cfg(all(feature = "perfwarn", not(atomiclock_bare)))]
+mod throttle;
+#[cfg(feature = "std")]
+mod keyed;
+#[cfg(feature = "std")]
+mod rate_limiter;
+#[cfg(all(feature = "perf-counters", target_os = "linux"))]
+pub mod perf;
+#[cfg(feature = "diagnostics")]
+pub mod diagnostics;
+#[cfg(feature = "prometheus")]
+pub mod prometheus;
+#[cfg(feature = "critical-section")]
+pub mod critical_section;
+#[cfg(feature = "rtic")]
+pub mod rtic;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "link-hooks")]
+pub mod link_hooks;
+#[cfg(all(feature = "lock_api", not(loom)))]
+pub mod lock_api;
+
+/**
+The lock under [raw::DefaultRawLock].
+*/
+pub use atomiclock;
+pub use atomic_cell::{AtomicCellSpin, Plain};
+pub use budget::BudgetLock;
+pub use cell::SpinCell;
+pub use ceiling::CeilingLock;
+pub use clock::Clock;
+pub use debug_state::DebugState;
+pub use dyn_lock::{DynGuard, DynLock};
+pub use interrupt::CriticalLock;
+pub use rwlock::RwLock;
+pub use history::{HistoryGuard, HistoryLock};
+pub use hook::HookedGuard;
+pub use lazy::Lazy;
+pub use left_right::{LeftRight, LeftRightGuard};
+pub use local::LocalGuard;
+pub use lock_array::LockArray;
+pub use multi::WouldBlock;
+pub use optimistic::{OptimisticGuard, OptimisticLock, OptimisticWriteGuard};
+pub use pin::{PinGuard, PinLock};
+pub use process::{ProcessGuard, ProcessLock};
+pub use raw_spin::RawSpinLock;
+pub use shared::SharedGuard;
+pub use split::SplitGuard;
+pub use static_lock::StaticLock;
+pub use token::LockToken;
+pub use transaction::CommitGuard;
+pub use view::{LockView, MappedGuard};
+pub use wait::SpinWait;
+#[cfg(feature = "alloc")]
+pub use owned::OwnedGuard;
+#[cfg(feature = "alloc")]
+pub use range::{RangeGuard, RangeLock, VecRangeGuard};
+#[cfg(feature = "test-util")]
+pub use test_lock::TestLock;
+#[cfg(feature = "std")]
+pub use keyed::{KeyedGuard, KeyedLocks};
+#[cfg(feature = "std")]
+pub use rate_limiter::RateLimiter;
+#[cfg(feature = "poison")]
+pub use poison::PoisonError;
+#[cfg(feature = "owner-tracking")]
+pub use owner::ContentionInfo;
+#[cfg(feature = "zeroize")]
+pub use secret::{SecretGuard, SecretLock};
+/**
+Generates a version of a struct with its own [Lock] on each field, for fine-grained locking of config and
+state structs, with the `derive` feature.
+
+For a struct `Config`, this generates `ConfigLocked`, with a lock on each field, named `Config.field`, and an
+accessor for each one's lock, with the field's visibility and docs:
+
+```
+# use atomiclock_spinlock::SpinLocked;
+#[derive(SpinLocked)]
+pub struct Config {
+    pub name: String,
+    pub retries: u32,
+}
+
+let config = ConfigLocked::new(Config { name: "primary".to_string(), retries: 3 });
+std::thread::scope(|s| {
+    //different fields don't contend
+    s.spawn(|| config.name().spin_lock().push_str("-1"));
+    s.spawn(|| *config.retries().spin_lock() += 1);
+});
+let Config { name, retries } = config.into_inner();
+assert_eq!((name.as_str(), retries), ("primary-1", 4));
+```
+
+`new` and `into_inner` convert between the two, as do `From` impls.  Each field is locked separately, so
+nothing keeps fields consistent with each other; fields that must change together belong in one field, or
+in a plain [Lock] of the whole struct.  Name the generated struct with `#[spin_locked(name = "...")]`.  Only
+structs with named fields are supported:
+
+```compile_fail
+# use atomiclock_spinlock::SpinLocked;
+#[derive(SpinLocked)]
+struct Pair(u32, u32);
+```
+*/
+#[cfg(feature = "derive")]
+pub use atomiclock_spinlock_derive::SpinLocked;
+/**
+Runs a method with a [Lock] field of `self` held, for types with many small methods over one lock, with the
+`derive` feature.
+
+`#[locked(field)]` locks `self.field` with [Lock::spin_lock] before the body, and binds the guard to `field`:
+
+```
+use atomiclock_spinlock::{locked, Lock};
+struct Stats { totals: Lock<(u64, u64)> }
+impl Stats {
+    #[locked(totals)]
+    fn record(&self, bytes: u64) {
+        totals.0 += 1;
+        totals.1 += bytes;
+    }
+
+    #[locked(totals)]
+    fn average(&self) -> Option<u64> {
+        totals.1.checked_div(totals.0)
+    }
+}
+let stats = Stats { totals: Lock::new((0, 0)) };
+stats.record(10);
+stats.record(20);
+assert_eq!(stats.average(), Some(15));
+```
+
+The lock is held until the method returns.  Arguments after the field choose how it's acquired:
+
+* `#[locked(field, warn)]` - with [Lock::spin_lock_warn], which warns when the lock is contended.
+* `#[locked(field, timeout = duration)]` - with [Lock::spin_lock_for], giving up after `duration`, a
+  `std::time::Duration`.  The method must return `Option<T>`, and returns `None` if it gives up; the body is
+  written as if the method returned `T`, and runs in a closure, so `return` and `?` apply to the `T`.
+  Requires `std`.
+
+```
+# use atomiclock_spinlock::{locked, Lock};
+# use std::time::Duration;
+struct Queue { items: Lock<Vec<u32>> }
+impl Queue {
+    #[locked(items, timeout = Duration::from_millis(1))]
+    fn pop(&self) -> Option<Option<u32>> {
+        items.pop()
+    }
+}
+let queue = Queue { items: Lock::new(vec![1]) };
+assert_eq!(queue.pop(), Some(Some(1)));
+let held = queue.items.spin_lock();
+assert_eq!(queue.pop(), None);
+```
+
+The method can't be `async`, since a spinlock mustn't be held across an `.await`.
+*/
+#[cfg(feature = "derive")]
+pub use atomiclock_spinlock_derive::locked;
+
 /**
 A simple spinlock type.
+
+Like `std::sync::Mutex`, a `Lock<T>` is [Send] and [Sync] when `T` is [Send]: the lock hands the data to
+one thread at a time, so `T` needn't be [Sync].
+
+```compile_fail
+# use atomiclock_spinlock::Lock;
+fn shared<T: Sync>(_: T) {}
+//Rc can't move between threads, so neither can a lock around it
+shared(Lock::new(std::rc::Rc::new(0)));
+```
+
+# Ordering
+Acquiring the lock, in any of the ways that return a guard, is an `Acquire` operation on the lock, and
+releasing it, by dropping the guard, is a `Release` operation; each acquisition synchronizes with the release
+before it.  So everything a holder did before releasing, to any memory, atomic or not, happens before
+everything the next holder does after acquiring.  That's all the lock promises:
+
+* Nothing outside the critical section is ordered with it.  Accesses before acquiring, or after releasing,
+  may move into the critical section, though accesses inside never move out.
+* Acquiring is not `SeqCst`, so the critical sections of different locks are only ordered through the
+  threads that hold both.
+* Failing to acquire, as [Lock::try_lock] returning `None` or a timeout does, synchronizes with nothing,
+  and neither do [Lock::is_locked], [Lock::waiters] or [Lock::peek_racy].
+* Other atomics a holder accesses with `Relaxed` ordering are ordered for the lock's next holders, but a
+  thread that reads them without taking the lock may see them in any order.  Give those accesses `Acquire`
+  and `Release` ordering, or use [Lock::acquire_fence] and [Lock::release_fence].
+* The ordering is between the CPUs.  Devices reading or writing memory by DMA aren't covered, unless the
+  platform keeps them coherent with the CPUs; [Lock::release_fence] and [Lock::acquire_fence] order
+  memory for them too, on AArch64 and RISC-V with outer-shareable and device barriers, as Linux's
+  `dma_wmb` and `dma_rmb` do.  On 32-bit ARM they're the atomic fences, a `dmb sy` on Cortex-M, which
+  orders memory for the whole system, but a `dmb ish` on Cortex-A, which leaves out devices outside the
+  inner shareable domain.  On x86, the CPUs and devices already agree on the order of accesses to ordinary
+  memory.
+
+A [RawLock] other than the default must order its acquisitions and releases in the same way.
  */
-#[derive(Debug)]
-pub struct Lock<T> {
-    lock: atomiclock::AtomicLock<T>
+pub struct Lock<T, R = raw::DefaultRawLock> {
+    raw: R,
+    data: core::cell::UnsafeCell<T>,
+    name: Option<&'static str>,
+    //number of threads currently spinning on the lock.  Informational only.
+    waiters: AtomicUsize,
+    #[cfg(feature = "diagnostics")]
+    stats: diagnostics::Stats,
+    parker: wait::Parker,
+    wakers: wakers::WakerList,
+    //the thread that acquired the lock, or 0, for assert_held_by_current
+    #[cfg(all(debug_assertions, feature = "std"))]
+    holder: core::sync::atomic::AtomicUsize,
+    //whether the other part of a split guard is still alive, see Guard::map_split
+    split: core::sync::atomic::AtomicBool,
+    //guards alive, for live_guards
+    #[cfg(debug_assertions)]
+    live: core::sync::atomic::AtomicUsize,
+    //whether a guard was dropped during a panic
+    #[cfg(feature = "poison")]
+    poisoned: core::sync::atomic::AtomicBool,
+    //the affinity domain of the last holder, see affinity::token
+    #[cfg(feature = "affinity")]
+    domain: core::sync::atomic::AtomicUsize,
+    #[cfg(feature = "handoff")]
+    handoff: handoff::Handoff,
+    //the holder, for timed-out acquisitions to report
+    #[cfg(feature = "owner-tracking")]
+    owner: owner::Owner,
+}
+
+/**
+Whether the lock paths are instrumented, that is, unless built with `--cfg atomiclock_bare`.
+*/
+#[cfg(any(feature = "events", feature = "diagnostics", feature = "link-hooks", all(feature = "rt-audit", feature = "std", debug_assertions), all(feature = "perf-counters", target_os = "linux")))]
+const INSTRUMENTED: bool = !cfg!(atomiclock_bare);
+
+/**
+Spins on an underlying lock, without any instrumentation.
+
+For the crate's own bookkeeping, which must not show up in (or recurse into) the instrumentation.
+*/
+fn spin_raw<T>(lock: &atomiclock::AtomicLock<T>) -> atomiclock::Guard<'_, T> {
+    loop {
+        if let Some(guard) = lock.lock() {
+            return guard;
+        }
+        core::hint::spin_loop();
+    }
+}
+
+/**
+Logs the contention warnings [Lock::spin_lock_warn_alloc_free] deferred, one per call site with how many
+times it warned, and returns how many call sites it logged.
+
+Call this from somewhere that can allocate, such as a housekeeping thread, or after the allocator-heavy
+work.  Without the `perfwarn` feature, nothing is deferred, and this returns 0.
+*/
+pub fn log_deferred_warnings() -> usize {
+    #[allow(unused_mut)]
+    let mut logged = 0;
+    #[cfg(all(feature = "perfwarn", not(atomiclock_bare)))]
+    throttle::take_deferred(|site, count| {
+        logged += 1;
+        match site {
+            Some(site) => logwise::warn_sync!("spin_lock_warn_alloc_free spun at {site}; investigate ways to reduce contention ({count} deferred warnings)",
+                site=std::string::ToString::to_string(site), count=count),
+            None => logwise::warn_sync!("spin_lock_warn_alloc_free spun at other call sites ({count} deferred warnings)", count=count),
+        }
+    });
+    logged
+}
+
+/**
+The address of a pointer, without exposing its provenance.
+
+Like `<*const T>::addr`, which is newer than our minimum Rust version.  These addresses only identify
+locks and threads; they are never turned back into pointers.
+*/
+#[inline]
+//unlike an `as` cast, transmuting a pointer to an integer discards its provenance, as addr() does
+#[allow(clippy::transmutes_expressible_as_ptr_casts)]
+fn addr<T: ?Sized>(ptr: *const T) -> usize {
+    unsafe { core::mem::transmute::<*const (), usize>(ptr.cast()) }
+}
+
+/**
+Tracks one spinning thread in [Lock::waiters] for as long as it's alive.
+*/
+struct Waiting<'a> {
+    waiters: &'a AtomicUsize,
+    #[cfg(feature = "diagnostics")]
+    stats: &'a diagnostics::Stats,
+    //stats for the call site, if the acquisition is instrumented
+    #[cfg(feature = "diagnostics")]
+    site: Option<&'a diagnostics::Stats>,
+    //the energy meter's reading when spinning started, if there's a meter
+    #[cfg(feature = "diagnostics")]
+    energy: Option<u64>,
+    //when spinning started, if the lock paths are instrumented
+    #[cfg(all(feature = "diagnostics", feature = "std"))]
+    started: Option<std::time::Instant>,
+    #[cfg(all(feature = "diagnostics", feature = "std"))]
+    lock_id: usize,
+    #[cfg(all(feature = "perf-counters", target_os = "linux"))]
+    _measurement: perf::Measurement,
+}
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        #[cfg(feature = "rt-audit")]
+        let _audit = audit::enter();
+        self.waiters.fetch_sub(1, Ordering::Relaxed);
+        #[cfg(feature = "diagnostics")]
+        if let (Some(start), Some(end)) = (self.energy, diagnostics::read_energy_meter()) {
+            let spent = end.saturating_sub(start);
+            self.stats.spun_energy(spent);
+            if let Some(site) = self.site {
+                site.spun_energy(spent);
+            }
+        }
+        #[cfg(all(feature = "diagnostics", feature = "std"))]
+        if let Some(started) = self.started {
+            let elapsed = started.elapsed();
+            self.stats.spun(elapsed);
+            if let Some(site) = self.site {
+                site.spun(elapsed);
+            }
+            diagnostics::attribute_to_current_thread(self.lock_id, elapsed);
+        }
+    }
+}
+
+/**
+Whether the target has only one thread, such as wasm without the atomics feature.
+
+There, a held lock can't be released while we spin, so contention is a re-entrancy bug.
+*/
+const SINGLE_THREADED: bool = cfg!(all(target_arch = "wasm32", not(target_feature = "atomics")));
+
+/**
+Returned by [Lock::spin_lock_checked] when spinning could never acquire the lock.
+
+This happens on single-threaded targets, where a held lock can't be released by anyone else.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct WouldDeadlock;
+
+impl core::fmt::Display for WouldDeadlock {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("lock is already held, and no other thread can release it")
+    }
 }
 
+#[cfg(feature = "std")]
+impl std::error::Error for WouldDeadlock {}
+
+
 /**
 A guard that provides access to the data in the lock.
+
+A guard may be dropped on a different thread than the one that acquired it, so it's [Send] when `T` is.
+It's [Sync] when `T` is both [Send] and [Sync].
+
+```compile_fail
+# use atomiclock_spinlock::{Guard, Lock};
+fn shared<T: Sync>(_: &T) {}
+//Cell is Send, but not Sync
+let lock = Lock::new(core::cell::Cell::new(0));
+let guard: Guard<'_, _> = lock.spin_lock();
+shared(&guard);
+```
  */
-#[derive(Debug)]
 #[must_use]
-pub struct Guard<'a, T>(atomiclock::Guard<'a, T>);
+pub struct Guard<'a, T, R: RawLock = raw::DefaultRawLock> {
+    //the underlying lock is held for as long as the guard exists
+    lock: &'a Lock<T, R>,
+    data: &'a mut T,
+    //releasing doesn't poison the lock, see Guard::defuse
+    #[cfg(feature = "poison")]
+    defused: bool,
+    #[cfg(feature = "acquired-at")]
+    acquired_at: std::time::Instant,
+}
 
-impl <'a, T> Guard<'a, T> {
+impl<'a, T, R: RawLock> Guard<'a, T, R> {
     pub fn get_mut(&mut self) -> &mut T {
-        self.0.as_mut()
+        self.data
+    }
+
+    /**
+    Dissolves the guard into a raw pointer to its lock, without releasing the lock.
+
+    The lock stays held until the guard is reconstituted with [Lock::guard_from_raw] and dropped.
+    This lets a lock be held across a boundary, such as a C callback, that can't carry the guard itself.
+*/
+    pub fn into_raw(guard: Self) -> *const Lock<T, R> {
+        let lock = guard.lock;
+        core::mem::forget(guard);
+        lock
+    }
+
+    /**
+    Dissolves the guard into the underlying [RawLock], which stays held.
+
+    The guard's release is recorded, but the lock is only released by unlocking the returned lock.
+    See [Lock::as_raw] for the caveats.
+*/
+    pub fn into_raw_lock(guard: Self) -> &'a R {
+        let lock = guard.lock;
+        core::mem::forget(guard);
+        lock.record_release();
+        lock.as_raw()
+    }
+
+    /**
+    When the lock was acquired, on the standard library's clock.  With the `test-clock` feature, that's
+    the acquiring thread's virtual time.
+
+    A guard reconstituted with [Lock::guard_from_raw] reports when it was reconstituted.  Requires the
+    `acquired-at` feature.
+*/
+    #[cfg(feature = "acquired-at")]
+    pub fn acquired_at(guard: &Self) -> std::time::Instant {
+        guard.acquired_at
+    }
+
+    /**
+    How long the lock has been held, so far.  Requires the `acquired-at` feature.
+
+    ```
+    # use atomiclock_spinlock::{Guard, Lock};
+    # use std::time::Duration;
+    let lock = Lock::new(Vec::<u32>::new());
+    let mut guard = lock.spin_lock();
+    for n in 0.. {
+        //a long batch gives others a turn
+        if n == 1000 || Guard::held_for(&guard) > Duration::from_millis(1) {
+            break;
+        }
+        guard.push(n);
+    }
+    ```
+*/
+    #[cfg(feature = "acquired-at")]
+    pub fn held_for(guard: &Self) -> std::time::Duration {
+        clock::StdClock.now().saturating_duration_since(guard.acquired_at)
+    }
+
+    /**
+    Clones the data and releases the lock, so the work on the copy happens without holding it.
+
+    ```
+    # use atomiclock_spinlock::{Guard, Lock};
+    let lock = Lock::new(vec![3, 1, 2]);
+    let mut sorted = Guard::to_owned(lock.spin_lock());
+    //sorting the copy doesn't hold the lock
+    sorted.sort();
+    assert_eq!(sorted, [1, 2, 3]);
+    ```
+
+    This is an associated function, since the guard dereferences to the data; `guard.to_owned()` clones
+    the data without releasing the lock.
+*/
+    pub fn to_owned(guard: Self) -> T
+    where
+        T: Clone,
+    {
+        guard.data.clone()
+    }
+
+    /**
+    Extends the guard's lifetime to `'static`, for storing it where the borrow checker can't follow, such
+    as the context object of a C callback.
+
+    # Safety
+    The lock must stay alive, and must not move, until the returned guard is dropped.  The guard must not
+    be used, or dropped, after that.  In particular, dropping the guard releases the lock; if the lock is
+    gone by then, that's a use after free.
+
+    [Guard::into_raw] is an alternative that keeps the lock held without a guard at all.
+*/
+    pub unsafe fn extend_lifetime(guard: Self) -> Guard<'static, T, R>
+    where
+        T: 'static,
+        R: 'static,
+    {
+        core::mem::transmute::<Guard<'a, T, R>, Guard<'static, T, R>>(guard)
+    }
+
+    /**
+    Leaves the lock held forever, returning the data for the rest of the lock's lifetime.
+
+    For values that are set up once and then frozen: nobody can acquire the lock again, so whoever holds
+    the returned reference has the only access.  Unlike [core::mem::forget], this records the lock as
+    sealed in its `diagnostics` stats, so reports show why it's held.
+
+    ```
+    # use atomiclock_spinlock::{Guard, Lock};
+    static CONFIG: Lock<Vec<&str>> = Lock::new(Vec::new());
+    let mut guard = CONFIG.spin_lock();
+    guard.push("verbose");
+    let config: &'static Vec<&str> = Guard::forget_locked(guard);
+    assert!(CONFIG.try_lock().is_none());
+    assert_eq!(config, &["verbose"]);
+    ```
+
+    This is an associated function, since the guard dereferences to the data; call it as
+    `Guard::forget_locked(guard)`.
+*/
+    pub fn forget_locked(guard: Self) -> &'a mut T {
+        #[cfg(feature = "diagnostics")]
+        guard.lock.stats.sealed();
+        //a sealed lock is held on purpose, so it isn't a live guard
+        #[cfg(debug_assertions)]
+        guard.lock.live.fetch_sub(1, Ordering::Relaxed);
+        let guard = core::mem::ManuallyDrop::new(guard);
+        //the lock is never released, so nobody else can reach the data for as long as it lives
+        unsafe { core::ptr::read(&guard.data) }
     }
 }
 
-//drop - we forward to the atomiclock implementation, duh
+impl<T, R: RawLock> Drop for Guard<'_, T, R> {
+    fn drop(&mut self) {
+        #[cfg(feature = "poison")]
+        if self.defused {
+            self.lock.release(true);
+            return;
+        }
+        self.lock.unlock_raw();
+    }
+}
+
+//the data is behind an UnsafeCell, so bound these ourselves
+unsafe impl<T: Send, R: Send> Send for Lock<T, R> {}
+unsafe impl<T: Send, R: Sync> Sync for Lock<T, R> {}
+unsafe impl<T: Send, R: RawLock + Sync> Send for Guard<'_, T, R> {}
+unsafe impl<T: Send + Sync, R: RawLock + Sync> Sync for Guard<'_, T, R> {}
 
 impl<T> Lock<T> {
+    const_fn! {
+        /**
+        Creates a new lock.
+*/
+        pub const fn new(data: T) -> Lock<T> {
+            Lock::build(data, raw::DefaultRawLock::new(), None)
+        }
+    }
+
+    const_fn! {
+        /**
+        Creates a new lock with a name, which appears in debug output.
+*/
+        pub const fn with_name(data: T, name: &'static str) -> Lock<T> {
+            Lock::build(data, raw::DefaultRawLock::new(), Some(name))
+        }
+    }
+}
+
+impl<T, R: RawLock> Lock<T, R> {
+    const_fn! {
+        /**
+        Creates a new lock on a raw lock of your choosing, which must be unlocked.  See [raw].
+*/
+        pub const fn with_raw(data: T, raw: R) -> Lock<T, R> {
+            Lock::build(data, raw, None)
+        }
+    }
+
+    const_fn! {
+        /**
+        Creates a new lock with a name, on a raw lock of your choosing, which must be unlocked.
+*/
+        pub const fn with_raw_and_name(data: T, raw: R, name: &'static str) -> Lock<T, R> {
+            Lock::build(data, raw, Some(name))
+        }
+    }
+
+    const_fn! {
+        const fn build(data: T, raw: R, name: Option<&'static str>) -> Lock<T, R> {
+            Lock {
+                raw,
+                data: core::cell::UnsafeCell::new(data),
+                name,
+                waiters: AtomicUsize::new(0),
+                #[cfg(feature = "diagnostics")]
+                stats: diagnostics::Stats::new(),
+                parker: wait::Parker::new(),
+                wakers: wakers::WakerList::new(),
+                #[cfg(all(debug_assertions, feature = "std"))]
+                holder: core::sync::atomic::AtomicUsize::new(0),
+                split: core::sync::atomic::AtomicBool::new(false),
+                #[cfg(debug_assertions)]
+                live: core::sync::atomic::AtomicUsize::new(0),
+                #[cfg(feature = "poison")]
+                poisoned: core::sync::atomic::AtomicBool::new(false),
+                #[cfg(feature = "affinity")]
+                domain: core::sync::atomic::AtomicUsize::new(0),
+                #[cfg(feature = "handoff")]
+                handoff: handoff::Handoff::new(),
+                #[cfg(feature = "owner-tracking")]
+                owner: owner::Owner::new(),
+            }
+        }
+    }
+
+    /**
+    The name of the lock, if any.
+*/
+    pub const fn name(&self) -> Option<&'static str> {
+        self.name
+    }
+
+    /**
+    The settings the lock waits with when contended.
+
+    These are the process-wide [config::defaults], unless the lock's name was tuned at build time, see [config].
+*/
+    #[inline]
+    pub fn settings(&self) -> config::Settings {
+        config::settings_for(self.name)
+    }
+
+    /**
+    The number of threads currently spinning on the lock.
+
+    This is a snapshot; it may be out of date by the time you read it.
+*/
+    pub fn waiters(&self) -> usize {
+        self.waiters.load(Ordering::Relaxed)
+    }
+
+    /**
+    Whether the lock is currently held.
+
+    This is a snapshot; it may be out of date by the time you read it.  It may also spuriously
+    report `true`.
+*/
+    pub fn is_locked(&self) -> bool {
+        self.raw.is_locked()
+    }
+
+    /**
+    The number of guards for this lock that are alive, counted only with debug assertions enabled;
+    otherwise, this is always 0.
+
+    This includes guards of every kind, such as an `OwnedGuard` stored in a struct, and guards dissolved
+    with [Guard::into_raw], but not a lock sealed with [Guard::forget_locked].  At the end of a test,
+    [assert_no_live_guards] checks that none were left behind.
+*/
+    pub fn live_guards(&self) -> usize {
+        #[cfg(debug_assertions)]
+        return self.live.load(Ordering::Relaxed);
+        #[cfg(not(debug_assertions))]
+        0
+    }
+
+    /**
+    Whether the lock is held, for [assert_unlocked].  Unlike [Lock::is_locked], this retries, so it's
+    practically never spuriously `true`.
+*/
+    #[doc(hidden)]
+    pub fn __is_locked_for_assert(&self) -> bool {
+        (0..64).all(|_| self.raw.is_locked())
+    }
+
+    /**
+    Whether the lock is held by a guard acquired on the current thread, for [assert_held_by_current].
+*/
+    #[doc(hidden)]
+    pub fn __held_by_current_for_assert(&self) -> bool {
+        #[cfg(all(debug_assertions, feature = "std"))]
+        return self.holder.load(Ordering::Relaxed) == assert::current_thread();
+        #[allow(unreachable_code)]
+        self.__is_locked_for_assert()
+    }
+
+    /**
+    A snapshot of the lock's statistics.
+*/
+    #[cfg(feature = "diagnostics")]
+    pub fn stats(&self) -> diagnostics::StatsSnapshot {
+        self.stats.snapshot()
+    }
+
     /**
-    Creates a new lock.
+    Makes a guard, once the underlying lock is acquired.
 */
-    pub const fn new(data: T) -> Lock<T> {
-        Lock {
-            lock: atomiclock::AtomicLock::new(data)
+    #[cfg_attr(feature = "owner-tracking", track_caller)]
+    fn acquired(&self) -> Guard<'_, T, R> {
+        #[cfg(feature = "rt-audit")]
+        let _audit = audit::enter();
+        #[cfg(feature = "events")]
+        events::emit(events::EventKind::Acquire, self);
+        #[cfg(feature = "diagnostics")]
+        self.stats.acquired();
+        #[cfg(all(debug_assertions, feature = "std"))]
+        self.holder.store(assert::current_thread(), Ordering::Relaxed);
+        #[cfg(debug_assertions)]
+        self.live.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "affinity")]
+        self.domain.store(affinity::token(), Ordering::Relaxed);
+        #[cfg(feature = "owner-tracking")]
+        self.owner.acquired();
+        tsan::acquire(self);
+        Guard {
+            lock: self,
+            //we hold the lock, so nobody else can reach the data
+            data: unsafe { &mut *self.data.get() },
+            #[cfg(feature = "poison")]
+            defused: false,
+            #[cfg(feature = "acquired-at")]
+            acquired_at: clock::StdClock.now(),
         }
     }
 
     /**
-    Spins until the lock can be acquired.
+    Records that the current thread found the lock contended, and will spin.
+
+    # Panics
+    On single-threaded targets, since the spin would never finish.
+*/
+    fn contended(&self) -> Waiting<'_> {
+        if SINGLE_THREADED {
+            panic!("Lock is already held; on a single-threaded target, spinning on it would never finish");
+        }
+        self.waiting()
+    }
+
+    /**
+    Like [Lock::contended], for waiters that can make progress on a single-threaded target, by yielding.
 */
-    pub fn spin_lock(&self) -> Guard<'_,T> {
+    fn waiting(&self) -> Waiting<'_> {
+        #[cfg(feature = "rt-audit")]
+        let _audit = audit::enter();
+        #[cfg(feature = "events")]
+        events::emit(events::EventKind::Contention, self);
+        #[cfg(feature = "link-hooks")]
+        link_hooks::contention(self as *const Self as *const core::ffi::c_void, self.name);
+        #[cfg(feature = "diagnostics")]
+        self.stats.contended();
+        //SeqCst, so a release that doesn't see us waiting is seen by our next attempt
+        self.waiters.fetch_add(1, Ordering::SeqCst);
+        Waiting {
+            waiters: &self.waiters,
+            #[cfg(feature = "diagnostics")]
+            stats: &self.stats,
+            #[cfg(feature = "diagnostics")]
+            site: None,
+            #[cfg(feature = "diagnostics")]
+            energy: diagnostics::read_energy_meter(),
+            #[cfg(all(feature = "diagnostics", feature = "std"))]
+            started: INSTRUMENTED.then(std::time::Instant::now),
+            #[cfg(all(feature = "diagnostics", feature = "std"))]
+            lock_id: addr(self),
+            #[cfg(all(feature = "perf-counters", target_os = "linux"))]
+            _measurement: perf::Measurement::begin(),
+        }
+    }
+
+    /**
+    The contended path: spins until the underlying lock is acquired, or for [Lock::spin], until `give_up`
+    returns true, which it reports as `false`.
+
+    The caller must hold a [Waiting] from [Self::contended].
+*/
+    #[inline]
+    fn spin_forever(&self) {
+        if self.raw.lock_blocking() {
+            return;
+        }
+        let settings = self.settings();
+        let mut spins = wait::Spins::new();
+        #[cfg(feature = "handoff")]
+        let mut requested = false;
         loop {
-            match self.lock.lock() {
-                None => {}
-                Some(guard) => {return Guard(guard)}
+            let token = self.parker.token();
+            if self.raw.try_lock() {
+                #[cfg(feature = "handoff")]
+                if requested {
+                    self.handoff.acquired();
+                }
+                return;
             }
+            #[cfg(feature = "handoff")]
+            {
+                if requested && self.handoff.take() {
+                    return;
+                }
+                if !requested && spins.count() >= settings.handoff_after {
+                    requested = self.handoff.request();
+                }
+                //the release that hands off won't wake us
+                if requested {
+                    self.wait(token, &mut spins, &settings, false);
+                    continue;
+                }
+            }
+            self.wait(token, &mut spins, &settings, true);
+        }
+    }
+    fn spin(&self, mut give_up: impl FnMut() -> bool) -> bool {
+        let settings = self.settings();
+        let mut spins = wait::Spins::new();
+        loop {
+            let token = self.parker.token();
+            if self.raw.try_lock() {
+                return true;
+            }
+            if give_up() {
+                return false;
+            }
+            self.wait(token, &mut spins, &settings, false);
+        }
+    }
 
+    /**
+    Waits after a failed attempt, backing off longer if the last holder was in another affinity domain.
+*/
+    #[inline]
+    fn wait(&self, token: wait::Token, spins: &mut wait::Spins, settings: &config::Settings, block: bool) {
+        #[cfg(feature = "affinity")]
+        if affinity::is_remote(self.domain.load(Ordering::Relaxed)) {
+            return self.parker.wait(token, spins, &affinity::remote(settings), block);
+        }
+        self.parker.wait(token, spins, settings, block);
+    }
+
+    /**
+    Releases the underlying lock, on behalf of a guard.
+*/
+    fn unlock_raw(&self) {
+        #[cfg(feature = "poison")]
+        self.poison_if_panicking();
+        self.release(true);
+    }
+
+    /**
+    Releases the underlying lock, and wakes waiters.
+
+    Condition waiters are only woken if the data may have changed.
+*/
+    #[inline]
+    fn release(&self, data_changed: bool) {
+        #[cfg(feature = "rt-audit")]
+        let _audit = audit::enter();
+        self.record_release();
+        //the lock stays held, for the waiter that asked for it
+        #[cfg(feature = "handoff")]
+        if self.handoff.hand_off() {
+            self.wakers.wake(data_changed);
+            return;
+        }
+        //guards only release once, and only the lock they hold
+        unsafe { self.raw.unlock() };
+        arch::released();
+        self.parker.unpark(&self.waiters);
+        self.wakers.wake(data_changed);
+    }
+
+    /**
+    Records that a guard is done with the lock, before the underlying lock is unlocked.
+*/
+    #[inline]
+    fn record_release(&self) {
+        #[cfg(feature = "events")]
+        events::emit(events::EventKind::Release, self);
+        #[cfg(feature = "link-hooks")]
+        link_hooks::release(self as *const Self as *const core::ffi::c_void, self.name);
+        #[cfg(all(debug_assertions, feature = "std"))]
+        self.holder.store(0, Ordering::Relaxed);
+        #[cfg(debug_assertions)]
+        self.live.fetch_sub(1, Ordering::Relaxed);
+        #[cfg(feature = "owner-tracking")]
+        self.owner.released();
+        tsan::release(self);
+    }
+
+    /**
+    Spins until the lock can be acquired.
+
+    # Panics
+    On single-threaded targets, panics if the lock is held.  See [Lock::spin_lock_checked].
+*/
+    #[cfg_attr(feature = "owner-tracking", track_caller)]
+    pub fn spin_lock(&self) -> Guard<'_, T, R> {
+        if self.raw.try_lock() {
+            return self.acquired();
         }
+        let _waiting = self.contended();
+        self.spin_forever();
+        self.acquired()
+    }
+
+    /**
+    Spins until the lock can be acquired, or returns an error if it never could be.
+
+    This is [Lock::spin_lock] without the panic, for targets where panicking is not an option.
+    On multi-threaded targets, it always succeeds.
+*/
+    #[cfg_attr(feature = "owner-tracking", track_caller)]
+    pub fn spin_lock_checked(&self) -> Result<Guard<'_, T, R>, WouldDeadlock> {
+        if self.raw.try_lock() {
+            return Ok(self.acquired());
+        }
+        if SINGLE_THREADED {
+            return Err(WouldDeadlock);
+        }
+        let _waiting = self.contended();
+        self.spin_forever();
+        Ok(self.acquired())
+    }
+
+    /**
+    Spins until the lock can be acquired, accumulating stats for the call site.
+
+    Usually you want [spin_lock_instrumented!] instead, which declares the call site for you.
+*/
+    #[cfg(feature = "diagnostics")]
+    #[cfg_attr(feature = "owner-tracking", track_caller)]
+    pub fn spin_lock_at(&self, site: &'static diagnostics::CallSite) -> Guard<'_, T, R> {
+        site.enter(self);
+        if self.raw.try_lock() {
+            site.stats.acquired();
+            return self.acquired();
+        }
+        let mut _waiting = self.contended();
+        site.stats.contended();
+        _waiting.site = Some(&site.stats);
+        self.spin_forever();
+        site.stats.acquired();
+        self.acquired()
     }
 
     /**
@@ -64,45 +1196,277 @@ impl<T> Lock<T> {
     1.  A spinlock is correct and easy to write.
     2.  You have the suspicion there's a "better" lock-free algorithm, but the tradeoffs are unclear. Worse cache coherency, more code, etc.
     3.  It would be nice to collect some data that would actually drive the decision to write a lock-free algorithm, but to do that you first have to write a program.
+
+    Brief contention can be tolerated by raising the warn threshold in [config].
+
+    To avoid flooding the log (and becoming a performance problem itself), warnings are rate-limited to
+    one per second per call site.  Each warning includes how many were suppressed since the last one.
+
+    Without the `perfwarn` feature, no warning is issued.
+
+    # Panics
+    With the `strict` feature, panics if the lock is contended; [Lock::try_lock] is the non-panicking
+    way to insist on no contention.  Also panics where [Lock::spin_lock] does.
     */
-    pub fn spin_lock_warn(&self) -> Guard<'_, T> {
-        let mut _warn: Option<PerfwarnInterval>;
-        loop {
-            match self.lock.lock() {
-                None => {
-                    _warn = Some(logwise::perfwarn_begin!("spin_lock_warn is spinning; investigate ways to reduce contention"));
-                }
-                Some(guard) => {
-                    _warn = None;
-                    return Guard(guard);
-                }
+    #[track_caller]
+    pub fn spin_lock_warn(&self) -> Guard<'_, T, R> {
+        if self.raw.try_lock() {
+            return self.acquired();
+        }
+        if cfg!(feature = "strict") {
+            panic!("spin_lock_warn encountered contention (strict mode)");
+        }
+        let _waiting = self.contended();
+        let settings = self.settings();
+        let threshold = settings.warn_threshold;
+        if threshold > 0 {
+            let mut spins = 0;
+            if self.spin(|| { spins += 1; spins > threshold }) {
+                drop(_waiting);
+                return self.acquired();
+            }
+        }
+        #[cfg(all(feature = "perfwarn", not(atomiclock_bare)))]
+        let site = core::panic::Location::caller();
+        #[cfg(all(feature = "perfwarn", not(atomiclock_bare)))]
+        let _warn: Option<PerfwarnInterval> = throttle::permit(site).map(|suppressed| {
+            let mode = if settings.low_latency { "low-latency" } else { "default" };
+            logwise::perfwarn_begin!("spin_lock_warn is spinning at {site} in {mode} mode; investigate ways to reduce contention ({suppressed} similar warnings suppressed)",
+                site=std::string::ToString::to_string(site), mode=mode, suppressed=suppressed)
+        });
+        self.spin_forever();
+        #[cfg(all(feature = "perfwarn", feature = "perf-counters", target_os = "linux", not(atomiclock_bare)))]
+        let warned = _warn.is_some();
+        #[cfg(all(feature = "perfwarn", not(atomiclock_bare)))]
+        drop(_warn);
+        drop(_waiting);
+        #[cfg(all(feature = "perfwarn", feature = "perf-counters", target_os = "linux", not(atomiclock_bare)))]
+        if warned {
+            if let Some(counters) = perf::last_spin() {
+                logwise::warn_sync!("spin_lock_warn spun for {cycles} cycles with {cache_misses} cache misses",
+                    cycles=counters.cycles, cache_misses=counters.cache_misses);
+            }
+        }
+        self.acquired()
+    }
+
+    /**
+    Like [Lock::spin_lock_warn], but guaranteed not to allocate, so it can be used inside a
+    `#[global_allocator]`, or anywhere else the warning mustn't recurse into the code that called it.
+
+    Instead of logging, a contended acquisition is counted for its call site in a fixed table, and
+    [log_deferred_warnings] logs the counts later, from somewhere that can allocate:
+
+    ```
+    # use atomiclock_spinlock::Lock;
+    static FREE_LIST: Lock<usize> = Lock::new(0);
+    *FREE_LIST.spin_lock_warn_alloc_free() += 1;
+    //later, outside the allocator
+    atomiclock_spinlock::log_deferred_warnings();
+    ```
+
+    The table holds the first 32 call sites to warn; the rest are counted together.  Nothing here reads the
+    clock or formats, but features listed in `audit::HAZARDS` still make the lock paths themselves allocate.
+
+    # Panics
+    With the `strict` feature, panics if the lock is contended, as [Lock::spin_lock_warn] does.  Also panics
+    where [Lock::spin_lock] does.
+    */
+    #[track_caller]
+    pub fn spin_lock_warn_alloc_free(&self) -> Guard<'_, T, R> {
+        if self.raw.try_lock() {
+            return self.acquired();
+        }
+        if cfg!(feature = "strict") {
+            panic!("spin_lock_warn_alloc_free encountered contention (strict mode)");
+        }
+        let _waiting = self.contended();
+        let threshold = self.settings().warn_threshold;
+        if threshold > 0 {
+            let mut spins = 0;
+            if self.spin(|| { spins += 1; spins > threshold }) {
+                drop(_waiting);
+                return self.acquired();
             }
         }
+        #[cfg(all(feature = "perfwarn", not(atomiclock_bare)))]
+        throttle::defer(core::panic::Location::caller());
+        self.spin_forever();
+        drop(_waiting);
+        self.acquired()
     }
 
     /**
     Spins until the lock is available, or times out.
+
+    The clock is only read every so often, so this may give up a little after the deadline; see
+    [Lock::spin_lock_until_with].
 */
-    pub fn spin_lock_until(&self, deadline: std::time::Instant) -> Option<Guard<'_,T>> {
-        loop {
-            if std::time::Instant::now() > deadline {
-                return None;
-            }
-            match self.lock.lock() {
-                None => {}
-                Some(guard) => {return Some(Guard(guard))}
-            }
+    #[cfg(feature = "std")]
+    #[cfg_attr(feature = "owner-tracking", track_caller)]
+    pub fn spin_lock_until(&self, deadline: std::time::Instant) -> Option<Guard<'_, T, R>> {
+        self.spin_lock_until_with(&clock::StdClock, deadline)
+    }
+
+    /**
+    Spins until the lock is available, or the duration elapses.
 
+    # Panics
+    Panics if the deadline overflows [std::time::Instant].  Use [Lock::spin_lock_until] to avoid this.
+*/
+    #[cfg(feature = "std")]
+    #[cfg_attr(feature = "owner-tracking", track_caller)]
+    pub fn spin_lock_for(&self, duration: std::time::Duration) -> Option<Guard<'_, T, R>> {
+        self.spin_lock_for_with(&clock::StdClock, duration)
+    }
+
+    /**
+    Like [Lock::spin_lock_until], but when it gives up, describes the thread holding the lock, how long it's
+    held it, and where it acquired it, for a timeout log that says what it waited on:
+
+    ```
+    # use atomiclock_spinlock::Lock;
+    # use std::time::{Duration, Instant};
+    let lock = Lock::with_name(0, "cache");
+    let held = lock.spin_lock();
+    if let Err(holder) = lock.spin_lock_until_or_holder(Instant::now() + Duration::from_millis(1)) {
+        eprintln!("giving up: {holder}");
+    }
+    drop(held);
+    ```
+
+    Requires the `owner-tracking` feature, which records the holder on every acquisition.
+*/
+    #[cfg(feature = "owner-tracking")]
+    #[track_caller]
+    pub fn spin_lock_until_or_holder(&self, deadline: std::time::Instant) -> Result<Guard<'_, T, R>, ContentionInfo> {
+        match self.spin_lock_until(deadline) {
+            Some(guard) => Ok(guard),
+            None => Err(self.holder()),
+        }
+    }
+
+    /**
+    Like [Lock::spin_lock_for], but when it gives up, describes the holder, as [Lock::spin_lock_until_or_holder].
+
+    # Panics
+    Panics if the deadline overflows [std::time::Instant].  Use [Lock::spin_lock_until_or_holder] to avoid this.
+*/
+    #[cfg(feature = "owner-tracking")]
+    #[track_caller]
+    pub fn spin_lock_for_or_holder(&self, duration: std::time::Duration) -> Result<Guard<'_, T, R>, ContentionInfo> {
+        match self.spin_lock_for(duration) {
+            Some(guard) => Ok(guard),
+            None => Err(self.holder()),
+        }
+    }
+
+    /**
+    Describes the lock's holder, if any.  This is a snapshot; the holder may be gone by the time you read it.
+*/
+    #[cfg(feature = "owner-tracking")]
+    pub fn holder(&self) -> ContentionInfo {
+        self.owner.info(self.name)
+    }
+
+    /**
+    The lock's last few acquisitions, oldest first, with the thread, call site, and when each acquired and
+    released the lock, ending with the current holder, if any.  This is a snapshot.
+
+    ```
+    # use atomiclock_spinlock::Lock;
+    let lock = Lock::new(0);
+    drop(lock.spin_lock());
+    let _held = lock.spin_lock();
+    let history = lock.recent_acquisitions();
+    assert_eq!(history.len(), 2);
+    assert!(history[0].released.is_some() && history[1].released.is_none());
+    ```
+
+    Requires the `acquisition-history` feature, which keeps the last 16 acquisitions of every lock.
+*/
+    #[cfg(feature = "acquisition-history")]
+    pub fn recent_acquisitions(&self) -> alloc::vec::Vec<diagnostics::Acquisition> {
+        self.owner.history()
+    }
+
+    /**
+    Spins until the lock is available, or the clock passes the deadline.
+
+    # Precision
+    Reading the clock can cost more than trying the lock, so it's read on the first failed attempt, and after
+    that, about once every 64 relax instructions' worth of waiting (see [arch]), rather than on every attempt.
+    So this may give up that long after the deadline, usually well under a microsecond, plus however late the
+    clock itself reports it.  With affinity's remote backoff, it may be a few times longer.
+
+    Where waits may yield the thread, with the `rayon`, `crossbeam` or `chaos` features, the clock is read on every
+    attempt instead.
+*/
+    #[cfg_attr(feature = "owner-tracking", track_caller)]
+    pub fn spin_lock_until_with<C: Clock>(&self, clock: &C, deadline: C::Instant) -> Option<Guard<'_, T, R>> {
+        if self.raw.try_lock() {
+            return Some(self.acquired());
+        }
+        if SINGLE_THREADED {
+            //nobody can release the lock before the deadline
+            #[cfg(feature = "diagnostics")]
+            self.stats.timed_out();
+            return None;
+        }
+        let _waiting = self.contended();
+        let settings = self.settings();
+        let mut reads = wait::ClockReads::new();
+        let acquired = self.spin(|| reads.due(&settings) && clock.now() > deadline);
+        #[cfg(feature = "diagnostics")]
+        if !acquired {
+            self.stats.timed_out();
+        }
+        //not in a closure, which would hide the caller from the holder's location
+        if acquired {
+            Some(self.acquired())
+        } else {
+            None
+        }
+    }
+
+    /**
+    Spins until the lock is available, or the duration elapses on the clock.
+
+    # Panics
+    Panics if the clock's addition does, e.g. on overflow.
+*/
+    #[cfg_attr(feature = "owner-tracking", track_caller)]
+    pub fn spin_lock_for_with<C: Clock>(&self, clock: &C, duration: C::Duration) -> Option<Guard<'_, T, R>> {
+        self.spin_lock_until_with(clock, clock.now() + duration)
+    }
+
+    /**
+    Reconstitutes a guard dissolved with [Guard::into_raw].
+
+    # Safety
+    The lock must be held by a guard that was dissolved with [Guard::into_raw], and that has not already
+    been reconstituted.
+*/
+    pub unsafe fn guard_from_raw(&self) -> Guard<'_, T, R> {
+        Guard {
+            lock: self,
+            data: &mut *self.data.get(),
+            #[cfg(feature = "poison")]
+            defused: false,
+            #[cfg(feature = "acquired-at")]
+            acquired_at: clock::StdClock.now(),
         }
     }
 
     /**
     No spin; provides access to the lock if available.
 */
-    pub fn try_lock(&self) -> Option<Guard<'_,T>> {
-        match self.lock.lock() {
-            None => None,
-            Some(guard) => Some(Guard(guard))
+    #[cfg_attr(feature = "owner-tracking", track_caller)]
+    pub fn try_lock(&self) -> Option<Guard<'_, T, R>> {
+        if self.raw.try_lock() {
+            Some(self.acquired())
+        } else {
+            None
         }
     }
 
@@ -110,35 +1474,263 @@ impl<T> Lock<T> {
     Consumes the lock and returns the inner data.
 */
     pub fn into_inner(self) -> T {
-        self.lock.into_inner()
+        self.data.into_inner()
+    }
+
+    /**
+    The underlying [RawLock], for code that drives it directly.
+
+    Acquiring it directly bypasses the instrumentation; [Lock::guard_from_raw_lock] wraps it back up in a
+    guard.  Releasing it directly doesn't wake waiters that sleep rather than spin (with `park`, `cortex-m`
+    or `wasm-wait`), nor async waiters, until the lock is next released through a [Guard].
+*/
+    pub const fn as_raw(&self) -> &R {
+        &self.raw
+    }
+
+    /**
+    Makes a guard for the raw lock acquired directly through [Lock::as_raw], recording the acquisition as
+    [Lock::try_lock] would.
+
+    ```
+    # use atomiclock_spinlock::{raw::RawLock, Guard, Lock};
+    let lock = Lock::new(1);
+    assert!(lock.as_raw().try_lock());
+    let mut guard = unsafe { lock.guard_from_raw_lock() };
+    *guard += 1;
+    let raw = Guard::into_raw_lock(guard);
+    unsafe { raw.unlock() };
+    assert_eq!(*lock.spin_lock(), 2);
+    ```
+
+    # Safety
+    The caller must hold the raw lock, and no guard may.
+*/
+    pub unsafe fn guard_from_raw_lock(&self) -> Guard<'_, T, R> {
+        self.acquired()
     }
 
+    /**
+    Resets the lock in the child of a `fork`, and returns whether it was held when the process forked.
+
+    Only the thread that called `fork` survives in the child, so a lock that another thread held at the time
+    would otherwise stay held forever.  This releases it, and clears the waiters left over from other threads,
+    so the child can use the lock.  Call it first thing in the child, such as from a `pthread_atfork` child
+    handler, for each lock the child needs:
+
+    ```ignore
+    static CACHE: Lock<Vec<u8>> = Lock::new(Vec::new());
+    extern "C" fn in_child() {
+        if unsafe { CACHE.reinit_after_fork() } {
+            //the holder may have been halfway through an update
+            unsafe { CACHE.data() }.clear();
+        }
+    }
+    unsafe { libc::pthread_atfork(None, None, Some(in_child)) };
+    ```
+
+    A thread that held the lock may have left the data halfway through an update, so when this returns
+    `true`, don't trust the data without checking it.  With the `poison` feature, such a lock is also
+    marked poisoned.  To have the data consistent instead, acquire the lock in the `pthread_atfork` prepare
+    handler, with [Guard::forget_locked], and release it through [Lock::as_raw] in the parent and child
+    handlers; then nothing holds it halfway through an update at the fork.
+
+    This doesn't reset async waiters, or the state the `park` and `std-mutex` features keep outside the
+    lock, so don't fork while other threads wait on the lock in those ways.
+
+    # Safety
+    Only call this in the child of a `fork`, before the child starts any threads, and while it holds no
+    guard for this lock.
+*/
+    pub unsafe fn reinit_after_fork(&self) -> bool {
+        //nothing else runs in the child, so a lock that won't come is held by a thread that didn't survive,
+        //and a hundred tries rules out spurious failures
+        let held = !(0..100).any(|_| self.raw.try_lock());
+        self.raw.unlock();
+        self.waiters.store(0, Ordering::Relaxed);
+        #[cfg(all(debug_assertions, feature = "std"))]
+        self.holder.store(0, Ordering::Relaxed);
+        self.split.store(false, Ordering::Relaxed);
+        #[cfg(debug_assertions)]
+        self.live.store(0, Ordering::Relaxed);
+        #[cfg(feature = "poison")]
+        if held {
+            self.poisoned.store(true, Ordering::Relaxed);
+        }
+        #[cfg(feature = "handoff")]
+        self.handoff.reset();
+        #[cfg(feature = "owner-tracking")]
+        self.owner.reset();
+        held
+    }
 
     /**
     Unsafely provides access to the underlying data.
 
+    To read the data racily, for heuristics, use [Lock::peek_racy] instead.
+
     # Safety
     This function is unsafe because it allows access to the data without a lock.
 */
+    #[allow(clippy::mut_from_ref)]
     pub unsafe fn data(&self) -> &mut T {
-        self.lock.data()
+        &mut *self.data.get()
+    }
+
+    /**
+    Copies the data without acquiring the lock, and without ordering the copy with the lock's holders, for
+    heuristics and metrics that tolerate a stale value, such as a queue length read to decide whether to
+    bother locking.
+
+    ```
+    # use atomiclock_spinlock::Lock;
+    let pending = Lock::new(0usize);
+    *pending.spin_lock() += 3;
+    //any usize is a usize, torn or not
+    if unsafe { pending.peek_racy() } > 0 {
+        let mut pending = pending.spin_lock();
+        *pending = pending.saturating_sub(1);
+    }
+    ```
+
+    The copy is a volatile read of the data, made while a holder may be writing it.  So it may be:
+
+    * stale: it doesn't synchronize with the lock, so it may miss the holders' latest writes, and the
+      writes it does see don't make any other memory the holders wrote visible;
+    * torn: each part of the data may come from a different write, so a pair of fields may never have
+      held the copied values together, and a multi-byte field may be half old and half new.
+
+    Use the copy for a guess, and acquire the lock to act on it.  Where the copy must be consistent, use an
+    [OptimisticLock], which checks that no writer came by.  This is a data race, which the Rust memory model
+    makes undefined behavior; like [OptimisticLock] and other sequence locks, it relies on a volatile read
+    of `Copy` data racing with writes doing no more than producing a torn value, as it does on the targets
+    this crate supports.  Miri reports it whenever a guard is alive, since the guard's `&mut T` is meant to
+    be exclusive, and ThreadSanitizer whenever a write races.
+
+    # Safety
+    Every mix of bytes from values the data has held must be a valid `T`, as it is for integers, floats,
+    and arrays and `#[repr(C)]` structs of them, but not for `bool`, `char`, enums, references or
+    pointers that will be dereferenced.
+*/
+    pub unsafe fn peek_racy(&self) -> T
+    where
+        T: Copy,
+    {
+        core::ptr::read_volatile(self.data.get())
+    }
+
+    /**
+    An acquire fence, for reading what a device, or a thread that doesn't take the lock, leaves in memory
+    the lock's holders then use, such as a buffer filled by DMA:
+
+    ```
+    # use atomiclock_spinlock::Lock;
+    # use core::sync::atomic::{AtomicBool, Ordering};
+    static DONE: AtomicBool = AtomicBool::new(false);
+    let buffer = Lock::new([0u8; 64]);
+    let mut held = buffer.spin_lock();
+    //point the device at held.as_mut_ptr(), start the transfer, and wait for it to finish
+    # DONE.store(true, Ordering::Relaxed);
+    while !DONE.load(Ordering::Relaxed) {}
+    buffer.acquire_fence();
+    let first = held[0];
+    ```
+
+    It orders the loads before it, such as of a status register or a flag, before the loads and stores
+    after it, for the CPUs and for devices.  See [Ordering](Lock#ordering).  It doesn't touch the lock.
+*/
+    #[inline]
+    pub fn acquire_fence(&self) {
+        fence::acquire();
+    }
+
+    /**
+    A release fence, for handing memory the lock's holders wrote to a device, or a thread that doesn't take
+    the lock, such as before starting DMA out of a buffer.
+
+    It orders the loads and stores before it before the stores after it, such as to a doorbell register or a
+    flag, for the CPUs and for devices.  See [Ordering](Lock#ordering).  It doesn't touch the lock.
+*/
+    #[inline]
+    pub fn release_fence(&self) {
+        fence::release();
     }
 }
 
+/**
+Spins until the lock can be acquired, accumulating stats for this call site.
+
+```text
+let guard = spin_lock_instrumented!(lock);
+```
+
+Each place the macro appears is tracked separately by `file:line:column`, so when one lock is taken from
+several places, you can tell which of them contend.  See `diagnostics::call_sites`.
+
+Without the `diagnostics` feature, this is the same as [Lock::spin_lock].
+*/
+#[cfg(feature = "diagnostics")]
+#[macro_export]
+macro_rules! spin_lock_instrumented {
+    ($lock:expr) => {{
+        static SITE: $crate::diagnostics::CallSite = $crate::diagnostics::CallSite::new(file!(), line!(), column!());
+        ($lock).spin_lock_at(&SITE)
+    }};
+}
+
+/**
+Spins until the lock can be acquired, accumulating stats for this call site.
+
+Without the `diagnostics` feature, this is the same as [Lock::spin_lock].
+*/
+#[cfg(not(feature = "diagnostics"))]
+#[macro_export]
+macro_rules! spin_lock_instrumented {
+    ($lock:expr) => {
+        ($lock).spin_lock()
+    };
+}
+
 /*boilerplate
 Locks are not clone, so not copy, Eq, Ord, Hash, etc.
 Can pass-through default for default type
 can support From for the data type
 
  */
-impl<T: Default> Default for Lock<T> {
-    fn default() -> Lock<T> {
-        Lock::new(Default::default())
+impl<T: Debug, R: RawLock> Debug for Lock<T, R> {
+    /**
+    Formats the lock without blocking.
+
+    The data is only formatted if the lock can be acquired without spinning, as with
+    [Lock::try_lock]; otherwise it is reported as locked.
+    */
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut s = f.debug_struct("Lock");
+        s.field("name", &self.name);
+        s.field("waiters", &self.waiters());
+        //the guard's drop wakes any waiters, even if formatting the data panics
+        match self.try_lock() {
+            Some(guard) => {
+                s.field("locked", &false);
+                s.field("data", &*guard);
+            }
+            None => {
+                s.field("locked", &true);
+                s.field("data", &format_args!("<locked>"));
+            }
+        }
+        s.finish()
     }
 }
-impl<T> From<T> for Lock<T> {
-    fn from(data: T) -> Lock<T> {
-        Lock::new(data)
+
+impl<T: Default, R: RawLock + Default> Default for Lock<T, R> {
+    fn default() -> Lock<T, R> {
+        Lock::with_raw(Default::default(), Default::default())
+    }
+}
+impl<T, R: RawLock + Default> From<T> for Lock<T, R> {
+    fn from(data: T) -> Lock<T, R> {
+        Lock::with_raw(data, Default::default())
     }
 }
 
@@ -148,37 +1740,46 @@ No default for guard
 I guess it could be created via from
  */
 
-impl<'a, T> From<&'a Lock<T>> for Guard<'a,T> {
+impl<'a, T, R: RawLock> From<&'a Lock<T, R>> for Guard<'a, T, R> {
     /**
     Implements From by spinning until the lock is acquired.
     */
-    fn from(lock: &'a Lock<T>) -> Guard<'a,T> {
+    fn from(lock: &'a Lock<T, R>) -> Guard<'a, T, R> {
         lock.spin_lock()
     }
 }
 
-impl<T> AsRef<T> for Guard<'_,T> {
+impl<T: Debug, R: RawLock> Debug for Guard<'_, T, R> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Guard")
+            .field("name", &self.lock.name)
+            .field("data", &**self)
+            .finish()
+    }
+}
+
+impl<T, R: RawLock> AsRef<T> for Guard<'_, T, R> {
     fn as_ref(&self) -> &T {
-        self.0.as_ref()
+        self
     }
 }
 
-impl<T> AsMut<T> for Guard<'_,T> {
+impl<T, R: RawLock> AsMut<T> for Guard<'_, T, R> {
     fn as_mut(&mut self) -> &mut T {
-        self.0.as_mut()
+        self
     }
 }
 
-impl<T> Deref for Guard<'_,T> {
+impl<T, R: RawLock> Deref for Guard<'_, T, R> {
     type Target = T;
     fn deref(&self) -> &T {
-        self.0.deref()
+        self.data
     }
 }
 
-impl<T> DerefMut for Guard<'_,T> {
+impl<T, R: RawLock> DerefMut for Guard<'_, T, R> {
     fn deref_mut(&mut self) -> &mut T {
-        self.0.deref_mut()
+        self.get_mut()
     }
 }
 
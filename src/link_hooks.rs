@@ -0,0 +1,166 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Instrumentation hooks that are overridden at link time, with the `link-hooks` feature.
+
+Every [Lock](crate::Lock) calls two C functions, which this crate defines as weak symbols that do nothing:
+
+```c
+void __spinlock_on_contention(const void *lock, const char *name, size_t name_len);
+void __spinlock_on_release(const void *lock, const char *name, size_t name_len);
+```
+
+The first is called when a thread finds a lock held, and will spin; the second as a guard releases the lock.
+`lock` is the lock's address, and `name` its name, not NUL-terminated, or null with a length of 0.  Any
+strong definition linked into the program, from Rust, C or a profiler's static library, replaces the
+default, with no registration and no dependency on this crate:
+
+```ignore
+#[no_mangle]
+pub extern "C" fn __spinlock_on_contention(lock: *const c_void, name: *const u8, name_len: usize) {
+    CONTENDED.fetch_add(1, Ordering::Relaxed);
+}
+```
+
+The hooks run on the lock paths, holding the lock in the release hook, so they must be quick, mustn't unwind,
+and mustn't lock a [Lock](crate::Lock) themselves.  With `--cfg atomiclock_bare`, they aren't called.
+
+Weak symbols need ELF or Mach-O, so the hooks are called on Linux, Android, the BSDs, Apple platforms and
+bare-metal ELF targets, on x86, x86_64, ARM, AArch64 and RISC-V; elsewhere, the feature does nothing.
+*/
+
+use core::ffi::c_void;
+
+/**
+`cfg`s the first items to the targets where the hooks are defined, and the others to the rest.
+*/
+macro_rules! where_supported {
+    ({ $($yes:item)* } else { $($no:item)* }) => {
+        $(
+            #[cfg(all(
+                any(target_os = "linux", target_os = "android", target_os = "freebsd", target_os = "netbsd",
+                    target_os = "openbsd", target_os = "none", target_vendor = "apple"),
+                any(target_arch = "x86", target_arch = "x86_64", target_arch = "arm", target_arch = "aarch64",
+                    target_arch = "riscv32", target_arch = "riscv64"),
+                any(not(target_vendor = "apple"), target_arch = "x86_64", target_arch = "aarch64"),
+            ))]
+            $yes
+        )*
+        $(
+            #[cfg(not(all(
+                any(target_os = "linux", target_os = "android", target_os = "freebsd", target_os = "netbsd",
+                    target_os = "openbsd", target_os = "none", target_vendor = "apple"),
+                any(target_arch = "x86", target_arch = "x86_64", target_arch = "arm", target_arch = "aarch64",
+                    target_arch = "riscv32", target_arch = "riscv64"),
+                any(not(target_vendor = "apple"), target_arch = "x86_64", target_arch = "aarch64"),
+            )))]
+            $no
+        )*
+    };
+}
+
+/**
+Defines a weak function, whose body is `ret`, and on 32-bit ARM, optionally in Thumb.
+*/
+#[allow(unused_macros)]
+macro_rules! weak_default {
+    ($name:literal, $ret:literal $(, $thumb:literal)?) => {
+        #[cfg(not(target_vendor = "apple"))]
+        core::arch::global_asm!(
+            concat!(".pushsection .text.", $name, ",\"ax\",%progbits"),
+            concat!(".weak ", $name),
+            concat!(".type ", $name, ",%function"),
+            $($thumb,)?
+            concat!($name, ":"),
+            $ret,
+            concat!(".size ", $name, ", . - ", $name),
+            ".popsection",
+        );
+        //Mach-O symbols take a leading underscore
+        #[cfg(target_vendor = "apple")]
+        core::arch::global_asm!(
+            ".pushsection __TEXT,__text,regular,pure_instructions",
+            concat!(".globl _", $name),
+            concat!(".weak_definition _", $name),
+            ".p2align 2",
+            concat!("_", $name, ":"),
+            $ret,
+            ".popsection",
+        );
+    };
+}
+
+#[cfg(not(target_arch = "arm"))]
+#[allow(unused_macros)]
+macro_rules! weak_hook {
+    ($name:literal) => {
+        weak_default!($name, "ret");
+    };
+}
+
+#[cfg(all(target_arch = "arm", target_feature = "thumb-mode"))]
+macro_rules! weak_hook {
+    ($name:literal) => {
+        weak_default!($name, "bx lr", ".thumb_func");
+    };
+}
+
+#[cfg(all(target_arch = "arm", not(target_feature = "thumb-mode")))]
+macro_rules! weak_hook {
+    ($name:literal) => {
+        weak_default!($name, "bx lr");
+    };
+}
+
+/**
+Calls the contention hook.
+*/
+#[inline]
+pub(crate) fn contention(lock: *const c_void, name: Option<&'static str>) {
+    if crate::INSTRUMENTED {
+        on_contention(lock, name);
+    }
+}
+
+/**
+Calls the release hook.
+*/
+#[inline]
+pub(crate) fn release(lock: *const c_void, name: Option<&'static str>) {
+    if crate::INSTRUMENTED {
+        on_release(lock, name);
+    }
+}
+
+where_supported!({
+    weak_hook!("__spinlock_on_contention");
+    weak_hook!("__spinlock_on_release");
+
+    extern "C" {
+        fn __spinlock_on_contention(lock: *const c_void, name: *const u8, name_len: usize);
+        fn __spinlock_on_release(lock: *const c_void, name: *const u8, name_len: usize);
+    }
+
+    #[inline]
+    fn on_contention(lock: *const c_void, name: Option<&'static str>) {
+        let (name, name_len) = parts(name);
+        //the weak default, or whatever overrides it, takes these arguments
+        unsafe { __spinlock_on_contention(lock, name, name_len) }
+    }
+
+    #[inline]
+    fn on_release(lock: *const c_void, name: Option<&'static str>) {
+        let (name, name_len) = parts(name);
+        unsafe { __spinlock_on_release(lock, name, name_len) }
+    }
+
+    fn parts(name: Option<&'static str>) -> (*const u8, usize) {
+        name.map_or((core::ptr::null(), 0), |name| (name.as_ptr(), name.len()))
+    }
+} else {
+    //no weak symbols here, so no hooks
+    #[inline]
+    fn on_contention(_lock: *const c_void, _name: Option<&'static str>) {}
+
+    #[inline]
+    fn on_release(_lock: *const c_void, _name: Option<&'static str>) {}
+});
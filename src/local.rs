@@ -0,0 +1,92 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Guards that can't leave their thread.
+*/
+
+use core::fmt::Debug;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+use crate::{Guard, Lock};
+
+/**
+A [Guard] that is deliberately `!Send`.
+
+Holding a spinlock across an `.await` is a latency bug: every task that wants the lock spins until the
+holder is polled again.  A future that holds a `LocalGuard` across an `.await` is itself `!Send`, so
+work-stealing executors (which require `Send` futures) reject it at compile time:
+
+```compile_fail
+# use atomiclock_spinlock::Lock;
+fn spawn<F: std::future::Future + Send>(_: F) {}
+static LOCK: Lock<u32> = Lock::new(0);
+async fn yield_now() {}
+spawn(async {
+    let guard = LOCK.spin_lock_local();
+    yield_now().await;
+    drop(guard);
+});
+```
+*/
+#[must_use]
+pub struct LocalGuard<'a, T> {
+    guard: Guard<'a, T>,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<T> Lock<T> {
+    /**
+    Spins until the lock can be acquired, returning a `!Send` [LocalGuard].
+*/
+    pub fn spin_lock_local(&self) -> LocalGuard<'_, T> {
+        Guard::into_local(self.spin_lock())
+    }
+
+    /**
+    No spin; provides a [LocalGuard] if the lock is available.
+*/
+    pub fn try_lock_local(&self) -> Option<LocalGuard<'_, T>> {
+        self.try_lock().map(Guard::into_local)
+    }
+}
+
+impl<'a, T> Guard<'a, T> {
+    /**
+    Converts the guard into a `!Send` [LocalGuard].
+*/
+    pub fn into_local(guard: Self) -> LocalGuard<'a, T> {
+        LocalGuard { guard, _not_send: PhantomData }
+    }
+}
+
+//boilerplate, same as Guard
+
+impl<T: Debug> Debug for LocalGuard<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("LocalGuard").field("guard", &self.guard).finish()
+    }
+}
+
+impl<T> Deref for LocalGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T> DerefMut for LocalGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T> AsRef<T> for LocalGuard<'_, T> {
+    fn as_ref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T> AsMut<T> for LocalGuard<'_, T> {
+    fn as_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
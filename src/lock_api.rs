@@ -0,0 +1,177 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+The spinlocks as [lock_api](https://crates.io/crates/lock_api) raw locks.
+
+Code written against `lock_api::Mutex` can use the spinlock, including its perfwarn instrumentation,
+by plugging in [RawSpinlock]:
+
+```text
+static DATA: atomiclock_spinlock::lock_api::Mutex<Vec<u8>> = atomiclock_spinlock::lock_api::Mutex::new(Vec::new());
+DATA.lock().push(1);
+```
+
+Locking goes through [Lock::spin_lock_warn], so contention is reported (and, with `strict`, panics) the same
+way.  Since `lock_api` is in the way, warnings report the call site inside `lock_api` rather than yours.
+
+Likewise, [RawSpinRwLock] plugs the reader-writer spinlock into `lock_api::RwLock`, with upgradable
+and mapped guards.
+*/
+
+use ::lock_api::{GuardSend, RawMutex, RawRwLock, RawRwLockDowngrade, RawRwLockUpgrade, RawRwLockUpgradeDowngrade};
+use crate::clock::Clock;
+use crate::{Guard, Lock};
+
+/**
+A raw spinlock, for use with [lock_api::Mutex].
+*/
+#[derive(Debug, Default)]
+pub struct RawSpinlock {
+    lock: Lock<()>,
+}
+
+/// A `lock_api` mutex protected by [RawSpinlock].
+pub type Mutex<T> = ::lock_api::Mutex<RawSpinlock, T>;
+/// A guard for [Mutex].
+pub type MutexGuard<'a, T> = ::lock_api::MutexGuard<'a, RawSpinlock, T>;
+
+unsafe impl RawMutex for RawSpinlock {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: RawSpinlock = RawSpinlock { lock: Lock::new(()) };
+    type GuardMarker = GuardSend;
+
+    fn lock(&self) {
+        //lock_api releases the lock with unlock
+        Guard::into_raw(self.lock.spin_lock_warn());
+    }
+
+    fn try_lock(&self) -> bool {
+        match self.lock.try_lock() {
+            Some(guard) => {
+                Guard::into_raw(guard);
+                true
+            }
+            None => false,
+        }
+    }
+
+    unsafe fn unlock(&self) {
+        drop(self.lock.guard_from_raw());
+    }
+
+    fn is_locked(&self) -> bool {
+        self.lock.is_locked()
+    }
+}
+
+#[cfg(feature = "std")]
+unsafe impl ::lock_api::RawMutexTimed for RawSpinlock {
+    type Duration = std::time::Duration;
+    type Instant = std::time::Instant;
+
+    fn try_lock_for(&self, timeout: std::time::Duration) -> bool {
+        match crate::clock::StdClock.now().checked_add(timeout) {
+            Some(deadline) => self.try_lock_until(deadline),
+            //a deadline too far off to represent, such as for Duration::MAX, is no deadline, as in parking_lot
+            None => {
+                RawMutex::lock(self);
+                true
+            }
+        }
+    }
+
+    fn try_lock_until(&self, timeout: std::time::Instant) -> bool {
+        self.lock.spin_lock_until(timeout).map(Guard::into_raw).is_some()
+    }
+}
+
+/**
+A raw reader-writer spinlock, for use with [lock_api::RwLock].
+
+This supports upgradable reads and downgrades, so all of `lock_api`'s guards are available.
+*/
+#[derive(Debug, Default)]
+pub struct RawSpinRwLock {
+    lock: crate::RwLock<()>,
+}
+
+/// A `lock_api` reader-writer lock protected by [RawSpinRwLock].
+pub type RwLock<T> = ::lock_api::RwLock<RawSpinRwLock, T>;
+/// A read guard for [RwLock].
+pub type RwLockReadGuard<'a, T> = ::lock_api::RwLockReadGuard<'a, RawSpinRwLock, T>;
+/// A write guard for [RwLock].
+pub type RwLockWriteGuard<'a, T> = ::lock_api::RwLockWriteGuard<'a, RawSpinRwLock, T>;
+/// An upgradable read guard for [RwLock].
+pub type RwLockUpgradableReadGuard<'a, T> = ::lock_api::RwLockUpgradableReadGuard<'a, RawSpinRwLock, T>;
+
+unsafe impl RawRwLock for RawSpinRwLock {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: RawSpinRwLock = RawSpinRwLock { lock: crate::RwLock::new(()) };
+    type GuardMarker = GuardSend;
+
+    fn lock_shared(&self) {
+        //lock_api releases the lock with the unlock methods
+        core::mem::forget(self.lock.read());
+    }
+
+    fn try_lock_shared(&self) -> bool {
+        self.lock.raw_try_read()
+    }
+
+    unsafe fn unlock_shared(&self) {
+        self.lock.raw_unlock_read();
+    }
+
+    fn lock_exclusive(&self) {
+        core::mem::forget(self.lock.write());
+    }
+
+    fn try_lock_exclusive(&self) -> bool {
+        self.lock.raw_try_write()
+    }
+
+    unsafe fn unlock_exclusive(&self) {
+        self.lock.raw_unlock_write();
+    }
+
+    fn is_locked_exclusive(&self) -> bool {
+        self.lock.is_locked_exclusive()
+    }
+}
+
+unsafe impl RawRwLockUpgrade for RawSpinRwLock {
+    fn lock_upgradable(&self) {
+        core::mem::forget(self.lock.upgradable_read());
+    }
+
+    fn try_lock_upgradable(&self) -> bool {
+        self.lock.raw_try_upgradable()
+    }
+
+    unsafe fn unlock_upgradable(&self) {
+        self.lock.raw_unlock_upgradable();
+    }
+
+    unsafe fn upgrade(&self) {
+        self.lock.raw_upgrade();
+    }
+
+    unsafe fn try_upgrade(&self) -> bool {
+        self.lock.raw_try_upgrade()
+    }
+}
+
+unsafe impl RawRwLockDowngrade for RawSpinRwLock {
+    unsafe fn downgrade(&self) {
+        self.lock.raw_downgrade_write();
+    }
+}
+
+unsafe impl RawRwLockUpgradeDowngrade for RawSpinRwLock {
+    unsafe fn downgrade_upgradable(&self) {
+        self.lock.raw_downgrade_upgradable();
+    }
+
+    unsafe fn downgrade_to_upgradable(&self) {
+        self.lock.raw_downgrade_write_to_upgradable();
+    }
+}
@@ -0,0 +1,177 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Arrays of locks, each on its own cache line, for striping.
+*/
+
+use core::fmt::Debug;
+use core::mem::{ManuallyDrop, MaybeUninit};
+use core::ops::Index;
+use crate::Lock;
+
+//a cache line, or two on targets that prefetch in pairs, so neighboring locks don't share one
+#[repr(align(128))]
+struct Padded<T>(T);
+
+/**
+`N` locks, each on its own cache line, for striping data across them, such as the buckets of a table or
+per-core state.
+
+Build one with [Lock::new_array], from a function of the index, or in a `static` with
+[LockArray::new]:
+
+```
+# use atomiclock_spinlock::{Lock, LockArray};
+static HITS: LockArray<u64, 8> = LockArray::new([0; 8]);
+*HITS.stripe(13).spin_lock() += 1;
+assert_eq!(*HITS[5].spin_lock(), 1);
+
+let buckets: LockArray<Vec<u32>, 4> = Lock::new_array(|index| vec![index as u32]);
+assert_eq!(buckets.iter().map(|bucket| bucket.spin_lock().len()).sum::<usize>(), 4);
+```
+
+Each lock takes a cache line, or two, whatever the size of `T`, so that threads working on different
+stripes don't slow each other down.
+*/
+pub struct LockArray<T, const N: usize> {
+    locks: [Padded<Lock<T>>; N],
+}
+
+//reinterprets one array as another with the same layout, which can't be transmuted when its length is generic
+union Cast<A, B> {
+    from: ManuallyDrop<A>,
+    to: ManuallyDrop<B>,
+}
+
+impl<T> Lock<T> {
+    /**
+    Creates `N` locks, with the data for each from `init` of its index.
+*/
+    pub fn new_array<const N: usize>(mut init: impl FnMut(usize) -> T) -> LockArray<T, N> {
+        LockArray { locks: core::array::from_fn(|index| Padded(Lock::new(init(index)))) }
+    }
+}
+
+impl<T: Copy, const N: usize> LockArray<T, N> {
+    const_fn! {
+        /**
+        Creates a lock for each value, which works in a `static`, unlike [Lock::new_array].
+
+        This needs `Copy` values, since a const fn can't move them out of the array one by one; for others,
+        use [Lock::new_array] or `From`.
+        */
+        pub const fn new(values: [T; N]) -> Self {
+            //an array of MaybeUninit needs no initialization
+            let mut locks: [MaybeUninit<Padded<Lock<T>>>; N] = unsafe { MaybeUninit::uninit().assume_init() };
+            let mut index = 0;
+            while index < N {
+                locks[index] = MaybeUninit::new(Padded(Lock::new(values[index])));
+                index += 1;
+            }
+            //each lock was initialized above
+            let locks = unsafe { ManuallyDrop::into_inner(Cast { from: ManuallyDrop::new(locks) }.to) };
+            LockArray { locks }
+        }
+    }
+}
+
+impl<T, const N: usize> LockArray<T, N> {
+    /**
+    The number of locks, `N`.
+*/
+    pub const fn len(&self) -> usize {
+        N
+    }
+
+    /**
+    Whether there are no locks, that is, `N` is 0.
+*/
+    pub const fn is_empty(&self) -> bool {
+        N == 0
+    }
+
+    /**
+    The lock at `index`, or `None` if it's out of range.
+*/
+    pub fn get(&self, index: usize) -> Option<&Lock<T>> {
+        self.locks.get(index).map(|padded| &padded.0)
+    }
+
+    /**
+    The lock for `n`, modulo `N`, such as for a thread, core or bucket number.
+
+    # Panics
+    If there are no locks.
+*/
+    pub fn stripe(&self, n: usize) -> &Lock<T> {
+        assert!(N > 0, "LockArray has no locks");
+        &self.locks[n % N].0
+    }
+
+    /**
+    The lock for a key, chosen by its hash with `hasher`, so that equal keys always get the same lock.
+
+    # Panics
+    If there are no locks.
+*/
+    pub fn stripe_for<K: core::hash::Hash + ?Sized>(&self, key: &K, hasher: &impl core::hash::BuildHasher) -> &Lock<T> {
+        //the high bits, which are better mixed in some hashers
+        self.stripe((hasher.hash_one(key) >> 32) as usize)
+    }
+
+    /**
+    The locks, in order.
+*/
+    pub fn iter(&self) -> impl Iterator<Item = &Lock<T>> {
+        self.locks.iter().map(|padded| &padded.0)
+    }
+
+    /**
+    Returns mutable references to the data, in order.  No locking is needed, since the borrow is exclusive.
+*/
+    pub fn get_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.locks.iter_mut().map(|padded| padded.0.data.get_mut())
+    }
+
+    /**
+    Consumes the locks, returning the data.
+*/
+    pub fn into_inner(self) -> [T; N] {
+        self.locks.map(|padded| padded.0.into_inner())
+    }
+}
+
+impl<T, const N: usize> Index<usize> for LockArray<T, N> {
+    type Output = Lock<T>;
+
+    /**
+    The lock at `index`.
+
+    # Panics
+    If `index` is out of range.
+*/
+    fn index(&self, index: usize) -> &Lock<T> {
+        &self.locks[index].0
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<T: Debug, const N: usize> Debug for LockArray<T, N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Default, const N: usize> Default for LockArray<T, N> {
+    fn default() -> Self {
+        Lock::new_array(|_| T::default())
+    }
+}
+
+impl<T, const N: usize> From<[T; N]> for LockArray<T, N> {
+    fn from(values: [T; N]) -> Self {
+        LockArray { locks: values.map(|value| Padded(Lock::new(value))) }
+    }
+}
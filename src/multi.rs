@@ -0,0 +1,114 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Taking several locks at once, all or nothing.
+*/
+
+use crate::{Guard, Lock};
+
+/**
+Returned by [Lock::try_lock_all] and [Lock::try_lock_many] when one of the locks was held.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WouldBlock {
+    index: usize,
+}
+
+impl WouldBlock {
+    /**
+    The position of the held lock in the locks passed to [Lock::try_lock_all] or [Lock::try_lock_many].
+*/
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl<T> Lock<T> {
+    /**
+    Takes every lock in `locks`, in order, without spinning.  If any is held, releases the ones already
+    taken, and says which one was held.
+
+    Since it never waits while holding a lock, this can't deadlock, however other threads order the same
+    locks, so a retry loop around it is safe where nested `spin_lock` calls might not be:
+
+    ```
+    # use atomiclock_spinlock::{Lock, SpinWait};
+    let accounts = [Lock::new(100), Lock::new(0)];
+    let mut wait = SpinWait::new();
+    let [mut from, mut to] = loop {
+        match Lock::try_lock_all([&accounts[0], &accounts[1]]) {
+            Ok(guards) => break guards,
+            Err(_) => wait.spin(),
+        }
+    };
+    *from -= 30;
+    *to += 30;
+    ```
+
+    The same lock appearing twice is reported as held, at its second position.
+*/
+    pub fn try_lock_all<'a, const N: usize>(locks: [&'a Lock<T>; N]) -> Result<[Guard<'a, T>; N], WouldBlock> {
+        let mut blocked = None;
+        let guards: [Option<Guard<'a, T>>; N] = core::array::from_fn(|index| {
+            if blocked.is_some() {
+                return None;
+            }
+            let guard = locks[index].try_lock();
+            if guard.is_none() {
+                blocked = Some(WouldBlock { index });
+            }
+            guard
+        });
+        match blocked {
+            //dropping the guards releases the locks taken so far
+            Some(blocked) => Err(blocked),
+            None => Ok(guards.map(|guard| guard.expect("every lock was taken"))),
+        }
+    }
+
+    /**
+    Like [Lock::try_lock_all], for a set of locks only known at run time, such as the accounts a transaction
+    touches.
+
+    ```
+    # use atomiclock_spinlock::Lock;
+    let accounts: Vec<Lock<u32>> = (0..4).map(Lock::new).collect();
+    let held = accounts[2].spin_lock();
+    let touched = [0, 2, 3].map(|i| &accounts[i]);
+    //the first is taken before the second is found held, and released again
+    let blocked = Lock::try_lock_many(touched).unwrap_err();
+    assert_eq!(blocked.index(), 1);
+    assert!(accounts[0].try_lock().is_some());
+    drop(held);
+    ```
+
+    On success, the guards are in the same order as the locks.  Requires the `alloc` feature.
+*/
+    #[cfg(feature = "alloc")]
+    pub fn try_lock_many<'a>(locks: impl IntoIterator<Item = &'a Lock<T>>) -> Result<alloc::vec::Vec<Guard<'a, T>>, WouldBlock>
+    where
+        T: 'a,
+    {
+        let mut guards = alloc::vec::Vec::new();
+        for (index, lock) in locks.into_iter().enumerate() {
+            match lock.try_lock() {
+                Some(guard) => guards.push(guard),
+                //dropping the guards releases the locks taken so far
+                None => return Err(WouldBlock { index }),
+            }
+        }
+        Ok(guards)
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl core::fmt::Display for WouldBlock {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "lock {} of the set is held", self.index)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for WouldBlock {}
@@ -0,0 +1,230 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Reading without taking the lock, and checking afterwards that nobody wrote.
+*/
+
+use core::cell::UnsafeCell;
+use core::fmt::Debug;
+use core::mem::MaybeUninit;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{fence, AtomicUsize, Ordering};
+use crate::{Guard, Lock};
+
+/**
+A lock for `Copy` data that readers can copy without acquiring.
+
+Writers take the lock as usual, with [OptimisticLock::spin_lock].  Readers take an [OptimisticGuard]: a
+consistent copy of the data, made without acquiring the lock, that can later [OptimisticGuard::validate]
+whether any writer has held the lock since.  Reads never block writers, so read-mostly data doesn't
+bounce the lock's cache line between readers.
+
+```
+# use atomiclock_spinlock::OptimisticLock;
+let bounds = OptimisticLock::new((0u32, 10u32));
+let snapshot = bounds.optimistic().unwrap();
+//speculative work, on the copy
+let width = snapshot.1 - snapshot.0;
+assert!(snapshot.validate());
+bounds.spin_lock().1 = 20;
+//a writer came by, so the work is stale
+assert!(!snapshot.validate());
+assert_eq!(width, 10);
+```
+
+[OptimisticLock::read] does the common part: read optimistically, and take the lock only on conflict.
+
+This is a sequence lock behind a [Lock]: the writer bumps a version before and after writing, and readers
+check it around their copy.  Like other sequence locks, the copy races with writers, and is discarded
+when it may be torn, before anyone sees it.
+*/
+pub struct OptimisticLock<T> {
+    lock: Lock<()>,
+    //odd while a writer holds the lock
+    version: AtomicUsize,
+    data: UnsafeCell<T>,
+}
+
+//readers copy the data to their own thread
+unsafe impl<T: Send> Sync for OptimisticLock<T> {}
+
+impl<T> OptimisticLock<T> {
+    const_fn! {
+        /**
+        Creates a new lock.
+        */
+        pub const fn new(data: T) -> Self {
+            OptimisticLock { lock: Lock::new(()), version: AtomicUsize::new(0), data: UnsafeCell::new(data) }
+        }
+    }
+
+    /**
+    Spins until the lock can be acquired, to write.
+*/
+    pub fn spin_lock(&self) -> OptimisticWriteGuard<'_, T> {
+        self.write(self.lock.spin_lock())
+    }
+
+    /**
+    No spin; acquires the lock to write, if available.
+*/
+    pub fn try_lock(&self) -> Option<OptimisticWriteGuard<'_, T>> {
+        Some(self.write(self.lock.try_lock()?))
+    }
+
+    fn write<'a>(&'a self, guard: Guard<'a, ()>) -> OptimisticWriteGuard<'a, T> {
+        let version = self.version.load(Ordering::Relaxed);
+        self.version.store(version + 1, Ordering::Relaxed);
+        //readers that see our writes see the odd version
+        fence(Ordering::Release);
+        OptimisticWriteGuard { lock: self, _guard: guard, version }
+    }
+
+    /**
+    Returns a mutable reference to the data.  No locking is needed, since the borrow is exclusive.
+*/
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    /**
+    Consumes the lock, returning the data.
+*/
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<T: Copy> OptimisticLock<T> {
+    /**
+    Copies the data without acquiring the lock, or returns `None` if a writer holds the lock, or wrote
+    during the copy.
+*/
+    pub fn optimistic(&self) -> Option<OptimisticGuard<'_, T>> {
+        let version = self.version.load(Ordering::Acquire);
+        if version % 2 == 1 {
+            return None;
+        }
+        //may race with a writer, so it isn't a T until the version shows it wasn't torn
+        let copy = unsafe { core::ptr::read_volatile(self.data.get() as *const MaybeUninit<T>) };
+        fence(Ordering::Acquire);
+        if self.version.load(Ordering::Relaxed) != version {
+            return None;
+        }
+        Some(OptimisticGuard { lock: self, snapshot: unsafe { copy.assume_init() }, version })
+    }
+
+    /**
+    Runs `f` on a copy of the data made without acquiring the lock, or if there's a writer, on the data
+    under the lock.
+*/
+    pub fn read<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        match self.optimistic() {
+            Some(snapshot) => f(&snapshot),
+            None => f(&self.spin_lock()),
+        }
+    }
+}
+
+/**
+A copy of an [OptimisticLock]'s data, which knows whether it's still current.
+*/
+pub struct OptimisticGuard<'a, T> {
+    lock: &'a OptimisticLock<T>,
+    snapshot: T,
+    version: usize,
+}
+
+impl<'a, T> OptimisticGuard<'a, T> {
+    /**
+    Whether no writer has acquired the lock since the copy was made, so the copy is still current.
+*/
+    pub fn validate(&self) -> bool {
+        self.lock.version.load(Ordering::Acquire) == self.version
+    }
+
+    /**
+    Takes the lock to write, if the copy is still current, so the write can build on work done with it.
+    Spins for the lock first, if needed.
+*/
+    pub fn upgrade(self) -> Option<OptimisticWriteGuard<'a, T>> {
+        let guard = self.lock.lock.spin_lock();
+        //only holders change the version, so holding the lock, it's stable
+        (self.lock.version.load(Ordering::Relaxed) == self.version).then(|| self.lock.write(guard))
+    }
+}
+
+/**
+A guard for writing to an [OptimisticLock].
+*/
+#[must_use]
+pub struct OptimisticWriteGuard<'a, T> {
+    lock: &'a OptimisticLock<T>,
+    //released after the version is bumped, see Drop
+    _guard: Guard<'a, ()>,
+    //the even version before this writer
+    version: usize,
+}
+
+impl<T> Drop for OptimisticWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.version.store(self.version + 2, Ordering::Release);
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<T> Deref for OptimisticGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.snapshot
+    }
+}
+
+impl<T> Deref for OptimisticWriteGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        //we hold the lock
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T> DerefMut for OptimisticWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T: Debug> Debug for OptimisticGuard<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("OptimisticGuard").field("snapshot", &self.snapshot).field("version", &self.version).finish()
+    }
+}
+
+impl<T: Debug> Debug for OptimisticWriteGuard<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("OptimisticWriteGuard").field(&**self).finish()
+    }
+}
+
+impl<T: Copy + Debug> Debug for OptimisticLock<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.optimistic() {
+            Some(snapshot) => f.debug_tuple("OptimisticLock").field(&snapshot.snapshot).finish(),
+            None => f.write_str("OptimisticLock(<locked>)"),
+        }
+    }
+}
+
+impl<T: Default> Default for OptimisticLock<T> {
+    fn default() -> Self {
+        OptimisticLock::new(T::default())
+    }
+}
+
+impl<T> From<T> for OptimisticLock<T> {
+    fn from(data: T) -> Self {
+        OptimisticLock::new(data)
+    }
+}
@@ -0,0 +1,118 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Guards that own a reference to their lock.
+*/
+
+use alloc::sync::Arc;
+use core::fmt::Debug;
+use core::ops::{Deref, DerefMut};
+use crate::Lock;
+
+/**
+A guard that keeps its lock alive via [Arc].
+
+Unlike [Guard](crate::Guard), this has no lifetime, so it can be stored in a struct, returned from the scope
+that locked it, and so on.
+*/
+#[must_use]
+pub struct OwnedGuard<T> {
+    lock: Arc<Lock<T>>,
+}
+
+impl<T> Lock<T> {
+    /**
+    Spins until the lock can be acquired, returning an [OwnedGuard].
+*/
+    pub fn spin_lock_owned(self: &Arc<Self>) -> OwnedGuard<T> {
+        //the guard's unlock is taken over by the OwnedGuard
+        core::mem::forget(self.spin_lock());
+        OwnedGuard { lock: self.clone() }
+    }
+
+    /**
+    No spin; provides an [OwnedGuard] if the lock is available.
+*/
+    pub fn try_lock_owned(self: &Arc<Self>) -> Option<OwnedGuard<T>> {
+        let guard = self.try_lock()?;
+        core::mem::forget(guard);
+        Some(OwnedGuard { lock: self.clone() })
+    }
+
+    /**
+    Returns the data if this is the last reference to the lock, or gives the reference back if it isn't.
+
+    For teardown, once the threads sharing the lock are done with it:
+
+    ```
+    # use atomiclock_spinlock::Lock;
+    # use std::sync::Arc;
+    let results = Arc::new(Lock::new(Vec::new()));
+    let worker = {
+        let results = results.clone();
+        std::thread::spawn(move || results.spin_lock().push(1))
+    };
+    worker.join().unwrap();
+    assert_eq!(results.try_into_inner().unwrap(), [1]);
+    ```
+
+    Outstanding [OwnedGuard]s hold references of their own, so while one exists, this fails.
+*/
+    pub fn try_into_inner(self: Arc<Self>) -> Result<T, Arc<Self>> {
+        Arc::try_unwrap(self).map(Lock::into_inner)
+    }
+}
+
+impl<T> OwnedGuard<T> {
+    /**
+    The lock this guard holds.
+*/
+    pub fn lock(&self) -> &Arc<Lock<T>> {
+        &self.lock
+    }
+}
+
+impl<T> Drop for OwnedGuard<T> {
+    fn drop(&mut self) {
+        self.lock.unlock_raw();
+    }
+}
+
+//Arc<Lock<T>> is Sync whenever T is Send, but sharing the guard shares the data, as with Guard
+unsafe impl<T: Send + Sync> Sync for OwnedGuard<T> {}
+
+//boilerplate, same as Guard
+
+impl<T: Debug> Debug for OwnedGuard<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("OwnedGuard")
+            .field("name", &self.lock.name())
+            .field("data", self.deref())
+            .finish()
+    }
+}
+
+impl<T> Deref for OwnedGuard<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        //we hold the lock
+        unsafe { self.lock.data() }
+    }
+}
+
+impl<T> DerefMut for OwnedGuard<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.lock.data() }
+    }
+}
+
+impl<T> AsRef<T> for OwnedGuard<T> {
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+impl<T> AsMut<T> for OwnedGuard<T> {
+    fn as_mut(&mut self) -> &mut T {
+        self
+    }
+}
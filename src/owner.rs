@@ -0,0 +1,227 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Who holds a lock, with the `owner-tracking` feature, so a timed-out acquisition can say what it waited on.
+
+With `acquisition-history`, the lock also keeps the last few holders, once they release it.
+*/
+
+use core::fmt::{Debug, Display};
+use core::panic::Location;
+use std::thread::Thread;
+use std::time::{Duration, Instant};
+
+/**
+The holder a timed-out acquisition waited on, from [Lock::spin_lock_until_or_holder](crate::Lock::spin_lock_until_or_holder)
+and [Lock::spin_lock_for_or_holder](crate::Lock::spin_lock_for_or_holder), or [Lock::holder](crate::Lock::holder).
+
+It formats as a line for the log:
+
+```
+# use atomiclock_spinlock::Lock;
+# use std::time::Duration;
+let lock = Lock::with_name(0, "cache");
+let _held = lock.spin_lock();
+let info = lock.spin_lock_for_or_holder(Duration::from_millis(1)).unwrap_err();
+assert_eq!(info.thread().unwrap().id(), std::thread::current().id());
+//lock "cache" is held by thread "main" (ThreadId(1)) for 1.2ms, since src/main.rs:2:13
+println!("{info}");
+```
+
+Where the lock was acquired is the caller of the [Lock](crate::Lock) method that acquired it, or, for the
+other ways of acquiring it, such as the futures and the other locks built on [Lock](crate::Lock), a place
+inside this crate.  A lock that's held may have no holder recorded, briefly while another thread takes it,
+or if it was acquired or released directly through [Lock::as_raw](crate::Lock::as_raw), which also leaves the
+last holder recorded after it's released.
+*/
+#[derive(Clone)]
+pub struct ContentionInfo {
+    name: Option<&'static str>,
+    holder: Option<Holder>,
+    //how long the holder had held it, when this was taken
+    held_for: Duration,
+}
+
+#[derive(Clone)]
+struct Holder {
+    thread: Thread,
+    since: Instant,
+    location: &'static Location<'static>,
+}
+
+impl ContentionInfo {
+    /**
+    The name of the lock, if any.
+*/
+    pub fn name(&self) -> Option<&'static str> {
+        self.name
+    }
+
+    /**
+    The thread holding the lock, if it was recorded.
+*/
+    pub fn thread(&self) -> Option<&Thread> {
+        self.holder.as_ref().map(|holder| &holder.thread)
+    }
+
+    /**
+    How long the holder had held the lock when the acquisition gave up.
+*/
+    pub fn held_for(&self) -> Option<Duration> {
+        self.holder.as_ref().map(|_| self.held_for)
+    }
+
+    /**
+    Where the holder acquired the lock.
+*/
+    pub fn location(&self) -> Option<&'static Location<'static>> {
+        self.holder.as_ref().map(|holder| holder.location)
+    }
+}
+
+/**
+The holder of one lock, kept by the lock.
+*/
+pub(crate) struct Owner {
+    holder: atomiclock::AtomicLock<Option<Holder>>,
+    #[cfg(feature = "acquisition-history")]
+    history: atomiclock::AtomicLock<History>,
+}
+
+//acquisitions kept by each lock, once released
+#[cfg(feature = "acquisition-history")]
+const HISTORY: usize = 16;
+
+/**
+The last [HISTORY] holders of a lock, as a ring, oldest at `next` once it's full.
+*/
+#[cfg(feature = "acquisition-history")]
+struct History {
+    past: [Option<(Holder, Instant)>; HISTORY],
+    next: usize,
+}
+
+#[cfg(feature = "acquisition-history")]
+const NO_PAST: Option<(Holder, Instant)> = None;
+
+impl Owner {
+    pub(crate) const fn new() -> Owner {
+        Owner {
+            holder: atomiclock::AtomicLock::new(None),
+            #[cfg(feature = "acquisition-history")]
+            history: atomiclock::AtomicLock::new(History { past: [NO_PAST; HISTORY], next: 0 }),
+        }
+    }
+
+    /**
+    Records that the current thread acquired the lock, at the caller's location.
+    */
+    #[inline]
+    #[track_caller]
+    pub(crate) fn acquired(&self) {
+        let holder = Holder { thread: std::thread::current(), since: Instant::now(), location: Location::caller() };
+        *crate::spin_raw(&self.holder) = Some(holder);
+    }
+
+    /**
+    Records that the holder is done with the lock.
+    */
+    #[inline]
+    pub(crate) fn released(&self) {
+        #[cfg(feature = "acquisition-history")]
+        let released = Instant::now();
+        let mut record = crate::spin_raw(&self.holder);
+        //the handle is dropped once the record is unlocked, so a reader doesn't wait on it
+        let holder = record.take();
+        //the holder moves to the history with the record still locked, so a reader sees it in one or the other
+        #[cfg(feature = "acquisition-history")]
+        let holder = holder.and_then(|holder| {
+            let mut history = crate::spin_raw(&self.history);
+            let next = history.next;
+            history.next = (next + 1) % HISTORY;
+            history.past[next].replace((holder, released)).map(|(evicted, _)| evicted)
+        });
+        drop(record);
+        drop(holder);
+    }
+
+    /**
+    Forgets the holder, for a lock reset after a fork, where the holder didn't survive.
+
+    # Safety
+    Only in the child of a fork, before it starts any threads.
+    */
+    pub(crate) unsafe fn reset(&self) {
+        //a thread that didn't survive may have been halfway through writing the record, so it's overwritten
+        //rather than dropped, leaking at most one thread handle
+        let _ = (0..100).any(|_| self.holder.lock().map(core::mem::forget).is_some());
+        core::ptr::write(self.holder.data(), None);
+        self.holder.unlock();
+        //the history is only a record, so a half-written one is forgotten too
+        #[cfg(feature = "acquisition-history")]
+        {
+            let _ = (0..100).any(|_| self.history.lock().map(core::mem::forget).is_some());
+            core::ptr::write(self.history.data(), History { past: [NO_PAST; HISTORY], next: 0 });
+            self.history.unlock();
+        }
+    }
+
+    pub(crate) fn info(&self, name: Option<&'static str>) -> ContentionInfo {
+        let holder = crate::spin_raw(&self.holder).clone();
+        let held_for = holder.as_ref().map_or(Duration::ZERO, |holder| holder.since.elapsed());
+        ContentionInfo { name, holder, held_for }
+    }
+
+    /**
+    The last few holders, oldest first, and then the current one, if any.
+    */
+    #[cfg(feature = "acquisition-history")]
+    pub(crate) fn history(&self) -> std::vec::Vec<crate::diagnostics::Acquisition> {
+        use crate::diagnostics::Acquisition;
+        let acquisition = |holder: &Holder, released| Acquisition {
+            thread: holder.thread.id(),
+            thread_name: holder.thread.name().map(std::string::String::from),
+            location: holder.location,
+            acquired: holder.since,
+            released,
+        };
+        //in the order released takes them, so a holder is seen exactly once
+        let record = crate::spin_raw(&self.holder);
+        let history = crate::spin_raw(&self.history);
+        let mut acquisitions: std::vec::Vec<Acquisition> = (0..HISTORY)
+            .filter_map(|i| history.past[(history.next + i) % HISTORY].as_ref())
+            .map(|(holder, released)| acquisition(holder, Some(*released)))
+            .collect();
+        acquisitions.extend((*record).as_ref().map(|holder| acquisition(holder, None)));
+        acquisitions
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl Debug for ContentionInfo {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ContentionInfo")
+            .field("name", &self.name)
+            .field("thread", &self.thread())
+            .field("held_for", &self.held_for())
+            .field("location", &self.location())
+            .finish()
+    }
+}
+
+impl Display for ContentionInfo {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.name {
+            Some(name) => write!(f, "lock {name:?} is held by ")?,
+            None => f.write_str("lock is held by ")?,
+        }
+        let Some(holder) = &self.holder else { return f.write_str("an unrecorded holder") };
+        match holder.thread.name() {
+            Some(name) => write!(f, "thread {name:?} ({:?})", holder.thread.id())?,
+            None => write!(f, "thread {:?}", holder.thread.id())?,
+        }
+        write!(f, " for {:?}, since {}", self.held_for, holder.location)
+    }
+}
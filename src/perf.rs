@@ -0,0 +1,130 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Linux perf-event counters for time spent spinning.
+
+With the `perf-counters` feature on Linux, each thread opens hardware counters for CPU cycles and
+cache misses the first time it spins on a lock.  The counters are sampled around the spin phase,
+so you can learn what spinning actually cost, not just how long it took.
+
+If the counters can't be opened (for example, due to `perf_event_paranoid` or a VM without a PMU),
+spinning proceeds as normal and no samples are recorded.
+*/
+
+use std::cell::{Cell, OnceCell};
+
+/**
+Hardware counts accumulated during one spin phase.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct SpinCounters {
+    pub cycles: u64,
+    pub cache_misses: u64,
+}
+
+/**
+The counters for the most recent spin phase on the current thread, if any were recorded.
+*/
+pub fn last_spin() -> Option<SpinCounters> {
+    LAST.with(|l| l.get())
+}
+
+const PERF_TYPE_HARDWARE: u32 = 0;
+const PERF_COUNT_HW_CPU_CYCLES: u64 = 0;
+const PERF_COUNT_HW_CACHE_MISSES: u64 = 3;
+const EXCLUDE_KERNEL: u64 = 1 << 5;
+const EXCLUDE_HV: u64 = 1 << 6;
+
+//PERF_ATTR_SIZE_VER0 layout of `struct perf_event_attr`
+#[repr(C)]
+#[derive(Default)]
+struct PerfEventAttr {
+    type_: u32,
+    size: u32,
+    config: u64,
+    sample_period: u64,
+    sample_type: u64,
+    read_format: u64,
+    flags: u64,
+    wakeup_events: u32,
+    bp_type: u32,
+    config1: u64,
+}
+
+struct Counter(libc::c_int);
+
+impl Counter {
+    fn open(config: u64) -> Option<Counter> {
+        let attr = PerfEventAttr {
+            type_: PERF_TYPE_HARDWARE,
+            size: core::mem::size_of::<PerfEventAttr>() as u32,
+            config,
+            flags: EXCLUDE_KERNEL | EXCLUDE_HV,
+            ..Default::default()
+        };
+        //pid 0, cpu -1: this thread, on any cpu
+        let fd = unsafe { libc::syscall(libc::SYS_perf_event_open, &attr as *const PerfEventAttr, 0, -1, -1, 0) };
+        if fd < 0 {
+            None
+        } else {
+            Some(Counter(fd as libc::c_int))
+        }
+    }
+
+    fn read(&self) -> u64 {
+        let mut value = 0u64;
+        let r = unsafe { libc::read(self.0, &mut value as *mut u64 as *mut libc::c_void, core::mem::size_of::<u64>()) };
+        if r == core::mem::size_of::<u64>() as isize { value } else { 0 }
+    }
+}
+
+impl Drop for Counter {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.0) };
+    }
+}
+
+struct Counters {
+    cycles: Counter,
+    cache_misses: Counter,
+}
+
+std::thread_local! {
+    static COUNTERS: OnceCell<Option<Counters>> = const { OnceCell::new() };
+    static LAST: Cell<Option<SpinCounters>> = const { Cell::new(None) };
+}
+
+fn sample() -> Option<SpinCounters> {
+    COUNTERS.with(|c| {
+        c.get_or_init(|| {
+            Some(Counters {
+                cycles: Counter::open(PERF_COUNT_HW_CPU_CYCLES)?,
+                cache_misses: Counter::open(PERF_COUNT_HW_CACHE_MISSES)?,
+            })
+        }).as_ref().map(|c| SpinCounters {
+            cycles: c.cycles.read(),
+            cache_misses: c.cache_misses.read(),
+        })
+    })
+}
+
+/**
+A spin phase in progress.
+*/
+pub(crate) struct Measurement(Option<SpinCounters>);
+
+impl Measurement {
+    pub(crate) fn begin() -> Self {
+        Measurement(if crate::INSTRUMENTED { sample() } else { None })
+    }
+}
+
+impl Drop for Measurement {
+    fn drop(&mut self) {
+        if let (Some(begin), Some(end)) = (self.0, sample()) {
+            LAST.with(|l| l.set(Some(SpinCounters {
+                cycles: end.cycles.wrapping_sub(begin.cycles),
+                cache_misses: end.cache_misses.wrapping_sub(begin.cache_misses),
+            })));
+        }
+    }
+}
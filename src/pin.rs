@@ -0,0 +1,149 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+A lock for data that mustn't move, such as futures and intrusive list nodes.
+*/
+
+use core::fmt::Debug;
+use core::ops::{Deref, DerefMut};
+use core::pin::Pin;
+use crate::{Guard, Lock};
+
+/**
+A [Lock] whose data is structurally pinned: once the lock is pinned, its data is too.
+
+A plain [Lock] can't offer this, since any `&Lock` (including one from a `Pin<&Lock>`) can take the lock
+and move the data out from under the guard's `&mut`.  A `PinLock` only hands out the data as
+[`Pin<&mut T>`](Pin), unless `T` is [Unpin].
+
+```
+# use atomiclock_spinlock::PinLock;
+# use core::marker::PhantomPinned;
+# use core::pin::{pin, Pin};
+//say, a node that others point into
+struct Node { value: u32, _pinned: PhantomPinned }
+impl Node {
+    fn bump(self: Pin<&mut Self>) {
+        //moving `value` doesn't move the node
+        unsafe { self.get_unchecked_mut().value += 1 }
+    }
+}
+let lock = pin!(PinLock::new(Node { value: 1, _pinned: PhantomPinned }));
+let mut guard = lock.as_ref().spin_lock();
+guard.as_mut().bump();
+assert_eq!(guard.value, 2);
+```
+
+Pin it on the stack with [core::pin::pin], or on the heap with `Box::pin`.
+*/
+pub struct PinLock<T> {
+    lock: Lock<T>,
+}
+
+impl<T> PinLock<T> {
+    const_fn! {
+        /**
+        Creates a new lock.  The data is pinned once the lock is.
+        */
+        pub const fn new(data: T) -> Self {
+            PinLock { lock: Lock::new(data) }
+        }
+    }
+
+    /**
+    Spins until the lock can be acquired.
+*/
+    pub fn spin_lock(self: Pin<&Self>) -> PinGuard<'_, T> {
+        PinGuard { guard: self.get_ref().lock.spin_lock() }
+    }
+
+    /**
+    No spin; provides access to the lock if available.
+*/
+    pub fn try_lock(self: Pin<&Self>) -> Option<PinGuard<'_, T>> {
+        Some(PinGuard { guard: self.get_ref().lock.try_lock()? })
+    }
+
+    /**
+    The pinned data.  No locking is needed, since the borrow is exclusive.
+*/
+    pub fn get_pin_mut(self: Pin<&mut Self>) -> Pin<&mut T> {
+        //pinning is structural, and we have the only reference to the lock, so nobody else holds it
+        unsafe { self.map_unchecked_mut(|this| this.lock.data()) }
+    }
+
+    /**
+    Whether the lock is currently held.
+*/
+    pub fn is_locked(&self) -> bool {
+        self.lock.is_locked()
+    }
+
+    /**
+    Consumes the lock, returning the data.  A lock can only be moved if it isn't pinned, or if `T` is [Unpin].
+*/
+    pub fn into_inner(self) -> T {
+        self.lock.into_inner()
+    }
+}
+
+/**
+A guard for [PinLock], which provides the data as [`Pin<&mut T>`](Pin).
+*/
+#[must_use]
+pub struct PinGuard<'a, T> {
+    guard: Guard<'a, T>,
+}
+
+impl<T> PinGuard<'_, T> {
+    /**
+    The pinned data.
+*/
+    pub fn as_mut(&mut self) -> Pin<&mut T> {
+        //guards only come from a pinned PinLock, whose data never moves
+        unsafe { Pin::new_unchecked(&mut *self.guard) }
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<T> Deref for PinGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T: Unpin> DerefMut for PinGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T: Debug> Debug for PinGuard<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("PinGuard").field(&*self.guard).finish()
+    }
+}
+
+impl<T: Debug> Debug for PinLock<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.lock.try_lock() {
+            Some(guard) => f.debug_tuple("PinLock").field(&*guard).finish(),
+            None => f.write_str("PinLock(<locked>)"),
+        }
+    }
+}
+
+impl<T: Default> Default for PinLock<T> {
+    fn default() -> Self {
+        PinLock::new(T::default())
+    }
+}
+
+impl<T> From<T> for PinLock<T> {
+    fn from(data: T) -> Self {
+        PinLock::new(data)
+    }
+}
@@ -0,0 +1,148 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Optional `std::sync::Mutex`-style poisoning.
+*/
+
+use core::sync::atomic::Ordering;
+use crate::raw::RawLock;
+use crate::{Guard, Lock};
+
+/**
+Returned when a lock is poisoned: a guard for it was dropped while its thread was panicking, so the
+data may be inconsistent.
+
+Like `std::sync::PoisonError`, it holds the guard anyway, so the caller can decide to go ahead.
+
+```
+# use atomiclock_spinlock::Lock;
+# use std::panic::{catch_unwind, AssertUnwindSafe};
+let lock = Lock::new(vec![1, 2]);
+let _ = catch_unwind(AssertUnwindSafe(|| {
+    let _guard = lock.spin_lock();
+    panic!();
+}));
+//the vec is fine, whatever the panic was
+let guard = lock.spin_lock_unpoisoned().unwrap_or_else(|e| e.into_inner());
+assert_eq!(*guard, [1, 2]);
+```
+*/
+pub struct PoisonError<G> {
+    guard: G,
+}
+
+impl<G> PoisonError<G> {
+    /**
+    Creates an error holding `guard`.
+*/
+    pub fn new(guard: G) -> Self {
+        PoisonError { guard }
+    }
+
+    /**
+    Returns the guard, to use the data anyway.
+*/
+    pub fn into_inner(self) -> G {
+        self.guard
+    }
+
+    /**
+    A reference to the guard.
+*/
+    pub fn get_ref(&self) -> &G {
+        &self.guard
+    }
+
+    /**
+    A mutable reference to the guard.
+*/
+    pub fn get_mut(&mut self) -> &mut G {
+        &mut self.guard
+    }
+}
+
+//a guard, or a guard for a lock found poisoned
+type Unpoisoned<'a, T, R> = Result<Guard<'a, T, R>, PoisonError<Guard<'a, T, R>>>;
+
+impl<T, R: RawLock> Lock<T, R> {
+    /**
+    Whether a guard for this lock was dropped during a panic.
+
+    Poisoning is only tracked, never enforced: [Lock::spin_lock] and [Lock::try_lock] ignore it.  Use
+    [Lock::spin_lock_unpoisoned] and [Lock::try_lock_unpoisoned] to check it on each acquisition, and
+    [Lock::clear_poison] to recover.
+*/
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned.load(Ordering::Relaxed)
+    }
+
+    /**
+    Clears the poisoned flag, once the data is known to be consistent again.
+*/
+    pub fn clear_poison(&self) {
+        self.poisoned.store(false, Ordering::Relaxed);
+    }
+
+    /**
+    Like [Lock::spin_lock], but fails if the lock is poisoned.  The error still holds the guard.
+*/
+    pub fn spin_lock_unpoisoned(&self) -> Unpoisoned<'_, T, R> {
+        Lock::unpoisoned(self.spin_lock())
+    }
+
+    /**
+    Like [Lock::try_lock], but fails if the lock is poisoned.  Returns `None` if the lock is held.
+*/
+    pub fn try_lock_unpoisoned(&self) -> Option<Unpoisoned<'_, T, R>> {
+        self.try_lock().map(Lock::unpoisoned)
+    }
+
+    fn unpoisoned(guard: Guard<'_, T, R>) -> Unpoisoned<'_, T, R> {
+        //the flag is set before the lock is released, so holding the lock, we see it
+        if guard.lock.is_poisoned() {
+            Err(PoisonError::new(guard))
+        } else {
+            Ok(guard)
+        }
+    }
+
+    /**
+    Poisons the lock if the current thread is panicking.  Called by guards as they release the lock.
+*/
+    pub(crate) fn poison_if_panicking(&self) {
+        if std::thread::panicking() {
+            self.poisoned.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+impl<T, R: RawLock> Guard<'_, T, R> {
+    /**
+    Releases this guard without poisoning the lock, even if the thread panics while holding it.
+
+    For critical sections that panic on purpose, after leaving the data consistent.
+
+    This is an associated function, since the guard dereferences to the data; call it as
+    `Guard::defuse(&mut guard)`.
+*/
+    pub fn defuse(guard: &mut Self) {
+        guard.defused = true;
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<G> core::fmt::Debug for PoisonError<G> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PoisonError").finish_non_exhaustive()
+    }
+}
+
+impl<G> core::fmt::Display for PoisonError<G> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("lock is poisoned: a thread panicked while holding it")
+    }
+}
+
+impl<G> std::error::Error for PoisonError<G> {}
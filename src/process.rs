@@ -0,0 +1,391 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+A spinlock that processes share, in shared memory.
+*/
+
+use core::cell::UnsafeCell;
+use core::fmt::Debug;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicU32, Ordering};
+use crate::{Clock, SpinWait};
+
+//state layout: bit 31 marks a robust lock, and the rest is the holder, or 0 when unlocked
+const ROBUST: u32 = 1 << 31;
+const HOLDER: u32 = !ROBUST;
+//the holder of a lock that isn't robust
+const LOCKED: u32 = 1;
+//the holder of a robust lock, when the process can't tell its ID, and so is never taken for dead
+const UNKNOWN: u32 = HOLDER;
+
+/**
+A spinlock with a fixed layout, for processes that map the same shared memory, such as with `mmap` or
+`shm_open`, to synchronize on.
+
+A [Lock](crate::Lock) keeps addresses and per-process bookkeeping beside its state, which mean nothing in
+another process, so this is a lock of its own: a `u32` that is 0 when unlocked and 1 when locked, followed by
+the data, as in C's `struct { _Atomic uint32_t state; T data; }`.  A robust lock, made with
+[ProcessLock::new_robust], sets bit 31 of the state and records the holder's process ID in the rest.  One process initializes it in the segment
+with [ProcessLock::init_in_place], and the others attach to it with [ProcessLock::from_raw_parts]:
+
+```
+# use atomiclock_spinlock::ProcessLock;
+# use std::mem::MaybeUninit;
+//stands in for a shared memory segment
+let mut segment = vec![MaybeUninit::<u64>::uninit(); 2];
+let (ptr, len) = (segment.as_mut_ptr().cast::<u8>(), segment.len() * 8);
+//in the process that creates the segment
+let lock = unsafe { ProcessLock::init_in_place(ptr, len, 0u32) };
+*lock.spin_lock() += 1;
+//in a process that maps it
+let attached = unsafe { ProcessLock::<u32>::from_raw_parts(ptr, len) };
+assert_eq!(*attached.try_lock().unwrap(), 1);
+```
+
+The data must mean the same in every process: plain values with a fixed layout, such as `#[repr(C)]`
+structs of integers, and no pointers, references or handles into one process's memory.  The lock waits like
+a [Lock](crate::Lock), with [SpinWait], but has no instrumentation, waiter bookkeeping or named debug output.
+
+If a process dies while holding the lock, it stays held.  A robust lock records which process holds it, so
+the others can detect that it died, and reclaim the lock with [ProcessLock::try_recover]; otherwise, a process
+that knows the holder is gone can release it with [ProcessLock::force_unlock].
+*/
+#[repr(C)]
+pub struct ProcessLock<T> {
+    state: AtomicU32,
+    data: UnsafeCell<T>,
+}
+
+/**
+A guard for a [ProcessLock].  Dereferences to the data, and releases the lock when dropped.
+*/
+#[must_use]
+pub struct ProcessGuard<'a, T> {
+    lock: &'a ProcessLock<T>,
+}
+
+unsafe impl<T: Send> Send for ProcessLock<T> {}
+unsafe impl<T: Send> Sync for ProcessLock<T> {}
+unsafe impl<T: Sync> Sync for ProcessGuard<'_, T> {}
+
+impl<T> ProcessLock<T> {
+    /**
+    Creates a new, unlocked lock, for placing in shared memory by value.
+*/
+    pub const fn new(data: T) -> ProcessLock<T> {
+        ProcessLock { state: AtomicU32::new(0), data: UnsafeCell::new(data) }
+    }
+
+    /**
+    Creates a new, unlocked lock that records the ID of the process that holds it, so [ProcessLock::try_recover]
+    can reclaim it from a process that died.
+
+    Acquiring it asks the OS for the process ID, which on Linux is a syscall, so it's a little slower than a
+    lock made with [ProcessLock::new].  Without `std`, the holder is recorded as unknown, and never recovered
+    from.  Every process must see the same process IDs, so they must share a PID namespace.
+*/
+    pub const fn new_robust(data: T) -> ProcessLock<T> {
+        ProcessLock { state: AtomicU32::new(ROBUST), data: UnsafeCell::new(data) }
+    }
+
+    /**
+    Checks that `len` bytes at `ptr` can hold a lock.
+
+    # Panics
+    Panics if `ptr` isn't aligned for the lock, or `len` is too short.
+*/
+    fn check(ptr: *mut u8, len: usize) {
+        assert!(crate::addr(ptr) % core::mem::align_of::<Self>() == 0, "{ptr:p} isn't aligned for a ProcessLock");
+        assert!(len >= core::mem::size_of::<Self>(),
+            "{len} bytes can't hold a ProcessLock of {} bytes", core::mem::size_of::<Self>());
+    }
+
+    /**
+    Initializes an unlocked lock over `data` in the `len` bytes at `ptr`, such as at the start of a newly
+    created shared memory segment, and returns it.
+
+    # Safety
+    The bytes must be valid for reads and writes for `'a`, and nothing else may use them meanwhile, except
+    through the lock, including other processes, which mustn't attach until this returns.  Whatever was there
+    before is overwritten without being dropped.
+
+    # Panics
+    Panics if `ptr` isn't aligned for the lock, or `len` is too short.
+*/
+    pub unsafe fn init_in_place<'a>(ptr: *mut u8, len: usize, data: T) -> &'a ProcessLock<T> {
+        Self::place(ptr, len, ProcessLock::new(data))
+    }
+
+    /**
+    Like [ProcessLock::init_in_place], for a lock made with [ProcessLock::new_robust].
+
+    # Safety
+    As for [ProcessLock::init_in_place].
+
+    # Panics
+    Panics if `ptr` isn't aligned for the lock, or `len` is too short.
+*/
+    pub unsafe fn init_robust_in_place<'a>(ptr: *mut u8, len: usize, data: T) -> &'a ProcessLock<T> {
+        Self::place(ptr, len, ProcessLock::new_robust(data))
+    }
+
+    unsafe fn place<'a>(ptr: *mut u8, len: usize, lock: ProcessLock<T>) -> &'a ProcessLock<T> {
+        Self::check(ptr, len);
+        let ptr = ptr.cast::<ProcessLock<T>>();
+        ptr.write(lock);
+        &*ptr
+    }
+
+    /**
+    Attaches to a lock in the `len` bytes at `ptr`, initialized by [ProcessLock::init_in_place], typically
+    in another process, or by any code that wrote the same layout.
+
+    # Safety
+    The bytes must hold an initialized `ProcessLock<T>`, and be valid for reads and writes for `'a`, with
+    nothing using them meanwhile except through the lock.
+
+    # Panics
+    Panics if `ptr` isn't aligned for the lock, or `len` is too short.
+*/
+    pub unsafe fn from_raw_parts<'a>(ptr: *mut u8, len: usize) -> &'a ProcessLock<T> {
+        Self::check(ptr, len);
+        &*ptr.cast::<ProcessLock<T>>()
+    }
+
+    /**
+    No spin; provides access to the data if the lock is available.
+*/
+    pub fn try_lock(&self) -> Option<ProcessGuard<'_, T>> {
+        let state = self.state.load(Ordering::Relaxed);
+        if state & HOLDER != 0 {
+            return None;
+        }
+        let holder = if state & ROBUST != 0 { ROBUST | current_process() } else { LOCKED };
+        self.take(state, holder)
+    }
+
+    fn take(&self, state: u32, holder: u32) -> Option<ProcessGuard<'_, T>> {
+        let acquired = self.state.compare_exchange(state, holder, Ordering::Acquire, Ordering::Relaxed).is_ok();
+        acquired.then(|| {
+            crate::tsan::acquire(self);
+            ProcessGuard { lock: self }
+        })
+    }
+
+    /**
+    Spins until the lock can be acquired, by this or any other process.
+*/
+    pub fn spin_lock(&self) -> ProcessGuard<'_, T> {
+        let mut wait = SpinWait::new();
+        loop {
+            if let Some(guard) = self.try_lock() {
+                return guard;
+            }
+            wait.spin();
+        }
+    }
+
+    /**
+    Spins until the lock is available, or the clock passes the deadline.
+
+    As with [Lock::spin_lock_until_with](crate::Lock::spin_lock_until_with), the clock is only read every so
+    often, so this may give up a little after the deadline.
+*/
+    pub fn spin_lock_until_with<C: Clock>(&self, clock: &C, deadline: C::Instant) -> Option<ProcessGuard<'_, T>> {
+        let settings = crate::config::defaults();
+        let mut reads = crate::wait::ClockReads::new();
+        let mut wait = SpinWait::new();
+        loop {
+            if let Some(guard) = self.try_lock() {
+                return Some(guard);
+            }
+            if reads.due(&settings) && clock.now() > deadline {
+                return None;
+            }
+            wait.spin();
+        }
+    }
+
+    /**
+    Spins until the lock is available, or the duration elapses on the clock.
+
+    # Panics
+    Panics if the clock's addition does, e.g. on overflow.
+*/
+    pub fn spin_lock_for_with<C: Clock>(&self, clock: &C, duration: C::Duration) -> Option<ProcessGuard<'_, T>> {
+        self.spin_lock_until_with(clock, clock.now() + duration)
+    }
+
+    /**
+    Spins until the lock is available, or times out.
+*/
+    #[cfg(feature = "std")]
+    pub fn spin_lock_until(&self, deadline: std::time::Instant) -> Option<ProcessGuard<'_, T>> {
+        self.spin_lock_until_with(&crate::clock::StdClock, deadline)
+    }
+
+    /**
+    Spins until the lock is available, or the duration elapses.
+
+    # Panics
+    Panics if the deadline overflows [std::time::Instant].  Use [ProcessLock::spin_lock_until] to avoid this.
+*/
+    #[cfg(feature = "std")]
+    pub fn spin_lock_for(&self, duration: std::time::Duration) -> Option<ProcessGuard<'_, T>> {
+        self.spin_lock_for_with(&crate::clock::StdClock, duration)
+    }
+
+    /**
+    Whether the lock is held, by any process.  This is a snapshot; it may be out of date by the time you read it.
+*/
+    pub fn is_locked(&self) -> bool {
+        self.state.load(Ordering::Relaxed) & HOLDER != 0
+    }
+
+    /**
+    The ID of the process that holds a robust lock, if it's held by one that recorded it.  This is a snapshot;
+    it may be out of date by the time you read it.
+*/
+    pub fn holder(&self) -> Option<u32> {
+        let state = self.state.load(Ordering::Relaxed);
+        let holder = state & HOLDER;
+        (state & ROBUST != 0 && holder != 0 && holder != UNKNOWN).then_some(holder)
+    }
+
+    /**
+    Reclaims a robust lock whose holder died, and returns a guard for it, or `None` if the lock isn't held by
+    a process that's known to be dead.
+
+    For a lock that times out, with [ProcessLock::spin_lock_for] say, this tells a stuck lock from a busy one:
+
+    ```
+    # use atomiclock_spinlock::ProcessLock;
+    # use std::time::Duration;
+    # let lock = ProcessLock::new_robust(0);
+    let guard = match lock.spin_lock_for(Duration::from_millis(100)) {
+        Some(guard) => guard,
+        //the holder may have died; otherwise keep waiting
+        None => lock.try_recover().unwrap_or_else(|| lock.spin_lock()),
+    };
+    ```
+
+    The holder may have died halfway through changing the data, so check it before trusting it.  A process
+    counts as dead once it has exited, even if its parent hasn't reaped it yet.  If its ID has been reused
+    by a new process since, it looks alive, and isn't recovered from.  Only available on Linux, with `std`,
+    where this reads `/proc`.
+*/
+    #[cfg(all(feature = "std", target_os = "linux"))]
+    pub fn try_recover(&self) -> Option<ProcessGuard<'_, T>> {
+        let state = self.state.load(Ordering::Relaxed);
+        let holder = self.holder()?;
+        if is_alive(holder) {
+            return None;
+        }
+        self.take(state, ROBUST | current_process())
+    }
+
+    /**
+    Releases the lock, whoever holds it, such as after its holder died.
+
+    The holder may have died halfway through changing the data, so check it before trusting it.
+
+    # Safety
+    Nothing may hold the lock, in any process; a holder that's still alive would go on using the data
+    alongside the next one.
+*/
+    pub unsafe fn force_unlock(&self) {
+        crate::tsan::release(self);
+        self.state.fetch_and(ROBUST, Ordering::Release);
+    }
+
+    /**
+    The data, without locking, since the borrow is exclusive.
+*/
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    /**
+    Consumes the lock and returns the inner data.
+*/
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+/**
+The current process's ID, as recorded in a robust lock.
+*/
+fn current_process() -> u32 {
+    #[cfg(feature = "std")]
+    match std::process::id() & HOLDER {
+        0 => UNKNOWN,
+        id => id,
+    }
+    #[cfg(not(feature = "std"))]
+    UNKNOWN
+}
+
+/**
+Whether the process is still running: it exists, and hasn't exited.
+*/
+#[cfg(all(feature = "std", target_os = "linux"))]
+fn is_alive(id: u32) -> bool {
+    match std::fs::read_to_string(std::format!("/proc/{id}/stat")) {
+        //the state follows the name, which is in parentheses and may contain anything
+        Ok(stat) => !matches!(stat.rsplit_once(')').and_then(|(_, rest)| rest.trim_start().chars().next()), Some('Z' | 'X')),
+        Err(error) => error.kind() != std::io::ErrorKind::NotFound,
+    }
+}
+
+impl<T> Deref for ProcessGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T> DerefMut for ProcessGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T> Drop for ProcessGuard<'_, T> {
+    fn drop(&mut self) {
+        crate::tsan::release(self.lock);
+        //unlocked, and still robust if it was
+        self.lock.state.fetch_and(ROBUST, Ordering::Release);
+        crate::arch::released();
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<T: Debug> Debug for ProcessLock<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.try_lock() {
+            Some(guard) => f.debug_tuple("ProcessLock").field(&&*guard).finish(),
+            None => f.write_str("ProcessLock(<locked>)"),
+        }
+    }
+}
+
+impl<T: Default> Default for ProcessLock<T> {
+    fn default() -> Self {
+        ProcessLock::new(T::default())
+    }
+}
+
+impl<T> From<T> for ProcessLock<T> {
+    fn from(data: T) -> Self {
+        ProcessLock::new(data)
+    }
+}
+
+impl<T: Debug> Debug for ProcessGuard<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("ProcessGuard").field(&**self).finish()
+    }
+}
@@ -0,0 +1,40 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Borrowing several fields of a guard's data at once.
+*/
+
+/**
+Borrows several fields of a guard's data mutably at once, as a tuple.
+
+```text
+let (a, b) = guard_project!(guard => a, b);
+```
+
+Writing `(&mut guard.a, &mut guard.b)` doesn't compile, since each field access goes through the guard's
+`DerefMut`, which borrows the whole guard.  This dereferences the guard once, then borrows the fields
+from that, so they can be used together:
+
+```
+# use atomiclock_spinlock::{guard_project, Lock};
+struct Account { balance: u64, history: Vec<u64>, limit: u64 }
+let lock = Lock::new(Account { balance: 10, history: Vec::new(), limit: 100 });
+let mut guard = lock.spin_lock();
+let (balance, history, limit) = guard_project!(guard => balance, history, limit);
+if *balance + 5 <= *limit {
+    history.push(*balance);
+    *balance += 5;
+}
+drop(guard);
+assert_eq!(lock.spin_lock().history, [10]);
+```
+
+Fields can be named, or numbered for tuple structs.  The borrows last as long as the guard is borrowed,
+so the lock stays held while they're in use.  This works with any guard that dereferences mutably.
+*/
+#[macro_export]
+macro_rules! guard_project {
+    ($guard:expr => $($field:tt),+ $(,)?) => {{
+        let data = &mut *$guard;
+        ($(&mut data.$field,)+)
+    }};
+}
@@ -0,0 +1,104 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Prometheus text-format exposition of the [diagnostics](crate::diagnostics) registry.
+
+Serve the output of [render] from your metrics endpoint with content type
+`text/plain; version=0.0.4`.
+
+Each registered lock becomes one series per metric, labeled with the lock's `name` (empty for
+unnamed locks) and `id`.  Instrumented call sites are exposed as `spinlock_call_site_*` series,
+labeled with the `site` (`file:line:column`) and the `id` of the lock they acquire.  Per-thread
+spin time is exposed as `spinlock_thread_spin_seconds_total`, labeled with the lock `id`, the
+`thread` id, and the `thread_name` (empty for unnamed threads).
+*/
+
+use crate::diagnostics::{call_sites, report, CallSiteReport, LockReport};
+use core::fmt::Write;
+use alloc::string::String;
+
+/**
+Renders all registered locks in the Prometheus text exposition format.
+*/
+pub fn render() -> String {
+    let reports = report();
+    let mut out = String::new();
+    metric(&mut out, &reports, "spinlock_acquisitions_total", "counter",
+           "Number of times the lock was acquired.",
+           |r| r.stats.acquisitions as f64);
+    metric(&mut out, &reports, "spinlock_contended_total", "counter",
+           "Number of acquisitions that found the lock held and had to spin.",
+           |r| r.stats.contended as f64);
+    metric(&mut out, &reports, "spinlock_spin_seconds_total", "counter",
+           "Total time spent spinning on the lock.",
+           |r| r.stats.spin_time.as_secs_f64());
+    metric(&mut out, &reports, "spinlock_spin_energy_joules_total", "counter",
+           "Estimated energy burned spinning on the lock, from the energy meter.",
+           |r| r.stats.spin_energy_nanojoules as f64 / 1e9);
+    metric(&mut out, &reports, "spinlock_timeouts_total", "counter",
+           "Number of deadline-based acquisitions that gave up.",
+           |r| r.stats.timeouts as f64);
+    metric(&mut out, &reports, "spinlock_locked", "gauge",
+           "Whether the lock is currently held.",
+           |r| if r.locked { 1.0 } else { 0.0 });
+    metric(&mut out, &reports, "spinlock_waiters", "gauge",
+           "Number of threads currently spinning on the lock.",
+           |r| r.waiters as f64);
+    metric(&mut out, &reports, "spinlock_low_latency", "gauge",
+           "Whether the lock waits in low-latency mode, only ever busy-spinning.",
+           |r| if r.low_latency { 1.0 } else { 0.0 });
+    let sites = call_sites();
+    site_metric(&mut out, &sites, "spinlock_call_site_acquisitions_total",
+                "Number of times the lock was acquired at the call site.",
+                |s| s.stats.acquisitions as f64);
+    site_metric(&mut out, &sites, "spinlock_call_site_contended_total",
+                "Number of acquisitions at the call site that had to spin.",
+                |s| s.stats.contended as f64);
+    site_metric(&mut out, &sites, "spinlock_call_site_spin_seconds_total",
+                "Total time spent spinning at the call site.",
+                |s| s.stats.spin_time.as_secs_f64());
+    #[cfg(feature = "std")]
+    thread_metric(&mut out);
+    out
+}
+
+#[cfg(feature = "std")]
+fn thread_metric(out: &mut String) {
+    writeln!(out, "# HELP spinlock_thread_spin_seconds_total Total time the thread spent spinning on the lock.").unwrap();
+    writeln!(out, "# TYPE spinlock_thread_spin_seconds_total counter").unwrap();
+    for thread in crate::diagnostics::threads() {
+        write!(out, "spinlock_thread_spin_seconds_total{{id=\"{}\",thread=\"{:?}\",thread_name=\"", thread.lock, thread.thread).unwrap();
+        push_label_value(out, thread.thread_name.as_deref().unwrap_or(""));
+        writeln!(out, "\"}} {}", thread.spin_time.as_secs_f64()).unwrap();
+    }
+}
+
+fn site_metric(out: &mut String, sites: &[CallSiteReport], name: &str, help: &str, value: impl Fn(&CallSiteReport) -> f64) {
+    writeln!(out, "# HELP {name} {help}").unwrap();
+    writeln!(out, "# TYPE {name} counter").unwrap();
+    for site in sites {
+        write!(out, "{name}{{site=\"").unwrap();
+        push_label_value(out, site.file);
+        writeln!(out, ":{}:{}\",id=\"{}\"}} {}", site.line, site.column, site.lock, value(site)).unwrap();
+    }
+}
+
+fn metric(out: &mut String, reports: &[LockReport], name: &str, kind: &str, help: &str, value: impl Fn(&LockReport) -> f64) {
+    writeln!(out, "# HELP {name} {help}").unwrap();
+    writeln!(out, "# TYPE {name} {kind}").unwrap();
+    for report in reports {
+        write!(out, "{name}{{name=\"").unwrap();
+        push_label_value(out, report.name.unwrap_or(""));
+        writeln!(out, "\",id=\"{}\"}} {}", report.id, value(report)).unwrap();
+    }
+}
+
+fn push_label_value(out: &mut String, s: &str) {
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
+        }
+    }
+}
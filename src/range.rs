@@ -0,0 +1,400 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Locking disjoint ranges of one buffer, either a [RangeLock] or a `Lock<Vec<T>>`, with [Lock::lock_range].
+*/
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::cell::UnsafeCell;
+use core::fmt::Debug;
+use core::marker::PhantomData;
+use core::ops::{Bound, Deref, DerefMut, Range, RangeBounds};
+use crate::{Lock, SpinWait};
+
+/**
+The range of a buffer of `len` elements that `range` stands for.
+
+# Panics
+Panics if it starts after it ends, or ends past the end of the buffer, as slicing does.
+*/
+fn resolve(range: impl RangeBounds<usize>, len: usize) -> Range<usize> {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start.checked_add(1).expect("range start overflows"),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end.checked_add(1).expect("range end overflows"),
+        Bound::Excluded(&end) => end,
+        Bound::Unbounded => len,
+    };
+    assert!(start <= end, "range starts at {start} but ends at {end}");
+    assert!(end <= len, "range end {end} is out of range for a buffer of length {len}");
+    start..end
+}
+
+fn overlaps(held: &[Range<usize>], range: &Range<usize>) -> bool {
+    held.iter().any(|h| h.start < range.end && range.start < h.end)
+}
+
+/**
+A buffer whose ranges are locked separately, so threads can work on disjoint parts of it at once, such
+as chunks of an image or a sample buffer.
+
+```
+# use atomiclock_spinlock::RangeLock;
+let samples = RangeLock::new(vec![1; 1000]);
+std::thread::scope(|s| {
+    for chunk in 0..4 {
+        let samples = &samples;
+        s.spawn(move || {
+            for sample in samples.lock_range(chunk * 250..(chunk + 1) * 250).iter_mut() {
+                *sample *= 2;
+            }
+        });
+    }
+});
+assert_eq!(samples.into_inner(), vec![2; 1000]);
+```
+
+Ranges that overlap wait for each other, and `lock_range(..)` takes the whole buffer.  The ranges held are
+kept in a list under a [Lock], which is only held while a range is taken or given back, so this suits a
+handful of ranges held for a while, not many brief ones.
+
+The buffer can't grow or shrink while shared; use [RangeLock::get_mut] or [RangeLock::into_inner] for that.
+Requires the `alloc` feature.
+*/
+pub struct RangeLock<T> {
+    //the ranges held
+    held: Lock<Vec<Range<usize>>>,
+    data: Box<[UnsafeCell<T>]>,
+}
+
+/**
+A guard for one range of a [RangeLock].  Dereferences to the range's elements.
+*/
+#[must_use]
+pub struct RangeGuard<'a, T> {
+    lock: &'a RangeLock<T>,
+    range: Range<usize>,
+    //for auto traits: the guard hands out its elements as a `&mut [T]` would
+    _data: PhantomData<&'a mut [T]>,
+}
+
+//the ranges handed out are disjoint, so this is like a Lock for each range
+unsafe impl<T: Send> Send for RangeLock<T> {}
+unsafe impl<T: Send> Sync for RangeLock<T> {}
+
+impl<T> RangeLock<T> {
+    /**
+    Creates a lock over the buffer, with no range held.
+*/
+    pub fn new(data: Vec<T>) -> Self {
+        let data = Box::into_raw(data.into_boxed_slice()) as *mut [UnsafeCell<T>];
+        //UnsafeCell<T> has the same layout as T
+        RangeLock { held: Lock::new(Vec::new()), data: unsafe { Box::from_raw(data) } }
+    }
+
+    /**
+    The number of elements in the buffer.
+*/
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /**
+    Whether the buffer is empty.
+*/
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /**
+    Takes the range, if it doesn't overlap one that's held.
+*/
+    fn take(&self, range: Range<usize>) -> Option<RangeGuard<'_, T>> {
+        if !range.is_empty() {
+            let mut held = self.held.spin_lock();
+            if overlaps(&held, &range) {
+                return None;
+            }
+            held.push(range.clone());
+        }
+        Some(RangeGuard { lock: self, range, _data: PhantomData })
+    }
+
+    /**
+    Spins until the range can be locked, that is, until no range that overlaps it is held.
+
+    # Panics
+    Panics if the range is out of bounds, as slicing does.
+*/
+    pub fn lock_range(&self, range: impl RangeBounds<usize>) -> RangeGuard<'_, T> {
+        let range = resolve(range, self.len());
+        let mut wait = SpinWait::new();
+        loop {
+            if let Some(guard) = self.take(range.clone()) {
+                return guard;
+            }
+            wait.spin();
+        }
+    }
+
+    /**
+    No spin; locks the range if no range that overlaps it is held.
+
+    # Panics
+    Panics if the range is out of bounds, as slicing does.
+*/
+    pub fn try_lock_range(&self, range: impl RangeBounds<usize>) -> Option<RangeGuard<'_, T>> {
+        self.take(resolve(range, self.len()))
+    }
+
+    /**
+    Whether any element of the range is locked.  This is a snapshot, which may be out of date.
+
+    # Panics
+    Panics if the range is out of bounds, as slicing does.
+*/
+    pub fn is_locked(&self, range: impl RangeBounds<usize>) -> bool {
+        let range = resolve(range, self.len());
+        overlaps(&self.held.spin_lock(), &range)
+    }
+
+    /**
+    The buffer, without locking, since the borrow is exclusive.
+*/
+    pub fn get_mut(&mut self) -> &mut [T] {
+        //UnsafeCell<T> has the same layout as T, and the borrow is exclusive
+        unsafe { &mut *(&mut *self.data as *mut [UnsafeCell<T>] as *mut [T]) }
+    }
+
+    /**
+    Consumes the lock and returns the buffer.
+*/
+    pub fn into_inner(self) -> Vec<T> {
+        let data = Box::into_raw(self.data) as *mut [T];
+        //UnsafeCell<T> has the same layout as T
+        unsafe { Box::from_raw(data) }.into_vec()
+    }
+}
+
+impl<T> RangeGuard<'_, T> {
+    /**
+    The range of the buffer this guard holds.
+*/
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    fn as_ptr(&self) -> *mut [T] {
+        //no other guard overlaps our range, so the elements are ours
+        &self.lock.data[self.range.clone()] as *const [UnsafeCell<T>] as *mut [T]
+    }
+}
+
+impl<T> Deref for RangeGuard<'_, T> {
+    type Target = [T];
+    fn deref(&self) -> &[T] {
+        unsafe { &*self.as_ptr() }
+    }
+}
+
+impl<T> DerefMut for RangeGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { &mut *self.as_ptr() }
+    }
+}
+
+impl<T> Drop for RangeGuard<'_, T> {
+    fn drop(&mut self) {
+        if self.range.is_empty() {
+            return;
+        }
+        let mut held = self.lock.held.spin_lock();
+        if let Some(index) = held.iter().position(|h| *h == self.range) {
+            held.swap_remove(index);
+        }
+    }
+}
+
+/**
+The ranges handed out of one `Lock<Vec<_>>`, which stays held for as long as any are.
+*/
+struct Ranges {
+    lock: usize,
+    //the vector's elements and length, which can't change while the lock is held
+    base: *mut u8,
+    len: usize,
+    held: Vec<Range<usize>>,
+}
+
+//the elements are only reached through the guards for the ranges, which are disjoint
+unsafe impl Send for Ranges {}
+
+/**
+The ranges held, for every `Lock<Vec<_>>` with any.
+*/
+static RANGES: atomiclock::AtomicLock<Vec<Ranges>> = atomiclock::AtomicLock::new(Vec::new());
+
+/**
+A guard for one range of a `Lock<Vec<T>>`, from [Lock::lock_range].  Dereferences to the range's elements.
+*/
+#[must_use]
+pub struct VecRangeGuard<'a, T> {
+    lock: &'a Lock<Vec<T>>,
+    range: Range<usize>,
+    base: *mut T,
+    //for auto traits: the guard hands out its elements as a `&mut [T]` would
+    _data: PhantomData<&'a mut [T]>,
+}
+
+//the guard hands out its elements as a `&mut [T]` would, and releases the lock, which is Sync, from any thread
+unsafe impl<T: Send> Send for VecRangeGuard<'_, T> {}
+unsafe impl<T: Sync> Sync for VecRangeGuard<'_, T> {}
+
+impl<T> Lock<Vec<T>> {
+    /**
+    Spins until the range of the vector can be locked, that is, until no range that overlaps it is held,
+    and the lock isn't otherwise held.
+
+    Guards for ranges that don't overlap can be held at once, so threads can work on disjoint parts of the
+    vector at once:
+
+    ```
+    # use atomiclock_spinlock::Lock;
+    let frame = Lock::new(vec![0u8; 64]);
+    std::thread::scope(|s| {
+        for (rows, shade) in [(0..32, 1), (32..64, 2)] {
+            let frame = &frame;
+            s.spawn(move || frame.lock_range(rows).fill(shade));
+        }
+    });
+    assert_eq!(frame.spin_lock().iter().map(|&pixel| pixel as u32).sum::<u32>(), 96);
+    ```
+
+    While any range is held, so is the lock, so the vector can't grow or shrink.  The ranges held are kept
+    in a list shared by every `Lock<Vec<_>>`, under a lock of its own, so this suits a handful of ranges
+    held for a while, not many brief ones.  A [RangeLock] keeps its list to itself.
+
+    # Panics
+    Panics if the range is out of bounds, as slicing does.
+*/
+    pub fn lock_range(&self, range: impl RangeBounds<usize>) -> VecRangeGuard<'_, T> {
+        let range = (range.start_bound().cloned(), range.end_bound().cloned());
+        let mut wait = SpinWait::new();
+        loop {
+            if let Some(guard) = self.take_range(range) {
+                return guard;
+            }
+            wait.spin();
+        }
+    }
+
+    /**
+    No spin; locks the range of the vector if no range that overlaps it is held, and the lock isn't
+    otherwise held.
+
+    # Panics
+    Panics if the range is out of bounds, as slicing does.
+*/
+    pub fn try_lock_range(&self, range: impl RangeBounds<usize>) -> Option<VecRangeGuard<'_, T>> {
+        self.take_range((range.start_bound().cloned(), range.end_bound().cloned()))
+    }
+
+    /**
+    Takes the range, adding it to the ranges held, or acquiring the lock for it if there are none.
+*/
+    fn take_range(&self, range: (Bound<usize>, Bound<usize>)) -> Option<VecRangeGuard<'_, T>> {
+        let address = crate::addr(self);
+        let mut all = crate::spin_raw(&RANGES);
+        if let Some(ranges) = all.iter_mut().find(|ranges| ranges.lock == address) {
+            let range = resolve(range, ranges.len);
+            if overlaps(&ranges.held, &range) {
+                return None;
+            }
+            ranges.held.push(range.clone());
+            return Some(VecRangeGuard { lock: self, range, base: ranges.base.cast(), _data: PhantomData });
+        }
+        let mut guard = self.try_lock()?;
+        let range = resolve(range, guard.len());
+        let (base, len) = (guard.as_mut_ptr(), guard.len());
+        //released by the last range's guard
+        core::mem::forget(guard);
+        all.push(Ranges { lock: address, base: base.cast(), len, held: alloc::vec![range.clone()] });
+        Some(VecRangeGuard { lock: self, range, base, _data: PhantomData })
+    }
+}
+
+impl<T> VecRangeGuard<'_, T> {
+    /**
+    The range of the vector this guard holds.
+*/
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+}
+
+impl<T> Deref for VecRangeGuard<'_, T> {
+    type Target = [T];
+    fn deref(&self) -> &[T] {
+        //no other guard overlaps our range, and the vector can't change while it's held
+        unsafe { core::slice::from_raw_parts(self.base.add(self.range.start), self.range.len()) }
+    }
+}
+
+impl<T> DerefMut for VecRangeGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { core::slice::from_raw_parts_mut(self.base.add(self.range.start), self.range.len()) }
+    }
+}
+
+impl<T> Drop for VecRangeGuard<'_, T> {
+    fn drop(&mut self) {
+        let address = crate::addr(self.lock);
+        let mut all = crate::spin_raw(&RANGES);
+        let Some(index) = all.iter().position(|ranges| ranges.lock == address) else {
+            return;
+        };
+        let held = &mut all[index].held;
+        if let Some(range) = held.iter().position(|h| *h == self.range) {
+            held.swap_remove(range);
+        }
+        if held.is_empty() {
+            all.swap_remove(index);
+            //the lock was held for the ranges, since the first was taken
+            self.lock.unlock_raw();
+        }
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<T> Debug for RangeLock<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.held.try_lock() {
+            Some(held) => f.debug_struct("RangeLock").field("len", &self.len()).field("held", &*held).finish(),
+            None => f.write_str("RangeLock(<locked>)"),
+        }
+    }
+}
+
+impl<T> From<Vec<T>> for RangeLock<T> {
+    fn from(data: Vec<T>) -> Self {
+        RangeLock::new(data)
+    }
+}
+
+impl<T: Debug> Debug for RangeGuard<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("RangeGuard").field("range", &self.range).field("data", &&**self).finish()
+    }
+}
+
+impl<T: Debug> Debug for VecRangeGuard<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("VecRangeGuard").field("range", &self.range).field("data", &&**self).finish()
+    }
+}
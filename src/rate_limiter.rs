@@ -0,0 +1,135 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+A token-bucket rate limiter.
+*/
+
+use core::fmt::Debug;
+use std::time::{Duration, Instant};
+use crate::clock::StdClock;
+use crate::{Clock, Lock};
+
+struct Bucket {
+    tokens: u64,
+    //when the bucket last gained a token, or was full
+    refilled: Instant,
+}
+
+/**
+Limits how often something happens, with a bucket of tokens that refills over time.
+
+The bucket holds up to `capacity` tokens, and gains one every `refill_every`.  Each use takes tokens
+with [RateLimiter::try_acquire], which fails when there aren't enough, so bursts of up to `capacity` are
+allowed, and the rate over time is one per `refill_every`.
+
+```
+# use atomiclock_spinlock::RateLimiter;
+# use std::time::Duration;
+let limiter = RateLimiter::new(3, Duration::from_secs(60));
+assert!(limiter.try_acquire(2));
+assert!(limiter.try_acquire(1));
+//empty until the next minute
+assert!(!limiter.try_acquire(1));
+```
+
+The bucket is behind a [Lock].  Give it a name with [RateLimiter::with_name] so it shows up by name in
+the lock's debug output and diagnostics, to spot limiters that contend.
+
+Time is read from the standard library's clock, so with the `test-clock` feature, tests can refill the
+bucket with `clock::manual::advance`.  Requires the `std` feature.
+*/
+pub struct RateLimiter {
+    bucket: Lock<Bucket>,
+    capacity: u64,
+    refill_every: Duration,
+}
+
+impl RateLimiter {
+    /**
+    Creates a full bucket of `capacity` tokens, which gains one every `refill_every`.
+
+    # Panics
+    If `refill_every` is zero.
+*/
+    pub fn new(capacity: u64, refill_every: Duration) -> Self {
+        RateLimiter::build(Lock::new(RateLimiter::full(capacity)), capacity, refill_every)
+    }
+
+    /**
+    Like [RateLimiter::new], with a name for the lock.
+*/
+    pub fn with_name(name: &'static str, capacity: u64, refill_every: Duration) -> Self {
+        RateLimiter::build(Lock::with_name(RateLimiter::full(capacity), name), capacity, refill_every)
+    }
+
+    fn full(capacity: u64) -> Bucket {
+        Bucket { tokens: capacity, refilled: StdClock.now() }
+    }
+
+    fn build(bucket: Lock<Bucket>, capacity: u64, refill_every: Duration) -> Self {
+        assert!(!refill_every.is_zero(), "RateLimiter must refill at some rate");
+        RateLimiter { bucket, capacity, refill_every }
+    }
+
+    /**
+    Takes `n` tokens if the bucket has them, returning whether it did.  If it doesn't, none are taken.
+*/
+    pub fn try_acquire(&self, n: u64) -> bool {
+        let mut bucket = self.bucket.spin_lock();
+        self.refill(&mut bucket);
+        match bucket.tokens.checked_sub(n) {
+            Some(left) => {
+                bucket.tokens = left;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /**
+    The number of tokens in the bucket now.  Another thread may take some right after this returns.
+*/
+    pub fn available(&self) -> u64 {
+        let mut bucket = self.bucket.spin_lock();
+        self.refill(&mut bucket);
+        bucket.tokens
+    }
+
+    /**
+    The most tokens the bucket holds.
+*/
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    fn refill(&self, bucket: &mut Bucket) {
+        let now = StdClock.now();
+        let gained = now.saturating_duration_since(bucket.refilled).as_nanos() / self.refill_every.as_nanos();
+        let tokens = bucket.tokens.saturating_add(u64::try_from(gained).unwrap_or(u64::MAX));
+        if tokens >= self.capacity {
+            bucket.tokens = self.capacity;
+            bucket.refilled = now;
+        } else {
+            bucket.tokens = tokens;
+            //keep the time toward the next token
+            let nanos = self.refill_every.as_nanos() * gained;
+            bucket.refilled += Duration::new((nanos / 1_000_000_000) as u64, (nanos % 1_000_000_000) as u32);
+        }
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl Debug for RateLimiter {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut s = f.debug_struct("RateLimiter");
+        s.field("capacity", &self.capacity);
+        s.field("refill_every", &self.refill_every);
+        match self.bucket.try_lock() {
+            Some(bucket) => s.field("tokens", &bucket.tokens),
+            None => s.field("tokens", &format_args!("<locked>")),
+        };
+        s.finish()
+    }
+}
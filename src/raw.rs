@@ -0,0 +1,176 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+The raw locks that a [Lock](crate::Lock) is built on.
+
+A [Lock](crate::Lock) is the spinning, deadlines, instrumentation and the rest, around a [RawLock] that only
+knows how to try to acquire itself and how to release.  The default is [DefaultRawLock], on
+[atomiclock](https://crates.io/crates/atomiclock), but anything that provides mutual exclusion will do: a test
+double, a hardware spinlock register (see [HardwareLock]), or a lock word in memory shared with another process.
+
+```
+use atomiclock_spinlock::{raw::RawLock, Lock};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+//a lock word, such as one in shared memory
+struct Word(AtomicBool);
+
+unsafe impl RawLock for Word {
+    fn try_lock(&self) -> bool {
+        self.0.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed).is_ok()
+    }
+    unsafe fn unlock(&self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+static COUNTER: Lock<u32, Word> = Lock::with_raw(0, Word(AtomicBool::new(false)));
+*COUNTER.spin_lock() += 1;
+assert_eq!(*COUNTER.spin_lock(), 1);
+```
+
+The other lock types in this crate, such as [RwLock](crate::RwLock), and the types built on [Lock](crate::Lock),
+use the default.
+*/
+
+mod hardware;
+pub use hardware::HardwareLock;
+
+use crate::sync;
+
+/**
+A lock without data, for a [Lock](crate::Lock) to spin on.
+
+# Safety
+Between a call to [RawLock::try_lock] that returns `true` and the next call to [RawLock::unlock], no other call
+to [RawLock::try_lock] may return `true`.  Acquiring must synchronize with the previous release, as `Acquire`
+and `Release` orderings do, so that the data the lock protects is visible to its next holder.
+
+A [Guard](crate::Guard) is [Send], so the lock must allow releasing it from a different thread than the one
+that acquired it.  A lock that must be released by its owner, such as a pthread mutex, can't be a [RawLock].
+*/
+pub unsafe trait RawLock {
+    /**
+    Tries to acquire the lock, without waiting.
+
+    This may fail spuriously, since a [Lock](crate::Lock) tries again.
+    */
+    fn try_lock(&self) -> bool;
+
+    /**
+    Releases the lock.
+
+    # Safety
+    The lock must be held, and the caller must be releasing it on behalf of its holder, such as by dropping
+    its guard.  That may be on a different thread than the one that acquired it.
+    */
+    unsafe fn unlock(&self);
+
+    /**
+    Whether the lock is currently held.
+
+    This is a snapshot, which may be out of date, and may spuriously report `true`.  The default tries to
+    acquire the lock, and releases it again if that works.
+    */
+    #[inline]
+    fn is_locked(&self) -> bool {
+        if self.try_lock() {
+            //we just acquired it
+            unsafe { self.unlock() };
+            false
+        } else {
+            true
+        }
+    }
+
+    /**
+    Acquires the lock, blocking the thread until it's available, for backends that can wait better than
+    by spinning.
+
+    Returns `false`, without acquiring the lock, to have the [Lock](crate::Lock) spin instead, which is what
+    the default does.  Deadlines and spin budgets always spin.
+    */
+    #[inline]
+    fn lock_blocking(&self) -> bool {
+        false
+    }
+}
+
+/**
+The default [RawLock], on atomiclock.
+
+With the `std-mutex` feature it's a flag behind a `std::sync::Mutex` instead, which blocks when contended,
+and under loom or shuttle it's modeled; see the `sync` module.
+*/
+pub struct DefaultRawLock(sync::AtomicLock<()>);
+
+impl DefaultRawLock {
+    const_fn! {
+        /**
+        Creates an unlocked lock.
+        */
+        pub const fn new() -> DefaultRawLock {
+            DefaultRawLock(sync::AtomicLock::new(()))
+        }
+    }
+
+    /**
+    The underlying atomiclock, for code that uses it directly.
+
+    Not available with `std-mutex`, or under loom or shuttle, where there's no atomiclock underneath.
+*/
+    #[cfg(not(any(loom, shuttle, feature = "std-mutex")))]
+    pub fn as_atomiclock(&self) -> &atomiclock::AtomicLock<()> {
+        #[cfg(feature = "chaos")]
+        return self.0.raw();
+        #[cfg(not(feature = "chaos"))]
+        &self.0
+    }
+}
+
+unsafe impl RawLock for DefaultRawLock {
+    #[inline]
+    fn try_lock(&self) -> bool {
+        //the lock stays held after the guard is gone, until unlock
+        self.0.lock().map(core::mem::forget).is_some()
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        self.0.unlock();
+    }
+
+    #[cfg(all(feature = "std-mutex", not(any(loom, shuttle))))]
+    fn lock_blocking(&self) -> bool {
+        core::mem::forget(self.0.lock_blocking());
+        true
+    }
+}
+
+//atomiclock's lock word, as a raw lock
+unsafe impl RawLock for atomiclock::AtomicLock<()> {
+    #[inline]
+    fn try_lock(&self) -> bool {
+        self.lock().map(core::mem::forget).is_some()
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        atomiclock::AtomicLock::unlock(self);
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl core::fmt::Debug for DefaultRawLock {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("DefaultRawLock").field(&self.is_locked()).finish()
+    }
+}
+
+impl Default for DefaultRawLock {
+    fn default() -> DefaultRawLock {
+        DefaultRawLock::new()
+    }
+}
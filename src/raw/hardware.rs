@@ -0,0 +1,96 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Raw locks on a lock word outside the CPU's atomics, such as a hardware spinlock register.
+*/
+
+use core::fmt::Debug;
+use core::sync::atomic::{fence, Ordering};
+use super::RawLock;
+
+/**
+A [RawLock] whose lock word is driven by your own code: a hardware spinlock register, as on the RP2040, i.MX
+and OMAP, or a reserved word of SRAM that each core of a heterogeneous system claims its own way, so a
+Cortex-A and a Cortex-M sharing memory can both use [Lock](crate::Lock) around the same data.
+
+`try_acquire` tries to claim the lock word once, and returns whether it did; `release` gives it back.  They
+usually read and write the register with [core::ptr::read_volatile] and [core::ptr::write_volatile].  Say,
+for an RP2040 SIO spinlock, where reading the register claims it, and writing releases it:
+
+```no_run
+use atomiclock_spinlock::{raw::HardwareLock, Lock};
+const SPINLOCK0: *mut u32 = 0xd000_0100 as *mut u32;
+
+fn claim() -> bool {
+    //reads as nonzero if this read claimed it
+    unsafe { core::ptr::read_volatile(SPINLOCK0) != 0 }
+}
+fn release() {
+    unsafe { core::ptr::write_volatile(SPINLOCK0, 1) }
+}
+
+//the register excludes the other core, and claim and release are each other's inverse
+static SHARED: Lock<[u8; 64], HardwareLock> = Lock::with_raw([0; 64], unsafe { HardwareLock::new(claim, release) });
+SHARED.spin_lock()[0] = 1;
+```
+
+With the default type parameters, the lock word is driven by function pointers, so the lock can be named
+in a `static`.  Use closures instead where the register's address is only known at runtime, such as one
+mapped into a Linux process on the Cortex-A side.
+
+# Ordering
+The lock fences around the closures: an `Acquire` fence after a successful `try_acquire`, and a `Release`
+fence before `release`, so the data the lock protects is ordered with the lock word as with any [RawLock].
+These are the CPU's ordinary fences, which only order memory for the cores in its coherence domain.  If the
+other side of the lock is outside that domain, or the data is in memory the CPU caches without coherence
+with the other side, put the barriers and cache maintenance the platform needs in the closures, such as a
+`dmb sy` on ARM, or a cache clean before `release` and an invalidate after `try_acquire`.
+
+[RawLock::is_locked] isn't overridden, so checking whether the lock is held, as [Lock::is_locked](crate::Lock::is_locked)
+does, briefly claims the lock word if it's free.
+*/
+pub struct HardwareLock<A = fn() -> bool, R = fn()> {
+    try_acquire: A,
+    release: R,
+}
+
+impl<A: Fn() -> bool, R: Fn()> HardwareLock<A, R> {
+    /**
+    Creates a lock driven by `try_acquire` and `release`.  The lock word must not be held.
+
+    # Safety
+    `try_acquire` must return `true` only when the caller now holds the lock word, to the exclusion of every
+    other holder on any core, until `release`, which may run on a different thread than the one that
+    acquired it, as [RawLock] requires.
+*/
+    pub const unsafe fn new(try_acquire: A, release: R) -> Self {
+        HardwareLock { try_acquire, release }
+    }
+}
+
+unsafe impl<A: Fn() -> bool, R: Fn()> RawLock for HardwareLock<A, R> {
+    #[inline]
+    fn try_lock(&self) -> bool {
+        let acquired = (self.try_acquire)();
+        if acquired {
+            fence(Ordering::Acquire);
+        }
+        acquired
+    }
+
+    #[inline]
+    unsafe fn unlock(&self) {
+        fence(Ordering::Release);
+        (self.release)();
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<A, R> Debug for HardwareLock<A, R> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        //reading a hardware lock word may claim it, so there's no state to show
+        f.debug_struct("HardwareLock").finish_non_exhaustive()
+    }
+}
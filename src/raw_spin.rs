@@ -0,0 +1,124 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+A spinlock without data.
+*/
+
+use core::fmt::Debug;
+use crate::{Guard, Lock};
+
+/**
+A spinlock that protects nothing in particular, for things the type system can't own: MMIO regions, C structs,
+or several statics at once.
+
+It waits, and warns, like a [Lock], but hands out no guards, so it's up to you to pair each
+[RawSpinLock::lock] with a [RawSpinLock::unlock].
+
+```
+# use atomiclock_spinlock::RawSpinLock;
+//protects the UART's registers
+static UART: RawSpinLock = RawSpinLock::with_name("uart");
+UART.lock();
+//...write to the registers...
+unsafe { UART.unlock() };
+assert!(!UART.is_locked());
+```
+
+For [lock_api](https://crates.io/crates/lock_api), see the `lock_api` module's `RawSpinlock`.
+*/
+pub struct RawSpinLock {
+    lock: Lock<()>,
+}
+
+impl RawSpinLock {
+    const_fn! {
+        /**
+        Creates a new lock.
+        */
+        pub const fn new() -> Self {
+            RawSpinLock { lock: Lock::new(()) }
+        }
+    }
+
+    const_fn! {
+        /**
+        Creates a new lock with a name, which appears in debug output.
+        */
+        pub const fn with_name(name: &'static str) -> Self {
+            RawSpinLock { lock: Lock::with_name((), name) }
+        }
+    }
+
+    /**
+    Spins until the lock can be acquired.  See [Lock::spin_lock].
+*/
+    pub fn lock(&self) {
+        Guard::into_raw(self.lock.spin_lock());
+    }
+
+    /**
+    Spins until the lock can be acquired, issuing a perfwarn if it's contended.  See [Lock::spin_lock_warn].
+*/
+    #[track_caller]
+    pub fn lock_warn(&self) {
+        Guard::into_raw(self.lock.spin_lock_warn());
+    }
+
+    /**
+    No spin; acquires the lock if available, and returns whether it did.
+*/
+    pub fn try_lock(&self) -> bool {
+        self.lock.try_lock().map(Guard::into_raw).is_some()
+    }
+
+    /**
+    Spins until the lock can be acquired, or the duration elapses, and returns whether it was acquired.
+    See [Lock::spin_lock_for].
+*/
+    #[cfg(feature = "std")]
+    pub fn try_lock_for(&self, duration: std::time::Duration) -> bool {
+        self.lock.spin_lock_for(duration).map(Guard::into_raw).is_some()
+    }
+
+    /**
+    Releases the lock.
+
+    # Safety
+    The lock must be held, and released only once for each time it was acquired.
+*/
+    pub unsafe fn unlock(&self) {
+        drop(self.lock.guard_from_raw());
+    }
+
+    /**
+    Whether the lock is currently held.  See [Lock::is_locked].
+*/
+    pub fn is_locked(&self) -> bool {
+        self.lock.is_locked()
+    }
+
+    /**
+    The underlying [Lock], for its stats and the rest of its API.
+*/
+    pub const fn as_lock(&self) -> &Lock<()> {
+        &self.lock
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl Debug for RawSpinLock {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("RawSpinLock")
+            .field("name", &self.lock.name())
+            .field("locked", &self.is_locked())
+            .finish()
+    }
+}
+
+impl Default for RawSpinLock {
+    fn default() -> Self {
+        RawSpinLock::new()
+    }
+}
@@ -0,0 +1,61 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Using [Lock] as a shared resource in [RTIC](https://rtic.rs) apps.
+
+RTIC resources are accessed through [rtic_core::Mutex], which lets library code be generic over how the
+resource is locked.  `&Lock<T>` implements it by spinning, so a `Lock` can be shared between RTIC tasks
+and code outside RTIC (other cores, or threads on a hosted target) and used by the same generic code.
+
+Spinning inside an RTIC task has the usual hazard: if a higher-priority task that also takes the lock
+preempts the holder, it spins forever.  [Ceiling] avoids this by first entering an RTIC critical
+section (typically on a resource whose ceiling covers every task that takes the lock), and only then
+spinning.
+*/
+
+use crate::Lock;
+
+impl<T> rtic_core::Mutex for &Lock<T> {
+    type T = T;
+
+    /**
+    Spins until the lock is acquired, then runs `f`.
+    */
+    fn lock<R>(&mut self, f: impl FnOnce(&mut T) -> R) -> R {
+        f(&mut self.spin_lock())
+    }
+}
+
+/**
+A [Lock] taken only after raising the priority ceiling via another RTIC [Mutex](rtic_core::Mutex).
+
+While the ceiling resource is locked, no task with priority at or below its ceiling can start, so none of
+them can preempt the holder of the spinlock.
+*/
+#[derive(Debug)]
+pub struct Ceiling<'a, T, M> {
+    lock: &'a Lock<T>,
+    ceiling: M,
+}
+
+impl<'a, T, M: rtic_core::Mutex> Ceiling<'a, T, M> {
+    /**
+    Creates a ceiling-aware view of `lock`.
+
+    `ceiling` is the RTIC resource proxy whose lock raises the priority ceiling.
+    */
+    pub fn new(lock: &'a Lock<T>, ceiling: M) -> Self {
+        Ceiling { lock, ceiling }
+    }
+}
+
+impl<T, M: rtic_core::Mutex> rtic_core::Mutex for Ceiling<'_, T, M> {
+    type T = T;
+
+    /**
+    Raises the ceiling, spins until the lock is acquired, then runs `f`.
+    */
+    fn lock<R>(&mut self, f: impl FnOnce(&mut T) -> R) -> R {
+        let lock = self.lock;
+        self.ceiling.lock(|_| f(&mut lock.spin_lock()))
+    }
+}
@@ -0,0 +1,695 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+A reader-writer spinlock.
+
+[RwLock] allows any number of readers, or one writer.  Additionally, one reader at a time may hold an
+*upgradable* read, which can later be upgraded to a write without releasing the lock.  While an upgradable
+read is held, new readers are allowed but new upgradable readers and writers are not.
+
+Read and write guards can be narrowed to part of the data, such as a field, with [ReadGuard::map] and
+[WriteGuard::map], for code that only needs that part.
+
+For read-mostly data on many cores, [BiasedRwLock] adds a reader bias, so reads don't all write the lock's
+reader count.
+
+Like [Lock](crate::Lock), this is not fair; in particular, a steady stream of readers can starve writers.
+*/
+
+use core::cell::UnsafeCell;
+use core::fmt::Debug;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::Ordering;
+use crate::sync::AtomicUsize;
+
+mod biased;
+
+pub use biased::{BiasedReadGuard, BiasedRwLock};
+
+//state layout: bit 0 is the writer, bit 1 the upgradable reader, and the rest count readers
+const WRITER: usize = 1;
+const UPGRADABLE: usize = 2;
+const READER: usize = 4;
+
+/**
+A reader-writer spinlock.
+*/
+pub struct RwLock<T> {
+    state: AtomicUsize,
+    name: Option<&'static str>,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Send for RwLock<T> {}
+unsafe impl<T: Send + Sync> Sync for RwLock<T> {}
+
+/**
+A guard that provides shared access to the data in an [RwLock].
+*/
+#[must_use]
+pub struct ReadGuard<'a, T> {
+    lock: &'a RwLock<T>,
+}
+
+/**
+A guard that provides exclusive access to the data in an [RwLock].
+*/
+#[must_use]
+pub struct WriteGuard<'a, T> {
+    lock: &'a RwLock<T>,
+}
+
+/**
+A guard that provides shared access to the data in an [RwLock], and can be upgraded to exclusive access.
+*/
+#[must_use]
+pub struct UpgradableGuard<'a, T> {
+    lock: &'a RwLock<T>,
+}
+
+/**
+A guard for part of the data in an [RwLock], from [ReadGuard::map] or [ReadGuard::try_map].  The read
+is released when it's dropped.
+*/
+#[must_use]
+pub struct MappedReadGuard<'a, T, U: ?Sized> {
+    lock: &'a RwLock<T>,
+    data: &'a U,
+}
+
+/**
+A guard for exclusive access to part of the data in an [RwLock], from [WriteGuard::map] or
+[WriteGuard::try_map].  The write is released when it's dropped.
+*/
+#[must_use]
+pub struct MappedWriteGuard<'a, T, U: ?Sized> {
+    lock: &'a RwLock<T>,
+    data: &'a mut U,
+}
+
+impl<T> RwLock<T> {
+    const_fn! {
+        /**
+        Creates a new lock.
+*/
+        pub const fn new(data: T) -> RwLock<T> {
+            RwLock::build(data, None)
+        }
+    }
+
+    const_fn! {
+        /**
+        Creates a new lock with a name, which appears in debug output.
+*/
+        pub const fn with_name(data: T, name: &'static str) -> RwLock<T> {
+            RwLock::build(data, Some(name))
+        }
+    }
+
+    const_fn! {
+        const fn build(data: T, name: Option<&'static str>) -> RwLock<T> {
+            RwLock {
+                state: AtomicUsize::new(0),
+                name,
+                data: UnsafeCell::new(data),
+            }
+        }
+    }
+
+    /**
+    The name of the lock, if any.
+*/
+    pub const fn name(&self) -> Option<&'static str> {
+        self.name
+    }
+
+    /**
+    The number of readers currently holding the lock, not counting an upgradable reader.
+
+    This is a snapshot; it may be out of date by the time you read it.
+*/
+    pub fn readers(&self) -> usize {
+        self.state.load(Ordering::Relaxed) / READER
+    }
+
+    /**
+    Whether a writer currently holds the lock.
+
+    This is a snapshot; it may be out of date by the time you read it.
+*/
+    pub fn is_locked_exclusive(&self) -> bool {
+        self.state.load(Ordering::Relaxed) & WRITER != 0
+    }
+
+    /**
+    Spins until shared access can be acquired.
+*/
+    pub fn read(&self) -> ReadGuard<'_, T> {
+        spin(|| self.raw_try_read());
+        ReadGuard { lock: self }
+    }
+
+    /**
+    No spin; provides shared access if available.
+*/
+    pub fn try_read(&self) -> Option<ReadGuard<'_, T>> {
+        self.raw_try_read().then(|| ReadGuard { lock: self })
+    }
+
+    /**
+    Spins until exclusive access can be acquired.
+*/
+    pub fn write(&self) -> WriteGuard<'_, T> {
+        spin(|| self.raw_try_write());
+        WriteGuard { lock: self }
+    }
+
+    /**
+    No spin; provides exclusive access if available.
+*/
+    pub fn try_write(&self) -> Option<WriteGuard<'_, T>> {
+        self.raw_try_write().then(|| WriteGuard { lock: self })
+    }
+
+    /**
+    Spins until upgradable shared access can be acquired.
+*/
+    pub fn upgradable_read(&self) -> UpgradableGuard<'_, T> {
+        spin(|| self.raw_try_upgradable());
+        UpgradableGuard { lock: self }
+    }
+
+    /**
+    No spin; provides upgradable shared access if available.
+*/
+    pub fn try_upgradable_read(&self) -> Option<UpgradableGuard<'_, T>> {
+        self.raw_try_upgradable().then(|| UpgradableGuard { lock: self })
+    }
+
+    /**
+    Consumes the lock and returns the inner data.
+*/
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+
+    /**
+    Unsafely provides access to the underlying data.
+
+    # Safety
+    This function is unsafe because it allows access to the data without a lock.
+*/
+    #[allow(clippy::mut_from_ref)]
+    pub unsafe fn data(&self) -> &mut T {
+        &mut *self.data.get()
+    }
+
+    /*
+    The raw state transitions.  These are also used by the lock_api integration, which manages its own guards.
+     */
+
+    pub(crate) fn raw_try_read(&self) -> bool {
+        #[cfg(feature = "chaos")]
+        crate::chaos::delay();
+        let state = self.state.fetch_add(READER, Ordering::Acquire);
+        if state & WRITER != 0 {
+            self.state.fetch_sub(READER, Ordering::Release);
+            false
+        } else {
+            crate::tsan::acquire(self);
+            true
+        }
+    }
+
+    pub(crate) fn raw_try_write(&self) -> bool {
+        #[cfg(feature = "chaos")]
+        crate::chaos::delay();
+        let acquired = self.state.compare_exchange(0, WRITER, Ordering::Acquire, Ordering::Relaxed).is_ok();
+        if acquired {
+            crate::tsan::acquire(self);
+        }
+        acquired
+    }
+
+    pub(crate) fn raw_try_upgradable(&self) -> bool {
+        #[cfg(feature = "chaos")]
+        crate::chaos::delay();
+        //if a writer holds the lock, we leave the bit set; the writer clears it on release
+        let acquired = self.state.fetch_or(UPGRADABLE, Ordering::Acquire) & (WRITER | UPGRADABLE) == 0;
+        if acquired {
+            crate::tsan::acquire(self);
+        }
+        acquired
+    }
+
+    pub(crate) fn raw_try_upgrade(&self) -> bool {
+        #[cfg(feature = "chaos")]
+        crate::chaos::delay();
+        let acquired = self.state.compare_exchange(UPGRADABLE, WRITER, Ordering::Acquire, Ordering::Relaxed).is_ok();
+        if acquired {
+            crate::tsan::acquire(self);
+        }
+        acquired
+    }
+
+    pub(crate) fn raw_upgrade(&self) {
+        spin(|| self.raw_try_upgrade());
+    }
+
+    pub(crate) fn raw_downgrade_write(&self) {
+        self.state.fetch_add(READER, Ordering::Acquire);
+        self.raw_unlock_write();
+    }
+
+    pub(crate) fn raw_downgrade_upgradable(&self) {
+        self.state.fetch_add(READER, Ordering::Acquire);
+        self.raw_unlock_upgradable();
+    }
+
+    pub(crate) fn raw_downgrade_write_to_upgradable(&self) {
+        #[cfg(feature = "chaos")]
+        crate::chaos::delay();
+        crate::tsan::release(self);
+        //readers may be transiently counted, so don't overwrite them
+        self.state.fetch_or(UPGRADABLE, Ordering::Acquire);
+        self.state.fetch_and(!WRITER, Ordering::Release);
+        crate::arch::released();
+    }
+
+    pub(crate) fn raw_unlock_read(&self) {
+        #[cfg(feature = "chaos")]
+        crate::chaos::delay();
+        crate::tsan::release(self);
+        self.state.fetch_sub(READER, Ordering::Release);
+        crate::arch::released();
+    }
+
+    pub(crate) fn raw_unlock_write(&self) {
+        #[cfg(feature = "chaos")]
+        crate::chaos::delay();
+        crate::tsan::release(self);
+        //also clears an upgradable bit set by a failed attempt while we held the lock
+        self.state.fetch_and(!(WRITER | UPGRADABLE), Ordering::Release);
+        crate::arch::released();
+    }
+
+    pub(crate) fn raw_unlock_upgradable(&self) {
+        #[cfg(feature = "chaos")]
+        crate::chaos::delay();
+        crate::tsan::release(self);
+        self.state.fetch_sub(UPGRADABLE, Ordering::Release);
+        crate::arch::released();
+    }
+}
+
+fn spin(mut attempt: impl FnMut() -> bool) {
+    let mut spins = crate::wait::Spins::new();
+    while !attempt() {
+        if crate::SINGLE_THREADED {
+            panic!("RwLock is already held; on a single-threaded target, spinning on it would never finish");
+        }
+        crate::wait::relax(&mut spins);
+    }
+}
+
+impl<'a, T> ReadGuard<'a, T> {
+    /**
+    Narrows the guard to the part of the data chosen by `f`, such as a field, keeping the read held.
+
+    ```
+    # use atomiclock_spinlock::rwlock::{ReadGuard, RwLock};
+    struct Config { name: String, retries: u32 }
+    let lock = RwLock::new(Config { name: "primary".to_string(), retries: 3 });
+    let name = ReadGuard::map(lock.read(), |config| config.name.as_str());
+    assert_eq!(&*name, "primary");
+    ```
+
+    This is an associated function, since the guard dereferences to the data; call it as
+    `ReadGuard::map(guard, f)`.
+*/
+    pub fn map<U: ?Sized>(guard: Self, f: impl FnOnce(&T) -> &U) -> MappedReadGuard<'a, T, U> {
+        //if f panics, the guard releases the read
+        let data = f(unsafe { &*guard.lock.data.get() });
+        let lock = guard.lock;
+        core::mem::forget(guard);
+        MappedReadGuard { lock, data }
+    }
+
+    /**
+    Like [ReadGuard::map], for a part that may not be there; returns the guard if `f` returns `None`.
+*/
+    pub fn try_map<U: ?Sized>(guard: Self, f: impl FnOnce(&T) -> Option<&U>) -> Result<MappedReadGuard<'a, T, U>, Self> {
+        match f(unsafe { &*guard.lock.data.get() }) {
+            Some(data) => {
+                let lock = guard.lock;
+                core::mem::forget(guard);
+                Ok(MappedReadGuard { lock, data })
+            }
+            None => Err(guard),
+        }
+    }
+}
+
+impl<'a, T, U: ?Sized> MappedReadGuard<'a, T, U> {
+    /**
+    Narrows the guard further, like [ReadGuard::map].
+*/
+    pub fn map<V: ?Sized>(guard: Self, f: impl FnOnce(&U) -> &V) -> MappedReadGuard<'a, T, V> {
+        let data = f(guard.data);
+        let lock = guard.lock;
+        core::mem::forget(guard);
+        MappedReadGuard { lock, data }
+    }
+
+    /**
+    Narrows the guard further, like [ReadGuard::try_map].
+*/
+    pub fn try_map<V: ?Sized>(guard: Self, f: impl FnOnce(&U) -> Option<&V>) -> Result<MappedReadGuard<'a, T, V>, Self> {
+        match f(guard.data) {
+            Some(data) => {
+                let lock = guard.lock;
+                core::mem::forget(guard);
+                Ok(MappedReadGuard { lock, data })
+            }
+            None => Err(guard),
+        }
+    }
+}
+
+impl<'a, T> WriteGuard<'a, T> {
+    /**
+    Narrows the guard to the part of the data chosen by `f`, keeping the write held.
+
+    ```
+    # use atomiclock_spinlock::rwlock::{RwLock, WriteGuard};
+    let lock = RwLock::new((0u32, vec![1, 2]));
+    let mut list = WriteGuard::map(lock.write(), |(_, list)| list);
+    list.push(3);
+    drop(list);
+    assert_eq!(lock.read().1, [1, 2, 3]);
+    ```
+*/
+    pub fn map<U: ?Sized>(guard: Self, f: impl FnOnce(&mut T) -> &mut U) -> MappedWriteGuard<'a, T, U> {
+        let data = f(unsafe { &mut *guard.lock.data.get() });
+        let lock = guard.lock;
+        core::mem::forget(guard);
+        MappedWriteGuard { lock, data }
+    }
+
+    /**
+    Like [WriteGuard::map], for a part that may not be there; returns the guard if `f` returns `None`.
+*/
+    pub fn try_map<U: ?Sized>(guard: Self, f: impl FnOnce(&mut T) -> Option<&mut U>) -> Result<MappedWriteGuard<'a, T, U>, Self> {
+        match f(unsafe { &mut *guard.lock.data.get() }) {
+            Some(data) => {
+                let lock = guard.lock;
+                core::mem::forget(guard);
+                Ok(MappedWriteGuard { lock, data })
+            }
+            None => Err(guard),
+        }
+    }
+
+    /**
+    Converts exclusive access into shared access, without letting another writer in.
+*/
+    pub fn downgrade(guard: Self) -> ReadGuard<'a, T> {
+        let lock = guard.lock;
+        core::mem::forget(guard);
+        lock.raw_downgrade_write();
+        ReadGuard { lock }
+    }
+
+    /**
+    Converts exclusive access into upgradable shared access, without letting another writer in.
+*/
+    pub fn downgrade_to_upgradable(guard: Self) -> UpgradableGuard<'a, T> {
+        let lock = guard.lock;
+        core::mem::forget(guard);
+        lock.raw_downgrade_write_to_upgradable();
+        UpgradableGuard { lock }
+    }
+}
+
+impl<'a, T> UpgradableGuard<'a, T> {
+    /**
+    Spins until the other readers are gone, then converts to exclusive access.
+*/
+    pub fn upgrade(guard: Self) -> WriteGuard<'a, T> {
+        let lock = guard.lock;
+        core::mem::forget(guard);
+        lock.raw_upgrade();
+        WriteGuard { lock }
+    }
+
+    /**
+    No spin; converts to exclusive access if there are no other readers.
+*/
+    pub fn try_upgrade(guard: Self) -> Result<WriteGuard<'a, T>, Self> {
+        if guard.lock.raw_try_upgrade() {
+            let lock = guard.lock;
+            core::mem::forget(guard);
+            Ok(WriteGuard { lock })
+        } else {
+            Err(guard)
+        }
+    }
+
+    /**
+    Converts to plain shared access, letting another upgradable reader in.
+*/
+    pub fn downgrade(guard: Self) -> ReadGuard<'a, T> {
+        let lock = guard.lock;
+        core::mem::forget(guard);
+        lock.raw_downgrade_upgradable();
+        ReadGuard { lock }
+    }
+}
+
+impl<'a, T, U: ?Sized> MappedWriteGuard<'a, T, U> {
+    /**
+    Narrows the guard further, like [WriteGuard::map].
+*/
+    pub fn map<V: ?Sized>(guard: Self, f: impl FnOnce(&mut U) -> &mut V) -> MappedWriteGuard<'a, T, V> {
+        let mut guard = core::mem::ManuallyDrop::new(guard);
+        let lock = guard.lock;
+        let data: *mut U = &mut *guard.data;
+        //the part outlives the forgotten guard, so if f panics, release here
+        let release = ReleaseWrite(lock);
+        let data = f(unsafe { &mut *data });
+        core::mem::forget(release);
+        MappedWriteGuard { lock, data }
+    }
+
+    /**
+    Narrows the guard further, like [WriteGuard::try_map].
+*/
+    pub fn try_map<V: ?Sized>(guard: Self, f: impl FnOnce(&mut U) -> Option<&mut V>) -> Result<MappedWriteGuard<'a, T, V>, Self> {
+        let mut guard = core::mem::ManuallyDrop::new(guard);
+        let lock = guard.lock;
+        let data: *mut U = &mut *guard.data;
+        let release = ReleaseWrite(lock);
+        let mapped = f(unsafe { &mut *data });
+        core::mem::forget(release);
+        match mapped {
+            Some(data) => Ok(MappedWriteGuard { lock, data }),
+            //f's borrow has ended, so the guard can have its part back
+            None => Err(MappedWriteGuard { lock, data: unsafe { &mut *data } }),
+        }
+    }
+}
+
+//releases the write if a projection panics
+struct ReleaseWrite<'a, T>(&'a RwLock<T>);
+
+impl<T> Drop for ReleaseWrite<'_, T> {
+    fn drop(&mut self) {
+        self.0.raw_unlock_write();
+    }
+}
+
+impl<T> Drop for ReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.raw_unlock_read();
+    }
+}
+
+impl<T> Drop for WriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.raw_unlock_write();
+    }
+}
+
+impl<T> Drop for UpgradableGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.raw_unlock_upgradable();
+    }
+}
+
+impl<T, U: ?Sized> Drop for MappedReadGuard<'_, T, U> {
+    fn drop(&mut self) {
+        self.lock.raw_unlock_read();
+    }
+}
+
+impl<T, U: ?Sized> Drop for MappedWriteGuard<'_, T, U> {
+    fn drop(&mut self) {
+        self.lock.raw_unlock_write();
+    }
+}
+
+/*boilerplate
+Same as Lock: no clone, so no eq, ord, hash, etc.
+ */
+
+impl<T: Debug> Debug for RwLock<T> {
+    /**
+    Formats the lock without blocking.
+
+    The data is only formatted if shared access can be acquired without spinning; otherwise
+    it is reported as locked.
+    */
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut s = f.debug_struct("RwLock");
+        s.field("name", &self.name);
+        match self.try_read() {
+            None => {
+                s.field("data", &format_args!("<locked>"));
+            }
+            Some(guard) => {
+                s.field("data", &*guard);
+            }
+        }
+        s.finish()
+    }
+}
+
+impl<T: Default> Default for RwLock<T> {
+    fn default() -> RwLock<T> {
+        RwLock::new(Default::default())
+    }
+}
+
+impl<T> From<T> for RwLock<T> {
+    fn from(data: T) -> RwLock<T> {
+        RwLock::new(data)
+    }
+}
+
+impl<T: Debug> Debug for ReadGuard<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ReadGuard").field("name", &self.lock.name).field("data", &**self).finish()
+    }
+}
+
+impl<T: Debug> Debug for WriteGuard<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("WriteGuard").field("name", &self.lock.name).field("data", &**self).finish()
+    }
+}
+
+impl<T: Debug> Debug for UpgradableGuard<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("UpgradableGuard").field("name", &self.lock.name).field("data", &**self).finish()
+    }
+}
+
+impl<T, U: ?Sized + Debug> Debug for MappedReadGuard<'_, T, U> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MappedReadGuard").field("name", &self.lock.name).field("data", &self.data).finish()
+    }
+}
+
+impl<T, U: ?Sized + Debug> Debug for MappedWriteGuard<'_, T, U> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MappedWriteGuard").field("name", &self.lock.name).field("data", &self.data).finish()
+    }
+}
+
+impl<T> Deref for ReadGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T> Deref for WriteGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T> DerefMut for WriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T> Deref for UpgradableGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T> AsRef<T> for ReadGuard<'_, T> {
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+impl<T> AsRef<T> for WriteGuard<'_, T> {
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+impl<T> AsMut<T> for WriteGuard<'_, T> {
+    fn as_mut(&mut self) -> &mut T {
+        self
+    }
+}
+
+impl<T> AsRef<T> for UpgradableGuard<'_, T> {
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+impl<T, U: ?Sized> Deref for MappedReadGuard<'_, T, U> {
+    type Target = U;
+    fn deref(&self) -> &U {
+        self.data
+    }
+}
+
+impl<T, U: ?Sized> Deref for MappedWriteGuard<'_, T, U> {
+    type Target = U;
+    fn deref(&self) -> &U {
+        self.data
+    }
+}
+
+impl<T, U: ?Sized> DerefMut for MappedWriteGuard<'_, T, U> {
+    fn deref_mut(&mut self) -> &mut U {
+        self.data
+    }
+}
+
+impl<T, U: ?Sized> AsRef<U> for MappedReadGuard<'_, T, U> {
+    fn as_ref(&self) -> &U {
+        self
+    }
+}
+
+impl<T, U: ?Sized> AsRef<U> for MappedWriteGuard<'_, T, U> {
+    fn as_ref(&self) -> &U {
+        self
+    }
+}
+
+impl<T, U: ?Sized> AsMut<U> for MappedWriteGuard<'_, T, U> {
+    fn as_mut(&mut self) -> &mut U {
+        self
+    }
+}
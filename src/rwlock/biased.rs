@@ -0,0 +1,323 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+A reader bias over [RwLock], after Dice and Kogan's *BRAVO*, so reads on many cores don't fight over its
+reader count.
+*/
+
+use core::fmt::Debug;
+use core::ops::Deref;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use super::{ReadGuard, RwLock, WriteGuard};
+
+//slots in the visible-reader table, shared by every biased lock
+const SLOTS: usize = 1024;
+
+//slow reads after a writer revokes the bias, before a reader may restore it
+const INHIBIT: usize = 256;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY: AtomicUsize = AtomicUsize::new(0);
+
+/**
+The visible readers: each slot holds the address of the lock a reader is reading, or 0.
+*/
+static VISIBLE: [AtomicUsize; SLOTS] = [EMPTY; SLOTS];
+
+/**
+The slot for the current thread reading `lock`.  Any slot would be correct, so this only spreads readers
+out: threads have their own stacks, so the address of a local tells them apart without a thread id.
+*/
+fn slot(lock: usize) -> &'static AtomicUsize {
+    let local = 0u8;
+    let thread = crate::addr(&local) >> 16;
+    let hash = ((thread ^ lock.rotate_left(16)) as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    &VISIBLE[(hash >> (64 - SLOTS.trailing_zeros())) as usize]
+}
+
+/**
+An [RwLock] biased towards readers, for read-mostly data on many cores.
+
+With the bias on, a read only marks a slot in a table of visible readers, picked for the thread and the lock,
+rather than adding to the lock's reader count, which every reader on every core would write.  A writer that
+finds the bias on revokes it, and waits for the visible readers of this lock to leave; until enough reads
+have gone through the reader count since, reads don't restore it, so a lock that's written often behaves
+like a plain [RwLock]:
+
+```
+# use atomiclock_spinlock::rwlock::BiasedRwLock;
+let routes = BiasedRwLock::new(vec!["10.0.0.0/8"]);
+assert!(routes.is_biased());
+std::thread::scope(|s| {
+    for _ in 0..4 {
+        s.spawn(|| assert!(!routes.read().is_empty()));
+    }
+});
+//revokes the bias
+routes.write().push("192.168.0.0/16");
+assert!(!routes.is_biased());
+assert_eq!(routes.read().len(), 2);
+```
+
+Reads that collide on a slot, such as a thread reading the lock again while it already is, go through the
+reader count instead.  Writes cost more than an [RwLock]'s when the bias is on, since the revocation scans
+the whole table, a few kilobytes.  There's no upgradable read.
+*/
+pub struct BiasedRwLock<T> {
+    lock: RwLock<T>,
+    bias: AtomicBool,
+    //slow reads, counted while the bias is off
+    slow_reads: AtomicUsize,
+    //the count of slow reads at which the bias may be restored
+    inhibit_until: AtomicUsize,
+    //a writer revoked the bias, but visible readers were left, so writers still look for them
+    draining: AtomicBool,
+}
+
+/**
+A guard that provides shared access to the data in a [BiasedRwLock].
+*/
+#[must_use]
+pub struct BiasedReadGuard<'a, T> {
+    lock: &'a BiasedRwLock<T>,
+    read: Read<'a, T>,
+}
+
+enum Read<'a, T> {
+    //marked in the table, with the bias on
+    Visible(&'static AtomicUsize),
+    //counted by the lock, until the guard is dropped
+    Counted(#[allow(dead_code)] ReadGuard<'a, T>),
+}
+
+impl<T> BiasedRwLock<T> {
+    const_fn! {
+        /**
+        Creates a new lock, with the bias on.
+        */
+        pub const fn new(data: T) -> BiasedRwLock<T> {
+            BiasedRwLock::from_lock(RwLock::new(data))
+        }
+    }
+
+    const_fn! {
+        /**
+        Creates a new lock with a name, which appears in debug output.
+        */
+        pub const fn with_name(data: T, name: &'static str) -> BiasedRwLock<T> {
+            BiasedRwLock::from_lock(RwLock::with_name(data, name))
+        }
+    }
+
+    const_fn! {
+        const fn from_lock(lock: RwLock<T>) -> BiasedRwLock<T> {
+            BiasedRwLock {
+                lock,
+                bias: AtomicBool::new(true),
+                slow_reads: AtomicUsize::new(0),
+                inhibit_until: AtomicUsize::new(0),
+                draining: AtomicBool::new(false),
+            }
+        }
+    }
+
+    /**
+    The name of the lock, if any.
+*/
+    pub const fn name(&self) -> Option<&'static str> {
+        self.lock.name()
+    }
+
+    /**
+    Whether reads currently take the biased path.
+
+    This is a snapshot; it may be out of date by the time you read it.
+*/
+    pub fn is_biased(&self) -> bool {
+        self.bias.load(Ordering::Relaxed)
+    }
+
+    /**
+    Spins until shared access can be acquired.
+*/
+    pub fn read(&self) -> BiasedReadGuard<'_, T> {
+        match self.try_visible() {
+            Some(read) => read,
+            None => self.counted(self.lock.read()),
+        }
+    }
+
+    /**
+    No spin; provides shared access if available.
+*/
+    pub fn try_read(&self) -> Option<BiasedReadGuard<'_, T>> {
+        self.try_visible().or_else(|| self.lock.try_read().map(|guard| self.counted(guard)))
+    }
+
+    /**
+    Spins until exclusive access can be acquired, revoking the bias if it's on, and then waiting for the
+    visible readers to leave.
+
+    It waits for them without holding the lock, so that one reading again meanwhile, through the reader
+    count, doesn't deadlock with the writer.
+*/
+    pub fn write(&self) -> WriteGuard<'_, T> {
+        loop {
+            let guard = self.lock.write();
+            if !self.revoke() {
+                return guard;
+            }
+            drop(guard);
+            let address = crate::addr(self);
+            let mut spins = crate::wait::Spins::new();
+            for slot in &VISIBLE {
+                while slot.load(Ordering::Relaxed) == address {
+                    if crate::SINGLE_THREADED {
+                        panic!("BiasedRwLock is being read; on a single-threaded target, waiting for the reader would never finish");
+                    }
+                    crate::wait::relax(&mut spins);
+                }
+            }
+        }
+    }
+
+    /**
+    No spin; provides exclusive access if available.
+
+    If the bias is on, but a visible reader means it can't provide access, the bias stays on.
+*/
+    pub fn try_write(&self) -> Option<WriteGuard<'_, T>> {
+        let guard = self.lock.try_write()?;
+        let biased = self.bias.load(Ordering::Relaxed);
+        if self.revoke() {
+            if biased {
+                self.bias.store(true, Ordering::SeqCst);
+            }
+            return None;
+        }
+        Some(guard)
+    }
+
+    /**
+    Consumes the lock and returns the inner data.
+*/
+    pub fn into_inner(self) -> T {
+        self.lock.into_inner()
+    }
+
+    /**
+    Reads through the table, if the bias is on and the slot is free.
+*/
+    fn try_visible(&self) -> Option<BiasedReadGuard<'_, T>> {
+        if !self.bias.load(Ordering::Relaxed) {
+            return None;
+        }
+        let address = crate::addr(self);
+        let slot = slot(address);
+        slot.compare_exchange(0, address, Ordering::SeqCst, Ordering::Relaxed).ok()?;
+        //SeqCst, paired with the writer's: either it sees our slot, or we see the bias revoked
+        if self.bias.load(Ordering::SeqCst) {
+            crate::tsan::acquire(&self.lock);
+            Some(BiasedReadGuard { lock: self, read: Read::Visible(slot) })
+        } else {
+            slot.store(0, Ordering::Release);
+            None
+        }
+    }
+
+    /**
+    Wraps a read through the reader count, and restores the bias once enough of them have gone by.
+*/
+    fn counted<'a>(&'a self, guard: ReadGuard<'a, T>) -> BiasedReadGuard<'a, T> {
+        //writers can't hold the lock while we read, so none can miss the bias coming back
+        if !self.bias.load(Ordering::Relaxed)
+            && self.slow_reads.fetch_add(1, Ordering::Relaxed).wrapping_sub(self.inhibit_until.load(Ordering::Relaxed)) < usize::MAX / 2 {
+            self.bias.store(true, Ordering::SeqCst);
+        }
+        BiasedReadGuard { lock: self, read: Read::Counted(guard) }
+    }
+
+    /**
+    With the write lock held, turns the bias off and holds off restoring it, if it's on or an earlier
+    revocation left visible readers, and returns whether there still are any.
+*/
+    fn revoke(&self) -> bool {
+        //both only change with the write lock held, or the read lock for the bias coming back
+        if !self.bias.load(Ordering::Relaxed) && !self.draining.load(Ordering::Relaxed) {
+            return false;
+        }
+        self.bias.store(false, Ordering::SeqCst);
+        self.inhibit_until.store(self.slow_reads.load(Ordering::Relaxed).wrapping_add(INHIBIT), Ordering::Relaxed);
+        //SeqCst, paired with the reader's: either we see its slot, or it sees the bias revoked
+        let address = crate::addr(self);
+        let visible = VISIBLE.iter().any(|slot| slot.load(Ordering::SeqCst) == address);
+        self.draining.store(visible, Ordering::Relaxed);
+        visible
+    }
+}
+
+impl<T> Drop for BiasedReadGuard<'_, T> {
+    fn drop(&mut self) {
+        if let Read::Visible(slot) = self.read {
+            crate::tsan::release(&self.lock.lock);
+            //the writer's wait sees our reads finish before it writes
+            slot.store(0, Ordering::Release);
+        }
+    }
+}
+
+impl<T> Deref for BiasedReadGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        //readers, visible or counted, exclude writers
+        unsafe { &*self.lock.lock.data.get() }
+    }
+}
+
+/*
+boilerplate
+
+Same as RwLock: no clone, so no eq, ord, hash, etc.
+ */
+
+impl<T: Debug> Debug for BiasedRwLock<T> {
+    /**
+    Formats the lock without blocking, as [RwLock]'s `Debug` does.
+    */
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut s = f.debug_struct("BiasedRwLock");
+        s.field("name", &self.name()).field("biased", &self.is_biased());
+        match self.try_read() {
+            None => {
+                s.field("data", &format_args!("<locked>"));
+            }
+            Some(guard) => {
+                s.field("data", &*guard);
+            }
+        }
+        s.finish()
+    }
+}
+
+impl<T: Default> Default for BiasedRwLock<T> {
+    fn default() -> Self {
+        BiasedRwLock::new(T::default())
+    }
+}
+
+impl<T> From<T> for BiasedRwLock<T> {
+    fn from(data: T) -> Self {
+        BiasedRwLock::new(data)
+    }
+}
+
+impl<T: Debug> Debug for BiasedReadGuard<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("BiasedReadGuard").field(&&**self).finish()
+    }
+}
+
+impl<T> AsRef<T> for BiasedReadGuard<'_, T> {
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
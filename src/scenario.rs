@@ -0,0 +1,181 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Multi-thread test scenarios, run in lockstep.
+
+A concurrent test usually needs its own handshakes (barriers, channels, flags) just to get the threads to
+do things in the interesting order, and it's easy to get those subtly wrong.  [scenario](crate::scenario!)
+declares the threads and numbers the steps; each thread waits for the step before its own to finish, so
+the steps run in order, one at a time, across threads:
+
+```
+# use atomiclock_spinlock::{scenario, Lock};
+let lock = Lock::new(0);
+scenario! {
+    t1 => |s| {
+        s.step(1);
+        let guard = lock.spin_lock();
+        s.step(3);
+        drop(guard);
+    },
+    t2 => |s| {
+        s.step(2);
+        assert!(lock.try_lock().is_none());
+        s.step(4);
+        *lock.spin_lock() += 1;
+    },
+}
+assert_eq!(lock.into_inner(), 1);
+```
+
+A step that is expected to block until a later step (say, spinning on a lock that another thread releases
+next) is started with [Actor::step_blocking] instead, which lets the following steps go ahead.
+
+Each thread is named after its label, so scenarios work with `TestLock::follow_schedule`.  A scenario
+that can't make progress panics, instead of hanging the test: when a step never runs, when a step number
+is used twice, when another thread panicked, or after [TIMEOUT].
+*/
+
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/**
+How long a step waits for its turn before the scenario is considered stuck.
+*/
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+/**
+The shared progress of a scenario.  Use [scenario](crate::scenario!) to create one.
+*/
+#[derive(Debug)]
+pub struct Scenario {
+    //steps 1..=completed are done
+    completed: AtomicUsize,
+    //threads that haven't finished
+    running: AtomicUsize,
+    poisoned: AtomicBool,
+}
+
+impl Scenario {
+    /**
+    A scenario for `threads` threads.
+*/
+    #[doc(hidden)]
+    pub fn new(threads: usize) -> Scenario {
+        Scenario { completed: AtomicUsize::new(0), running: AtomicUsize::new(threads), poisoned: AtomicBool::new(false) }
+    }
+
+    /**
+    The handle for one of the threads.  Dropping it marks the thread finished.
+*/
+    #[doc(hidden)]
+    pub fn actor(&self) -> Actor<'_> {
+        Actor { scenario: self, current: None }
+    }
+}
+
+/**
+One thread's handle on a [Scenario], for marking its steps.
+*/
+#[derive(Debug)]
+pub struct Actor<'a> {
+    scenario: &'a Scenario,
+    //the step this thread is running, if any
+    current: Option<usize>,
+}
+
+impl Actor<'_> {
+    /**
+    Finishes this thread's current step, and waits until step `n - 1` is finished, to start step `n`.
+
+    # Panics
+    If step `n - 1` can never finish, if step `n` already ran, or if `n` isn't after this thread's
+    previous step.
+*/
+    pub fn step(&mut self, n: usize) {
+        self.begin(n);
+        self.current = Some(n);
+    }
+
+    /**
+    Like [Actor::step], but lets step `n + 1` start right away, since this step is expected to block
+    until a later one.
+*/
+    pub fn step_blocking(&mut self, n: usize) {
+        self.begin(n);
+        self.scenario.completed.store(n, Ordering::Release);
+    }
+
+    fn begin(&mut self, n: usize) {
+        assert!(n > 0, "scenario steps are numbered from 1");
+        if let Some(current) = self.current {
+            assert!(n > current, "step {n} comes after this thread's step {current}");
+        }
+        self.finish();
+        let started = Instant::now();
+        loop {
+            let completed = self.scenario.completed.load(Ordering::Acquire);
+            if completed == n - 1 {
+                return;
+            }
+            assert!(completed < n, "step {n} ran twice");
+            assert!(!self.scenario.poisoned.load(Ordering::Relaxed), "another thread in the scenario panicked");
+            //everyone else is finished, so nobody can run the missing step
+            assert!(self.scenario.running.load(Ordering::Acquire) > 1, "step {} never ran", completed + 1);
+            assert!(started.elapsed() < TIMEOUT, "scenario stuck waiting for step {}", completed + 1);
+            std::thread::yield_now();
+        }
+    }
+
+    fn finish(&mut self) {
+        if let Some(current) = self.current.take() {
+            self.scenario.completed.store(current, Ordering::Release);
+        }
+    }
+}
+
+impl Drop for Actor<'_> {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            self.scenario.poisoned.store(true, Ordering::Relaxed);
+        } else {
+            self.finish();
+        }
+        self.scenario.running.fetch_sub(1, Ordering::Release);
+    }
+}
+
+/**
+Runs a multi-thread test scenario, in numbered steps.
+
+```text
+scenario! {
+    label => |actor| { ... actor.step(1); ... },
+    ...
+}
+```
+
+Each `label` runs on its own thread, named `label`.  Its body marks its steps with [Actor::step] and
+[Actor::step_blocking]; code before a thread's first step runs concurrently with everything else.
+The macro returns once every thread has finished, and panics if any of them did.  See the
+[scenario module](mod@crate::scenario) for an example.
+
+Requires the `test-util` and `std` features.
+*/
+#[macro_export]
+macro_rules! scenario {
+    ($($label:ident => |$actor:ident| $body:expr),+ $(,)?) => {{
+        let scenario = $crate::scenario::Scenario::new(0 $(+ { let _ = stringify!($label); 1 })+);
+        ::std::thread::scope(|scope| {
+            $(
+                ::std::thread::Builder::new()
+                    .name(::std::string::String::from(stringify!($label)))
+                    .spawn_scoped(scope, || {
+                        #[allow(unused_mut)]
+                        let mut $actor = scenario.actor();
+                        $body
+                    })
+                    .expect("failed to spawn a scenario thread");
+            )+
+        });
+    }};
+}
@@ -0,0 +1,150 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+A lock for secrets, with the `zeroize` feature.
+*/
+
+use core::fmt::Debug;
+use core::ops::{Deref, DerefMut};
+use zeroize::Zeroize;
+use crate::{Guard, Lock};
+
+/**
+A [Lock] for key material and other secrets, which wipes the data with [zeroize](https://crates.io/crates/zeroize)
+when the lock is dropped, and never formats it.
+
+```
+# use atomiclock_spinlock::SecretLock;
+let key = SecretLock::new([0x2au8; 32]);
+key.spin_lock()[0] = 0x17;
+assert_eq!(format!("{key:?}"), "SecretLock(<redacted>)");
+//the lock's copy is wiped
+let key = key.into_inner();
+assert_eq!(key[0], 0x17);
+```
+
+[SecretLock::into_inner] wipes the lock it leaves behind, so from then on the secret is the caller's to wipe.
+Like all zeroizing, this can't reach copies that moves of the lock leave behind, so for secrets that get
+moved around, keep the data behind a `Box`, which zeroize wipes on the heap.
+
+Guards are [SecretGuard]s, which don't format the data either.  There's no way to get at the underlying
+[Lock], since it and its guards would.
+*/
+pub struct SecretLock<T: Zeroize> {
+    lock: Lock<T>,
+}
+
+/**
+A guard for a [SecretLock], which doesn't format the data.
+*/
+#[must_use]
+pub struct SecretGuard<'a, T> {
+    guard: Guard<'a, T>,
+}
+
+impl<T: Zeroize> SecretLock<T> {
+    const_fn! {
+        /**
+        Creates a new lock.
+        */
+        pub const fn new(data: T) -> Self {
+            SecretLock { lock: Lock::new(data) }
+        }
+    }
+
+    const_fn! {
+        /**
+        Creates a new lock with a name, for per-lock settings, events, link hooks and the holder
+        reports of owner tracking.  The lock's own debug output stays redacted.
+        */
+        pub const fn with_name(data: T, name: &'static str) -> Self {
+            SecretLock { lock: Lock::with_name(data, name) }
+        }
+    }
+
+    /**
+    Spins until the lock can be acquired.  See [Lock::spin_lock].
+*/
+    pub fn spin_lock(&self) -> SecretGuard<'_, T> {
+        SecretGuard { guard: self.lock.spin_lock() }
+    }
+
+    /**
+    No spin; provides access to the lock if available.
+*/
+    pub fn try_lock(&self) -> Option<SecretGuard<'_, T>> {
+        self.lock.try_lock().map(|guard| SecretGuard { guard })
+    }
+
+    /**
+    Whether the lock is currently held.  See [Lock::is_locked].
+*/
+    pub fn is_locked(&self) -> bool {
+        self.lock.is_locked()
+    }
+
+    /**
+    The data.  No locking is needed, since the borrow is exclusive.
+*/
+    pub fn get_mut(&mut self) -> &mut T {
+        //we have the only reference to the lock, so nobody else holds it
+        unsafe { self.lock.data() }
+    }
+
+    /**
+    Consumes the lock and returns the inner data, wiping the lock.
+*/
+    pub fn into_inner(self) -> T {
+        let mut this = core::mem::MaybeUninit::new(self);
+        //move the lock out; what's left is never dropped, only wiped
+        let lock = unsafe { core::ptr::read(&(*this.as_ptr()).lock) };
+        this.zeroize();
+        lock.into_inner()
+    }
+}
+
+impl<T: Zeroize> Drop for SecretLock<T> {
+    fn drop(&mut self) {
+        self.get_mut().zeroize();
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<T: Zeroize> Debug for SecretLock<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("SecretLock(<redacted>)")
+    }
+}
+
+impl<T: Zeroize + Default> Default for SecretLock<T> {
+    fn default() -> Self {
+        SecretLock::new(T::default())
+    }
+}
+
+impl<T: Zeroize> From<T> for SecretLock<T> {
+    fn from(data: T) -> Self {
+        SecretLock::new(data)
+    }
+}
+
+impl<T> Debug for SecretGuard<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("SecretGuard(<redacted>)")
+    }
+}
+
+impl<T> Deref for SecretGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T> DerefMut for SecretGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
@@ -0,0 +1,78 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Read-only guards.
+*/
+
+use core::fmt::Debug;
+use core::ops::Deref;
+use crate::Guard;
+
+/**
+A [Guard] that only gives shared access to the data.  Create one with [Guard::share].
+
+For handing a held lock to code that may read the data, but mustn't change it:
+
+```compile_fail
+# use atomiclock_spinlock::{Guard, Lock};
+let lock = Lock::new(0);
+let shared = Guard::share(lock.spin_lock());
+*shared += 1;
+```
+*/
+#[must_use]
+pub struct SharedGuard<'a, T> {
+    guard: Guard<'a, T>,
+}
+
+impl<'a, T> Guard<'a, T> {
+    /**
+    Turns the guard into a [SharedGuard], which can only read the data.  The lock stays held until
+    it's dropped.
+
+    ```
+    # use atomiclock_spinlock::{Guard, Lock, SharedGuard};
+    fn report(totals: SharedGuard<'_, Vec<u32>>) -> u32 {
+        totals.iter().sum()
+    }
+    let lock = Lock::new(vec![1, 2]);
+    let mut guard = lock.spin_lock();
+    guard.push(3);
+    assert_eq!(report(Guard::share(guard)), 6);
+    ```
+
+    This is an associated function, since the guard dereferences to the data; call it as
+    `Guard::share(guard)`.
+*/
+    pub fn share(guard: Self) -> SharedGuard<'a, T> {
+        SharedGuard { guard }
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<T> Deref for SharedGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T> AsRef<T> for SharedGuard<'_, T> {
+    fn as_ref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T: Debug> Debug for SharedGuard<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("SharedGuard").field(&*self.guard).finish()
+    }
+}
+
+impl<'a, T> From<Guard<'a, T>> for SharedGuard<'a, T> {
+    fn from(guard: Guard<'a, T>) -> Self {
+        SharedGuard { guard }
+    }
+}
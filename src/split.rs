@@ -0,0 +1,105 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Splitting a guard into guards for disjoint parts of the data.
+*/
+
+use core::fmt::Debug;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::Ordering;
+use crate::{Guard, Lock};
+
+/**
+A guard for part of a lock's data, from [Guard::map_split] or [Guard::split].
+
+The lock is released when the last of the two parts is dropped.
+*/
+#[must_use]
+pub struct SplitGuard<'a, T, U> {
+    lock: &'a Lock<T>,
+    data: &'a mut U,
+}
+
+impl<'a, T> Guard<'a, T> {
+    /**
+    Splits the guard into two guards for disjoint parts of the data, chosen by `f`.  Each part can be
+    used, moved, and dropped on its own; the lock is released once both are dropped.
+
+    ```
+    # use atomiclock_spinlock::{Guard, Lock};
+    struct Buffers { input: Vec<u8>, output: Vec<u8> }
+    let lock = Lock::new(Buffers { input: vec![1, 2], output: Vec::new() });
+    let (input, mut output) = Guard::map_split(lock.spin_lock(), |b| (&mut b.input, &mut b.output));
+    std::thread::scope(|s| {
+        //the parts can go to different threads
+        s.spawn(move || output.extend(input.iter().map(|n| n * 2)));
+    });
+    assert_eq!(lock.spin_lock().output, [2, 4]);
+    ```
+
+    This is an associated function, since the guard dereferences to the data; call it as
+    `Guard::map_split(guard, f)`.
+*/
+    pub fn map_split<U, V>(
+        guard: Self,
+        f: impl FnOnce(&mut T) -> (&mut U, &mut V),
+    ) -> (SplitGuard<'a, T, U>, SplitGuard<'a, T, V>) {
+        let guard = core::mem::ManuallyDrop::new(guard);
+        let lock = guard.lock;
+        //the parts take over the guard's unlock, but if f panics, release here
+        let release = Release(lock);
+        let (u, v) = f(unsafe { core::ptr::read(&guard.data) });
+        core::mem::forget(release);
+        lock.split.store(true, Ordering::Relaxed);
+        (SplitGuard { lock, data: u }, SplitGuard { lock, data: v })
+    }
+}
+
+impl<'a, A, B> Guard<'a, (A, B)> {
+    /**
+    Splits a guard for a pair into guards for each element, like [Guard::map_split].
+*/
+    #[allow(clippy::type_complexity)]
+    pub fn split(guard: Self) -> (SplitGuard<'a, (A, B), A>, SplitGuard<'a, (A, B), B>) {
+        Guard::map_split(guard, |(a, b)| (a, b))
+    }
+}
+
+struct Release<'a, T>(&'a Lock<T>);
+
+impl<T> Drop for Release<'_, T> {
+    fn drop(&mut self) {
+        self.0.unlock_raw();
+    }
+}
+
+impl<T, U> Drop for SplitGuard<'_, T, U> {
+    fn drop(&mut self) {
+        //the first part dropped clears the flag; the second releases the lock, after the first's writes
+        if !self.lock.split.swap(false, Ordering::AcqRel) {
+            self.lock.unlock_raw();
+        }
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<T, U> Deref for SplitGuard<'_, T, U> {
+    type Target = U;
+    fn deref(&self) -> &U {
+        self.data
+    }
+}
+
+impl<T, U> DerefMut for SplitGuard<'_, T, U> {
+    fn deref_mut(&mut self) -> &mut U {
+        self.data
+    }
+}
+
+impl<T, U: Debug> Debug for SplitGuard<'_, T, U> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("SplitGuard").field(&self.data).finish()
+    }
+}
@@ -0,0 +1,90 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Named, self-registering static locks.
+*/
+
+use core::fmt::Debug;
+use core::ops::Deref;
+#[cfg(feature = "diagnostics")]
+use core::sync::atomic::{AtomicBool, Ordering};
+use crate::Lock;
+
+/**
+A [Lock] declared by [static_spinlock!](crate::static_spinlock).
+
+This dereferences to the underlying [Lock].  With the `diagnostics` feature, the lock is
+registered with `diagnostics::register` the first time it is used.
+*/
+pub struct StaticLock<T> {
+    lock: Lock<T>,
+    #[cfg(feature = "diagnostics")]
+    registered: AtomicBool,
+}
+
+impl<T> StaticLock<T> {
+    /**
+    Wraps a lock for use as a static.
+
+    # Safety
+    The result must be used to initialize a `static`.  Use [static_spinlock!](crate::static_spinlock) instead.
+*/
+    #[doc(hidden)]
+    pub const unsafe fn new_static(lock: Lock<T>) -> Self {
+        StaticLock {
+            lock,
+            #[cfg(feature = "diagnostics")]
+            registered: AtomicBool::new(false),
+        }
+    }
+}
+
+impl<T: Send + 'static> Deref for StaticLock<T> {
+    type Target = Lock<T>;
+    fn deref(&self) -> &Lock<T> {
+        #[cfg(feature = "diagnostics")]
+        if crate::INSTRUMENTED && !self.registered.load(Ordering::Relaxed) && !self.registered.swap(true, Ordering::Relaxed) {
+            //new_static's contract guarantees we live in a static
+            let lock: &'static Lock<T> = unsafe { &*(&self.lock as *const Lock<T>) };
+            crate::diagnostics::register(lock);
+        }
+        &self.lock
+    }
+}
+
+impl<T: Debug> Debug for StaticLock<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.lock.fmt(f)
+    }
+}
+
+/**
+Declares `static` locks, named after their identifiers.
+
+```text
+static_spinlock! {
+    static COUNTER: u64 = 0;
+    pub static QUEUE: Vec<u8> = Vec::new();
+}
+
+*COUNTER.spin_lock() += 1;
+```
+
+Each static is a [StaticLock] of the given type, which dereferences to a [Lock] whose
+[name](Lock::name) is the identifier.  With the `diagnostics` feature, the lock is registered with
+the diagnostics registry the first time it is used, so it shows up in reports without any other
+boilerplate.
+*/
+#[macro_export]
+macro_rules! static_spinlock {
+    ($($(#[$attr:meta])* $vis:vis static $name:ident: $t:ty = $init:expr;)*) => {
+        $(
+            $(#[$attr])*
+            $vis static $name: $crate::StaticLock<$t> = {
+                //outside the unsafe block, so the initializer can't call unsafe code unawares
+                let lock = $crate::Lock::with_name($init, stringify!($name));
+                //the result initializes the static
+                unsafe { $crate::StaticLock::new_static(lock) }
+            };
+        )*
+    };
+}
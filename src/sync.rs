@@ -0,0 +1,211 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+The synchronization state of a [Lock](crate::Lock) and [RwLock](crate::RwLock), swapped for
+[loom](https://crates.io/crates/loom)'s under `cfg(loom)`, or [shuttle](https://crates.io/crates/shuttle)'s
+under `cfg(shuttle)`.
+
+Built with `RUSTFLAGS="--cfg loom"`, a lock's state lives in loom atomics and contended locks yield to
+loom's scheduler, so loom can model-check code that uses the lock, including this crate's own tests in
+`tests/loom.rs`.  The protected data is not tracked by loom; only the lock's ordering is.
+
+`cfg(shuttle)` works the same way, for shuttle's randomized schedulers.  Shuttle's atomics are `const`, so
+nothing else changes; see `tests/shuttle.rs`.
+
+Under loom, the constructors of [Lock](crate::Lock) and the types built on it are not `const`, since loom's
+atomics can't be created in const contexts.  So locks can't be `static`, and the `lock_api` feature is
+unavailable.
+
+The crate's internal bookkeeping (registries, waker lists, and so on) is not modeled.
+
+With the `chaos` feature, the lock is wrapped to add random delays; see [chaos](crate::chaos).
+
+With the `std-mutex` feature, a [Lock](crate::Lock)'s state is a flag behind a `std::sync::Mutex` instead,
+and contended acquisitions sleep on a `std::sync::Condvar` until it's released, so the crate's instrumentation
+can be compared between the spinlock and an OS mutex, without changing the code that uses the locks.  This
+takes precedence over `chaos`, but not over loom or shuttle.
+*/
+
+#[cfg(not(any(loom, shuttle, feature = "chaos", feature = "std-mutex")))]
+pub(crate) use atomiclock::AtomicLock;
+#[cfg(all(feature = "chaos", not(any(loom, shuttle, feature = "std-mutex"))))]
+pub(crate) use crate::chaos::AtomicLock;
+#[cfg(all(feature = "std-mutex", not(any(loom, shuttle))))]
+pub(crate) use self::os::AtomicLock;
+#[cfg(not(any(loom, shuttle)))]
+pub(crate) use core::sync::atomic::AtomicUsize;
+
+#[cfg(loom)]
+use loom::sync::atomic;
+#[cfg(all(shuttle, not(loom)))]
+use shuttle::sync::atomic;
+#[cfg(any(loom, shuttle))]
+pub(crate) use self::modeled::AtomicLock;
+#[cfg(any(loom, shuttle))]
+pub(crate) use atomic::AtomicUsize;
+
+/**
+Waits after a failed attempt to acquire a modeled lock.
+*/
+#[cfg(any(loom, shuttle))]
+pub(crate) fn relax() {
+    #[cfg(loom)]
+    loom::thread::yield_now();
+    #[cfg(all(shuttle, not(loom)))]
+    shuttle::thread::yield_now();
+}
+
+#[cfg(any(loom, shuttle))]
+mod modeled {
+    use core::cell::UnsafeCell;
+    use core::ops::{Deref, DerefMut};
+    use super::atomic::{AtomicBool, Ordering};
+
+    /**
+    [atomiclock::AtomicLock], on a modeled atomic.
+    */
+    pub(crate) struct AtomicLock<T> {
+        lock: AtomicBool,
+        data: UnsafeCell<T>,
+    }
+
+    //as atomiclock
+    unsafe impl<T> Send for AtomicLock<T> {}
+    unsafe impl<T> Sync for AtomicLock<T> {}
+
+    impl<T> AtomicLock<T> {
+        const_fn! {
+            pub(crate) const fn new(data: T) -> Self {
+                AtomicLock { lock: AtomicBool::new(false), data: UnsafeCell::new(data) }
+            }
+        }
+
+        pub(crate) fn lock(&self) -> Option<Guard<'_, T>> {
+            self.lock
+                .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+                .ok()
+                .map(|_| Guard { lock: self, data: unsafe { &mut *self.data.get() } })
+        }
+
+        pub(crate) fn unlock(&self) {
+            let old = self.lock.swap(false, Ordering::Release);
+            assert!(old);
+        }
+    }
+
+    /**
+    [atomiclock::Guard], for the modeled lock.
+    */
+    #[must_use]
+    pub(crate) struct Guard<'a, T> {
+        lock: &'a AtomicLock<T>,
+        data: &'a mut T,
+    }
+
+    impl<T> Deref for Guard<'_, T> {
+        type Target = T;
+        fn deref(&self) -> &T {
+            self.data
+        }
+    }
+
+    impl<T> DerefMut for Guard<'_, T> {
+        fn deref_mut(&mut self) -> &mut T {
+            self.data
+        }
+    }
+
+    impl<T> Drop for Guard<'_, T> {
+        fn drop(&mut self) {
+            self.lock.unlock();
+        }
+    }
+}
+
+#[cfg(all(feature = "std-mutex", not(any(loom, shuttle))))]
+mod os {
+    use core::cell::UnsafeCell;
+    use core::ops::{Deref, DerefMut};
+    use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+
+    /**
+    [atomiclock::AtomicLock], on an OS mutex.
+    */
+    pub(crate) struct AtomicLock<T> {
+        //whether the lock is held
+        locked: Mutex<bool>,
+        unlocked: Condvar,
+        data: UnsafeCell<T>,
+    }
+
+    //as atomiclock
+    unsafe impl<T> Send for AtomicLock<T> {}
+    unsafe impl<T> Sync for AtomicLock<T> {}
+
+    impl<T> AtomicLock<T> {
+        pub(crate) const fn new(data: T) -> Self {
+            AtomicLock { locked: Mutex::new(false), unlocked: Condvar::new(), data: UnsafeCell::new(data) }
+        }
+
+        //the flag's mutex is never held while running outside code, so poisoning is harmless
+        fn locked(&self) -> MutexGuard<'_, bool> {
+            self.locked.lock().unwrap_or_else(PoisonError::into_inner)
+        }
+
+        pub(crate) fn lock(&self) -> Option<Guard<'_, T>> {
+            let mut locked = self.locked();
+            if *locked {
+                return None;
+            }
+            *locked = true;
+            Some(Guard { lock: self, data: unsafe { &mut *self.data.get() } })
+        }
+
+        /**
+        Sleeps until the lock can be acquired.
+        */
+        pub(crate) fn lock_blocking(&self) -> Guard<'_, T> {
+            let mut locked = self.locked();
+            while *locked {
+                locked = self.unlocked.wait(locked).unwrap_or_else(PoisonError::into_inner);
+            }
+            *locked = true;
+            Guard { lock: self, data: unsafe { &mut *self.data.get() } }
+        }
+
+        pub(crate) fn unlock(&self) {
+            let mut locked = self.locked();
+            assert!(*locked);
+            *locked = false;
+            drop(locked);
+            self.unlocked.notify_one();
+        }
+    }
+
+    /**
+    [atomiclock::Guard], for the OS mutex.
+    */
+    #[must_use]
+    pub(crate) struct Guard<'a, T> {
+        lock: &'a AtomicLock<T>,
+        data: &'a mut T,
+    }
+
+    impl<T> Deref for Guard<'_, T> {
+        type Target = T;
+        fn deref(&self) -> &T {
+            self.data
+        }
+    }
+
+    impl<T> DerefMut for Guard<'_, T> {
+        fn deref_mut(&mut self) -> &mut T {
+            self.data
+        }
+    }
+
+    impl<T> Drop for Guard<'_, T> {
+        fn drop(&mut self) {
+            self.lock.unlock();
+        }
+    }
+}
@@ -0,0 +1,260 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+A lock for exercising contention and timeout paths in tests.
+*/
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use crate::{Clock, DynGuard, DynLock, Guard, Lock};
+
+/**
+A [Lock] that can be told to act busy.
+
+Code under test usually sees contention only by luck.  A `TestLock` reports the lock as held for the next
+N attempts to acquire it ([TestLock::busy_for_attempts]) or until a time ([TestLock::busy_for]), so
+contended, timed-out and retry paths run deterministically.  While it acts busy, the lock is not actually
+held; it's free as soon as the busy period ends.
+
+With `std`, a `TestLock` can also follow a script of which thread acquires it next
+([TestLock::follow_schedule]), to turn a reported interleaving into a reproducible test.
+
+Acquiring returns an ordinary [Guard], and `TestLock` implements [DynLock].
+
+```
+# use atomiclock_spinlock::TestLock;
+let lock = TestLock::new(0);
+lock.busy_for_attempts(3);
+assert!(lock.try_lock().is_none());
+//spins through the remaining two busy attempts
+*lock.spin_lock() += 1;
+assert_eq!(lock.attempts(), 4);
+```
+
+Requires the `test-util` feature.
+*/
+#[derive(Debug, Default)]
+pub struct TestLock<T> {
+    lock: Lock<T>,
+    busy_attempts: AtomicUsize,
+    #[cfg(feature = "std")]
+    busy_until: atomiclock::AtomicLock<Option<std::time::Instant>>,
+    //the scripted thread names, and how far through them we are
+    #[cfg(feature = "std")]
+    schedule: atomiclock::AtomicLock<(&'static [&'static str], usize)>,
+    attempts: AtomicUsize,
+}
+
+impl<T> TestLock<T> {
+    const_fn! {
+        /**
+        Creates a new lock, which isn't busy.
+        */
+        pub const fn new(data: T) -> Self {
+            TestLock {
+                lock: Lock::new(data),
+                busy_attempts: AtomicUsize::new(0),
+                #[cfg(feature = "std")]
+                busy_until: atomiclock::AtomicLock::new(None),
+                #[cfg(feature = "std")]
+                schedule: atomiclock::AtomicLock::new((&[], 0)),
+                attempts: AtomicUsize::new(0),
+            }
+        }
+    }
+
+    /**
+    Reports the lock as held for the next `attempts` attempts to acquire it, from any thread.
+
+    A busy attempt counts as one, whether it comes from [TestLock::try_lock] or from one turn of a spin.
+*/
+    pub fn busy_for_attempts(&self, attempts: usize) {
+        self.busy_attempts.store(attempts, Ordering::Relaxed);
+    }
+
+    /**
+    Reports the lock as held until `duration` has passed on the standard library's clock.
+
+    With the `test-clock` feature, this is virtual time, so the busy period can be ended with
+    `clock::manual::advance` instead of waiting.
+*/
+    #[cfg(feature = "std")]
+    pub fn busy_for(&self, duration: std::time::Duration) {
+        let until = crate::clock::StdClock.now() + duration;
+        *crate::spin_raw(&self.busy_until) = Some(until);
+    }
+
+    /**
+    Hands the lock to threads in the order of `names`, then goes back to normal.
+
+    Threads are matched by [name](std::thread::Thread::name).  Until the schedule is done, the lock acts busy
+    for every thread but the next one in it, so the others spin (or time out) until their turn, and
+    unnamed threads, or those not in the schedule, wait until the end.
+
+    ```
+    # use atomiclock_spinlock::TestLock;
+    # use std::thread;
+    let lock = TestLock::new(String::new());
+    lock.follow_schedule(&["a", "c", "b"]);
+    thread::scope(|s| {
+        for name in ["a", "b", "c"] {
+            let lock = &lock;
+            thread::Builder::new().name(name.into()).spawn_scoped(s, move || lock.spin_lock().push_str(name)).unwrap();
+        }
+    });
+    assert_eq!(lock.into_inner(), "acb");
+    ```
+*/
+    #[cfg(feature = "std")]
+    pub fn follow_schedule(&self, names: &'static [&'static str]) {
+        *crate::spin_raw(&self.schedule) = (names, 0);
+    }
+
+    /**
+    Whether every thread in the schedule from [TestLock::follow_schedule] has had its turn.
+*/
+    #[cfg(feature = "std")]
+    pub fn schedule_done(&self) -> bool {
+        let (names, next) = *crate::spin_raw(&self.schedule);
+        next >= names.len()
+    }
+
+    /**
+    Stops acting busy, and abandons any schedule.
+*/
+    pub fn clear_busy(&self) {
+        self.busy_attempts.store(0, Ordering::Relaxed);
+        #[cfg(feature = "std")]
+        {
+            *crate::spin_raw(&self.busy_until) = None;
+            *crate::spin_raw(&self.schedule) = (&[], 0);
+        }
+    }
+
+    /**
+    The number of attempts to acquire the lock so far, busy or not.
+*/
+    pub fn attempts(&self) -> usize {
+        self.attempts.load(Ordering::Relaxed)
+    }
+
+    /**
+    No spin; acquires the lock if it's available and not acting busy.
+*/
+    pub fn try_lock(&self) -> Option<Guard<'_, T>> {
+        self.attempts.fetch_add(1, Ordering::Relaxed);
+        if self.busy() {
+            return None;
+        }
+        #[cfg(feature = "std")]
+        {
+            let mut schedule = crate::spin_raw(&self.schedule);
+            let (names, next) = *schedule;
+            if let Some(&name) = names.get(next) {
+                if std::thread::current().name() != Some(name) {
+                    return None;
+                }
+                let guard = self.lock.try_lock()?;
+                schedule.1 += 1;
+                return Some(guard);
+            }
+        }
+        self.lock.try_lock()
+    }
+
+    /**
+    Spins until the lock is available and done acting busy.
+*/
+    pub fn spin_lock(&self) -> Guard<'_, T> {
+        let mut spins = crate::wait::Spins::new();
+        loop {
+            if let Some(guard) = self.try_lock() {
+                return guard;
+            }
+            crate::wait::relax(&mut spins);
+        }
+    }
+
+    /**
+    Spins until the lock is available and done acting busy, or the clock passes the deadline.
+*/
+    pub fn spin_lock_until_with<C: Clock>(&self, clock: &C, deadline: C::Instant) -> Option<Guard<'_, T>> {
+        let mut spins = crate::wait::Spins::new();
+        loop {
+            if let Some(guard) = self.try_lock() {
+                return Some(guard);
+            }
+            if clock.now() > deadline {
+                return None;
+            }
+            crate::wait::relax(&mut spins);
+        }
+    }
+
+    /**
+    Spins until the lock is available and done acting busy, or the duration elapses on the clock.
+
+    # Panics
+    Panics if the clock's addition does, e.g. on overflow.
+*/
+    pub fn spin_lock_for_with<C: Clock>(&self, clock: &C, duration: C::Duration) -> Option<Guard<'_, T>> {
+        self.spin_lock_until_with(clock, clock.now() + duration)
+    }
+
+    /**
+    Spins until the lock is available and done acting busy, or times out.
+*/
+    #[cfg(feature = "std")]
+    pub fn spin_lock_until(&self, deadline: std::time::Instant) -> Option<Guard<'_, T>> {
+        self.spin_lock_until_with(&crate::clock::StdClock, deadline)
+    }
+
+    /**
+    Spins until the lock is available and done acting busy, or the duration elapses.
+
+    # Panics
+    Panics if the deadline overflows [std::time::Instant].  Use [TestLock::spin_lock_until] to avoid this.
+*/
+    #[cfg(feature = "std")]
+    pub fn spin_lock_for(&self, duration: std::time::Duration) -> Option<Guard<'_, T>> {
+        self.spin_lock_for_with(&crate::clock::StdClock, duration)
+    }
+
+    /**
+    The underlying lock, which doesn't act busy.
+*/
+    pub fn inner(&self) -> &Lock<T> {
+        &self.lock
+    }
+
+    /**
+    Consumes the lock, returning the data.
+*/
+    pub fn into_inner(self) -> T {
+        self.lock.into_inner()
+    }
+
+    //whether this attempt should report the lock as held
+    fn busy(&self) -> bool {
+        if self.busy_attempts.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1)).is_ok() {
+            return true;
+        }
+        #[cfg(feature = "std")]
+        if let Some(until) = *crate::spin_raw(&self.busy_until) {
+            return crate::clock::StdClock.now() < until;
+        }
+        false
+    }
+}
+
+impl<T: Send> DynLock for TestLock<T> {
+    fn lock_dyn(&self) -> DynGuard<'_> {
+        Guard::into_raw(self.spin_lock());
+        unsafe { DynGuard::new(self) }
+    }
+    fn try_lock_dyn(&self) -> Option<DynGuard<'_>> {
+        Guard::into_raw(self.try_lock()?);
+        Some(unsafe { DynGuard::new(self) })
+    }
+    unsafe fn unlock_dyn(&self) {
+        drop(self.lock.guard_from_raw());
+    }
+}
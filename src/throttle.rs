@@ -0,0 +1,107 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Rate-limiting for contention warnings.
+
+A pathological lock can contend millions of times per second.  Warning on each of them would flood
+logwise and become a performance problem in its own right, so we allow one warning per call site per
+[INTERVAL], and count the rest.
+
+Warnings that can't be logged where they happen, since logging allocates, are counted in a fixed table
+instead, without allocating, and logged later.
+*/
+
+use core::panic::Location;
+use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use std::vec::Vec;
+
+const INTERVAL: Duration = Duration::from_secs(1);
+
+struct Site {
+    location: &'static Location<'static>,
+    last_warning: Instant,
+    suppressed: u64,
+}
+
+static SITES: atomiclock::AtomicLock<Vec<Site>> = atomiclock::AtomicLock::new(Vec::new());
+
+/**
+Decides whether the call site may warn now.
+
+Returns the number of warnings suppressed at this call site since it last warned, or `None` if
+this warning should be suppressed too.
+*/
+pub(crate) fn permit(location: &'static Location<'static>) -> Option<u64> {
+    let now = Instant::now();
+    let mut sites = crate::spin_raw(&SITES);
+    match sites.iter_mut().find(|s| s.location == location) {
+        None => {
+            sites.push(Site { location, last_warning: now, suppressed: 0 });
+            Some(0)
+        }
+        Some(site) => {
+            if now.duration_since(site.last_warning) >= INTERVAL {
+                site.last_warning = now;
+                Some(core::mem::take(&mut site.suppressed))
+            } else {
+                site.suppressed += 1;
+                None
+            }
+        }
+    }
+}
+
+//call sites counted in the deferred table; any more are only counted in DEFERRED_ELSEWHERE
+const DEFERRED_SITES: usize = 32;
+
+struct Deferred {
+    location: AtomicPtr<Location<'static>>,
+    count: AtomicUsize,
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY: Deferred = Deferred { location: AtomicPtr::new(core::ptr::null_mut()), count: AtomicUsize::new(0) };
+static DEFERRED: [Deferred; DEFERRED_SITES] = [EMPTY; DEFERRED_SITES];
+static DEFERRED_ELSEWHERE: AtomicUsize = AtomicUsize::new(0);
+
+/**
+Counts a warning at the call site, to log later, without allocating or locking.
+
+Slots are claimed for good, so the table holds the first [DEFERRED_SITES] call sites to warn.
+*/
+pub(crate) fn defer(location: &'static Location<'static>) {
+    let wanted = location as *const Location<'static> as *mut Location<'static>;
+    for slot in &DEFERRED {
+        let claimed = match slot.location.compare_exchange(core::ptr::null_mut(), wanted, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => wanted,
+            Err(claimed) => claimed,
+        };
+        //the same call site can have more than one Location, so compare them, not the pointers
+        if unsafe { &*claimed } == location {
+            slot.count.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+    }
+    DEFERRED_ELSEWHERE.fetch_add(1, Ordering::Relaxed);
+}
+
+/**
+Takes the deferred warnings, calling `f` with each call site that warned and how many times, and then with
+`None` for the call sites that didn't fit in the table.
+*/
+pub(crate) fn take_deferred(mut f: impl FnMut(Option<&'static Location<'static>>, usize)) {
+    for slot in &DEFERRED {
+        let location = slot.location.load(Ordering::Acquire);
+        if location.is_null() {
+            break;
+        }
+        let count = slot.count.swap(0, Ordering::Relaxed);
+        if count > 0 {
+            f(Some(unsafe { &*location }), count);
+        }
+    }
+    let elsewhere = DEFERRED_ELSEWHERE.swap(0, Ordering::Relaxed);
+    if elsewhere > 0 {
+        f(None, elsewhere);
+    }
+}
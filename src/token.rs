@@ -0,0 +1,109 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Releasing a lock, and taking it again, in a loop.
+*/
+
+use core::fmt::Debug;
+use crate::{Guard, Lock};
+
+/**
+A released [Guard], which remembers its lock so it can take it again with [LockToken::relock].
+
+For loops that do a little work under the lock, then release it so others can get a turn:
+
+```
+# use atomiclock_spinlock::{Guard, Lock};
+let queue = Lock::new(vec![1, 2, 3]);
+let mut token = Guard::unlock_to_token(queue.spin_lock());
+let mut sum = 0;
+loop {
+    let next = token.relock().pop();
+    let Some(n) = next else { break };
+    //the lock is free while we work on n
+    sum += n;
+}
+assert_eq!(sum, 6);
+assert_eq!(token.relocks(), 4);
+```
+
+The token also counts its relocks, and how many of them found the lock held, to tell whether releasing
+in between is worth it.
+*/
+#[must_use]
+pub struct LockToken<'a, T> {
+    lock: &'a Lock<T>,
+    relocks: u64,
+    contended: u64,
+}
+
+impl<'a, T> Guard<'a, T> {
+    /**
+    Releases the lock, returning a token to take it again with.
+
+    This is an associated function, since the guard dereferences to the data; call it as
+    `Guard::unlock_to_token(guard)`.
+*/
+    pub fn unlock_to_token(guard: Self) -> LockToken<'a, T> {
+        let lock = guard.lock;
+        drop(guard);
+        LockToken { lock, relocks: 0, contended: 0 }
+    }
+}
+
+impl<'a, T> LockToken<'a, T> {
+    /**
+    Spins until the lock can be acquired again.
+
+    The token can be used again once the guard is dropped.  Relocking while the guard is alive spins
+    forever, like any other re-entrant acquisition.
+*/
+    pub fn relock(&mut self) -> Guard<'a, T> {
+        self.relocks += 1;
+        if let Some(guard) = self.lock.try_lock() {
+            return guard;
+        }
+        self.contended += 1;
+        self.lock.spin_lock()
+    }
+
+    /**
+    The lock this token takes.
+*/
+    pub fn lock(&self) -> &'a Lock<T> {
+        self.lock
+    }
+
+    /**
+    How many times [LockToken::relock] was called.
+*/
+    pub fn relocks(&self) -> u64 {
+        self.relocks
+    }
+
+    /**
+    How many calls to [LockToken::relock] found the lock held, and had to spin.
+*/
+    pub fn contended(&self) -> u64 {
+        self.contended
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<T> Debug for LockToken<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("LockToken")
+            .field("name", &self.lock.name())
+            .field("relocks", &self.relocks)
+            .field("contended", &self.contended)
+            .finish()
+    }
+}
+
+impl<'a, T> From<&'a Lock<T>> for LockToken<'a, T> {
+    fn from(lock: &'a Lock<T>) -> Self {
+        LockToken { lock, relocks: 0, contended: 0 }
+    }
+}
@@ -0,0 +1,146 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Rolling back a guard's data when a multi-step change fails, and committing changes made to a copy.
+*/
+
+use core::fmt::Debug;
+use core::ops::{Deref, DerefMut};
+use crate::Guard;
+
+//restores the snapshot when dropped, unless the change was committed
+struct Rollback<'a, T> {
+    data: &'a mut T,
+    snapshot: Option<T>,
+}
+
+impl<T> Drop for Rollback<'_, T> {
+    fn drop(&mut self) {
+        if let Some(snapshot) = self.snapshot.take() {
+            *self.data = snapshot;
+        }
+    }
+}
+
+impl<T: Clone> Guard<'_, T> {
+    /**
+    Runs `f` on the data, restoring a snapshot taken beforehand if `f` returns `Err` or panics.
+
+    This gives a change of several steps all-or-nothing semantics, without undoing each step by hand.
+    The snapshot is a clone of the data, so this suits small values, or ones that are cheap to clone.
+
+    ```
+    # use atomiclock_spinlock::{Guard, Lock};
+    let accounts = Lock::new([100u32, 0]);
+    let mut guard = accounts.spin_lock();
+    let moved: Result<(), &str> = Guard::transaction(&mut guard, |accounts| {
+        accounts[1] += 150;
+        accounts[0] = accounts[0].checked_sub(150).ok_or("insufficient funds")?;
+        Ok(())
+    });
+    assert!(moved.is_err());
+    assert_eq!(*guard, [100, 0]);
+    ```
+
+    The lock stays held throughout.  If `f` panics, the data is restored while unwinding, before the
+    guard is dropped.
+
+    This is an associated function, since the guard dereferences to the data; call it as
+    `Guard::transaction(&mut guard, f)`.
+*/
+    pub fn transaction<R, E>(guard: &mut Self, f: impl FnOnce(&mut T) -> Result<R, E>) -> Result<R, E> {
+        let snapshot = Some(guard.data.clone());
+        let mut rollback = Rollback { data: &mut *guard.data, snapshot };
+        let result = f(rollback.data);
+        if result.is_ok() {
+            rollback.snapshot = None;
+        }
+        result
+    }
+}
+
+/**
+A held lock, with a working copy of its data to change.  The changes take effect only on
+[CommitGuard::commit]; dropping the guard discards them.  Create one with [Guard::working_copy].
+*/
+#[must_use]
+pub struct CommitGuard<'a, T> {
+    guard: Guard<'a, T>,
+    copy: T,
+}
+
+impl<'a, T: Clone> Guard<'a, T> {
+    /**
+    Starts a working copy of the data, for changes that are checked before they're applied.
+
+    ```
+    # use atomiclock_spinlock::{CommitGuard, Guard, Lock};
+    let lock = Lock::new(vec![1, 2]);
+    let mut copy = Guard::working_copy(lock.spin_lock());
+    copy.push(3);
+    let committed = CommitGuard::try_commit(copy, |v| if v.len() <= 2 { Ok(()) } else { Err("too long") });
+    assert_eq!(committed, Err("too long"));
+    assert_eq!(*lock.spin_lock(), [1, 2]);
+    ```
+
+    Unlike [Guard::transaction], the original stays untouched until the commit, so it can be compared
+    with the copy, with [CommitGuard::original].
+
+    This is an associated function, since the guard dereferences to the data; call it as
+    `Guard::working_copy(guard)`.
+*/
+    pub fn working_copy(guard: Self) -> CommitGuard<'a, T> {
+        let copy = guard.data.clone();
+        CommitGuard { guard, copy }
+    }
+}
+
+impl<'a, T> CommitGuard<'a, T> {
+    /**
+    Applies the working copy to the data, returning the guard, which still holds the lock.
+*/
+    pub fn commit(this: Self) -> Guard<'a, T> {
+        let CommitGuard { mut guard, copy } = this;
+        *guard = copy;
+        guard
+    }
+
+    /**
+    Applies the working copy if `validate` accepts it, and discards it otherwise.  Either way, the lock is
+    released.
+*/
+    pub fn try_commit<E>(this: Self, validate: impl FnOnce(&T) -> Result<(), E>) -> Result<(), E> {
+        validate(&this.copy)?;
+        drop(CommitGuard::commit(this));
+        Ok(())
+    }
+
+    /**
+    The data as it was, without the changes to the working copy.
+*/
+    pub fn original(this: &Self) -> &T {
+        &this.guard
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<T> Deref for CommitGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.copy
+    }
+}
+
+impl<T> DerefMut for CommitGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.copy
+    }
+}
+
+impl<T: Debug> Debug for CommitGuard<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("CommitGuard").field("original", &*self.guard).field("copy", &self.copy).finish()
+    }
+}
@@ -0,0 +1,46 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+[ThreadSanitizer](https://doc.rust-lang.org/beta/unstable-book/compiler-flags/sanitizer.html#threadsanitizer)
+annotations.
+
+TSan follows the locks' atomics, but not the standalone fences the crate uses to pair releases with
+waiters, so the edges it infers depend on the details of each path.  Built with `--cfg tsan`, every
+acquisition and release also tells TSan directly that it synchronizes on the lock's address, so code
+using the locks gets the same happens-before edges as with a lock TSan knows natively:
+
+```text
+RUSTFLAGS="-Zsanitizer=thread --cfg tsan" cargo +nightly test -Zbuild-std --target x86_64-unknown-linux-gnu
+```
+
+Without `--cfg tsan` these are no-ops.
+*/
+
+#[cfg(tsan)]
+extern "C" {
+    fn __tsan_acquire(addr: *mut core::ffi::c_void);
+    fn __tsan_release(addr: *mut core::ffi::c_void);
+}
+
+/**
+Tells TSan that the current thread acquired the lock at `addr`.
+*/
+#[inline(always)]
+pub(crate) fn acquire<T: ?Sized>(addr: *const T) {
+    #[cfg(tsan)]
+    unsafe {
+        __tsan_acquire(addr as *const () as *mut core::ffi::c_void)
+    };
+    let _ = addr;
+}
+
+/**
+Tells TSan that the current thread is about to release the lock at `addr`.
+*/
+#[inline(always)]
+pub(crate) fn release<T: ?Sized>(addr: *const T) {
+    #[cfg(tsan)]
+    unsafe {
+        __tsan_release(addr as *const () as *mut core::ffi::c_void)
+    };
+    let _ = addr;
+}
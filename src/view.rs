@@ -0,0 +1,278 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Handing out the lock on part of the data: guards narrowed to a field, and views that only acquire those.
+*/
+
+use core::fmt::Debug;
+use core::ops::{Deref, DerefMut};
+use crate::raw::{DefaultRawLock, RawLock};
+use crate::{Clock, Guard, Lock};
+
+/**
+A guard for part of a lock's data, from [Guard::map] or a [LockView].  The lock is released when it's
+dropped.
+*/
+#[must_use]
+pub struct MappedGuard<'a, T, U: ?Sized, R: RawLock = DefaultRawLock> {
+    lock: &'a Lock<T, R>,
+    data: &'a mut U,
+    #[cfg(feature = "poison")]
+    defused: bool,
+}
+
+impl<'a, T, R: RawLock> Guard<'a, T, R> {
+    /**
+    Narrows the guard to the part of the data chosen by `f`, such as a field, keeping the lock held.
+
+    ```
+    # use atomiclock_spinlock::{Guard, Lock};
+    let lock = Lock::new((0u32, vec![1, 2]));
+    let mut list = Guard::map(lock.spin_lock(), |(_, list)| list);
+    list.push(3);
+    drop(list);
+    assert_eq!(lock.spin_lock().1, [1, 2, 3]);
+    ```
+
+    This is an associated function, since the guard dereferences to the data; call it as
+    `Guard::map(guard, f)`.
+*/
+    pub fn map<U: ?Sized>(guard: Self, f: impl FnOnce(&mut T) -> &mut U) -> MappedGuard<'a, T, U, R> {
+        let mut guard = core::mem::ManuallyDrop::new(guard);
+        let lock = guard.lock;
+        #[cfg(feature = "poison")]
+        let defused = guard.defused;
+        let data: *mut T = &mut *guard.data;
+        //the part outlives the forgotten guard, so if f panics, release here
+        let release = Release(lock);
+        let data = f(unsafe { &mut *data });
+        core::mem::forget(release);
+        MappedGuard {
+            lock,
+            data,
+            #[cfg(feature = "poison")]
+            defused,
+        }
+    }
+}
+
+impl<'a, T, U: ?Sized, R: RawLock> MappedGuard<'a, T, U, R> {
+    /**
+    Narrows the guard further, like [Guard::map].
+*/
+    pub fn map<V: ?Sized>(guard: Self, f: impl FnOnce(&mut U) -> &mut V) -> MappedGuard<'a, T, V, R> {
+        let mut guard = core::mem::ManuallyDrop::new(guard);
+        let lock = guard.lock;
+        #[cfg(feature = "poison")]
+        let defused = guard.defused;
+        let data: *mut U = &mut *guard.data;
+        let release = Release(lock);
+        let data = f(unsafe { &mut *data });
+        core::mem::forget(release);
+        MappedGuard {
+            lock,
+            data,
+            #[cfg(feature = "poison")]
+            defused,
+        }
+    }
+}
+
+struct Release<'a, T, R: RawLock>(&'a Lock<T, R>);
+
+impl<T, R: RawLock> Drop for Release<'_, T, R> {
+    fn drop(&mut self) {
+        self.0.unlock_raw();
+    }
+}
+
+impl<T, U: ?Sized, R: RawLock> Drop for MappedGuard<'_, T, U, R> {
+    fn drop(&mut self) {
+        #[cfg(feature = "poison")]
+        if self.defused {
+            self.lock.release(true);
+            return;
+        }
+        self.lock.unlock_raw();
+    }
+}
+
+/**
+The lock on one part of a [Lock]'s data, from [Lock::view], for handing a subsystem the lock on just the
+part it works on.
+
+A view acquires the whole lock, the same ways the lock does, but its guards are [MappedGuard]s for the
+part:
+
+```
+# use atomiclock_spinlock::{Lock, LockView};
+struct State { stats: Stats, queue: Vec<u32> }
+struct Stats { served: u64 }
+
+//the stats code sees the stats, and nothing else of the state
+fn record(stats: LockView<'_, State, Stats>) {
+    stats.spin_lock().served += 1;
+}
+
+let lock = Lock::new(State { stats: Stats { served: 0 }, queue: vec![1] });
+record(lock.view(|state| &mut state.stats));
+assert_eq!(lock.spin_lock().stats.served, 1);
+```
+
+The part is chosen by a function, rather than a closure, so views are `Copy` and can be handed out freely.
+Acquiring through a view contends with the other views and with the lock itself, as they all hold one lock.
+*/
+pub struct LockView<'a, T, U: ?Sized, R: RawLock = DefaultRawLock> {
+    lock: &'a Lock<T, R>,
+    project: fn(&mut T) -> &mut U,
+}
+
+impl<T, R: RawLock> Lock<T, R> {
+    /**
+    A view of the lock whose guards are for the part of the data chosen by `project`, such as a field.
+*/
+    pub fn view<U: ?Sized>(&self, project: fn(&mut T) -> &mut U) -> LockView<'_, T, U, R> {
+        LockView { lock: self, project }
+    }
+}
+
+impl<'a, T, U: ?Sized, R: RawLock> LockView<'a, T, U, R> {
+    fn map(&self, guard: Guard<'a, T, R>) -> MappedGuard<'a, T, U, R> {
+        Guard::map(guard, self.project)
+    }
+
+    /**
+    The name of the lock, if any.
+*/
+    pub const fn name(&self) -> Option<&'static str> {
+        self.lock.name()
+    }
+
+    /**
+    Whether the lock is currently held, as [Lock::is_locked].
+*/
+    pub fn is_locked(&self) -> bool {
+        self.lock.is_locked()
+    }
+
+    /**
+    Spins until the lock can be acquired, as [Lock::spin_lock].
+*/
+    #[cfg_attr(feature = "owner-tracking", track_caller)]
+    pub fn spin_lock(&self) -> MappedGuard<'a, T, U, R> {
+        self.map(self.lock.spin_lock())
+    }
+
+    /**
+    Spins until the lock can be acquired, or returns an error if it never could be, as
+    [Lock::spin_lock_checked].
+*/
+    #[cfg_attr(feature = "owner-tracking", track_caller)]
+    pub fn spin_lock_checked(&self) -> Result<MappedGuard<'a, T, U, R>, crate::WouldDeadlock> {
+        self.lock.spin_lock_checked().map(|guard| self.map(guard))
+    }
+
+    /**
+    Spins until the lock can be acquired, warning about contention, as [Lock::spin_lock_warn].
+*/
+    #[cfg_attr(feature = "owner-tracking", track_caller)]
+    pub fn spin_lock_warn(&self) -> MappedGuard<'a, T, U, R> {
+        self.map(self.lock.spin_lock_warn())
+    }
+
+    /**
+    No spin; acquires the lock if available, as [Lock::try_lock].
+*/
+    #[cfg_attr(feature = "owner-tracking", track_caller)]
+    pub fn try_lock(&self) -> Option<MappedGuard<'a, T, U, R>> {
+        self.lock.try_lock().map(|guard| self.map(guard))
+    }
+
+    /**
+    Spins until the lock is available, or times out, as [Lock::spin_lock_until].
+*/
+    #[cfg(feature = "std")]
+    #[cfg_attr(feature = "owner-tracking", track_caller)]
+    pub fn spin_lock_until(&self, deadline: std::time::Instant) -> Option<MappedGuard<'a, T, U, R>> {
+        self.lock.spin_lock_until(deadline).map(|guard| self.map(guard))
+    }
+
+    /**
+    Spins until the lock is available, or the duration elapses, as [Lock::spin_lock_for].
+
+    # Panics
+    Panics if the deadline overflows [std::time::Instant].
+*/
+    #[cfg(feature = "std")]
+    #[cfg_attr(feature = "owner-tracking", track_caller)]
+    pub fn spin_lock_for(&self, duration: std::time::Duration) -> Option<MappedGuard<'a, T, U, R>> {
+        self.lock.spin_lock_for(duration).map(|guard| self.map(guard))
+    }
+
+    /**
+    Spins until the lock is available, or the clock passes the deadline, as [Lock::spin_lock_until_with].
+*/
+    #[cfg_attr(feature = "owner-tracking", track_caller)]
+    pub fn spin_lock_until_with<C: Clock>(&self, clock: &C, deadline: C::Instant) -> Option<MappedGuard<'a, T, U, R>> {
+        self.lock.spin_lock_until_with(clock, deadline).map(|guard| self.map(guard))
+    }
+
+    /**
+    Spins until the lock is available, or the duration elapses on the clock, as [Lock::spin_lock_for_with].
+
+    # Panics
+    Panics if the clock's addition does, e.g. on overflow.
+*/
+    #[cfg_attr(feature = "owner-tracking", track_caller)]
+    pub fn spin_lock_for_with<C: Clock>(&self, clock: &C, duration: C::Duration) -> Option<MappedGuard<'a, T, U, R>> {
+        self.lock.spin_lock_for_with(clock, duration).map(|guard| self.map(guard))
+    }
+}
+
+/*
+boilerplate
+ */
+
+impl<T, U: ?Sized, R: RawLock> Clone for LockView<'_, T, U, R> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, U: ?Sized, R: RawLock> Copy for LockView<'_, T, U, R> {}
+
+impl<T, U: ?Sized, R: RawLock> Debug for LockView<'_, T, U, R> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("LockView").field("name", &self.lock.name()).field("locked", &self.is_locked()).finish()
+    }
+}
+
+impl<T, U: ?Sized, R: RawLock> Deref for MappedGuard<'_, T, U, R> {
+    type Target = U;
+    fn deref(&self) -> &U {
+        self.data
+    }
+}
+
+impl<T, U: ?Sized, R: RawLock> DerefMut for MappedGuard<'_, T, U, R> {
+    fn deref_mut(&mut self) -> &mut U {
+        self.data
+    }
+}
+
+impl<T, U: ?Sized, R: RawLock> AsRef<U> for MappedGuard<'_, T, U, R> {
+    fn as_ref(&self) -> &U {
+        self
+    }
+}
+
+impl<T, U: ?Sized, R: RawLock> AsMut<U> for MappedGuard<'_, T, U, R> {
+    fn as_mut(&mut self) -> &mut U {
+        self
+    }
+}
+
+impl<T, U: ?Sized + Debug, R: RawLock> Debug for MappedGuard<'_, T, U, R> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MappedGuard").field("name", &self.lock.name()).field("data", &self.data).finish()
+    }
+}
@@ -0,0 +1,355 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Waiting on a contended lock, for targets that can do better than spinning.
+
+With the `wasm-wait` feature on wasm with threads (the `atomics` target feature), after spinning briefly,
+waiters block in `memory.atomic.wait32` until a release notifies them.  Pure spinning in a browser
+worker is both slow and battery-hostile.
+
+Note that browsers don't allow the main thread to wait, so with this feature, a lock must not be
+contended on the main thread.
+
+With the `rayon` feature, a waiter on a rayon worker thread runs other pending rayon jobs between attempts,
+from its own deque first and then by stealing, instead of spinning.  So a contended lock in data-parallel code
+doesn't stall the workers that would otherwise be making progress.  Beware that those jobs run on the waiting
+thread, so a job must not need a lock that the thread already holds.
+
+With the `park` feature, after the spin phase ([Settings::spins_before_yield] waits), waiters park through
+[parking_lot_core](https://crates.io/crates/parking_lot_core), keyed on the lock's address, and each release
+that has waiters unparks one.  This makes a hybrid lock, which stops burning a core once it's clear the
+holder will be a while.  Low-latency waiters never park.
+
+Waiters that may give up, with a deadline or a spin budget, never block in either way, since they have to
+keep checking whether to.
+
+Everywhere else, a [Parker] is zero-sized and waiting is just [relax](crate::arch::relax).
+*/
+
+#[cfg(all(feature = "wasm-wait", target_arch = "wasm32", target_feature = "atomics"))]
+use core::sync::atomic::{fence, AtomicU32, Ordering};
+use crate::config::Settings;
+use crate::sync::AtomicUsize;
+
+/**
+How many times to spin before waiting.
+*/
+#[cfg(all(feature = "wasm-wait", target_arch = "wasm32", target_feature = "atomics"))]
+const SPINS: u32 = 100;
+
+/**
+Runs one pending rayon job, if we're on a worker thread and there is one.  Returns whether a job was run.
+*/
+#[cfg(all(feature = "rayon", not(feature = "crossbeam")))]
+fn help_rayon() -> bool {
+    match rayon::yield_local() {
+        Some(rayon::Yield::Executed) => true,
+        Some(rayon::Yield::Idle) => rayon::yield_now() == Some(rayon::Yield::Executed),
+        None => false,
+    }
+}
+
+/**
+The state of one waiter's backoff: how many times it has waited, and with `crossbeam`, crossbeam's escalation.
+*/
+#[derive(Debug, Default)]
+pub(crate) struct Spins {
+    count: u32,
+    //created on the first wait, since Backoff::new isn't const
+    #[cfg(all(feature = "crossbeam", not(any(loom, shuttle))))]
+    backoff: Option<crossbeam_utils::Backoff>,
+}
+
+impl Spins {
+    pub(crate) const fn new() -> Spins {
+        Spins {
+            count: 0,
+            #[cfg(all(feature = "crossbeam", not(any(loom, shuttle))))]
+            backoff: None,
+        }
+    }
+
+    /**
+    The number of waits so far.  Saturates at `u32::MAX`.
+    */
+    pub(crate) fn count(&self) -> u32 {
+        self.count
+    }
+}
+
+impl Clone for Spins {
+    //crossbeam's Backoff can't be cloned, so a clone starts its escalation over
+    fn clone(&self) -> Spins {
+        Spins {
+            count: self.count,
+            #[cfg(all(feature = "crossbeam", not(any(loom, shuttle))))]
+            backoff: None,
+        }
+    }
+}
+
+/**
+Waits briefly after a failed attempt, for locks without a [Parker].
+*/
+#[inline]
+pub(crate) fn relax(spins: &mut Spins) {
+    relax_with(spins, &crate::config::defaults());
+}
+
+/**
+Like [relax], with the settings of a particular lock.
+*/
+#[inline]
+pub(crate) fn relax_with(spins: &mut Spins, settings: &Settings) {
+    let _ = settings;
+    spins.count = spins.count.saturating_add(1);
+    #[cfg(any(loom, shuttle))]
+    crate::sync::relax();
+    //crossbeam's escalation replaces ours, except that low-latency waiters never yield
+    #[cfg(all(feature = "crossbeam", not(any(loom, shuttle))))]
+    {
+        let backoff = spins.backoff.get_or_insert_with(crossbeam_utils::Backoff::new);
+        if settings.low_latency {
+            backoff.spin();
+        } else {
+            backoff.snooze();
+        }
+    }
+    #[cfg(not(any(feature = "crossbeam", loom, shuttle)))]
+    {
+        #[cfg(feature = "rayon")]
+        if !settings.low_latency && spins.count >= settings.spins_before_yield && help_rayon() {
+            return;
+        }
+        for _ in 0..hints(spins.count, settings) {
+            crate::arch::relax();
+        }
+    }
+}
+
+/**
+How many relax instructions the wait numbered `count` issues, counting from 1: doubling with each wait, up to
+the cap.
+*/
+#[inline]
+fn hints(count: u32, settings: &Settings) -> u32 {
+    1u32.checked_shl(count.saturating_sub(1)).unwrap_or(u32::MAX).min(settings.backoff_cap).max(1)
+}
+
+/**
+About how many relax instructions a waiter with a deadline issues between reads of its clock.
+*/
+pub(crate) const RELAXES_PER_CLOCK_READ: u32 = 64;
+
+/**
+Decides when a waiter with a deadline reads its clock.
+
+Reading the clock can cost more than an attempt and a wait put together, so rather than on every attempt, it's
+read on the first, and then once the waits since the last read have issued about [RELAXES_PER_CLOCK_READ] relax
+instructions.  So early on, while waits are short, that's every few attempts, and once they reach the backoff
+cap, every `RELAXES_PER_CLOCK_READ / backoff_cap` attempts, or on every attempt with a large cap.
+
+Where a wait may yield the thread, which takes an unknown time, the clock is read on every attempt.
+*/
+#[derive(Debug)]
+pub(crate) struct ClockReads {
+    waits: u32,
+    //relax instructions since the last read
+    relaxes: u32,
+}
+
+impl ClockReads {
+    pub(crate) const fn new() -> ClockReads {
+        ClockReads { waits: 0, relaxes: RELAXES_PER_CLOCK_READ }
+    }
+
+    /**
+    Whether to read the clock before the next wait.
+    */
+    #[inline]
+    pub(crate) fn due(&mut self, settings: &Settings) -> bool {
+        if cfg!(any(feature = "crossbeam", feature = "rayon", feature = "chaos", loom, shuttle)) {
+            return true;
+        }
+        let due = self.relaxes >= RELAXES_PER_CLOCK_READ;
+        if due {
+            self.relaxes = 0;
+        }
+        self.waits = self.waits.saturating_add(1);
+        self.relaxes = self.relaxes.saturating_add(hints(self.waits, settings));
+        due
+    }
+}
+
+/**
+The backoff the locks use between failed attempts, for retry loops of your own.
+
+```
+# use atomiclock_spinlock::SpinWait;
+# use core::sync::atomic::{AtomicU32, Ordering};
+let flag = AtomicU32::new(1);
+let mut wait = SpinWait::new();
+while flag.compare_exchange_weak(1, 2, Ordering::Acquire, Ordering::Relaxed).is_err() {
+    wait.spin();
+}
+```
+
+Each [SpinWait::spin] issues the target's spin-loop hint, backing off as configured in [config](crate::config).
+After a few spins, with the `rayon` feature, a
+waiter on a rayon worker thread runs other pending rayon jobs instead, so those jobs
+must not need anything the waiting thread holds.  Under loom and shuttle, it yields to the model's scheduler, so retry loops built on it can
+be model-checked.
+
+With the `crossbeam` feature, it follows crossbeam's `Backoff` escalation instead, spinning and
+then yielding the thread, like the locks do.  A clone starts that escalation over.
+
+Unlike the locks, it never waits with `wasm-wait`: there's no release to wake it.
+*/
+#[derive(Debug, Default, Clone)]
+pub struct SpinWait {
+    spins: Spins,
+}
+
+impl SpinWait {
+    /**
+    Creates a backoff that hasn't spun yet.
+    */
+    pub const fn new() -> SpinWait {
+        SpinWait { spins: Spins::new() }
+    }
+
+    /**
+    Waits briefly after a failed attempt, escalating with the number of spins so far.
+    */
+    #[inline]
+    pub fn spin(&mut self) {
+        relax(&mut self.spins);
+    }
+
+    /**
+    Starts over, as after a successful attempt.
+    */
+    pub fn reset(&mut self) {
+        self.spins = Spins::new();
+    }
+
+    /**
+    The number of spins since the backoff was created or reset.  Saturates at `u32::MAX`.
+    */
+    pub fn spins(&self) -> u32 {
+        self.spins.count()
+    }
+}
+
+/**
+Per-lock state for waiting.
+*/
+#[derive(Debug)]
+pub(crate) struct Parker {
+    //incremented by each release that has waiters
+    #[cfg(all(feature = "wasm-wait", target_arch = "wasm32", target_feature = "atomics"))]
+    epoch: AtomicU32,
+    //likewise, for waiters parked in parking_lot_core
+    #[cfg(all(feature = "park", not(any(loom, shuttle))))]
+    park_epoch: core::sync::atomic::AtomicU32,
+}
+
+/**
+A snapshot of the [Parker], taken before an attempt to acquire.
+
+If the attempt fails, waiting on the token returns as soon as any release happened after the snapshot,
+so no wakeup is lost.
+*/
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Token {
+    #[cfg(all(feature = "wasm-wait", target_arch = "wasm32", target_feature = "atomics"))]
+    epoch: u32,
+    #[cfg(all(feature = "park", not(any(loom, shuttle))))]
+    park_epoch: u32,
+}
+
+impl Parker {
+    pub(crate) const fn new() -> Parker {
+        Parker {
+            #[cfg(all(feature = "wasm-wait", target_arch = "wasm32", target_feature = "atomics"))]
+            epoch: AtomicU32::new(0),
+            #[cfg(all(feature = "park", not(any(loom, shuttle))))]
+            park_epoch: core::sync::atomic::AtomicU32::new(0),
+        }
+    }
+
+    /**
+    Takes a snapshot before an attempt to acquire.
+
+    The caller must already be counted in the lock's waiters.
+    */
+    #[inline]
+    pub(crate) fn token(&self) -> Token {
+        //pairs with the fence in `unpark`: either the release sees us waiting, or we see the release
+        #[cfg(any(all(feature = "wasm-wait", target_arch = "wasm32", target_feature = "atomics"), all(feature = "park", not(any(loom, shuttle)))))]
+        core::sync::atomic::fence(core::sync::atomic::Ordering::SeqCst);
+        Token {
+            #[cfg(all(feature = "wasm-wait", target_arch = "wasm32", target_feature = "atomics"))]
+            epoch: self.epoch.load(Ordering::Acquire),
+            #[cfg(all(feature = "park", not(any(loom, shuttle))))]
+            park_epoch: self.park_epoch.load(core::sync::atomic::Ordering::Acquire),
+        }
+    }
+
+    /**
+    Waits after a failed attempt to acquire.  `spins` counts the waits so far.
+
+    Only blocks the thread if `block`, since a waiter that may give up has to keep checking whether to.
+    */
+    #[inline]
+    pub(crate) fn wait(&self, token: Token, spins: &mut Spins, settings: &Settings, block: bool) {
+        let _ = block;
+        #[cfg(all(feature = "wasm-wait", target_arch = "wasm32", target_feature = "atomics"))]
+        if block && !settings.low_latency && spins.count() >= SPINS {
+            unsafe {
+                core::arch::wasm32::memory_atomic_wait32(self.epoch.as_ptr() as *mut i32, token.epoch as i32, -1);
+            }
+            return;
+        }
+        #[cfg(all(feature = "park", not(any(loom, shuttle))))]
+        if block && !settings.low_latency && spins.count() >= settings.spins_before_yield {
+            let epoch = &self.park_epoch;
+            //parks unless a release came after the token; unpark bumps the epoch before taking the same queue lock
+            unsafe {
+                parking_lot_core::park(crate::addr(self), || epoch.load(core::sync::atomic::Ordering::Acquire) == token.park_epoch,
+                                       || {}, |_, _| {}, parking_lot_core::DEFAULT_PARK_TOKEN, None);
+            }
+            return;
+        }
+        let _ = token;
+        relax_with(spins, settings);
+    }
+
+    /**
+    Wakes a waiter, if there are any, after the lock was released.
+    */
+    #[inline]
+    pub(crate) fn unpark(&self, waiters: &AtomicUsize) {
+        #[cfg(all(feature = "wasm-wait", target_arch = "wasm32", target_feature = "atomics"))]
+        {
+            fence(Ordering::SeqCst);
+            if waiters.load(Ordering::Relaxed) > 0 {
+                self.epoch.fetch_add(1, Ordering::Release);
+                unsafe {
+                    core::arch::wasm32::memory_atomic_notify(self.epoch.as_ptr() as *mut i32, 1);
+                }
+            }
+        }
+        #[cfg(all(feature = "park", not(any(loom, shuttle))))]
+        {
+            use core::sync::atomic::{fence, Ordering};
+            fence(Ordering::SeqCst);
+            if waiters.load(Ordering::Relaxed) > 0 {
+                self.park_epoch.fetch_add(1, Ordering::Release);
+                unsafe {
+                    parking_lot_core::unpark_one(crate::addr(self), |_| parking_lot_core::DEFAULT_UNPARK_TOKEN);
+                }
+            }
+        }
+        let _ = waiters;
+    }
+}
@@ -0,0 +1,270 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Per-lock lists of async waiters, so releasing a lock can wake them.
+
+The list is intrusive: each waiting future embeds a [Node], and the lock links the nodes together.
+So registering a waiter doesn't allocate, and works without `alloc`.
+
+There are two lists.  Futures waiting to acquire the lock are on the `lock` list, and one of them is woken
+per release.  Futures waiting for a condition on the data ([Lock::wait_async_until](crate::Lock::wait_async_until))
+are on the `condition` list, and all of them are woken whenever the data may have changed.
+*/
+
+use core::cell::UnsafeCell;
+use core::marker::PhantomPinned;
+use core::ptr;
+use core::sync::atomic::{fence, AtomicBool, AtomicUsize, Ordering};
+use core::task::Waker;
+
+/**
+Which list a node is on.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Kind {
+    Lock,
+    Condition,
+}
+
+/**
+A waiter, embedded in a pinned future.
+
+Everything but `linked` is only accessed under the list's lock.
+*/
+#[derive(Debug)]
+pub(crate) struct Node {
+    linked: AtomicBool,
+    inner: UnsafeCell<NodeInner>,
+    _pin: PhantomPinned,
+}
+
+#[derive(Debug)]
+struct NodeInner {
+    waker: Option<Waker>,
+    prev: *const Node,
+    next: *const Node,
+    kind: Kind,
+    //the list's epoch when the node was registered
+    epoch: u64,
+    //whether the node was woken as a lock waiter, and so is responsible for passing the wakeup on
+    woken: bool,
+}
+
+//the raw pointers are only followed under the list's lock
+unsafe impl Send for Node {}
+unsafe impl Sync for Node {}
+
+impl Node {
+    pub(crate) const fn new() -> Node {
+        Node {
+            linked: AtomicBool::new(false),
+            inner: UnsafeCell::new(NodeInner {
+                waker: None,
+                prev: ptr::null(),
+                next: ptr::null(),
+                kind: Kind::Lock,
+                epoch: 0,
+                woken: false,
+            }),
+            _pin: PhantomPinned,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Lists {
+    lock: Ends,
+    condition: Ends,
+    //bumped each time the condition list is woken, so nodes that re-register meanwhile aren't woken twice
+    epoch: u64,
+}
+
+#[derive(Debug)]
+struct Ends {
+    head: *const Node,
+    tail: *const Node,
+}
+
+impl Lists {
+    fn ends(&mut self, kind: Kind) -> &mut Ends {
+        match kind {
+            Kind::Lock => &mut self.lock,
+            Kind::Condition => &mut self.condition,
+        }
+    }
+
+    unsafe fn push_back(&mut self, node: *const Node) {
+        let inner = &mut *(*node).inner.get();
+        let ends = self.ends(inner.kind);
+        inner.prev = ends.tail;
+        inner.next = ptr::null();
+        if ends.tail.is_null() {
+            ends.head = node;
+        } else {
+            (*(*ends.tail).inner.get()).next = node;
+        }
+        ends.tail = node;
+    }
+
+    unsafe fn unlink(&mut self, node: *const Node) {
+        let inner = &mut *(*node).inner.get();
+        let (prev, next) = (inner.prev, inner.next);
+        let ends = self.ends(inner.kind);
+        if prev.is_null() {
+            ends.head = next;
+        } else {
+            (*(*prev).inner.get()).next = next;
+        }
+        if next.is_null() {
+            ends.tail = prev;
+        } else {
+            (*(*next).inner.get()).prev = prev;
+        }
+        inner.prev = ptr::null();
+        inner.next = ptr::null();
+    }
+}
+
+/**
+The async waiters on one lock.
+*/
+#[derive(Debug)]
+pub(crate) struct WakerList {
+    //number of linked nodes, so releases can skip the list when nobody is waiting
+    len: AtomicUsize,
+    lists: atomiclock::AtomicLock<Lists>,
+}
+
+impl WakerList {
+    pub(crate) const fn new() -> WakerList {
+        WakerList {
+            len: AtomicUsize::new(0),
+            lists: atomiclock::AtomicLock::new(Lists {
+                lock: Ends { head: ptr::null(), tail: ptr::null() },
+                condition: Ends { head: ptr::null(), tail: ptr::null() },
+                epoch: 0,
+            }),
+        }
+    }
+
+    /**
+    Registers the node to be woken with `waker`, on the given list.
+
+    After this returns, the caller must check the lock (or condition) again before waiting, since a
+    release may have happened before registration.
+
+    # Safety
+    The node must be pinned, and [WakerList::unregister]ed from this list before it is dropped.
+    */
+    pub(crate) unsafe fn register(&self, node: &Node, kind: Kind, waker: &Waker) {
+        let mut lists = crate::spin_raw(&self.lists);
+        if node.linked.load(Ordering::Relaxed) && (*node.inner.get()).kind != kind {
+            lists.unlink(node);
+            node.linked.store(false, Ordering::Relaxed);
+            self.len.fetch_sub(1, Ordering::Relaxed);
+        }
+        {
+            let inner = &mut *node.inner.get();
+            match &inner.waker {
+                Some(w) if w.will_wake(waker) => {}
+                _ => inner.waker = Some(waker.clone()),
+            }
+            inner.woken = false;
+        }
+        if !node.linked.load(Ordering::Relaxed) {
+            let inner = &mut *node.inner.get();
+            inner.kind = kind;
+            inner.epoch = lists.epoch;
+            lists.push_back(node);
+            node.linked.store(true, Ordering::Relaxed);
+            self.len.fetch_add(1, Ordering::Relaxed);
+        }
+        drop(lists);
+        //pairs with the fence in wake, so either we see the release or it sees us
+        fence(Ordering::SeqCst);
+    }
+
+    /**
+    Removes the node from the list, if it's on it.
+
+    If the node had been woken as a lock waiter but the caller is not going to take the lock, pass
+    `acquired = false`, and the wakeup is passed on to another waiter.
+    */
+    pub(crate) fn unregister(&self, node: &Node, acquired: bool) {
+        let woken = {
+            let mut lists = crate::spin_raw(&self.lists);
+            if node.linked.load(Ordering::Relaxed) {
+                unsafe { lists.unlink(node) };
+                node.linked.store(false, Ordering::Relaxed);
+                self.len.fetch_sub(1, Ordering::Relaxed);
+            }
+            let inner = unsafe { &mut *node.inner.get() };
+            inner.waker = None;
+            core::mem::replace(&mut inner.woken, false)
+        };
+        if woken && !acquired {
+            self.wake(false);
+        }
+    }
+
+    /**
+    Called after the lock is released.  Wakes one lock waiter, and, if `data_changed`, all condition waiters.
+    */
+    #[inline]
+    pub(crate) fn wake(&self, data_changed: bool) {
+        fence(Ordering::SeqCst);
+        if self.len.load(Ordering::Relaxed) != 0 {
+            self.wake_slow(data_changed);
+        }
+    }
+
+    /**
+    [WakerList::wake], once there are wakers, kept out of line so that releases without them stay small.
+    */
+    #[cold]
+    fn wake_slow(&self, data_changed: bool) {
+        let mut lists = crate::spin_raw(&self.lists);
+        let waker = unsafe { self.pop(&mut lists, Kind::Lock, u64::MAX) };
+        if !data_changed {
+            drop(lists);
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+            return;
+        }
+        lists.epoch += 1;
+        let epoch = lists.epoch;
+        drop(lists);
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        //wake outside the list's lock, one at a time, in case a waker polls inline
+        loop {
+            let mut lists = crate::spin_raw(&self.lists);
+            let waker = unsafe { self.pop(&mut lists, Kind::Condition, epoch) };
+            drop(lists);
+            match waker {
+                Some(waker) => waker.wake(),
+                None => break,
+            }
+        }
+    }
+
+    /**
+    Unlinks the first node on the list, if it was registered before `epoch`, and takes its waker.
+    */
+    unsafe fn pop(&self, lists: &mut Lists, kind: Kind, epoch: u64) -> Option<Waker> {
+        let node = lists.ends(kind).head;
+        if node.is_null() {
+            return None;
+        }
+        if (*(*node).inner.get()).epoch >= epoch {
+            return None;
+        }
+        lists.unlink(node);
+        (*node).linked.store(false, Ordering::Relaxed);
+        self.len.fetch_sub(1, Ordering::Relaxed);
+        let inner = &mut *(*node).inner.get();
+        inner.woken = kind == Kind::Lock;
+        inner.waker.take()
+    }
+}
@@ -0,0 +1,52 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Locks keep their recent holders, with the `acquisition-history` feature.
+
+Run with `cargo test --features acquisition-history --test acquisition_history`.
+*/
+
+use atomiclock_spinlock::{diagnostics, Lock};
+
+#[test]
+fn keeps_the_last_holders_in_order() {
+    let lock = Lock::new(0u32);
+    std::thread::scope(|s| {
+        std::thread::Builder::new()
+            .name("early".to_string())
+            .spawn_scoped(s, || {
+                for _ in 0..10 {
+                    *lock.spin_lock() += 1;
+                }
+            })
+            .unwrap();
+    });
+    for _ in 0..10 {
+        *lock.spin_lock() += 1;
+    }
+    let held = lock.spin_lock();
+    let line = line!() - 1;
+    let history = lock.recent_acquisitions();
+    //the last 16 releases, and the holder
+    assert_eq!(history.len(), 17);
+    let (past, current) = history.split_at(16);
+    assert!(past.iter().all(|acquisition| acquisition.released.is_some_and(|released| released >= acquisition.acquired)));
+    assert!(history.windows(2).all(|pair| pair[0].acquired <= pair[1].acquired));
+    //the first four of the early thread's were evicted
+    assert!(past[..6].iter().all(|acquisition| acquisition.thread_name.as_deref() == Some("early")));
+    assert!(past[6..].iter().all(|acquisition| acquisition.thread == std::thread::current().id()));
+    assert_eq!(current[0].released, None);
+    assert_eq!(current[0].thread, std::thread::current().id());
+    assert_eq!((current[0].location.file(), current[0].location.line()), (file!(), line));
+    drop(held);
+    assert_eq!(lock.recent_acquisitions().len(), 16);
+}
+
+#[test]
+fn reports_include_the_history() {
+    static LOCK: Lock<u32> = Lock::with_name(0, "reported");
+    diagnostics::register(&LOCK);
+    drop(LOCK.spin_lock());
+    let report = diagnostics::report().into_iter().find(|report| report.name == Some("reported")).unwrap();
+    assert_eq!(report.history, LOCK.recent_acquisitions());
+    assert_eq!(report.history.len(), 1);
+}
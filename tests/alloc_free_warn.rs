@@ -0,0 +1,90 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+[Lock::spin_lock_warn_alloc_free] inside a global allocator, where warning must not allocate.
+*/
+#![cfg(all(feature = "std", not(feature = "strict")))]
+
+use atomiclock_spinlock::Lock;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+thread_local! {
+    //set while the thread is acquiring a lock that mustn't allocate
+    static ACQUIRING: Cell<bool> = const { Cell::new(false) };
+}
+
+//allocations made while acquiring
+static RECURSED: AtomicUsize = AtomicUsize::new(0);
+
+fn acquiring<G>(acquire: impl FnOnce() -> G) -> G {
+    ACQUIRING.with(|a| a.set(true));
+    let guard = acquire();
+    ACQUIRING.with(|a| a.set(false));
+    guard
+}
+
+struct Counting;
+
+static ALLOCATED: Lock<usize> = Lock::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if ACQUIRING.with(|a| a.get()) {
+            RECURSED.fetch_add(1, Ordering::Relaxed);
+        }
+        *acquiring(|| ALLOCATED.spin_lock_warn_alloc_free()) += layout.size();
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        *acquiring(|| ALLOCATED.spin_lock_warn_alloc_free()) -= layout.size();
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/**
+Allocates from several threads, so the allocator's lock contends, and checks it never allocated.
+*/
+#[test]
+fn allocator_lock() {
+    thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                for i in 0..10_000 {
+                    drop(std::hint::black_box(vec![i; 16]));
+                }
+            });
+        }
+    });
+    assert_eq!(RECURSED.load(Ordering::Relaxed), 0);
+}
+
+/**
+Contends a lock on purpose, and checks the warning was deferred rather than allocated, and then logged.
+*/
+#[test]
+fn deferred_are_logged() {
+    static LOCK: Lock<u32> = Lock::new(0);
+    let held = LOCK.spin_lock();
+    thread::scope(|s| {
+        let waiter = s.spawn(|| *acquiring(|| LOCK.spin_lock_warn_alloc_free()) += 1);
+        while LOCK.waiters() == 0 {
+            std::hint::spin_loop();
+        }
+        drop(held);
+        waiter.join().unwrap();
+    });
+    assert_eq!(*LOCK.spin_lock(), 1);
+    assert_eq!(RECURSED.load(Ordering::Relaxed), 0);
+    let logged = atomiclock_spinlock::log_deferred_warnings();
+    if cfg!(feature = "perfwarn") {
+        assert!(logged >= 1);
+    } else {
+        assert_eq!(logged, 0);
+    }
+}
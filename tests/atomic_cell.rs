@@ -0,0 +1,84 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+[AtomicCellSpin] at each width, atomic and locked.
+*/
+#![cfg(feature = "std")]
+
+use atomiclock_spinlock::{AtomicCellSpin, Plain};
+use std::thread;
+
+const ITERATIONS: u64 = 10_000;
+
+//an odd size, which takes the lock
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+struct Rgb(u8, u8, u8);
+unsafe impl Plain for Rgb {}
+
+/**
+Increments from several threads with `update`, and checks none were lost.
+*/
+fn no_lost_updates<T: Plain + Send + PartialEq + std::fmt::Debug>(zero: T, increment: fn(T) -> T, expected: T) {
+    let cell = AtomicCellSpin::new(zero);
+    thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                for _ in 0..ITERATIONS / 4 {
+                    cell.update(increment);
+                }
+            });
+        }
+    });
+    assert_eq!(cell.into_inner(), expected);
+}
+
+#[test]
+fn every_width() {
+    no_lost_updates(0u8, |n| n.wrapping_add(1), (ITERATIONS % 256) as u8);
+    no_lost_updates(0u16, |n| n + 1, ITERATIONS as u16);
+    no_lost_updates(0u32, |n| n + 1, ITERATIONS as u32);
+    no_lost_updates([0u32; 2], |[a, b]| [a + 1, b + 2], [ITERATIONS as u32, 2 * ITERATIONS as u32]);
+    no_lost_updates([0u64; 2], |[a, b]| [a + 1, b + 2], [ITERATIONS, 2 * ITERATIONS]);
+    no_lost_updates(Rgb(0, 0, 0), |Rgb(r, g, b)| Rgb(r.wrapping_add(1), g, b), Rgb((ITERATIONS % 256) as u8, 0, 0));
+}
+
+#[test]
+fn wide_values_are_never_torn() {
+    let cell = AtomicCellSpin::new([0u64; 2]);
+    thread::scope(|s| {
+        s.spawn(|| {
+            for n in 1..=ITERATIONS {
+                cell.set([n, n]);
+            }
+        });
+        let mut last = 0;
+        while last < ITERATIONS {
+            let [a, b] = cell.get();
+            assert_eq!(a, b);
+            assert!(a >= last);
+            last = a;
+        }
+    });
+}
+
+#[test]
+fn compare_exchange_compares_bytes() {
+    let cell = AtomicCellSpin::new(0.0f64);
+    assert_eq!(cell.compare_exchange(-0.0, 1.0), Err(0.0));
+    assert_eq!(cell.compare_exchange(0.0, f64::NAN), Ok(0.0));
+    assert!(cell.compare_exchange(f64::NAN, 2.0).is_ok());
+    assert_eq!(cell.replace(3.0), 2.0);
+
+    let locked = AtomicCellSpin::new(Rgb(1, 2, 3));
+    assert!(!AtomicCellSpin::<Rgb>::is_lock_free());
+    assert_eq!(locked.compare_exchange(Rgb(0, 0, 0), Rgb(4, 5, 6)), Err(Rgb(1, 2, 3)));
+    assert_eq!(locked.compare_exchange(Rgb(1, 2, 3), Rgb(4, 5, 6)), Ok(Rgb(1, 2, 3)));
+    assert_eq!(format!("{locked:?}"), "AtomicCellSpin(Rgb(4, 5, 6))");
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn wide_values_are_lock_free_with_cmpxchg16b() {
+    assert_eq!(AtomicCellSpin::<u128>::is_lock_free(), !cfg!(miri) && std::is_x86_feature_detected!("cmpxchg16b"));
+    assert!(AtomicCellSpin::<u64>::is_lock_free());
+}
@@ -0,0 +1,82 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Send and Sync are part of the API, so these fail to compile if they change.
+
+The negative cases are `compile_fail` examples on [Lock] and [Guard], and in the `soundness` suite.
+*/
+
+use atomiclock_spinlock::{Guard, Lock};
+use core::cell::Cell;
+
+fn assert_send<T: Send>() {}
+fn assert_sync<T: Sync>() {}
+
+#[test]
+fn lock_needs_only_send() {
+    assert_send::<Lock<u32>>();
+    assert_sync::<Lock<u32>>();
+    //like Mutex, the lock makes a Send type Sync
+    assert_send::<Lock<Cell<u32>>>();
+    assert_sync::<Lock<Cell<u32>>>();
+}
+
+#[test]
+fn guard_follows_data() {
+    assert_send::<Guard<'static, u32>>();
+    assert_sync::<Guard<'static, u32>>();
+    assert_send::<Guard<'static, Cell<u32>>>();
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn owned_guard_is_send() {
+    assert_send::<atomiclock_spinlock::OwnedGuard<Cell<u32>>>();
+    assert_sync::<atomiclock_spinlock::OwnedGuard<u32>>();
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn range_lock_is_like_a_lock() {
+    use atomiclock_spinlock::{RangeGuard, RangeLock};
+    assert_send::<RangeLock<Cell<u32>>>();
+    assert_sync::<RangeLock<Cell<u32>>>();
+    //guards for different ranges go to different threads
+    assert_send::<RangeGuard<'static, Cell<u32>>>();
+    assert_sync::<RangeGuard<'static, u32>>();
+    assert_send::<atomiclock_spinlock::VecRangeGuard<'static, Cell<u32>>>();
+    assert_sync::<atomiclock_spinlock::VecRangeGuard<'static, u32>>();
+}
+
+#[test]
+fn process_lock_is_like_a_lock() {
+    use atomiclock_spinlock::{ProcessGuard, ProcessLock};
+    assert_send::<ProcessLock<Cell<u32>>>();
+    assert_sync::<ProcessLock<Cell<u32>>>();
+    assert_sync::<ProcessGuard<'static, u32>>();
+}
+
+#[test]
+fn left_right_shares_its_copies() {
+    use atomiclock_spinlock::{LeftRight, LeftRightGuard};
+    assert_send::<LeftRight<u32>>();
+    assert_sync::<LeftRight<u32>>();
+    assert_send::<LeftRight<Cell<u32>>>();
+    //unlike a lock, readers share the data, so it must be Sync too
+    assert_sync::<LeftRightGuard<'static, u32>>();
+}
+
+#[test]
+fn lock_arrays_are_locks() {
+    use atomiclock_spinlock::LockArray;
+    assert_send::<LockArray<Cell<u32>, 4>>();
+    assert_sync::<LockArray<Cell<u32>, 4>>();
+}
+
+#[test]
+fn biased_rwlock_is_an_rwlock() {
+    use atomiclock_spinlock::rwlock::{BiasedReadGuard, BiasedRwLock};
+    assert_send::<BiasedRwLock<u32>>();
+    assert_sync::<BiasedRwLock<u32>>();
+    assert_send::<BiasedRwLock<Cell<u32>>>();
+    assert_sync::<BiasedReadGuard<'static, u32>>();
+}
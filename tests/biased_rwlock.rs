@@ -0,0 +1,89 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Readers of a [BiasedRwLock], biased and not, racing writers.
+*/
+#![cfg(feature = "std")]
+
+use atomiclock_spinlock::rwlock::BiasedRwLock;
+use std::thread;
+
+#[test]
+fn readers_never_see_a_write_in_progress() {
+    let lock = BiasedRwLock::new((0u64, 0u64));
+    thread::scope(|s| {
+        s.spawn(|| {
+            for _ in 0..1000 {
+                let mut pair = lock.write();
+                pair.0 += 1;
+                pair.1 += 1;
+                drop(pair);
+                //let the readers bring the bias back now and then
+                thread::yield_now();
+            }
+        });
+        for _ in 0..4 {
+            s.spawn(|| {
+                for _ in 0..20_000 {
+                    let pair = *lock.read();
+                    assert_eq!(pair.0, pair.1);
+                }
+            });
+        }
+    });
+    assert_eq!(lock.into_inner(), (1000, 1000));
+}
+
+#[test]
+fn reads_restore_the_bias() {
+    let lock = BiasedRwLock::new(0);
+    *lock.write() += 1;
+    assert!(!lock.is_biased());
+    let mut reads = 0;
+    while !lock.is_biased() {
+        assert_eq!(*lock.read(), 1);
+        reads += 1;
+        assert!(reads <= 1000, "the bias never came back");
+    }
+    //biased reads, nested or not, still exclude writers
+    let outer = lock.read();
+    let inner = lock.read();
+    assert!(lock.try_write().is_none());
+    drop((outer, inner));
+    assert!(lock.try_write().is_some());
+}
+
+#[test]
+fn writers_wait_for_visible_readers() {
+    let lock = BiasedRwLock::with_name(0, "config");
+    let read = lock.read();
+    assert!(lock.try_write().is_none());
+    //so the writer still waits for the reader
+    assert!(lock.is_biased());
+    thread::scope(|s| {
+        let writer = s.spawn(|| *lock.write() = 1);
+        thread::sleep(std::time::Duration::from_millis(10));
+        assert_eq!(*read, 0);
+        drop(read);
+        writer.join().unwrap();
+    });
+    assert_eq!(format!("{lock:?}"), r#"BiasedRwLock { name: Some("config"), biased: false, data: 1 }"#);
+}
+
+#[test]
+fn reads_again_while_a_writer_waits() {
+    let lock = BiasedRwLock::new(0);
+    let outer = lock.read();
+    assert!(lock.is_biased());
+    thread::scope(|s| {
+        let writer = s.spawn(|| *lock.write() = 1);
+        //the writer has revoked the bias, and waits for the visible read
+        while lock.is_biased() {
+            std::hint::spin_loop();
+        }
+        let inner = lock.read();
+        assert_eq!((*outer, *inner), (0, 0));
+        drop((inner, outer));
+        writer.join().unwrap();
+    });
+    assert_eq!(*lock.read(), 1);
+}
@@ -0,0 +1,45 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Exporting contention timelines, with the `events` feature.
+
+Run with `cargo test --features events --test chrome_trace`.
+*/
+
+use atomiclock_spinlock::events::{self, ChromeTrace};
+use atomiclock_spinlock::Lock;
+use std::sync::Barrier;
+
+static TRACE: ChromeTrace = ChromeTrace::new();
+
+#[test]
+fn waits_and_holds_on_a_timeline() {
+    events::set_sink(&TRACE).ok();
+    let lock = Lock::with_name(0, "shared \"counter\"");
+    let barrier = Barrier::new(2);
+    std::thread::scope(|s| {
+        let guard = lock.spin_lock();
+        s.spawn(|| {
+            barrier.wait();
+            *lock.spin_lock() += 1;
+        });
+        barrier.wait();
+        //until the other thread is spinning
+        while lock.waiters() == 0 {
+            std::hint::spin_loop();
+        }
+        drop(guard);
+    });
+    let mut json = Vec::new();
+    TRACE.write_json(&mut json).unwrap();
+    let json = String::from_utf8(json).unwrap();
+    assert!(json.starts_with(r#"{"displayTimeUnit":"ns","traceEvents":[{"#) && json.ends_with("}]}"), "{json}");
+    assert_eq!(json.matches(r#""name":"hold shared \"counter\"""#).count(), 2, "{json}");
+    assert_eq!(json.matches(r#""name":"wait shared \"counter\"""#).count(), 1, "{json}");
+    //the holds are on different rows
+    assert!(json.contains(r#""tid":1,"#) && json.contains(r#""tid":2,"#), "{json}");
+    assert_eq!(TRACE.dropped(), 0);
+    TRACE.clear();
+    let mut json = Vec::new();
+    TRACE.write_json(&mut json).unwrap();
+    assert_eq!(json, br#"{"displayTimeUnit":"ns","traceEvents":[]}"#);
+}
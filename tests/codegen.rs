@@ -0,0 +1,70 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Checks the code the uncontended lock paths compile to, with `--cfg atomiclock_bare`.
+
+This builds the `codegen` example with the instrumentation features enabled and `--cfg atomiclock_bare`, and
+checks its assembly: acquiring and releasing is one compare-and-swap, the release and the fence that pairs it
+with waiters, with calls only on the paths that can't be taken without contention or a broken lock.
+
+The build takes a while, so this only runs with optimizations: `cargo test --release --test codegen`.
+*/
+#![cfg(all(target_arch = "x86_64", target_os = "linux", not(debug_assertions), not(loom), not(shuttle)))]
+
+use std::path::Path;
+use std::process::Command;
+
+//features that instrument the lock paths, which atomiclock_bare compiles out
+const INSTRUMENTATION: &str = "events,diagnostics,perfwarn,perf-counters,rt-audit";
+
+//calls that are allowed: asserts that the lock was held, and waking async waiters, which only happens with them
+const COLD_CALLS: &[&str] = &["panicking", "wake_slow"];
+
+/**
+The instructions of `function` in the assembly, without directives or labels.
+*/
+fn instructions<'a>(asm: &'a str, function: &str) -> Vec<&'a str> {
+    let start = asm.lines().position(|l| l == format!("{function}:")).expect("function not found");
+    asm.lines().skip(start + 1)
+        .take_while(|l| !l.contains(".cfi_endproc"))
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('.') && !l.ends_with(':'))
+        .collect()
+}
+
+#[test]
+fn try_lock_is_bare() {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+    //a target directory of its own, since the outer build holds the usual one
+    let target = manifest.join("target").join("codegen");
+    let status = Command::new(env!("CARGO"))
+        .current_dir(manifest)
+        .env("RUSTFLAGS", "--cfg atomiclock_bare")
+        .args(["rustc", "--quiet", "--release", "--example", "codegen", "--features", INSTRUMENTATION])
+        .arg("--target-dir").arg(&target)
+        .args(["--", "--emit", "asm"])
+        .status()
+        .expect("can't run cargo");
+    assert!(status.success());
+
+    //the most recent build's assembly
+    let examples = target.join("release").join("examples");
+    let asm = std::fs::read_dir(&examples).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            let name = path.file_name().unwrap().to_string_lossy();
+            name.starts_with("codegen-") && name.ends_with(".s")
+        })
+        .max_by_key(|path| path.metadata().unwrap().modified().unwrap())
+        .expect("no assembly");
+    let asm = std::fs::read_to_string(asm).unwrap();
+
+    let instructions = instructions(&asm, "codegen_try_lock");
+    let cas = instructions.iter().filter(|i| i.contains("cmpxchg")).count();
+    assert_eq!(cas, 1, "{instructions:#?}");
+    for call in instructions.iter().filter(|i| i.starts_with("call") || (i.starts_with("jmp") && i.contains('@'))) {
+        assert!(COLD_CALLS.iter().any(|c| call.contains(c)), "unexpected call: {call}\n{instructions:#?}");
+    }
+    assert!(!instructions.iter().any(|i| i.starts_with("rdtsc") || i.starts_with("syscall")), "{instructions:#?}");
+    //a compare-and-swap, a little bookkeeping, the release, the fence, and the paths out
+    assert!(instructions.len() <= 40, "{} instructions: {instructions:#?}", instructions.len());
+}
@@ -0,0 +1,211 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Concurrent use of the collections, and the channel.
+*/
+#![cfg(feature = "std")]
+
+use atomiclock_spinlock::channel::{Channel, RecvError, SendError, TrySendError};
+use atomiclock_spinlock::collections::{Interner, LruCache, Memo, Slab, SpinMap, SpinRing};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::thread;
+
+const THREADS: usize = 4;
+const ITERATIONS: usize = 1000;
+
+#[test]
+fn map_counts() {
+    let map = SpinMap::with_shards(2);
+    thread::scope(|s| {
+        for _ in 0..THREADS {
+            s.spawn(|| {
+                for n in 0..ITERATIONS {
+                    map.with_entry(n % 10, |entry| *entry.or_insert(0) += 1);
+                }
+            });
+        }
+    });
+    assert_eq!(map.len(), 10);
+    let mut entries: Vec<_> = map.into_entries().collect();
+    entries.sort();
+    assert_eq!(entries, (0..10).map(|n| (n, THREADS * ITERATIONS / 10)).collect::<Vec<_>>());
+}
+
+#[test]
+fn memo_recomputes_after_panic() {
+    let memo = Memo::new();
+    let panicked = catch_unwind(AssertUnwindSafe(|| memo.get_or_compute("key", |_| panic!("failed"))));
+    assert!(panicked.is_err());
+    assert_eq!(memo.get("key"), None);
+    assert_eq!(memo.get_or_compute("key", |key| key.len()), 3);
+    assert_eq!(memo.get_or_compute("key", |_| unreachable!()), 3);
+    memo.invalidate("key");
+    assert_eq!(memo.get("key"), None);
+}
+
+#[test]
+fn slab_keys() {
+    let slab = Slab::new();
+    thread::scope(|s| {
+        for t in 0..THREADS {
+            let slab = &slab;
+            s.spawn(move || {
+                let mut keys = Vec::new();
+                for n in 0..ITERATIONS {
+                    keys.push((slab.insert((t, n)), n));
+                    if n % 3 == 0 {
+                        let (key, n) = keys.swap_remove(n % keys.len());
+                        assert_eq!(slab.remove(key), Some((t, n)));
+                        assert!(!slab.contains(key));
+                    }
+                }
+                for (key, n) in keys {
+                    assert_eq!(slab.get_cloned(key), Some((t, n)));
+                }
+            });
+        }
+    });
+    assert_eq!(slab.len(), THREADS * (ITERATIONS - ITERATIONS.div_ceil(3)));
+}
+
+#[test]
+fn interner_agrees_across_threads() {
+    let interner: Interner<str> = Interner::with_shards(4);
+    let symbols: Vec<Vec<_>> = thread::scope(|s| {
+        let threads: Vec<_> = (0..THREADS)
+            .map(|_| s.spawn(|| (0..100).map(|n| interner.intern(n.to_string())).collect()))
+            .collect();
+        threads.into_iter().map(|t| t.join().unwrap()).collect()
+    });
+    assert!(symbols.iter().all(|s| *s == symbols[0]));
+    assert_eq!(interner.len(), 100);
+    for (n, &symbol) in symbols[0].iter().enumerate() {
+        assert_eq!(*interner.resolve(symbol), n.to_string());
+        assert_eq!(interner.get(&n.to_string()), Some(symbol));
+    }
+}
+
+#[test]
+fn ring_keeps_latest() {
+    let ring = SpinRing::<(usize, usize), 16>::new();
+    thread::scope(|s| {
+        for t in 0..THREADS {
+            let ring = &ring;
+            s.spawn(move || {
+                for n in 0..ITERATIONS {
+                    ring.push((t, n));
+                }
+            });
+        }
+        for _ in 0..100 {
+            let snapshot: Vec<_> = ring.snapshot().collect();
+            assert!(snapshot.len() <= 16);
+            //each thread's samples stay in order
+            for t in 0..THREADS {
+                let mine: Vec<_> = snapshot.iter().filter(|&&(u, _)| u == t).collect();
+                assert!(mine.windows(2).all(|w| w[0].1 < w[1].1));
+            }
+        }
+    });
+    assert_eq!(ring.pushed(), (THREADS * ITERATIONS) as u64);
+    assert_eq!(ring.len(), 16);
+    ring.clear();
+    assert!(ring.is_empty());
+    assert_eq!(ring.latest(), None);
+}
+
+#[test]
+fn channel_disconnects() {
+    let channel = Channel::<u32, 2>::new();
+    let (sender, receiver) = channel.split().unwrap();
+    assert!(channel.split().is_none());
+    sender.try_send(1).unwrap();
+    sender.try_send(2).unwrap();
+    assert_eq!(sender.try_send(3), Err(TrySendError::Full(3)));
+    let more = sender.clone();
+    drop(sender);
+    assert_eq!(receiver.try_recv(), Ok(1));
+    drop(more);
+    //what's buffered is still delivered
+    assert_eq!(receiver.recv(), Ok(2));
+    assert_eq!(receiver.recv(), Err(RecvError));
+    drop(receiver);
+    let (sender, receiver) = channel.split().unwrap();
+    drop(receiver);
+    assert_eq!(sender.send(4), Err(SendError(4)));
+}
+
+//polls a future to completion, parking between polls
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    struct Unpark(thread::Thread);
+    impl std::task::Wake for Unpark {
+        fn wake(self: std::sync::Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+    let waker = std::sync::Arc::new(Unpark(thread::current())).into();
+    let mut cx = std::task::Context::from_waker(&waker);
+    let mut future = std::pin::pin!(future);
+    loop {
+        if let std::task::Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        thread::park();
+    }
+}
+
+#[test]
+fn channel_async() {
+    let channel = Channel::<usize, 1>::new();
+    let (sender, receiver) = channel.split().unwrap();
+    thread::scope(|s| {
+        s.spawn(move || {
+            block_on(async {
+                for n in 0..ITERATIONS {
+                    sender.send_async(n).await.unwrap();
+                }
+            })
+        });
+        block_on(async {
+            for n in 0..ITERATIONS {
+                assert_eq!(receiver.recv_async().await, Ok(n));
+            }
+            assert_eq!(receiver.recv_async().await, Err(RecvError));
+        });
+    });
+}
+
+#[test]
+fn lru_matches_model() {
+    const CAPACITY: usize = 8;
+    let cache = LruCache::with_shards(CAPACITY, 1);
+    //most recently used last
+    let mut model: Vec<(u32, u32)> = Vec::new();
+    let mut state = 12345u32;
+    for n in 0..10_000 {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let key = state % 16;
+        let position = model.iter().position(|&(k, _)| k == key);
+        match state / 16 % 3 {
+            0 => {
+                let previous = position.map(|i| model.remove(i).1);
+                if previous.is_none() && model.len() == CAPACITY {
+                    model.remove(0);
+                }
+                model.push((key, n));
+                assert_eq!(cache.insert(key, n), previous);
+            }
+            1 => {
+                let expected = position.map(|i| model.remove(i));
+                model.extend(expected);
+                assert_eq!(cache.get_cloned(&key), expected.map(|(_, v)| v));
+            }
+            _ => {
+                let expected = position.map(|i| model.remove(i).1);
+                assert_eq!(cache.remove(&key), expected);
+            }
+        }
+        assert_eq!(cache.len(), model.len());
+    }
+}
@@ -0,0 +1,130 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+`#[derive(SpinLocked)]` and `#[locked]`, with the `derive` feature.
+
+Run with `cargo test --features derive --test derive`.
+*/
+
+use atomiclock_spinlock::{locked, Lock, SpinLocked};
+use std::fmt::Debug;
+use std::time::Duration;
+
+#[derive(SpinLocked, Debug, PartialEq)]
+struct State {
+    /// Requests served.
+    served: u64,
+    errors: Vec<String>,
+    r#type: &'static str,
+}
+
+#[derive(SpinLocked)]
+#[spin_locked(name = "Slots")]
+struct Pair<T: Debug, const N: usize>
+where
+    T: Clone,
+{
+    left: [T; N],
+    right: Option<T>,
+}
+
+#[test]
+fn fields_lock_separately() {
+    let state = StateLocked::new(State { served: 0, errors: Vec::new(), r#type: "worker" });
+    let served = state.served().spin_lock();
+    //another field is still free
+    state.errors().spin_lock().push("timeout".to_string());
+    assert!(state.served().is_locked());
+    assert!(!state.errors().is_locked());
+    drop(served);
+    std::thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                for _ in 0..1000 {
+                    *state.served().spin_lock() += 1;
+                }
+            });
+        }
+    });
+    assert_eq!(*state.r#type().spin_lock(), "worker");
+    assert_eq!(
+        State::from(state),
+        State { served: 4000, errors: vec!["timeout".to_string()], r#type: "worker" }
+    );
+}
+
+#[test]
+fn locks_are_named_after_fields() {
+    let state = StateLocked::from(State { served: 0, errors: Vec::new(), r#type: "worker" });
+    assert_eq!(state.served().debug_state().name, Some("State.served"));
+    assert_eq!(state.r#type().debug_state().name, Some("State.type"));
+}
+
+#[test]
+fn generics_and_renaming() {
+    let slots: Slots<u8, 2> = Slots::new(Pair { left: [1, 2], right: None });
+    *slots.right().spin_lock() = Some(3);
+    slots.left().spin_lock()[0] = 0;
+    let pair = slots.into_inner();
+    assert_eq!((pair.left, pair.right), ([0, 2], Some(3)));
+}
+
+struct Account {
+    balance: Lock<i64>,
+    history: Lock<Vec<i64>>,
+}
+
+impl Account {
+    #[locked(balance)]
+    fn deposit(&self, amount: i64) -> i64 {
+        *balance += amount;
+        *balance
+    }
+
+    #[locked(history, warn)]
+    fn log(&self, amount: i64) {
+        history.push(amount);
+    }
+
+    #[locked(balance, timeout = Duration::from_millis(1))]
+    fn withdraw(&self, amount: i64) -> Option<Result<i64, i64>> {
+        if *balance < amount {
+            return Err(*balance);
+        }
+        *balance -= amount;
+        Ok(*balance)
+    }
+
+    #[locked(history)]
+    fn into_history(self) -> Vec<i64> {
+        std::mem::take(&mut *history)
+    }
+}
+
+#[test]
+fn methods_run_locked() {
+    //with the `strict` feature, spin_lock_warn panics on contention, so the warn method is then only
+    //called once the threads are done
+    let contended = !cfg!(feature = "strict");
+    let account = Account { balance: Lock::new(0), history: Lock::new(Vec::new()) };
+    std::thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                for _ in 0..1000 {
+                    account.deposit(1);
+                    if contended {
+                        account.log(1);
+                    }
+                }
+            });
+        }
+    });
+    if !contended {
+        account.log(1);
+    }
+    assert_eq!(account.withdraw(5000), Some(Err(4000)));
+    assert_eq!(account.withdraw(1000), Some(Ok(3000)));
+    let held = account.balance.spin_lock();
+    assert_eq!(account.withdraw(1), None);
+    drop(held);
+    assert_eq!(account.into_history().len(), if contended { 4000 } else { 1 });
+}
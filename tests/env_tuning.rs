@@ -0,0 +1,30 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Environment variable tuning, with the `env-tuning` feature.
+
+Run with `cargo test --features env-tuning --test env_tuning`.  The environment is read once per process,
+so this is its own test binary, with a single test.
+*/
+
+use atomiclock_spinlock::config;
+
+#[test]
+fn environment_overrides_defaults() {
+    std::env::set_var("ATOMICLOCK_SPIN_BACKOFF_CAP", "8");
+    std::env::set_var("ATOMICLOCK_SPIN_WARN_THRESHOLD", "not a number");
+    std::env::set_var("ATOMICLOCK_SPIN_LOW_LATENCY", "1");
+    let defaults = config::defaults();
+    assert_eq!(defaults.backoff_cap, 8);
+    assert!(defaults.low_latency);
+    assert_eq!(defaults.warn_threshold, config::Settings::new().warn_threshold);
+
+    //the environment takes precedence over the program
+    let mut settings = config::Settings::new();
+    settings.backoff_cap = 2;
+    settings.warn_threshold = 100;
+    config::set_defaults(settings);
+    let defaults = config::defaults();
+    assert_eq!(defaults.backoff_cap, 8);
+    assert_eq!(defaults.warn_threshold, 100);
+    assert!(defaults.low_latency);
+}
@@ -0,0 +1,70 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Resetting a lock in the child of a `fork`, on Linux.
+*/
+#![cfg(all(target_os = "linux", not(loom), not(shuttle)))]
+
+use atomiclock_spinlock::Lock;
+use std::sync::mpsc;
+use std::thread;
+
+static COUNTER: Lock<u32> = Lock::new(0);
+
+/**
+Forks, runs `child` in the child, and returns the child's exit status.
+*/
+fn in_child(child: impl FnOnce() -> bool) -> i32 {
+    unsafe {
+        let pid = libc::fork();
+        assert!(pid >= 0);
+        if pid == 0 {
+            //without running the parent's atexit handlers or unwinding into the test harness
+            libc::_exit(if child() { 0 } else { 1 });
+        }
+        let mut status = 0;
+        assert_eq!(libc::waitpid(pid, &mut status, 0), pid);
+        assert!(libc::WIFEXITED(status));
+        libc::WEXITSTATUS(status)
+    }
+}
+
+#[test]
+fn held_by_another_thread() {
+    let (held, release) = (mpsc::channel(), mpsc::channel::<()>());
+    let holder = thread::spawn(move || {
+        let mut guard = COUNTER.spin_lock();
+        *guard += 1;
+        held.0.send(()).unwrap();
+        release.1.recv().unwrap();
+    });
+    held.1.recv().unwrap();
+    let status = in_child(|| {
+        //the holder didn't survive the fork
+        if !unsafe { COUNTER.reinit_after_fork() } {
+            return false;
+        }
+        #[cfg(feature = "poison")]
+        if !COUNTER.is_poisoned() {
+            return false;
+        }
+        let mut guard = COUNTER.spin_lock();
+        *guard += 1;
+        *guard == 2
+    });
+    assert_eq!(status, 0);
+    //the parent's lock is untouched
+    assert!(COUNTER.is_locked());
+    release.0.send(()).unwrap();
+    holder.join().unwrap();
+    assert_eq!(*COUNTER.spin_lock(), 1);
+}
+
+#[test]
+fn not_held() {
+    let lock = Lock::new(0);
+    let status = in_child(|| {
+        let was_held = unsafe { lock.reinit_after_fork() };
+        !was_held && lock.try_lock().is_some()
+    });
+    assert_eq!(status, 0);
+}
@@ -0,0 +1,57 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Handing the lock to a starving waiter, with the `handoff` feature.
+
+Run with `cargo test --features handoff --test handoff`.
+*/
+//waiters block in the OS mutex instead
+#![cfg(not(feature = "std-mutex"))]
+
+use atomiclock_spinlock::{config, Lock};
+use std::thread;
+use std::time::Duration;
+
+//ask for a handoff on the first failed attempt
+fn hand_off_right_away() {
+    let mut settings = config::Settings::new();
+    settings.handoff_after = 0;
+    config::set_defaults(settings);
+}
+
+#[test]
+fn released_to_the_waiter_that_asked() {
+    hand_off_right_away();
+    let lock = Lock::new(0u32);
+    thread::scope(|s| {
+        let guard = lock.spin_lock();
+        s.spawn(|| {
+            let mut guard = lock.spin_lock();
+            *guard = 1;
+            thread::sleep(Duration::from_millis(50));
+        });
+        //give the waiter time to ask
+        while lock.waiters() == 0 {
+            thread::yield_now();
+        }
+        thread::sleep(Duration::from_millis(50));
+        drop(guard);
+        //the lock went straight to the waiter, so we can't beat it to the lock
+        assert_eq!(*lock.spin_lock(), 1);
+    });
+}
+
+#[test]
+fn many_waiters() {
+    hand_off_right_away();
+    let lock = Lock::new(0u32);
+    thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                for _ in 0..10_000 {
+                    *lock.spin_lock() += 1;
+                }
+            });
+        }
+    });
+    assert_eq!(lock.into_inner(), 40_000);
+}
@@ -0,0 +1,75 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+[HardwareLock], on an emulated hardware spinlock register.
+*/
+
+use atomiclock_spinlock::raw::HardwareLock;
+use atomiclock_spinlock::Lock;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/**
+A register that, like an RP2040 SIO spinlock, is claimed by reading it and released by writing it.
+*/
+struct Register {
+    claimed: AtomicBool,
+    reads: AtomicUsize,
+}
+
+impl Register {
+    const fn new() -> Register {
+        Register { claimed: AtomicBool::new(false), reads: AtomicUsize::new(0) }
+    }
+
+    fn read(&self) -> u32 {
+        self.reads.fetch_add(1, Ordering::Relaxed);
+        //the hardware orders nothing else, so neither does the emulation
+        u32::from(!self.claimed.swap(true, Ordering::Relaxed))
+    }
+
+    fn write(&self) {
+        self.claimed.store(false, Ordering::Relaxed);
+    }
+}
+
+static SPINLOCK0: Register = Register::new();
+
+fn claim() -> bool {
+    SPINLOCK0.read() != 0
+}
+
+fn release() {
+    SPINLOCK0.write()
+}
+
+static COUNTER: Lock<u64, HardwareLock> = Lock::with_raw(0, unsafe { HardwareLock::new(claim, release) });
+
+#[test]
+fn static_lock_on_a_register() {
+    std::thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                for _ in 0..10_000 {
+                    *COUNTER.spin_lock() += 1;
+                }
+            });
+        }
+    });
+    assert_eq!(*COUNTER.spin_lock(), 40_000);
+    assert!(!SPINLOCK0.claimed.load(Ordering::Relaxed));
+}
+
+#[test]
+fn closures_for_a_register_found_at_runtime() {
+    let register = Box::new(Register::new());
+    let register = &*register;
+    let lock = Lock::with_raw(Vec::new(), unsafe { HardwareLock::new(|| register.read() != 0, || register.write()) });
+    lock.spin_lock().push(1);
+    let held = lock.spin_lock();
+    assert!(lock.try_lock().is_none());
+    drop(held);
+    let reads = register.reads.load(Ordering::Relaxed);
+    //formatting the raw lock doesn't touch the register
+    let _ = format!("{:?}", lock.as_raw());
+    assert_eq!(register.reads.load(Ordering::Relaxed), reads);
+    assert_eq!(lock.into_inner(), [1]);
+}
@@ -0,0 +1,144 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Proof harnesses for the [Kani](https://github.com/model-checking/kani) model checker.
+
+Each harness runs every sequence of operations up to a bounded length, and proves that no two guards are
+ever alive at once, and that a lock is never released when it is not held (releasing asserts that it is).
+
+Run with `cargo kani --tests --harness-timeout 10m`.  Kani has no model of threads, so these cover a
+single thread's sequences; see the loom and shuttle suites for interleavings.
+*/
+#![cfg(kani)]
+
+use atomiclock_spinlock::rwlock::{ReadGuard, UpgradableGuard, WriteGuard};
+use atomiclock_spinlock::{Guard, Lock, RwLock};
+
+//operations per sequence
+const STEPS: usize = 6;
+
+#[derive(kani::Arbitrary)]
+enum LockOp {
+    SpinLock,
+    TryLock,
+    Release,
+    IntoRaw,
+    FromRaw,
+}
+
+enum Held<'a> {
+    Free,
+    Guard(Guard<'a, u8>),
+    Raw,
+}
+
+#[kani::proof]
+#[kani::unwind(8)]
+fn lock_is_exclusive() {
+    let lock = Lock::new(0u8);
+    let mut held = Held::Free;
+    for _ in 0..STEPS {
+        held = match (kani::any(), held) {
+            (LockOp::SpinLock, Held::Free) => Held::Guard(lock.spin_lock()),
+            (LockOp::TryLock, Held::Free) => lock.try_lock().map_or(Held::Free, Held::Guard),
+            (LockOp::SpinLock | LockOp::TryLock, held @ (Held::Guard(_) | Held::Raw)) => {
+                assert!(lock.try_lock().is_none());
+                held
+            }
+            (LockOp::Release, Held::Guard(guard)) => {
+                drop(guard);
+                Held::Free
+            }
+            (LockOp::IntoRaw, Held::Guard(guard)) => {
+                Guard::into_raw(guard);
+                Held::Raw
+            }
+            (LockOp::FromRaw, Held::Raw) => {
+                drop(unsafe { lock.guard_from_raw() });
+                Held::Free
+            }
+            (_, held) => held,
+        };
+        assert_eq!(lock.is_locked(), !matches!(held, Held::Free));
+    }
+}
+
+#[derive(kani::Arbitrary)]
+enum RwOp {
+    TryRead,
+    TryWrite,
+    TryUpgradable,
+    DropRead,
+    DropWrite,
+    DropUpgradable,
+    TryUpgrade,
+    Downgrade,
+    DowngradeToUpgradable,
+    DowngradeUpgradable,
+}
+
+#[kani::proof]
+#[kani::unwind(8)]
+fn rwlock_is_exclusive() {
+    let lock = RwLock::new(0u8);
+    let mut reads: Option<ReadGuard<'_, u8>> = None;
+    let mut more_reads: Option<ReadGuard<'_, u8>> = None;
+    let mut write: Option<WriteGuard<'_, u8>> = None;
+    let mut upgradable: Option<UpgradableGuard<'_, u8>> = None;
+    for _ in 0..STEPS {
+        match kani::any() {
+            RwOp::TryRead => {
+                let guard = lock.try_read();
+                if write.is_some() {
+                    assert!(guard.is_none());
+                }
+                if reads.is_none() {
+                    reads = guard;
+                } else if more_reads.is_none() {
+                    more_reads = guard;
+                }
+            }
+            RwOp::TryWrite => {
+                let guard = lock.try_write();
+                if reads.is_some() || more_reads.is_some() || write.is_some() || upgradable.is_some() {
+                    assert!(guard.is_none());
+                }
+                write = write.or(guard);
+            }
+            RwOp::TryUpgradable => {
+                let guard = lock.try_upgradable_read();
+                if write.is_some() || upgradable.is_some() {
+                    assert!(guard.is_none());
+                }
+                upgradable = upgradable.or(guard);
+            }
+            RwOp::DropRead => reads = more_reads.take(),
+            RwOp::DropWrite => write = None,
+            RwOp::DropUpgradable => upgradable = None,
+            RwOp::TryUpgrade => {
+                if let Some(guard) = upgradable.take() {
+                    match UpgradableGuard::try_upgrade(guard) {
+                        Ok(guard) => {
+                            assert!(reads.is_none() && more_reads.is_none());
+                            write = Some(guard);
+                        }
+                        Err(guard) => upgradable = Some(guard),
+                    }
+                }
+            }
+            RwOp::Downgrade if reads.is_none() => reads = write.take().map(WriteGuard::downgrade),
+            RwOp::DowngradeToUpgradable => {
+                if let Some(guard) = write.take() {
+                    upgradable = Some(WriteGuard::downgrade_to_upgradable(guard));
+                }
+            }
+            RwOp::DowngradeUpgradable if reads.is_none() => {
+                reads = upgradable.take().map(UpgradableGuard::downgrade);
+            }
+            _ => {}
+        }
+        let readers = reads.is_some() as usize + more_reads.is_some() as usize;
+        assert!(write.is_none() || (readers == 0 && upgradable.is_none()));
+        assert_eq!(lock.readers(), readers);
+        assert_eq!(lock.is_locked_exclusive(), write.is_some());
+    }
+}
@@ -0,0 +1,67 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Readers of a [LeftRight] racing writers.
+*/
+#![cfg(feature = "std")]
+
+use atomiclock_spinlock::LeftRight;
+use std::thread;
+
+const ITERATIONS: u64 = 10_000;
+
+#[test]
+fn reads_see_whole_writes_in_order() {
+    let data = LeftRight::new([0u64; 8]);
+    thread::scope(|s| {
+        s.spawn(|| {
+            for n in 1..=ITERATIONS {
+                data.write(|data| *data = [n; 8]);
+            }
+        });
+        for _ in 0..2 {
+            s.spawn(|| {
+                let mut last = 0;
+                while last < ITERATIONS {
+                    let read = *data.read();
+                    assert!(read.iter().all(|&n| n == read[0]), "torn {read:?}");
+                    assert!(read[0] >= last);
+                    last = read[0];
+                }
+            });
+        }
+    });
+}
+
+#[test]
+fn writers_update_both_copies() {
+    let data = LeftRight::new(0u64);
+    thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                for _ in 0..1000 {
+                    data.write(|n| *n += 1);
+                }
+            });
+        }
+    });
+    let mut data = data;
+    let (left, right) = data.get_mut();
+    assert_eq!((*left, *right), (4000, 4000));
+}
+
+#[test]
+fn a_held_read_keeps_its_copy() {
+    let data = LeftRight::new(1);
+    let before = data.read();
+    thread::scope(|s| {
+        let writer = s.spawn(|| data.write(|n| *n = 2));
+        //the writer has updated the other copy, and waits for this read
+        while *data.read() != 2 {
+            std::hint::spin_loop();
+        }
+        assert_eq!(*before, 1);
+        assert!(!writer.is_finished());
+        drop(before);
+    });
+    assert_eq!(format!("{data:?}"), "LeftRight(2)");
+}
@@ -0,0 +1,66 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Overriding the weak hooks at link time, with the `link-hooks` feature.
+
+Run with `cargo test --features link-hooks --test link_hooks`.
+*/
+
+use atomiclock_spinlock::Lock;
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use std::sync::Barrier;
+
+//the lock under test, so hooks from other locks aren't counted
+static WATCHED: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut());
+static CONTENDED: AtomicUsize = AtomicUsize::new(0);
+static RELEASED: AtomicUsize = AtomicUsize::new(0);
+static NAMED: AtomicUsize = AtomicUsize::new(0);
+
+fn count(counter: &AtomicUsize, lock: *const c_void, name: *const u8, name_len: usize) {
+    if lock != WATCHED.load(Ordering::Relaxed) {
+        return;
+    }
+    counter.fetch_add(1, Ordering::Relaxed);
+    let name = unsafe { std::slice::from_raw_parts(name, name_len) };
+    if name == b"watched" {
+        NAMED.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn __spinlock_on_contention(lock: *const c_void, name: *const u8, name_len: usize) {
+    count(&CONTENDED, lock, name, name_len);
+}
+
+#[no_mangle]
+pub extern "C" fn __spinlock_on_release(lock: *const c_void, name: *const u8, name_len: usize) {
+    count(&RELEASED, lock, name, name_len);
+}
+
+#[test]
+fn overrides_are_called() {
+    let lock = Lock::with_name(0, "watched");
+    WATCHED.store(&lock as *const Lock<i32> as *mut c_void, Ordering::Relaxed);
+    *lock.spin_lock() += 1;
+    assert_eq!(CONTENDED.load(Ordering::Relaxed), 0);
+    assert_eq!(RELEASED.load(Ordering::Relaxed), 1);
+
+    let barrier = Barrier::new(2);
+    std::thread::scope(|s| {
+        let guard = lock.spin_lock();
+        s.spawn(|| {
+            barrier.wait();
+            *lock.spin_lock() += 1;
+        });
+        barrier.wait();
+        //until the other thread is spinning
+        while lock.waiters() == 0 {
+            std::hint::spin_loop();
+        }
+        drop(guard);
+    });
+    assert_eq!(*lock.spin_lock(), 2);
+    assert!(CONTENDED.load(Ordering::Relaxed) >= 1);
+    assert_eq!(RELEASED.load(Ordering::Relaxed), 4);
+    assert_eq!(NAMED.load(Ordering::Relaxed), CONTENDED.load(Ordering::Relaxed) + 4);
+}
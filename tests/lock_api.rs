@@ -0,0 +1,18 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+The spinlocks through `lock_api`, with the `lock_api` feature.
+*/
+#![cfg(feature = "std")]
+
+use atomiclock_spinlock::lock_api::Mutex;
+use std::time::Duration;
+
+#[test]
+fn timeouts_too_long_for_a_deadline_wait() {
+    let mutex = Mutex::new(0);
+    *mutex.try_lock_for(Duration::MAX).unwrap() += 1;
+    let held = mutex.lock();
+    assert!(mutex.try_lock_for(Duration::from_millis(1)).is_none());
+    drop(held);
+    assert_eq!(*mutex.try_lock_for(Duration::MAX).unwrap(), 1);
+}
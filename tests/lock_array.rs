@@ -0,0 +1,49 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Striping across a [LockArray].
+*/
+#![cfg(feature = "std")]
+
+use atomiclock_spinlock::{Lock, LockArray};
+use std::collections::hash_map::RandomState;
+use std::thread;
+
+static COUNTS: LockArray<u64, 4> = LockArray::new([0; 4]);
+
+#[test]
+fn stripes_by_thread() {
+    thread::scope(|s| {
+        for thread in 0..8 {
+            s.spawn(move || {
+                for _ in 0..1000 {
+                    *COUNTS.stripe(thread).spin_lock() += 1;
+                }
+            });
+        }
+    });
+    //two threads per stripe
+    assert!(COUNTS.iter().all(|count| *count.spin_lock() == 2000));
+}
+
+#[test]
+fn stripes_by_key() {
+    let hasher = RandomState::new();
+    let buckets: LockArray<Vec<&str>, 8> = Lock::new_array(|_| Vec::new());
+    let keys = ["alpha", "beta", "gamma", "delta", "alpha"];
+    for key in keys {
+        buckets.stripe_for(key, &hasher).spin_lock().push(key);
+    }
+    //equal keys land in the same bucket
+    assert_eq!(buckets.stripe_for("alpha", &hasher).spin_lock().iter().filter(|&&key| key == "alpha").count(), 2);
+    let mut all: Vec<&str> = buckets.into_inner().into_iter().flatten().collect();
+    all.sort_unstable();
+    assert_eq!(all, ["alpha", "alpha", "beta", "delta", "gamma"]);
+}
+
+#[test]
+fn each_lock_on_its_own_line() {
+    let locks: LockArray<u8, 3> = Lock::new_array(|index| index as u8);
+    let addresses: Vec<usize> = locks.iter().map(|lock| lock as *const Lock<u8> as usize).collect();
+    assert!(addresses.windows(2).all(|pair| pair[1] - pair[0] >= 128));
+    assert_eq!((locks.len(), locks.get(3).is_none(), *locks[2].spin_lock()), (3, true, 2));
+}
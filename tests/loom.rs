@@ -0,0 +1,137 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Model-checks the lock paths with [loom](https://crates.io/crates/loom).
+
+Run with `RUSTFLAGS="--cfg loom" cargo test --release --test loom`, and with `--features handoff` to check
+handing off too.
+*/
+#![cfg(loom)]
+
+use atomiclock_spinlock::{Clock, Lock};
+use core::cell::Cell;
+use loom::sync::atomic::{AtomicBool, Ordering};
+use loom::sync::Arc;
+use loom::thread;
+
+//a clock that ticks once per reading, so deadlines are a number of attempts
+struct Attempts(Cell<u32>);
+
+impl Clock for Attempts {
+    type Instant = u32;
+    type Duration = u32;
+    fn now(&self) -> u32 {
+        let now = self.0.get();
+        self.0.set(now + 1);
+        now
+    }
+}
+
+//checks that no two guards are alive at once
+struct Exclusive(AtomicBool);
+
+impl Exclusive {
+    fn enter(&self) {
+        assert!(!self.0.swap(true, Ordering::Relaxed), "two guards alive at once");
+    }
+    fn leave(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn spin_lock() {
+    loom::model(|| {
+        let lock = Arc::new((Lock::new(0), Exclusive(AtomicBool::new(false))));
+        let threads: Vec<_> = (0..2)
+            .map(|_| {
+                let lock = lock.clone();
+                thread::spawn(move || {
+                    let mut guard = lock.0.spin_lock();
+                    lock.1.enter();
+                    *guard += 1;
+                    lock.1.leave();
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+        assert_eq!(*lock.0.spin_lock(), 2);
+    });
+}
+
+//with handoff, the waiter asks on its first failed attempt, so a release may hand it the lock
+#[cfg(feature = "handoff")]
+#[test]
+fn handoff() {
+    let mut settings = atomiclock_spinlock::config::Settings::new();
+    settings.handoff_after = 0;
+    atomiclock_spinlock::config::set_defaults(settings);
+    loom::model(|| {
+        let lock = Arc::new((Lock::new(0), Exclusive(AtomicBool::new(false))));
+        let threads: Vec<_> = (0..2)
+            .map(|_| {
+                let lock = lock.clone();
+                thread::spawn(move || {
+                    let mut guard = lock.0.spin_lock();
+                    lock.1.enter();
+                    *guard += 1;
+                    lock.1.leave();
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+        assert_eq!(*lock.0.spin_lock(), 2);
+    });
+}
+
+#[test]
+fn try_lock() {
+    loom::model(|| {
+        let lock = Arc::new((Lock::new(0), Exclusive(AtomicBool::new(false))));
+        let other = lock.clone();
+        let t = thread::spawn(move || {
+            if let Some(mut guard) = other.0.try_lock() {
+                other.1.enter();
+                *guard += 1;
+                other.1.leave();
+            }
+        });
+        if let Some(mut guard) = lock.0.try_lock() {
+            lock.1.enter();
+            *guard += 1;
+            lock.1.leave();
+        }
+        t.join().unwrap();
+        //at least one attempt succeeds, since a failed one means the other held the lock
+        let count = *lock.0.spin_lock();
+        assert!(count == 1 || count == 2);
+        assert!(!lock.0.is_locked());
+    });
+}
+
+#[test]
+fn spin_lock_until() {
+    loom::model(|| {
+        let lock = Arc::new((Lock::new(0), Exclusive(AtomicBool::new(false))));
+        let other = lock.clone();
+        let t = thread::spawn(move || {
+            let mut guard = other.0.spin_lock();
+            other.1.enter();
+            *guard += 1;
+            other.1.leave();
+        });
+        let clock = Attempts(Cell::new(0));
+        if let Some(mut guard) = lock.0.spin_lock_until_with(&clock, 2) {
+            lock.1.enter();
+            *guard += 1;
+            lock.1.leave();
+        }
+        t.join().unwrap();
+        //a timed-out attempt leaves the lock usable
+        let count = *lock.0.spin_lock();
+        assert!(count == 1 || count == 2);
+    });
+}
@@ -0,0 +1,745 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Exercises the public API, for [Miri](https://github.com/rust-lang/miri) to check the unsafe plumbing underneath.
+
+Run with `MIRIFLAGS="-Zmiri-strict-provenance" cargo +nightly miri test --test miri`.  The tests also run
+natively, as part of `cargo test`.
+*/
+#![cfg(feature = "std")]
+
+use atomiclock_spinlock::ceiling::Priority;
+use atomiclock_spinlock::intent::{Exclusive, IntentExclusive, IntentGuard, IntentLock, IntentShared, Shared};
+use atomiclock_spinlock::intrusive::{Guarded, Intrusive};
+use atomiclock_spinlock::rwlock::{MappedWriteGuard, ReadGuard, UpgradableGuard, WriteGuard};
+use atomiclock_spinlock::collections::{AppendLog, Pool, PoolGuard};
+use atomiclock_spinlock::{LockView, MappedGuard, BudgetLock, CeilingLock, DynLock, Guard, KeyedLocks, Lazy, Lock, ProcessLock, RangeLock, RawSpinLock, RwLock};
+use std::future::Future;
+use std::pin::{pin, Pin};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread;
+
+//a waker that counts its wakeups
+struct Counter(AtomicUsize);
+
+impl Wake for Counter {
+    fn wake(self: Arc<Self>) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+fn counting_waker() -> (Arc<Counter>, Waker) {
+    let counter = Arc::new(Counter(AtomicUsize::new(0)));
+    (counter.clone(), Waker::from(counter))
+}
+
+#[test]
+fn guards() {
+    let lock = Lock::with_name(1, "guards");
+    *lock.spin_lock() += 1;
+    *lock.spin_lock_checked().unwrap() += 1;
+    //try_lock may fail spuriously, and Miri makes sure it sometimes does
+    while lock.try_lock().map(|mut guard| *guard += 1).is_none() {}
+    *lock.spin_lock_for(std::time::Duration::from_secs(10)).unwrap() += 1;
+    {
+        let mut guard = lock.spin_lock();
+        *guard.get_mut() += 1;
+        assert!(lock.try_lock().is_none());
+        assert!(format!("{lock:?}").contains("<locked>"));
+    }
+    let raw = Guard::into_raw(lock.spin_lock());
+    assert!(lock.is_locked());
+    assert_eq!(raw, &lock as *const _);
+    *unsafe { lock.guard_from_raw() } += 1;
+    *Guard::from(&lock) += 1;
+    assert_eq!(lock.into_inner(), 8);
+}
+
+#[test]
+fn assertions() {
+    let lock = Lock::new(0);
+    atomiclock_spinlock::assert_unlocked!(lock);
+    let guard = lock.spin_lock();
+    atomiclock_spinlock::assert_held_by_current!(lock);
+    thread::scope(|s| {
+        assert!(s.spawn(|| atomiclock_spinlock::assert_unlocked!(lock)).join().is_err());
+        assert!(s.spawn(|| atomiclock_spinlock::assert_held_by_current!(lock, "not ours")).join().is_err());
+    });
+    drop(guard);
+    atomiclock_spinlock::assert_unlocked!(&lock, "released");
+}
+
+#[test]
+fn contended() {
+    let lock = Arc::new(Lock::new(0));
+    let threads: Vec<_> = (0..3)
+        .map(|_| {
+            let lock = lock.clone();
+            thread::spawn(move || {
+                for _ in 0..10 {
+                    *lock.spin_lock() += 1;
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    assert_eq!(*lock.spin_lock(), 30);
+}
+
+#[test]
+fn guard_variants() {
+    let lock = Arc::new(Lock::new(vec![1]));
+    let owned = lock.spin_lock_owned();
+    assert!(lock.try_lock_owned().is_none());
+    //owned guards may move to another thread
+    thread::spawn(move || {
+        let mut owned = owned;
+        owned.push(2);
+    })
+    .join()
+    .unwrap();
+    let mut local = lock.spin_lock_local();
+    local.push(3);
+    drop(local);
+    let mut local = Guard::into_local(lock.spin_lock());
+    local.push(4);
+    drop(local);
+    assert_eq!(*lock.spin_lock_local(), [1, 2, 3, 4]);
+}
+
+#[test]
+fn transaction() {
+    let lock = Lock::new(vec![1]);
+    let mut guard = lock.spin_lock();
+    let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        Guard::transaction(&mut guard, |v| -> Result<(), ()> {
+            v.push(2);
+            panic!("mid-transaction");
+        })
+    }));
+    assert!(panicked.is_err());
+    assert_eq!(*guard, [1]);
+    assert_eq!(Guard::transaction(&mut guard, |v| v.pop().ok_or(())), Ok(1));
+    assert!(guard.is_empty());
+}
+
+#[test]
+fn unlock_hooks() {
+    let lock = Lock::new(vec![1]);
+    let mut hooked = Guard::on_unlock(lock.spin_lock(), |v| v.push(0));
+    hooked.push(2);
+    drop(hooked);
+    let hooked = Guard::on_unlock(lock.spin_lock(), |v| v.clear());
+    let mut guard = atomiclock_spinlock::HookedGuard::into_guard(hooked);
+    guard.push(3);
+    drop(guard);
+    assert_eq!(*lock.spin_lock(), [1, 2, 0, 3]);
+}
+
+#[test]
+fn pinned() {
+    let mut lock = Box::pin(atomiclock_spinlock::PinLock::new(vec![1]));
+    lock.as_ref().spin_lock().push(2);
+    lock.as_mut().get_pin_mut().push(3);
+    assert_eq!(Pin::into_inner(lock).into_inner(), [1, 2, 3]);
+}
+
+#[test]
+fn sealed() {
+    let lock = Lock::new(vec![1]);
+    let data = Guard::forget_locked(lock.spin_lock());
+    data.push(2);
+    assert!(lock.try_lock().is_none());
+    assert_eq!(data, &[1, 2]);
+}
+
+#[test]
+fn lock_all() {
+    let locks = [Lock::new(vec![0]), Lock::new(vec![1])];
+    let held = locks[1].spin_lock();
+    //try_lock may fail spuriously, so the first lock can report as held too
+    while Lock::try_lock_all([&locks[0], &locks[1]]).map(|_| ()).unwrap_err().index() != 1 {}
+    //the first lock was released again
+    locks[0].spin_lock().push(2);
+    drop(held);
+    let [a, b] = loop {
+        if let Ok(guards) = Lock::try_lock_all([&locks[0], &locks[1]]) {
+            break guards;
+        }
+    };
+    assert_eq!((&*a, &*b), (&vec![0, 2], &vec![1]));
+}
+
+#[test]
+fn lock_many() {
+    let locks: Vec<Lock<u32>> = (0..3).map(Lock::new).collect();
+    let held = locks[2].spin_lock();
+    //try_lock may fail spuriously, so an earlier lock can report as held too
+    while Lock::try_lock_many(&locks).map(|_| ()).unwrap_err().index() != 2 {}
+    //the earlier locks were released again
+    *locks[0].spin_lock() += 10;
+    drop(held);
+    let guards = loop {
+        if let Ok(guards) = Lock::try_lock_many(locks.iter().rev()) {
+            break guards;
+        }
+    };
+    assert_eq!(guards.iter().map(|g| **g).collect::<Vec<_>>(), [2, 1, 10]);
+}
+
+#[test]
+fn range_lock() {
+    let buffer = RangeLock::new(vec![0u32; 8]);
+    std::thread::scope(|s| {
+        for chunk in 0..4 {
+            let buffer = &buffer;
+            s.spawn(move || {
+                let mut range = buffer.lock_range(chunk * 2..chunk * 2 + 2);
+                range.fill(chunk as u32);
+            });
+        }
+    });
+    let halves = (buffer.lock_range(..4), buffer.lock_range(4..));
+    assert!(buffer.try_lock_range(3..5).is_none());
+    assert_eq!((&*halves.0, &*halves.1), (&[0, 0, 1, 1][..], &[2, 2, 3, 3][..]));
+    drop(halves);
+    assert!(!buffer.is_locked(..));
+    assert_eq!(buffer.into_inner(), [0, 0, 1, 1, 2, 2, 3, 3]);
+}
+
+#[test]
+fn lock_range() {
+    let buffer = Lock::new(vec![0u32; 8]);
+    std::thread::scope(|s| {
+        for chunk in 0..4 {
+            let buffer = &buffer;
+            s.spawn(move || {
+                let mut range = buffer.lock_range(chunk * 2..chunk * 2 + 2);
+                range.fill(chunk as u32);
+            });
+        }
+    });
+    let halves = (buffer.lock_range(..4), buffer.lock_range(4..));
+    assert!(buffer.try_lock_range(3..5).is_none());
+    //the ranges hold the lock
+    assert!(buffer.try_lock().is_none());
+    assert_eq!((&*halves.0, &*halves.1), (&[0, 0, 1, 1][..], &[2, 2, 3, 3][..]));
+    drop(halves);
+    buffer.spin_lock().push(4);
+    assert_eq!(*buffer.lock_range(7..), [3, 4]);
+    assert_eq!(buffer.into_inner(), [0, 0, 1, 1, 2, 2, 3, 3, 4]);
+}
+
+#[test]
+fn intent_lock() {
+    let tree = IntentLock::new([IntentLock::new(0), IntentLock::new(0)]);
+    std::thread::scope(|s| {
+        for i in 0..2 {
+            let tree = &tree;
+            s.spawn(move || {
+                let root: IntentGuard<_, IntentExclusive> = tree.lock();
+                let mut leaf: IntentGuard<_, Exclusive> = root[i].lock_under(&root);
+                *leaf += 1;
+            });
+        }
+    });
+    //try_lock may fail spuriously, so these retry
+    let intent = loop {
+        if let Some(guard) = tree.try_lock::<IntentShared>() {
+            break guard;
+        }
+    };
+    //intention modes are compatible with each other, but a writer below excludes a reader above
+    let writing: IntentGuard<_, IntentExclusive> = tree.lock();
+    assert!(tree.try_lock::<Shared>().is_none());
+    assert!(tree.try_lock::<Exclusive>().is_none());
+    drop(writing);
+    let reading: IntentGuard<_, Shared> = tree.lock();
+    assert!(tree.try_lock::<IntentExclusive>().is_none());
+    assert_eq!(reading.iter().map(|leaf| *leaf.lock_under::<Shared, _, _>(&reading)).sum::<u32>(), 2);
+    drop((reading, intent));
+    assert!(!tree.is_locked());
+}
+
+#[test]
+fn extended_lifetime() {
+    struct Context {
+        guard: Option<Guard<'static, Vec<u32>>>,
+    }
+    let lock = Box::new(Lock::new(vec![1]));
+    //the lock outlives the context, which drops the guard first
+    let mut context = Context { guard: Some(unsafe { Guard::extend_lifetime(lock.spin_lock()) }) };
+    context.guard.as_mut().unwrap().push(2);
+    context.guard = None;
+    assert_eq!(*lock.spin_lock(), [1, 2]);
+}
+
+#[test]
+fn split_guards() {
+    let lock = Lock::new((vec![1], vec![2]));
+    let (mut a, b) = Guard::split(lock.spin_lock());
+    let b = thread::scope(|s| {
+        s.spawn(move || {
+            assert_eq!(*b, [2]);
+            b
+        })
+        .join()
+        .unwrap()
+    });
+    a.push(3);
+    drop(a);
+    //still held by the other part
+    assert!(lock.try_lock().is_none());
+    drop(b);
+    assert_eq!(lock.spin_lock().0, [1, 3]);
+}
+
+atomiclock_spinlock::static_spinlock! {
+    static STATIC: Vec<u32> = Vec::new();
+}
+
+atomiclock_spinlock::spin_lazy_static! {
+    static ref LAZY: Vec<u32> = vec![1, 2, 3];
+}
+
+#[test]
+fn statics() {
+    STATIC.spin_lock().push(1);
+    assert_eq!(STATIC.name(), Some("STATIC"));
+    assert_eq!(*STATIC.spin_lock(), [1]);
+    assert_eq!(LAZY.len(), 3);
+    let lazy = Lazy::new(|| String::from("lazy"));
+    assert!(Lazy::get(&lazy).is_none());
+    assert_eq!(Lazy::force(&lazy), "lazy");
+    assert_eq!(Lazy::get(&lazy).map(String::as_str), Some("lazy"));
+}
+
+#[test]
+fn rwlock() {
+    let lock = RwLock::new(vec![1]);
+    {
+        let a = lock.read();
+        let b = lock.try_read().unwrap();
+        assert_eq!(lock.readers(), 2);
+        assert!(lock.try_write().is_none());
+        assert_eq!(*a, *b);
+    }
+    lock.write().push(2);
+    let upgradable = lock.upgradable_read();
+    assert!(lock.try_upgradable_read().is_none());
+    let read = lock.read();
+    let upgradable = UpgradableGuard::try_upgrade(upgradable).unwrap_err();
+    drop(read);
+    let mut write = UpgradableGuard::upgrade(upgradable);
+    write.push(3);
+    let upgradable = WriteGuard::downgrade_to_upgradable(write);
+    let read = UpgradableGuard::downgrade(upgradable);
+    assert_eq!(*read, [1, 2, 3]);
+    drop(read);
+    let read = WriteGuard::downgrade(lock.write());
+    assert!(lock.try_write().is_none());
+    drop(read);
+    assert!(!lock.is_locked_exclusive());
+    assert_eq!(lock.into_inner(), [1, 2, 3]);
+}
+
+#[test]
+fn rwlock_mapped_guards() {
+    let lock = RwLock::new((String::from("name"), vec![1, 2]));
+    let name = ReadGuard::map(lock.read(), |(name, _)| name.as_str());
+    let first = ReadGuard::try_map(lock.read(), |(_, list)| list.first()).unwrap();
+    assert_eq!((&*name, *first), ("name", 1));
+    assert!(lock.try_write().is_none());
+    drop((name, first));
+
+    let list = WriteGuard::map(lock.write(), |(_, list)| list);
+    let list = MappedWriteGuard::try_map(list, |list| list.get_mut(5)).unwrap_err();
+    let mut last = MappedWriteGuard::map(list, |list| list.last_mut().unwrap());
+    *last += 1;
+    assert_eq!(lock.readers(), 0);
+    drop(last);
+    //a panicking projection releases the write
+    let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        drop(MappedWriteGuard::map(WriteGuard::map(lock.write(), |(_, list)| list), |_| -> &mut i32 { panic!() }));
+    }));
+    assert!(panicked.is_err());
+    assert!(!lock.is_locked_exclusive());
+    assert_eq!(lock.into_inner().1, [1, 3]);
+}
+
+struct NoPriority;
+impl Priority for NoPriority {
+    type Level = u8;
+    fn raise(ceiling: u8) -> u8 {
+        ceiling
+    }
+    fn restore(_: u8) {}
+}
+
+#[test]
+fn dynamic() {
+    let plain = Lock::new(1);
+    let ceiling: CeilingLock<u32, NoPriority> = CeilingLock::new(2, 5);
+    let locks: [&dyn DynLock; 2] = [&plain, &ceiling];
+    let guards: Vec<_> = locks.iter().map(|lock| lock.lock_dyn()).collect();
+    assert!(locks.iter().all(|lock| lock.try_lock_dyn().is_none()));
+    drop(guards);
+    *ceiling.lock() += 1;
+    assert_eq!(ceiling.into_inner(), 3);
+    let mutex = atomiclock_spinlock::compat::spin::Mutex::new(4);
+    *mutex.lock() += 1;
+    assert_eq!(mutex.into_inner(), 5);
+}
+
+#[test]
+fn async_waiters() {
+    let lock = Lock::new(0);
+    let held = lock.spin_lock();
+    let (first, first_waker) = counting_waker();
+    let (second, second_waker) = counting_waker();
+    let mut a = pin!(lock.lock_async());
+    let mut b = pin!(lock.lock_async());
+    assert!(a.as_mut().poll(&mut Context::from_waker(&first_waker)).is_pending());
+    assert!(b.as_mut().poll(&mut Context::from_waker(&second_waker)).is_pending());
+    //a dropped waiter leaves the list
+    {
+        let mut c = pin!(lock.lock_async());
+        assert!(c.as_mut().poll(&mut Context::from_waker(&second_waker)).is_pending());
+    }
+    drop(held);
+    assert_eq!(first.0.load(Ordering::Relaxed), 1);
+    let Poll::Ready(mut guard) = a.as_mut().poll(&mut Context::from_waker(&first_waker)) else { panic!() };
+    *guard += 1;
+    drop(guard);
+    assert_eq!(second.0.load(Ordering::Relaxed), 1);
+    let Poll::Ready(guard) = b.as_mut().poll(&mut Context::from_waker(&second_waker)) else { panic!() };
+    assert_eq!(*guard, 1);
+    let mut polled = pin!(std::future::poll_fn(|cx| lock.poll_lock(cx).map(|_| ())));
+    assert!(polled.as_mut().poll(&mut Context::from_waker(&first_waker)).is_pending());
+    drop(guard);
+    assert!(polled.as_mut().poll(&mut Context::from_waker(&first_waker)).is_ready());
+}
+
+#[test]
+fn async_conditions() {
+    let lock = Lock::new(0);
+    let (woken, waker) = counting_waker();
+    let mut cx = Context::from_waker(&waker);
+    let mut waiting = pin!(lock.wait_async_until(|n| *n > 0));
+    assert!(waiting.as_mut().poll(&mut cx).is_pending());
+    //the waiter released the lock
+    *lock.spin_lock() += 1;
+    assert_eq!(woken.0.load(Ordering::Relaxed), 1);
+    assert!(waiting.as_mut().poll(&mut cx).is_ready());
+    let held = lock.spin_lock();
+    let mut timeout = pin!(lock.lock_async_timeout(std::time::Duration::ZERO));
+    let Poll::Ready(None) = timeout.as_mut().poll(&mut cx) else { panic!() };
+    drop(held);
+}
+
+#[test]
+fn blocking_pool() {
+    static LOCK: Lock<u32> = Lock::new(0);
+    let held = LOCK.spin_lock();
+    let (_, waker) = counting_waker();
+    let mut cx = Context::from_waker(&waker);
+    let spawner = |job| {
+        thread::spawn(job);
+    };
+    let mut future = pin!(LOCK.lock_blocking(std::time::Duration::ZERO, spawner));
+    assert!(future.as_mut().poll(&mut cx).is_pending());
+    assert!(future.as_mut().poll(&mut cx).is_pending());
+    drop(held);
+    let guard = loop {
+        if let Poll::Ready(guard) = future.as_mut().poll(&mut cx) {
+            break guard;
+        }
+        thread::yield_now();
+    };
+    drop(guard);
+    drop(LOCK.spin_lock());
+}
+
+#[test]
+fn append_log() {
+    let log = AppendLog::<String, 8>::new();
+    thread::scope(|s| {
+        s.spawn(|| {
+            for n in 0..8 {
+                log.append(n.to_string()).unwrap();
+            }
+        });
+        let mut seen = 0;
+        while seen < 8 {
+            for (n, entry) in log.iter().enumerate().skip(seen) {
+                assert_eq!(*entry, n.to_string());
+                seen += 1;
+            }
+            thread::yield_now();
+        }
+    });
+    assert_eq!(log.append(String::new()), Err(String::new()));
+    //half full, so drop only drops what was appended
+    let log = AppendLog::<Box<u32>, 4>::new();
+    log.append(Box::new(1)).unwrap();
+    log.append(Box::new(2)).unwrap();
+    drop(log);
+}
+
+#[test]
+fn pool() {
+    let pool = Pool::<Box<u32>, 1>::new(|| Box::new(0));
+    let mut first = pool.acquire();
+    **first = 1;
+    let second = pool.acquire();
+    let detached = PoolGuard::detach(pool.acquire());
+    drop(first);
+    //the pool is full, so this one is dropped
+    drop(second);
+    assert_eq!(**pool.try_acquire().unwrap(), 1);
+    assert_eq!(*detached, 0);
+}
+
+#[test]
+fn spin_budget() {
+    let mut lock = BudgetLock::<u32, 1000>::new(1);
+    *lock.get_mut() += 1;
+    let held = lock.as_lock().spin_lock();
+    assert!(lock.spin_lock().is_none());
+    drop(held);
+    *lock.spin_lock().unwrap() += 1;
+    assert_eq!(lock.into_inner(), 3);
+}
+
+#[test]
+fn raw_spin_lock() {
+    let lock = RawSpinLock::new();
+    lock.lock();
+    assert!(!lock.try_lock());
+    unsafe { lock.unlock() };
+    //try_lock can fail spuriously under miri
+    while !lock.try_lock() {}
+    assert!(lock.is_locked());
+    unsafe { lock.unlock() };
+    assert_eq!(lock.as_lock().live_guards(), 0);
+}
+
+#[test]
+fn keyed_locks() {
+    let locks = KeyedLocks::new();
+    let first = locks.lock(&1);
+    let second = locks.lock(&2);
+    assert_eq!(locks.len(), 2);
+    assert!(locks.is_locked(&1));
+    assert_eq!(*first.key(), 1);
+    drop(first);
+    //try_lock can fail spuriously under miri
+    let again = loop {
+        if let Some(guard) = locks.try_lock(&1) {
+            break guard;
+        }
+    };
+    drop(again);
+    drop(second);
+    assert!(locks.is_empty());
+}
+
+struct Node {
+    lock: RawSpinLock,
+    id: u32,
+    visits: Guarded<u64>,
+}
+//the lock word is always the field, and Node holds no other Intrusive struct
+unsafe impl Intrusive for Node {
+    fn lock_word(&self) -> &RawSpinLock {
+        &self.lock
+    }
+}
+
+#[test]
+fn intrusive_lock() {
+    let node = Node { lock: RawSpinLock::new(), id: 1, visits: Guarded::new(0) };
+    let mut guard = node.lock();
+    *guard.get(|node| &node.visits) += guard.id as u64;
+    assert!(node.try_lock().is_none());
+    drop(guard);
+    let mut node = node;
+    assert_eq!(*node.visits.get_mut(), 1);
+}
+
+#[test]
+fn process_lock() {
+    let mut segment = vec![std::mem::MaybeUninit::<u64>::uninit(); 2];
+    let (ptr, len) = (segment.as_mut_ptr().cast::<u8>(), segment.len() * 8);
+    let lock = unsafe { ProcessLock::init_in_place(ptr, len, 0u64) };
+    let attached = unsafe { ProcessLock::<u64>::from_raw_parts(ptr, len) };
+    std::thread::scope(|s| {
+        for lock in [lock, attached] {
+            s.spawn(move || *lock.spin_lock() += 1);
+        }
+    });
+    let held = attached.spin_lock();
+    assert!(lock.try_lock().is_none());
+    assert_eq!(*held, 2);
+}
+
+#[test]
+fn debug_state() {
+    let lock = Lock::with_name(0, "counter");
+    assert!(!lock.debug_state().locked);
+    let guard = lock.spin_lock();
+    let state = lock.debug_state();
+    assert!(state.locked);
+    assert_eq!(state.name, Some("counter"));
+    assert_eq!(state.live_guards, if cfg!(debug_assertions) { 1 } else { 0 });
+    assert_eq!(state.owner != 0, cfg!(debug_assertions));
+    drop(guard);
+    assert_eq!(lock.debug_state().owner, 0);
+}
+
+#[test]
+fn peek_racy() {
+    let lock = Lock::new([1u32, 2]);
+    lock.spin_lock()[1] = 3;
+    //with no guard alive, nothing races with it, so it's exact
+    assert_eq!(unsafe { lock.peek_racy() }, [1, 3]);
+}
+
+#[test]
+fn fences_order_a_relaxed_flag() {
+    use core::cell::UnsafeCell;
+    use core::sync::atomic::{AtomicBool, Ordering};
+    //a buffer handed over without the lock, as to a device
+    struct Buffer(UnsafeCell<u32>);
+    unsafe impl Sync for Buffer {}
+    impl Buffer {
+        fn get(&self) -> *mut u32 {
+            self.0.get()
+        }
+    }
+    let lock = Lock::new(());
+    let buffer = Buffer(UnsafeCell::new(0));
+    let ready = AtomicBool::new(false);
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            unsafe { *buffer.get() = 7 };
+            lock.release_fence();
+            ready.store(true, Ordering::Relaxed);
+        });
+        while !ready.load(Ordering::Relaxed) {
+            std::hint::spin_loop();
+        }
+        lock.acquire_fence();
+        //Miri reports a data race if the fences don't order the write before the read
+        assert_eq!(unsafe { *buffer.get() }, 7);
+    });
+}
+
+#[test]
+fn left_right() {
+    use atomiclock_spinlock::LeftRight;
+    let data = LeftRight::new(vec![0u32]);
+    thread::scope(|s| {
+        s.spawn(|| {
+            for n in 1..4 {
+                data.write(|data| data.push(n));
+            }
+        });
+        for _ in 0..2 {
+            s.spawn(|| {
+                //Miri reports a data race if a writer updates a copy that's being read
+                let read = data.read();
+                assert!(read.iter().copied().eq(0..read.len() as u32));
+            });
+        }
+    });
+    assert_eq!(data.into_inner(), [0, 1, 2, 3]);
+}
+
+#[test]
+fn atomic_cell() {
+    use atomiclock_spinlock::AtomicCellSpin;
+    //atomic, and locked
+    let small = AtomicCellSpin::new(0u32);
+    let wide = AtomicCellSpin::new([0u64; 3]);
+    thread::scope(|s| {
+        for _ in 0..2 {
+            s.spawn(|| {
+                small.update(|n| n + 1);
+                wide.update(|[a, b, c]| [a + 1, b, c]);
+            });
+        }
+    });
+    assert_eq!((small.get(), wide.get()), (2, [2, 0, 0]));
+}
+
+#[test]
+fn lock_views() {
+    struct State {
+        hits: u64,
+        log: Vec<&'static str>,
+    }
+    let lock = Lock::with_name(State { hits: 0, log: Vec::new() }, "state");
+    let hits: LockView<'_, State, u64> = lock.view(|state| &mut state.hits);
+    let log = lock.view(|state| &mut state.log);
+    thread::scope(|s| {
+        for _ in 0..2 {
+            s.spawn(move || {
+                *hits.spin_lock() += 1;
+                log.spin_lock().push("hit");
+            });
+        }
+    });
+    let held: MappedGuard<'_, State, u64> = hits.spin_lock();
+    assert!(log.try_lock().is_none());
+    assert_eq!(format!("{log:?}"), r#"LockView { name: Some("state"), locked: true }"#);
+    drop(held);
+    let first = MappedGuard::map(log.spin_lock(), |log| &mut log[0]);
+    assert_eq!(*first, "hit");
+    drop(first);
+    let state = lock.into_inner();
+    assert_eq!((state.hits, state.log.len()), (2, 2));
+}
+
+#[test]
+fn lock_array() {
+    use atomiclock_spinlock::LockArray;
+    static CONST: LockArray<u32, 2> = LockArray::new([1, 2]);
+    let mut built: LockArray<Vec<u32>, 3> = Lock::new_array(|index| vec![index as u32]);
+    thread::scope(|s| {
+        for n in 0..2 {
+            let built = &built;
+            s.spawn(move || {
+                *CONST.stripe(n).spin_lock() += 10;
+                built.stripe(n + 1).spin_lock().push(n as u32);
+            });
+        }
+    });
+    built.get_mut().for_each(|data| data.push(9));
+    assert_eq!((*CONST[0].spin_lock(), *CONST[1].spin_lock()), (11, 12));
+    assert_eq!(built.into_inner(), [vec![0, 9], vec![1, 0, 9], vec![2, 1, 9]]);
+}
+
+#[test]
+fn biased_rwlock() {
+    use atomiclock_spinlock::rwlock::BiasedRwLock;
+    let lock = BiasedRwLock::new(vec![0u32]);
+    thread::scope(|s| {
+        s.spawn(|| {
+            for n in 1..3 {
+                lock.write().push(n);
+            }
+        });
+        for _ in 0..2 {
+            s.spawn(|| {
+                //Miri reports a data race if a biased read overlaps a write
+                let read = lock.read();
+                assert!(read.iter().copied().eq(0..read.len() as u32));
+            });
+        }
+    });
+    assert_eq!(lock.into_inner(), [0, 1, 2]);
+}
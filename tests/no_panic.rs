@@ -0,0 +1,69 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Checks that the lock paths cannot panic.
+
+`#[no_panic]` fails to link if the optimizer can't prove a function never panics, so these only
+mean anything with optimizations: run them with `cargo test --release`.  Some features give up the
+guarantee, see the crate documentation.
+*/
+#![cfg(all(not(debug_assertions), not(loom)))]
+
+use atomiclock_spinlock::{Clock, Guard, Lock, WouldDeadlock};
+use core::sync::atomic::{AtomicU64, Ordering};
+use no_panic::no_panic;
+
+#[no_panic]
+fn spin_lock(lock: &Lock<u32>) -> Guard<'_, u32> {
+    lock.spin_lock()
+}
+
+#[no_panic]
+fn spin_lock_checked(lock: &Lock<u32>) -> Result<Guard<'_, u32>, WouldDeadlock> {
+    lock.spin_lock_checked()
+}
+
+#[no_panic]
+fn try_lock(lock: &Lock<u32>) -> Option<Guard<'_, u32>> {
+    lock.try_lock()
+}
+
+//std's clock can panic if the OS clock fails, so check the deadline path with a clock that can't
+struct Ticks(AtomicU64);
+
+impl Clock for Ticks {
+    type Instant = u64;
+    type Duration = u64;
+    fn now(&self) -> u64 {
+        self.0.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+#[no_panic]
+fn spin_lock_until_with<'a>(lock: &'a Lock<u32>, clock: &Ticks, deadline: u64) -> Option<Guard<'a, u32>> {
+    lock.spin_lock_until_with(clock, deadline)
+}
+
+//the cycle counter, which is what code that can't afford a panic would use
+#[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
+#[no_panic]
+fn spin_lock_until_cycles(lock: &Lock<u32>, deadline: u64) -> Option<Guard<'_, u32>> {
+    lock.spin_lock_until_with(&atomiclock_spinlock::clock::CycleClock, deadline)
+}
+
+//releasing is not checked: atomiclock asserts the lock was held, which the optimizer can't rule out
+
+#[test]
+fn lock_paths() {
+    let lock = Lock::new(0);
+    *spin_lock(&lock) += 1;
+    *spin_lock_checked(&lock).unwrap() += 1;
+    *try_lock(&lock).unwrap() += 1;
+    let clock = Ticks(AtomicU64::new(0));
+    *spin_lock_until_with(&lock, &clock, 10).unwrap() += 1;
+    let held = lock.spin_lock();
+    assert!(spin_lock_until_with(&lock, &clock, 20).is_none());
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
+    assert!(spin_lock_until_cycles(&lock, 0).is_none());
+    drop(held);
+    assert_eq!(lock.into_inner(), 4);
+}
@@ -0,0 +1,46 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Optimistic reads racing writers.
+
+Not for Miri: the readers' copies race with the writers by design, and are discarded when torn.
+*/
+#![cfg(feature = "std")]
+
+use atomiclock_spinlock::OptimisticLock;
+use std::thread;
+
+const ITERATIONS: u64 = 10_000;
+
+#[test]
+fn copies_are_never_torn() {
+    let lock = OptimisticLock::new([0u64; 8]);
+    thread::scope(|s| {
+        s.spawn(|| {
+            for n in 1..=ITERATIONS {
+                *lock.spin_lock() = [n; 8];
+            }
+        });
+        for _ in 0..2 {
+            s.spawn(|| {
+                let mut last = 0;
+                while last < ITERATIONS {
+                    let copy = lock.read(|data| *data);
+                    assert!(copy.iter().all(|&n| n == copy[0]), "torn {copy:?}");
+                    assert!(copy[0] >= last);
+                    last = copy[0];
+                }
+            });
+        }
+    });
+}
+
+#[test]
+fn upgrade_only_when_current() {
+    let lock = OptimisticLock::new(1);
+    let stale = lock.optimistic().unwrap();
+    let current = lock.optimistic().unwrap();
+    *current.upgrade().unwrap() += 1;
+    assert!(!stale.validate());
+    assert!(stale.upgrade().is_none());
+    assert_eq!(lock.into_inner(), 2);
+}
@@ -0,0 +1,56 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Timed-out acquisitions describe the holder, with the `owner-tracking` feature.
+
+Run with `cargo test --features owner-tracking --test owner_tracking`.
+*/
+
+use atomiclock_spinlock::Lock;
+use std::sync::Barrier;
+use std::time::{Duration, Instant};
+
+#[test]
+fn timeout_describes_the_holder() {
+    let lock = Lock::with_name(0, "cache");
+    let (locked, timed_out) = (Barrier::new(2), Barrier::new(2));
+    std::thread::scope(|s| {
+        let holder = std::thread::Builder::new()
+            .name("holder".to_string())
+            .spawn_scoped(s, || {
+                let guard = lock.spin_lock();
+                let line = line!() - 1;
+                locked.wait();
+                timed_out.wait();
+                drop(guard);
+                line
+            })
+            .unwrap();
+        locked.wait();
+        let info = lock.spin_lock_for_or_holder(Duration::from_millis(5)).unwrap_err();
+        timed_out.wait();
+        let line = holder.join().unwrap();
+        assert_eq!(info.name(), Some("cache"));
+        assert_eq!(info.thread().unwrap().name(), Some("holder"));
+        //held from before the wait, which gave up after at least 5ms
+        assert!(info.held_for().unwrap() >= Duration::from_millis(5));
+        let location = info.location().unwrap();
+        assert_eq!((location.file(), location.line()), (file!(), line));
+        let logged = info.to_string();
+        assert!(logged.starts_with(r#"lock "cache" is held by thread "holder" (ThreadId("#), "{logged}");
+        assert!(logged.ends_with(&format!("since {location}")), "{logged}");
+    });
+}
+
+#[test]
+fn released_locks_have_no_holder() {
+    let lock = Lock::new(0);
+    assert!(lock.holder().thread().is_none());
+    let guard = lock.try_lock().unwrap();
+    assert_eq!(lock.holder().location().unwrap().line(), line!() - 1);
+    drop(guard);
+    assert!(lock.holder().thread().is_none());
+    assert_eq!(lock.holder().to_string(), "lock is held by an unrecorded holder");
+    let deadline = Instant::now() + Duration::from_secs(1);
+    *lock.spin_lock_until_or_holder(deadline).unwrap() += 1;
+    assert!(lock.holder().held_for().is_none());
+}
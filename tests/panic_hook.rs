@@ -0,0 +1,57 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Listing the registered locks that are held, with the `diagnostics` feature.
+
+Run with `cargo test --features diagnostics --test panic_hook`, and with `owner-tracking` too for the holders.
+*/
+#![cfg(feature = "std")]
+
+use atomiclock_spinlock::{diagnostics, Lock};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+static QUEUE: Lock<Vec<u32>> = Lock::with_name(Vec::new(), "queue");
+static IDLE: Lock<u32> = Lock::with_name(0, "idle");
+static UNNAMED: Lock<u32> = Lock::new(0);
+
+fn held() -> String {
+    let mut held = String::new();
+    diagnostics::write_held(&mut held).unwrap();
+    held
+}
+
+#[test]
+fn lists_only_held_locks() {
+    diagnostics::register(&QUEUE);
+    diagnostics::register(&IDLE);
+    diagnostics::register(&UNNAMED);
+    let queue = QUEUE.spin_lock();
+    let unnamed = UNNAMED.spin_lock();
+    let line = line!() - 2;
+    let held = held();
+    let lines: Vec<&str> = held.lines().collect();
+    assert_eq!(lines.len(), 2, "{held}");
+    assert!(lines[0].starts_with(r#"lock "queue" is held"#), "{held}");
+    assert!(lines[1].starts_with(&format!("lock {:#x} is held", &UNNAMED as *const Lock<u32> as usize)), "{held}");
+    assert!(lines.iter().all(|line| line.ends_with("; 0 waiting")), "{held}");
+    if cfg!(feature = "owner-tracking") {
+        let thread = std::thread::current();
+        let by = match thread.name() {
+            Some(name) => format!(" by thread {name:?} ({:?})", thread.id()),
+            None => format!(" by thread {:?}", thread.id()),
+        };
+        assert!(lines[0].contains(&by), "{held}");
+        assert!(lines[0].contains(&format!("since {}:{line}:", file!())), "{held}");
+    }
+    drop((queue, unnamed));
+    assert_eq!(self::held(), "");
+
+    //the panicking thread's guard is still alive when the hook runs, and the hook doesn't wait on it
+    diagnostics::install_panic_hook();
+    diagnostics::install_panic_hook();
+    let panicked = catch_unwind(AssertUnwindSafe(|| {
+        let _queue = QUEUE.spin_lock();
+        panic!("under the lock");
+    }));
+    assert!(panicked.is_err());
+    assert_eq!(self::held(), "");
+}
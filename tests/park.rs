@@ -0,0 +1,60 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Spinning then parking, with the `park` feature.
+
+Run with `cargo test --features park --test park`.
+*/
+
+use atomiclock_spinlock::{config, Lock};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn parked_waiters_are_woken() {
+    //park on the first failed attempt
+    let mut settings = config::Settings::new();
+    settings.spins_before_yield = 0;
+    config::set_defaults(settings);
+
+    let lock = Lock::new(0u32);
+    thread::scope(|s| {
+        let guard = lock.spin_lock();
+        for _ in 0..4 {
+            s.spawn(|| {
+                for _ in 0..1000 {
+                    *lock.spin_lock() += 1;
+                }
+            });
+        }
+        //give the waiters time to park
+        thread::sleep(Duration::from_millis(50));
+        drop(guard);
+    });
+    assert_eq!(lock.into_inner(), 4000);
+}
+
+//formats slowly, so a waiter parks meanwhile
+struct Slow;
+
+impl std::fmt::Debug for Slow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        thread::sleep(Duration::from_millis(50));
+        f.write_str("Slow")
+    }
+}
+
+#[test]
+fn debug_wakes_parked_waiters() {
+    let mut settings = config::Settings::new();
+    settings.spins_before_yield = 0;
+    config::set_defaults(settings);
+
+    let lock = Lock::new(Slow);
+    thread::scope(|s| {
+        s.spawn(|| {
+            thread::sleep(Duration::from_millis(10));
+            drop(lock.spin_lock());
+        });
+        assert!(format!("{lock:?}").contains("data: Slow"));
+    });
+}
@@ -0,0 +1,86 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Poisoning, with the `poison` feature.
+
+Run with `cargo test --features poison --test poison`.
+*/
+
+use atomiclock_spinlock::{Guard, Lock};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+fn panic_holding(lock: &Lock<u32>) {
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let mut guard = lock.spin_lock();
+        *guard += 1;
+        panic!("while holding the lock");
+    }));
+    assert!(result.is_err());
+}
+
+#[test]
+fn panic_poisons() {
+    let lock = Lock::new(0);
+    assert!(!lock.is_poisoned());
+    assert!(lock.spin_lock_unpoisoned().is_ok());
+    panic_holding(&lock);
+    assert!(lock.is_poisoned());
+    assert!(lock.spin_lock_unpoisoned().is_err());
+    assert!(lock.try_lock_unpoisoned().unwrap().is_err());
+    //released on failure, and the plain acquisitions still work
+    assert_eq!(*lock.spin_lock(), 1);
+}
+
+#[test]
+fn held_reports_none() {
+    let lock = Lock::new(0);
+    let _guard = lock.spin_lock();
+    assert!(lock.try_lock_unpoisoned().is_none());
+}
+
+#[test]
+fn released_without_panic_is_clean() {
+    let lock = Lock::new(0);
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        drop(lock.spin_lock());
+        panic!("after releasing the lock");
+    }));
+    assert!(result.is_err());
+    assert!(!lock.is_poisoned());
+}
+
+#[test]
+fn clear_poison_recovers() {
+    let lock = Lock::new(0);
+    panic_holding(&lock);
+    *lock.spin_lock() = 0;
+    lock.clear_poison();
+    assert!(!lock.is_poisoned());
+    assert!(lock.spin_lock_unpoisoned().is_ok());
+}
+
+#[test]
+fn defused_guard_does_not_poison() {
+    let lock = Lock::new(0);
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let mut guard = lock.spin_lock();
+        Guard::defuse(&mut guard);
+        panic!("on purpose");
+    }));
+    assert!(result.is_err());
+    assert!(!lock.is_poisoned());
+}
+
+#[test]
+fn error_holds_guard() {
+    let lock = Lock::new(0);
+    panic_holding(&lock);
+    let mut error = lock.spin_lock_unpoisoned().unwrap_err();
+    assert_eq!(**error.get_ref(), 1);
+    **error.get_mut() += 1;
+    //still held through the error
+    assert!(lock.try_lock().is_none());
+    let guard = error.into_inner();
+    assert_eq!(*guard, 2);
+    drop(guard);
+    assert!(lock.is_poisoned());
+}
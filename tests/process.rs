@@ -0,0 +1,131 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Sharing a [ProcessLock] between processes, in an anonymous shared mapping, on Linux.
+*/
+#![cfg(all(feature = "std", target_os = "linux", not(loom), not(shuttle)))]
+
+use atomiclock_spinlock::ProcessLock;
+use std::time::Duration;
+
+const ROUNDS: u64 = 10_000;
+
+//the layout both processes agree on
+#[repr(C)]
+#[derive(Default)]
+struct Counters {
+    total: u64,
+    by_child: u64,
+}
+
+/**
+Maps `len` bytes of memory that a forked child shares with its parent.
+*/
+fn shared_mapping(len: usize) -> *mut u8 {
+    let ptr = unsafe {
+        libc::mmap(std::ptr::null_mut(), len, libc::PROT_READ | libc::PROT_WRITE, libc::MAP_SHARED | libc::MAP_ANONYMOUS, -1, 0)
+    };
+    assert_ne!(ptr, libc::MAP_FAILED);
+    ptr.cast()
+}
+
+/**
+Forks, runs `child` in the child and `parent` meanwhile, and waits for the child, asserting it succeeded.
+*/
+fn in_child(child: impl FnOnce() -> bool, parent: impl FnOnce()) {
+    unsafe {
+        let pid = libc::fork();
+        assert!(pid >= 0);
+        if pid == 0 {
+            libc::_exit(if child() { 0 } else { 1 });
+        }
+        parent();
+        let mut status = 0;
+        assert_eq!(libc::waitpid(pid, &mut status, 0), pid);
+        assert!(libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0, "the child failed");
+    }
+}
+
+#[test]
+fn processes_exclude_each_other() {
+    let len = std::mem::size_of::<ProcessLock<Counters>>();
+    let ptr = shared_mapping(len);
+    let lock = unsafe { ProcessLock::init_in_place(ptr, len, Counters::default()) };
+    in_child(|| {
+        let lock = unsafe { ProcessLock::<Counters>::from_raw_parts(ptr, len) };
+        for _ in 0..ROUNDS {
+            let mut counters = lock.spin_lock();
+            counters.total += 1;
+            counters.by_child += 1;
+        }
+        true
+    }, || {
+        for _ in 0..ROUNDS {
+            lock.spin_lock().total += 1;
+        }
+    });
+    let counters = lock.try_lock().unwrap();
+    assert_eq!((counters.total, counters.by_child), (2 * ROUNDS, ROUNDS));
+    drop(counters);
+    unsafe { libc::munmap(ptr.cast(), len) };
+}
+
+#[test]
+fn held_by_another_process() {
+    let len = std::mem::size_of::<ProcessLock<u32>>();
+    let ptr = shared_mapping(len);
+    let lock = unsafe { ProcessLock::init_in_place(ptr, len, 0u32) };
+    let guard = lock.spin_lock();
+    in_child(|| {
+        let lock = unsafe { ProcessLock::<u32>::from_raw_parts(ptr, len) };
+        lock.is_locked() && lock.try_lock().is_none() && lock.spin_lock_for(Duration::from_millis(10)).is_none()
+    }, || {});
+    drop(guard);
+    in_child(|| {
+        let lock = unsafe { ProcessLock::<u32>::from_raw_parts(ptr, len) };
+        //the child dies holding the lock
+        std::mem::forget(lock.spin_lock());
+        true
+    }, || {});
+    assert!(lock.try_lock().is_none());
+    unsafe { lock.force_unlock() };
+    assert!(lock.try_lock().is_some());
+    unsafe { libc::munmap(ptr.cast(), len) };
+}
+
+#[test]
+fn recovering_from_a_dead_holder() {
+    let len = std::mem::size_of::<ProcessLock<u32>>();
+    let ptr = shared_mapping(len);
+    let lock = unsafe { ProcessLock::init_robust_in_place(ptr, len, 0u32) };
+    //a live holder isn't recovered from
+    let guard = lock.spin_lock();
+    assert_eq!(lock.holder(), Some(std::process::id()));
+    assert!(lock.try_recover().is_none());
+    drop(guard);
+    assert_eq!(lock.holder(), None);
+    unsafe {
+        let pid = libc::fork();
+        assert!(pid >= 0);
+        if pid == 0 {
+            let lock = ProcessLock::<u32>::from_raw_parts(ptr, len);
+            let mut guard = lock.spin_lock();
+            *guard += 1;
+            //dies holding the lock
+            std::mem::forget(guard);
+            libc::_exit(0);
+        }
+        //wait for it to exit, but leave it a zombie
+        let mut info: libc::siginfo_t = std::mem::zeroed();
+        assert_eq!(libc::waitid(libc::P_PID, pid as libc::id_t, &mut info, libc::WEXITED | libc::WNOWAIT), 0);
+        assert_eq!(lock.holder(), Some(pid as u32));
+        assert!(lock.spin_lock_for(Duration::from_millis(10)).is_none());
+        let recovered = lock.try_recover().expect("the holder is dead");
+        assert_eq!(*recovered, 1);
+        assert_eq!(lock.holder(), Some(std::process::id()));
+        drop(recovered);
+        assert!(!lock.is_locked());
+        let mut status = 0;
+        assert_eq!(libc::waitpid(pid, &mut status, 0), pid);
+        libc::munmap(ptr.cast(), len);
+    }
+}
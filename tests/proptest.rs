@@ -0,0 +1,252 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Property tests of the lock state machine, with [proptest](https://crates.io/crates/proptest).
+
+Threads run generated programs against a few shared locks: each step acquires some of the locks (in index
+order, so the programs can't deadlock), in various ways, writes the data, and then releases the guards in a
+generated order, some of them on a different thread.  Throughout, at most one guard per lock may be alive,
+and the data must never be seen torn or changed under a holder.
+*/
+#![cfg(feature = "std")]
+
+use atomiclock_spinlock::{Lock, RwLock};
+use proptest::prelude::*;
+use std::ops::DerefMut;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+const LOCKS: usize = 3;
+
+//written one word at a time, so a second writer in the critical section would tear it
+type Data = [u64; 4];
+
+fn write(data: &mut Data, value: u64) {
+    for word in data {
+        *word = value;
+        thread::yield_now();
+    }
+}
+
+fn untorn(data: &Data) -> u64 {
+    assert!(data.iter().all(|&word| word == data[0]), "torn: {data:?}");
+    data[0]
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Acquire {
+    Spin,
+    Try,
+    Timed,
+    Owned,
+    Local,
+}
+
+#[derive(Debug, Clone)]
+struct Step {
+    //which locks to acquire
+    locks: [bool; LOCKS],
+    how: Acquire,
+    //guards are released in order of their keys
+    keys: [u8; LOCKS],
+    //released on the dropper thread instead, where possible
+    hand_off: bool,
+}
+
+fn step() -> impl Strategy<Value = Step> {
+    let how = prop_oneof![
+        Just(Acquire::Spin),
+        Just(Acquire::Try),
+        Just(Acquire::Timed),
+        Just(Acquire::Owned),
+        Just(Acquire::Local),
+    ];
+    (any::<[bool; LOCKS]>(), how, any::<[u8; LOCKS]>(), any::<bool>())
+        .prop_map(|(locks, how, keys, hand_off)| Step { locks, how, keys, hand_off })
+}
+
+struct Shared {
+    locks: Vec<Arc<Lock<Data>>>,
+    //set while a guard is alive
+    held: Vec<AtomicBool>,
+}
+
+impl Shared {
+    fn enter(&self, i: usize) {
+        assert!(!self.held[i].swap(true, Ordering::Relaxed), "two guards alive for lock {i}");
+    }
+    fn leave(&self, i: usize, data: &Data, value: u64) {
+        assert_eq!(untorn(data), value, "lock {i} changed under its holder");
+        self.held[i].store(false, Ordering::Relaxed);
+    }
+}
+
+type Released = (usize, u64, Box<dyn DerefMut<Target = Data> + Send>);
+
+fn run(shared: &Shared, thread: u64, program: &[Step], dropper: &mpsc::Sender<Released>) {
+    for (n, step) in program.iter().enumerate() {
+        let value = thread << 32 | n as u64;
+        let mut guards: Vec<(u8, usize, Box<dyn DerefMut<Target = Data> + '_>)> = Vec::new();
+        let mut owned: Vec<(u8, usize, Box<dyn DerefMut<Target = Data> + Send>)> = Vec::new();
+        for i in (0..LOCKS).filter(|&i| step.locks[i]) {
+            let lock = &shared.locks[i];
+            let key = step.keys[i];
+            match step.how {
+                Acquire::Spin => guards.push((key, i, Box::new(lock.spin_lock()))),
+                Acquire::Try => guards.extend(lock.try_lock().map(|guard| (key, i, Box::new(guard) as _))),
+                Acquire::Timed => guards.extend(
+                    lock.spin_lock_for(Duration::from_micros(50)).map(|guard| (key, i, Box::new(guard) as _)),
+                ),
+                Acquire::Owned => owned.push((key, i, Box::new(lock.spin_lock_owned()))),
+                Acquire::Local => guards.push((key, i, Box::new(lock.spin_lock_local()))),
+            }
+        }
+        for (_, i, guard) in &mut guards {
+            shared.enter(*i);
+            untorn(guard);
+            write(guard, value);
+        }
+        for (_, i, guard) in &mut owned {
+            shared.enter(*i);
+            untorn(guard);
+            write(guard, value);
+        }
+        guards.sort_by_key(|&(key, ..)| key);
+        owned.sort_by_key(|&(key, ..)| key);
+        for (_, i, guard) in guards {
+            shared.leave(i, &guard, value);
+            drop(guard);
+        }
+        for (_, i, guard) in owned {
+            if step.hand_off {
+                dropper.send((i, value, guard)).unwrap();
+            } else {
+                shared.leave(i, &guard, value);
+                drop(guard);
+            }
+        }
+    }
+}
+
+fn check_locks(programs: Vec<Vec<Step>>) {
+    let shared = Shared {
+        locks: (0..LOCKS).map(|_| Arc::new(Lock::new([0; 4]))).collect(),
+        held: (0..LOCKS).map(|_| AtomicBool::new(false)).collect(),
+    };
+    let (sender, receiver) = mpsc::channel::<Released>();
+    thread::scope(|s| {
+        let shared = &shared;
+        s.spawn(move || {
+            for (i, value, guard) in receiver {
+                shared.leave(i, &guard, value);
+                drop(guard);
+            }
+        });
+        for (t, program) in programs.iter().enumerate() {
+            let sender = sender.clone();
+            s.spawn(move || run(shared, t as u64, program, &sender));
+        }
+        drop(sender);
+    });
+    for lock in shared.locks {
+        let lock = Arc::into_inner(lock).unwrap();
+        assert!(!lock.is_locked());
+        untorn(&lock.into_inner());
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum RwStep {
+    Read,
+    TryRead,
+    Write,
+    TryWrite,
+    Upgrade,
+}
+
+fn rw_step() -> impl Strategy<Value = RwStep> {
+    prop_oneof![
+        Just(RwStep::Read),
+        Just(RwStep::TryRead),
+        Just(RwStep::Write),
+        Just(RwStep::TryWrite),
+        Just(RwStep::Upgrade),
+    ]
+}
+
+//who holds the rwlock, by occupancy: readers, and whether there's a writer
+struct Occupancy {
+    readers: AtomicUsize,
+    writer: AtomicBool,
+}
+
+impl Occupancy {
+    fn read(&self, data: &Data) {
+        self.readers.fetch_add(1, Ordering::Relaxed);
+        assert!(!self.writer.load(Ordering::Relaxed), "reader alongside a writer");
+        untorn(data);
+        thread::yield_now();
+        untorn(data);
+        self.readers.fetch_sub(1, Ordering::Relaxed);
+    }
+    fn write(&self, data: &mut Data, value: u64) {
+        assert!(!self.writer.swap(true, Ordering::Relaxed), "two writers");
+        assert_eq!(self.readers.load(Ordering::Relaxed), 0, "writer alongside a reader");
+        untorn(data);
+        write(data, value);
+        self.writer.store(false, Ordering::Relaxed);
+    }
+}
+
+fn check_rwlock(programs: Vec<Vec<RwStep>>) {
+    let lock = RwLock::new([0; 4]);
+    let occupancy = Occupancy { readers: AtomicUsize::new(0), writer: AtomicBool::new(false) };
+    thread::scope(|s| {
+        for (t, program) in programs.iter().enumerate() {
+            let (lock, occupancy) = (&lock, &occupancy);
+            s.spawn(move || {
+                for (n, step) in program.iter().enumerate() {
+                    let value = (t as u64) << 32 | n as u64;
+                    match step {
+                        RwStep::Read => occupancy.read(&lock.read()),
+                        RwStep::TryRead => {
+                            if let Some(guard) = lock.try_read() {
+                                occupancy.read(&guard);
+                            }
+                        }
+                        RwStep::Write => occupancy.write(&mut lock.write(), value),
+                        RwStep::TryWrite => {
+                            if let Some(mut guard) = lock.try_write() {
+                                occupancy.write(&mut guard, value);
+                            }
+                        }
+                        RwStep::Upgrade => {
+                            let guard = lock.upgradable_read();
+                            occupancy.read(&guard);
+                            let mut guard = atomiclock_spinlock::rwlock::UpgradableGuard::upgrade(guard);
+                            occupancy.write(&mut guard, value);
+                        }
+                    }
+                }
+            });
+        }
+    });
+    assert!(!lock.is_locked_exclusive());
+    assert_eq!(lock.readers(), 0);
+    untorn(&lock.into_inner());
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn locks(programs in prop::collection::vec(prop::collection::vec(step(), 1..8), 1..4)) {
+        check_locks(programs);
+    }
+
+    #[test]
+    fn rwlock(programs in prop::collection::vec(prop::collection::vec(rw_step(), 1..12), 1..4)) {
+        check_rwlock(programs);
+    }
+}
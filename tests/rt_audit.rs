@@ -0,0 +1,44 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Realtime-safety auditing, with the `rt-audit` feature.
+
+Run with `cargo test --features rt-audit --test rt_audit`.  If the lock paths allocate, the allocator
+below aborts the test binary.
+*/
+#![cfg(feature = "std")]
+
+use atomiclock_spinlock::audit::{in_lock_path, AuditAlloc, HAZARDS};
+use atomiclock_spinlock::Lock;
+use std::sync::Arc;
+
+#[global_allocator]
+static ALLOCATOR: AuditAlloc<std::alloc::System> = AuditAlloc(std::alloc::System);
+
+#[test]
+fn lock_paths_dont_allocate() {
+    //with hazardous features, such as under --all-features, the lock paths may allocate
+    if !HAZARDS.is_empty() {
+        return;
+    }
+    let lock = Arc::new(Lock::new(Vec::with_capacity(100)));
+    assert!(!in_lock_path());
+    let contender = {
+        let lock = lock.clone();
+        std::thread::spawn(move || {
+            for n in 0..100 {
+                lock.spin_lock().push(n);
+            }
+        })
+    };
+    for n in 0..100 {
+        let mut guard = lock.spin_lock();
+        guard.push(n);
+        drop(guard);
+        if let Some(guard) = lock.try_lock() {
+            drop(guard);
+        }
+    }
+    contender.join().unwrap();
+    assert_eq!(lock.spin_lock().len(), 200);
+    assert!(!in_lock_path());
+}
@@ -0,0 +1,45 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Wiping secrets, with the `zeroize` feature.
+
+Run with `cargo test --features zeroize --test secret`.
+*/
+
+use atomiclock_spinlock::SecretLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use zeroize::Zeroize;
+
+static WIPED: AtomicUsize = AtomicUsize::new(0);
+
+//counts how often it's wiped
+struct Key([u8; 4]);
+
+impl Zeroize for Key {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+        WIPED.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn wipes_on_drop_not_into_inner() {
+    let lock = SecretLock::new(Key([1, 2, 3, 4]));
+    lock.spin_lock().0[0] = 5;
+    drop(lock);
+    assert_eq!(WIPED.swap(0, Ordering::Relaxed), 1);
+
+    let lock = SecretLock::new(Key([1, 2, 3, 4]));
+    let key = lock.into_inner();
+    //the caller has the only copy now, so it's the caller's to wipe
+    assert_eq!(WIPED.load(Ordering::Relaxed), 0);
+    assert_eq!(key.0, [1, 2, 3, 4]);
+}
+
+#[test]
+fn never_formats_the_data() {
+    let lock = SecretLock::with_name([0xdeu8, 0xad], "key");
+    assert_eq!(format!("{lock:?}"), "SecretLock(<redacted>)");
+    let guard = lock.spin_lock();
+    assert_eq!(format!("{guard:?}"), "SecretGuard(<redacted>)");
+    assert_eq!(*guard, [0xde, 0xad]);
+}
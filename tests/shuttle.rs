@@ -0,0 +1,146 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Exercises multi-lock scenarios under [shuttle](https://crates.io/crates/shuttle)'s randomized schedulers.
+
+Run with `RUSTFLAGS="--cfg shuttle" cargo test --release --test shuttle`.
+*/
+#![cfg(shuttle)]
+
+use atomiclock_spinlock::{Lock, RwLock};
+use shuttle::sync::Arc;
+use shuttle::thread;
+use std::collections::VecDeque;
+
+const ITERATIONS: usize = 1000;
+
+//moves money between two accounts in both directions, while an auditor checks the total
+fn transfers() {
+    let accounts = Arc::new([Lock::new(100), Lock::new(100)]);
+    let threads: Vec<_> = [(0, 1), (1, 0), (0, 1)]
+        .into_iter()
+        .map(|(from, to)| {
+            let accounts = accounts.clone();
+            thread::spawn(move || {
+                //always in index order, so opposite transfers can't deadlock
+                let (first, second) = (from.min(to), from.max(to));
+                let mut a = accounts[first].spin_lock();
+                let mut b = accounts[second].spin_lock();
+                let (from, to) = if from == first { (&mut *a, &mut *b) } else { (&mut *b, &mut *a) };
+                *from -= 10;
+                *to += 10;
+            })
+        })
+        .collect();
+    let auditor = {
+        let accounts = accounts.clone();
+        thread::spawn(move || {
+            let a = accounts[0].spin_lock();
+            let b = accounts[1].spin_lock();
+            assert_eq!(*a + *b, 200);
+        })
+    };
+    for t in threads {
+        t.join().unwrap();
+    }
+    auditor.join().unwrap();
+    assert_eq!(*accounts[0].spin_lock(), 90);
+    assert_eq!(*accounts[1].spin_lock(), 110);
+}
+
+#[test]
+fn transfers_random() {
+    shuttle::check_random(transfers, ITERATIONS);
+}
+
+#[test]
+fn transfers_pct() {
+    shuttle::check_pct(transfers, ITERATIONS, 3);
+}
+
+//writers keep a pair equal; readers must never see it torn
+fn rwlock() {
+    let lock = Arc::new(RwLock::new((0, 0)));
+    let writer = {
+        let lock = lock.clone();
+        thread::spawn(move || {
+            for _ in 0..2 {
+                let mut guard = lock.write();
+                guard.0 += 1;
+                thread::yield_now();
+                guard.1 += 1;
+            }
+        })
+    };
+    let upgrader = {
+        let lock = lock.clone();
+        thread::spawn(move || {
+            let guard = lock.upgradable_read();
+            let seen = *guard;
+            let mut guard = atomiclock_spinlock::rwlock::UpgradableGuard::upgrade(guard);
+            //nobody could write while we held the upgradable read
+            assert_eq!(*guard, seen);
+            guard.0 += 1;
+            guard.1 += 1;
+        })
+    };
+    let readers: Vec<_> = (0..2)
+        .map(|_| {
+            let lock = lock.clone();
+            thread::spawn(move || {
+                for _ in 0..2 {
+                    let guard = lock.read();
+                    assert_eq!(guard.0, guard.1);
+                }
+            })
+        })
+        .collect();
+    writer.join().unwrap();
+    upgrader.join().unwrap();
+    for t in readers {
+        t.join().unwrap();
+    }
+    assert_eq!(*lock.read(), (3, 3));
+}
+
+#[test]
+fn rwlock_random() {
+    shuttle::check_random(rwlock, ITERATIONS);
+}
+
+#[test]
+fn rwlock_pct() {
+    shuttle::check_pct(rwlock, ITERATIONS, 3);
+}
+
+//async consumers wait for a producer, condition-variable style
+fn condition() {
+    let queue = Arc::new(Lock::new(VecDeque::new()));
+    let consumers: Vec<_> = (0..2)
+        .map(|_| {
+            let queue = queue.clone();
+            shuttle::future::spawn(async move { queue.wait_async_until(|q| !q.is_empty()).await.pop_front().unwrap() })
+        })
+        .collect();
+    let producer = {
+        let queue = queue.clone();
+        thread::spawn(move || {
+            for i in 0..2 {
+                queue.spin_lock().push_back(i);
+            }
+        })
+    };
+    let mut got: Vec<u32> = consumers.into_iter().map(|c| shuttle::future::block_on(c).unwrap()).collect();
+    producer.join().unwrap();
+    got.sort();
+    assert_eq!(got, [0, 1]);
+}
+
+#[test]
+fn condition_random() {
+    shuttle::check_random(condition, ITERATIONS);
+}
+
+#[test]
+fn condition_pct() {
+    shuttle::check_pct(condition, ITERATIONS, 3);
+}
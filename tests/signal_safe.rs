@@ -0,0 +1,120 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Async-signal-safety of the lock paths, with the `rt-audit` feature.
+
+Run with `cargo test --features rt-audit --test signal_safe`.  A handler checks that the lock paths don't
+allocate, and a child process checks they make no syscalls, under seccomp's strict mode, which kills it on any
+syscall but `read`, `write`, `exit` and `sigreturn`.
+*/
+#![cfg(all(target_os = "linux", not(loom), not(shuttle)))]
+
+use atomiclock_spinlock::audit::SIGNAL_HAZARDS;
+use atomiclock_spinlock::Lock;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+//counts allocations made while IN_HANDLER is set
+struct CountingAlloc;
+
+static IN_HANDLER: AtomicBool = AtomicBool::new(false);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if IN_HANDLER.load(Ordering::Relaxed) {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if IN_HANDLER.load(Ordering::Relaxed) {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        }
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAlloc = CountingAlloc;
+
+static SHARED: Lock<u32> = Lock::new(0);
+static HANDLER_ONLY: Lock<u32> = Lock::new(0);
+static MISSED: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn on_signal(_: libc::c_int) {
+    IN_HANDLER.store(true, Ordering::Relaxed);
+    match SHARED.try_lock() {
+        Some(mut shared) => *shared += 1,
+        None => {
+            MISSED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    //only ever locked here, so the interrupted thread can't hold it
+    *HANDLER_ONLY.spin_lock() += 1;
+    IN_HANDLER.store(false, Ordering::Relaxed);
+}
+
+#[test]
+fn handler_doesnt_allocate() {
+    //with hazardous features, such as under --all-features, the lock paths may allocate
+    if !SIGNAL_HAZARDS.is_empty() {
+        return;
+    }
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        libc::sigemptyset(&mut action.sa_mask);
+        assert_eq!(libc::sigaction(libc::SIGUSR1, &action, std::ptr::null_mut()), 0);
+        //delivered to this thread before raise returns
+        assert_eq!(libc::raise(libc::SIGUSR1), 0);
+        let held = SHARED.spin_lock();
+        assert_eq!(libc::raise(libc::SIGUSR1), 0);
+        drop(held);
+    }
+    assert_eq!(*SHARED.spin_lock(), 1);
+    assert_eq!(MISSED.load(Ordering::Relaxed), 1);
+    assert_eq!(*HANDLER_ONLY.spin_lock(), 2);
+    assert_eq!(ALLOCATIONS.load(Ordering::Relaxed), 0);
+}
+
+#[test]
+fn lock_paths_make_no_syscalls() {
+    if !SIGNAL_HAZARDS.is_empty() {
+        return;
+    }
+    let lock = Lock::new(0);
+    unsafe {
+        let mut pipe = [0; 2];
+        assert_eq!(libc::pipe(pipe.as_mut_ptr()), 0);
+        let child = libc::fork();
+        assert!(child >= 0);
+        if child == 0 {
+            //strict mode allows exit, but not exit_group, which libc's exits use
+            if libc::prctl(libc::PR_SET_SECCOMP, libc::SECCOMP_MODE_STRICT) != 0 {
+                libc::syscall(libc::SYS_exit, 2);
+            }
+            let mut guard = lock.spin_lock();
+            *guard += 1;
+            let failed = lock.try_lock().is_none();
+            drop(guard);
+            let acquired = lock.try_lock().is_some();
+            let report = [u8::from(failed && acquired)];
+            libc::write(pipe[1], report.as_ptr().cast(), 1);
+            libc::syscall(libc::SYS_exit, 0);
+        }
+        libc::close(pipe[1]);
+        let mut status = 0;
+        assert_eq!(libc::waitpid(child, &mut status, 0), child);
+        let mut report = [0u8];
+        let read = libc::read(pipe[0], report.as_mut_ptr().cast(), 1);
+        libc::close(pipe[0]);
+        if libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 2 {
+            //seccomp isn't available, as in some containers
+            return;
+        }
+        assert!(libc::WIFEXITED(status), "the child was killed, by signal {}, for making a syscall", libc::WTERMSIG(status));
+        assert_eq!(libc::WEXITSTATUS(status), 0);
+        assert_eq!((read, report[0]), (1, 1));
+    }
+}
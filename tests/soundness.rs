@@ -0,0 +1,15 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Misuses of the API that must not compile, in `tests/soundness`.
+
+Each case is checked against the compiler's error, in the `.stderr` file next to it, so a case can't pass by
+failing for some unrelated reason.  The messages change between compiler versions, so this runs on stable;
+after a toolchain update, review the new messages and regenerate them with
+`TRYBUILD=overwrite cargo test --test soundness`.
+*/
+#![cfg(not(any(miri, loom, shuttle)))]
+
+#[test]
+fn misuse_fails_to_compile() {
+    trybuild::TestCases::new().compile_fail("tests/soundness/*.rs");
+}
@@ -0,0 +1,20 @@
+//a CeilingGuard must restore the priority on the thread that raised it
+use atomiclock_spinlock::ceiling::Priority;
+use atomiclock_spinlock::CeilingLock;
+
+struct Host;
+
+impl Priority for Host {
+    type Level = u8;
+    fn raise(ceiling: u8) -> u8 {
+        ceiling
+    }
+    fn restore(_previous: u8) {}
+}
+
+static LOCK: CeilingLock<u32, Host> = CeilingLock::new(0, 5);
+
+fn main() {
+    let guard = LOCK.lock();
+    std::thread::spawn(move || drop(guard));
+}
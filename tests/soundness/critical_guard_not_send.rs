@@ -0,0 +1,18 @@
+//a CriticalGuard must restore interrupts on the core that disabled them
+use atomiclock_spinlock::interrupt::Interrupts;
+use atomiclock_spinlock::CriticalLock;
+
+struct Host;
+
+unsafe impl Interrupts for Host {
+    type State = ();
+    fn disable() {}
+    unsafe fn restore(_state: ()) {}
+}
+
+static LOCK: CriticalLock<u32, Host> = CriticalLock::new(0);
+
+fn main() {
+    let guard = LOCK.lock();
+    std::thread::spawn(move || drop(guard));
+}
@@ -0,0 +1,9 @@
+//some DynLocks must be released on the core that acquired them
+use atomiclock_spinlock::{DynLock, Lock};
+
+static LOCK: Lock<u32> = Lock::new(0);
+
+fn main() {
+    let guard = LOCK.lock_dyn();
+    std::thread::spawn(move || drop(guard));
+}
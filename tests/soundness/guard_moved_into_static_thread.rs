@@ -0,0 +1,8 @@
+//a guard for a local lock can't go to a thread that may outlive the lock
+use atomiclock_spinlock::Lock;
+
+fn main() {
+    let lock = Lock::new(0);
+    let mut guard = lock.spin_lock();
+    std::thread::spawn(move || *guard += 1);
+}
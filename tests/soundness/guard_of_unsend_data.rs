@@ -0,0 +1,9 @@
+//a guard hands out the data, so it can only cross threads if the data can
+use atomiclock_spinlock::{Guard, Lock};
+use std::rc::Rc;
+
+fn main() {
+    let lock: &'static Lock<Rc<u32>> = Box::leak(Box::new(Lock::new(Rc::new(0))));
+    let guard: Guard<'static, Rc<u32>> = lock.spin_lock();
+    std::thread::spawn(move || drop(guard));
+}
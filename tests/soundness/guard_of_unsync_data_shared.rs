@@ -0,0 +1,11 @@
+//sharing a guard shares the data, so it needs Sync data
+use atomiclock_spinlock::Lock;
+use std::cell::Cell;
+
+fn main() {
+    let lock = Lock::new(Cell::new(0));
+    let guard = lock.spin_lock();
+    std::thread::scope(|s| {
+        s.spawn(|| guard.set(1));
+    });
+}
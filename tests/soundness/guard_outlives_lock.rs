@@ -0,0 +1,11 @@
+//a guard borrows its lock, so the lock can't be dropped while it's held
+use atomiclock_spinlock::Lock;
+
+fn main() {
+    let guard;
+    {
+        let lock = Lock::new(0);
+        guard = lock.spin_lock();
+    }
+    drop(guard);
+}
@@ -0,0 +1,9 @@
+//a LocalGuard must be released on the thread that took it
+use atomiclock_spinlock::Lock;
+
+static LOCK: Lock<u32> = Lock::new(0);
+
+fn main() {
+    let guard = LOCK.spin_lock_local();
+    std::thread::spawn(move || drop(guard));
+}
@@ -0,0 +1,12 @@
+//sharing an owned guard shares the data, so it needs Sync data, like Guard
+use atomiclock_spinlock::Lock;
+use std::cell::Cell;
+use std::sync::Arc;
+
+fn main() {
+    let lock = Arc::new(Lock::new(Cell::new(0)));
+    let guard = lock.spin_lock_owned();
+    std::thread::scope(|s| {
+        s.spawn(|| guard.set(1));
+    });
+}
@@ -0,0 +1,10 @@
+//a PinGuard doesn't hand out `&mut T` for data that must not move
+use atomiclock_spinlock::PinLock;
+use std::marker::PhantomPinned;
+use std::pin::pin;
+
+fn main() {
+    let lock = pin!(PinLock::new(PhantomPinned));
+    let mut guard = lock.as_ref().spin_lock();
+    let _moved = std::mem::replace(&mut *guard, PhantomPinned);
+}
@@ -0,0 +1,11 @@
+//the data is only borrowed for as long as the guard holds the lock
+use atomiclock_spinlock::Lock;
+
+fn main() {
+    let lock = Lock::new(0);
+    let data: &mut i32 = {
+        let mut guard = lock.spin_lock();
+        &mut *guard
+    };
+    *data += 1;
+}
@@ -0,0 +1,8 @@
+//a SharedGuard can be cloned, so it only reads
+use atomiclock_spinlock::{Guard, Lock};
+
+fn main() {
+    let lock = Lock::new(0);
+    let mut shared = Guard::share(lock.spin_lock());
+    *shared += 1;
+}
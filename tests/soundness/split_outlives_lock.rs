@@ -0,0 +1,12 @@
+//each part of a split guard borrows the lock, as the guard did
+use atomiclock_spinlock::{Guard, Lock, SplitGuard};
+
+fn main() {
+    let part: SplitGuard<'_, (u32, u32), u32>;
+    {
+        let lock = Lock::new((0, 0));
+        let (a, _b) = Guard::split(lock.spin_lock());
+        part = a;
+    }
+    drop(part);
+}
@@ -0,0 +1,8 @@
+//map_split's parts must be disjoint
+use atomiclock_spinlock::{Guard, Lock};
+
+fn main() {
+    let lock = Lock::new((0, 0));
+    let (a, b) = Guard::map_split(lock.spin_lock(), |pair| (&mut pair.0, &mut pair.0));
+    drop((a, b));
+}
@@ -0,0 +1,14 @@
+//the initializer isn't inside the macro's unsafe block, so it can't call unsafe code without its own
+use atomiclock_spinlock::static_spinlock;
+
+const unsafe fn unchecked() -> u32 {
+    0
+}
+
+static_spinlock! {
+    static COUNTER: u32 = unchecked();
+}
+
+fn main() {
+    *COUNTER.spin_lock() += 1;
+}
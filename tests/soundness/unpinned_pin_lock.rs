@@ -0,0 +1,7 @@
+//a PinLock only locks once it's pinned
+use atomiclock_spinlock::PinLock;
+
+fn main() {
+    let lock = PinLock::new(0);
+    drop(lock.spin_lock());
+}
@@ -0,0 +1,6 @@
+//locks hold sized data only, so there's no unsized guard to misuse
+use atomiclock_spinlock::Lock;
+
+fn lock_slice(_lock: &Lock<[u32]>) {}
+
+fn main() {}
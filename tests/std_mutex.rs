@@ -0,0 +1,41 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Locks on an OS mutex, with the `std-mutex` feature.
+
+Run with `cargo test --features std-mutex --test std_mutex`.
+*/
+
+use atomiclock_spinlock::Lock;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn contended_waiters_sleep_until_released() {
+    let lock = Lock::new(0u32);
+    thread::scope(|s| {
+        let guard = lock.spin_lock();
+        for _ in 0..4 {
+            s.spawn(|| {
+                for _ in 0..1000 {
+                    *lock.spin_lock() += 1;
+                }
+            });
+        }
+        //give the waiters time to block
+        thread::sleep(Duration::from_millis(50));
+        drop(guard);
+    });
+    assert_eq!(lock.into_inner(), 4000);
+}
+
+#[test]
+fn same_api() {
+    let lock = Lock::new(1);
+    let guard = lock.try_lock().unwrap();
+    assert!(lock.is_locked());
+    assert!(lock.try_lock().is_none());
+    assert!(lock.spin_lock_for(Duration::from_millis(1)).is_none());
+    drop(guard);
+    assert!(!lock.is_locked());
+    assert_eq!(*lock.spin_lock_for(Duration::from_millis(1)).unwrap(), 1);
+}
@@ -0,0 +1,101 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Timeout behavior, on virtual time.
+
+Run with `cargo test --features test-clock --test test_clock`.
+*/
+
+use atomiclock_spinlock::clock::{manual, Clock, StdClock};
+use atomiclock_spinlock::{Lock, RateLimiter};
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::time::Duration;
+
+struct Noop;
+
+impl Wake for Noop {
+    fn wake(self: Arc<Self>) {}
+}
+
+#[test]
+fn spin_lock_for_times_out() {
+    manual::reset();
+    let lock = Lock::new(0);
+    let held = lock.spin_lock();
+    manual::set_tick(Duration::from_millis(1));
+    assert!(lock.spin_lock_for(Duration::from_secs(60)).is_none());
+    //one reading per attempt, so it gave up after about 60,000 attempts
+    assert!((Duration::from_secs(60)..Duration::from_secs(61)).contains(&manual::elapsed()));
+    drop(held);
+    assert!(lock.spin_lock_for(Duration::ZERO).is_some());
+}
+
+#[test]
+fn spin_lock_until_passed() {
+    manual::reset();
+    let lock = Lock::new(0);
+    let _held = lock.spin_lock();
+    let deadline = StdClock.now() + Duration::from_secs(1);
+    manual::advance(Duration::from_secs(2));
+    assert!(lock.spin_lock_until(deadline).is_none());
+}
+
+#[test]
+fn time_moves_by_hand() {
+    manual::reset();
+    manual::set_tick(Duration::ZERO);
+    let before = StdClock.now();
+    std::thread::sleep(Duration::from_millis(10));
+    assert_eq!(StdClock.now(), before);
+    manual::advance(Duration::from_secs(1));
+    assert_eq!(StdClock.now() - before, Duration::from_secs(1));
+}
+
+#[test]
+fn lock_async_timeout() {
+    manual::reset();
+    let lock = Lock::new(0);
+    let held = lock.spin_lock();
+    let waker = Waker::from(Arc::new(Noop));
+    let mut cx = Context::from_waker(&waker);
+    manual::set_tick(Duration::ZERO);
+    let mut future = pin!(lock.lock_async_timeout(Duration::from_secs(5)));
+    for _ in 0..10 {
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+    }
+    manual::advance(Duration::from_secs(6));
+    assert!(matches!(future.as_mut().poll(&mut cx), Poll::Ready(None)));
+    drop(held);
+}
+
+#[test]
+fn rate_limiter_refills() {
+    manual::reset();
+    manual::set_tick(Duration::ZERO);
+    let limiter = RateLimiter::new(4, Duration::from_secs(1));
+    assert!(limiter.try_acquire(4));
+    assert!(!limiter.try_acquire(1));
+    manual::advance(Duration::from_millis(2500));
+    assert_eq!(limiter.available(), 2);
+    //the half second toward the next token is kept
+    manual::advance(Duration::from_millis(500));
+    assert_eq!(limiter.available(), 3);
+    assert!(!limiter.try_acquire(4));
+    manual::advance(Duration::from_secs(60));
+    assert_eq!(limiter.available(), 4);
+}
+
+#[cfg(feature = "acquired-at")]
+#[test]
+fn guards_know_how_long_they_were_held() {
+    use atomiclock_spinlock::Guard;
+    manual::reset();
+    manual::set_tick(Duration::ZERO);
+    let lock = Lock::new(0);
+    let guard = lock.spin_lock();
+    manual::advance(Duration::from_secs(2));
+    assert_eq!(Guard::held_for(&guard), Duration::from_secs(2));
+    assert_eq!(Guard::acquired_at(&guard) + Duration::from_secs(2), StdClock.now());
+}
@@ -0,0 +1,137 @@
+//SPDX-License-Identifier: MIT OR Apache-2.0
+/*!
+Forced contention with [TestLock].
+
+Run with `cargo test --features test-util,test-clock --test test_lock`.
+*/
+
+use atomiclock_spinlock::clock::manual;
+use atomiclock_spinlock::{DynLock, TestLock};
+use std::time::Duration;
+
+#[test]
+fn busy_attempts() {
+    let lock = TestLock::new(0);
+    lock.busy_for_attempts(2);
+    assert!(lock.try_lock().is_none());
+    assert!(lock.try_lock_dyn().is_none());
+    *lock.try_lock().unwrap() += 1;
+    assert_eq!(lock.attempts(), 3);
+    assert!(!lock.inner().is_locked());
+}
+
+#[test]
+fn times_out_while_busy() {
+    manual::reset();
+    let lock = TestLock::new(0);
+    lock.busy_for(Duration::from_secs(10));
+    manual::set_tick(Duration::from_millis(1));
+    assert!(lock.spin_lock_for(Duration::from_secs(1)).is_none());
+    //the busy period ends before this deadline
+    assert!(lock.spin_lock_for(Duration::from_secs(60)).is_some());
+    assert!(manual::elapsed() >= Duration::from_secs(10));
+}
+
+#[test]
+fn clear_busy() {
+    let lock = TestLock::new(0);
+    lock.busy_for_attempts(usize::MAX);
+    lock.busy_for(Duration::from_secs(3600));
+    assert!(lock.try_lock().is_none());
+    lock.clear_busy();
+    assert!(lock.try_lock().is_some());
+}
+
+#[test]
+fn really_held() {
+    let lock = TestLock::new(0);
+    let held = lock.spin_lock();
+    assert!(lock.try_lock().is_none());
+    drop(held);
+    drop(lock.lock_dyn());
+    assert_eq!(lock.into_inner(), 0);
+}
+
+#[test]
+fn scripted_order() {
+    //the order the threads start in doesn't matter
+    for _ in 0..20 {
+        let lock = TestLock::new(Vec::new());
+        lock.follow_schedule(&["a", "c", "b", "a"]);
+        std::thread::scope(|s| {
+            for name in ["b", "c", "a"] {
+                let lock = &lock;
+                std::thread::Builder::new()
+                    .name(name.into())
+                    .spawn_scoped(s, move || {
+                        let turns = if name == "a" { 2 } else { 1 };
+                        for _ in 0..turns {
+                            lock.spin_lock().push(name);
+                        }
+                    })
+                    .unwrap();
+            }
+        });
+        assert!(lock.schedule_done());
+        assert_eq!(lock.into_inner(), ["a", "c", "b", "a"]);
+    }
+}
+
+#[test]
+fn unscheduled_threads_wait() {
+    let lock = TestLock::new(0);
+    lock.follow_schedule(&["someone else"]);
+    assert!(lock.try_lock().is_none());
+    assert!(lock.spin_lock_for(Duration::from_millis(1)).is_none());
+    lock.clear_busy();
+    assert!(lock.try_lock().is_some());
+}
+
+#[test]
+fn scenario_blocking_step() {
+    let lock = TestLock::new(Vec::new());
+    let released = std::sync::atomic::AtomicBool::new(false);
+    atomiclock_spinlock::scenario! {
+        holder => |s| {
+            s.step(1);
+            let mut guard = lock.spin_lock();
+            guard.push(1);
+            s.step(3);
+            released.store(true, std::sync::atomic::Ordering::Relaxed);
+            drop(guard);
+        },
+        waiter => |s| {
+            s.step_blocking(2);
+            let mut guard = lock.spin_lock();
+            assert!(released.load(std::sync::atomic::Ordering::Relaxed));
+            guard.push(2);
+        },
+    }
+    assert_eq!(lock.into_inner(), [1, 2]);
+}
+
+#[test]
+fn scenario_with_schedule() {
+    let lock = TestLock::new(Vec::new());
+    lock.follow_schedule(&["second", "first"]);
+    atomiclock_spinlock::scenario! {
+        first => |s| {
+            s.step_blocking(1);
+            lock.spin_lock().push("first");
+        },
+        second => |s| {
+            s.step(2);
+            lock.spin_lock().push("second");
+        },
+    }
+    assert_eq!(lock.into_inner(), ["second", "first"]);
+}
+
+#[test]
+#[should_panic]
+fn scenario_missing_step() {
+    atomiclock_spinlock::scenario! {
+        t1 => |s| s.step(1),
+        t2 => |s| s.step(3),
+    }
+}